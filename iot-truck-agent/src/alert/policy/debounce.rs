@@ -14,6 +14,7 @@ impl AlertDebouncer {
         cooldown_periods.insert("DrowsyDriver".to_string(), Duration::from_secs(30));
         cooldown_periods.insert("LaneDeparture".to_string(), Duration::from_secs(10));
         cooldown_periods.insert("HarshBraking".to_string(), Duration::from_secs(5));
+        cooldown_periods.insert("HarshCornering".to_string(), Duration::from_secs(5));
         cooldown_periods.insert("HighTemperature".to_string(), Duration::from_secs(60));
 
         Self {
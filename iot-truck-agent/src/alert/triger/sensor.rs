@@ -2,6 +2,12 @@ use crate::alert::types::{Alert, AlertSeverity, AlertType};
 use crate::sensors::types::SensorEvent;
 use tracing::{info, warn};
 
+// Acceleration along the truck's axes (g); a laden truck rarely reaches
+// these without the driver forcing it
+const HARSH_BRAKING_G: f32 = 0.4;
+const RAPID_ACCELERATION_G: f32 = 0.3;
+const HARSH_CORNERING_G: f32 = 0.35;
+
 pub struct SensorTriggerEngine;
 
 impl SensorTriggerEngine {
@@ -12,24 +18,32 @@ impl SensorTriggerEngine {
     pub fn trigger_from_sensor(&self, sensor_event: &SensorEvent) -> Option<Alert> {
         match &sensor_event.values {
             crate::sensors::types::SensorValues::Imu(imu) => {
-                let g_force =
-                    (imu.accel_x.powi(2) + imu.accel_y.powi(2) + imu.accel_z.powi(2)).sqrt();
-                if g_force > 0.8 {
-                    // Harsh braking
+                // Gravity-free truck axes; nothing to judge before the
+                // mounting is calibrated
+                let (Some(longitudinal), Some(lateral)) = (imu.longitudinal_g, imu.lateral_g) else {
+                    return None;
+                };
+                if longitudinal <= -HARSH_BRAKING_G {
                     Some(Alert::new(
                         AlertType::HarshBraking,
                         AlertSeverity::Warning,
                         "Harsh braking detected",
                         &sensor_event.sensor_id,
                     ))
-                } else if g_force > 0.6 {
-                    // Rapid acceleration
+                } else if longitudinal >= RAPID_ACCELERATION_G {
                     Some(Alert::new(
                         AlertType::RapidAcceleration,
                         AlertSeverity::Warning,
                         "Rapid acceleration detected",
                         &sensor_event.sensor_id,
                     ))
+                } else if lateral.abs() >= HARSH_CORNERING_G {
+                    Some(Alert::new(
+                        AlertType::HarshCornering,
+                        AlertSeverity::Warning,
+                        "Harsh cornering detected",
+                        &sensor_event.sensor_id,
+                    ))
                 } else {
                     None
                 }
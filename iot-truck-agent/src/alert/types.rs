@@ -20,6 +20,7 @@ pub enum AlertType {
     // Sensor-based alerts
     HarshBraking,
     RapidAcceleration,
+    HarshCornering,
     SeatbeltNotFastened,
     DoorOpenWhileMoving,
     OverSpeeding,
@@ -0,0 +1,245 @@
+use crate::budget::types::{CycleCounters, DataCategory, Degradation};
+use crate::config::BudgetConfig;
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use parking_lot::{Mutex, MutexGuard};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::time::{interval, Duration};
+use tracing::{error, info, warn};
+
+pub mod types;
+
+// Metrics
+metrics::describe_gauge!("data_budget_used_bytes", "Bytes sent this billing cycle, per category");
+metrics::describe_gauge!("data_budget_used_percent", "Share of the cycle's data plan used");
+metrics::describe_gauge!("data_budget_degraded", "1 while behaviour is degraded to save data");
+
+const MB: u64 = 1024 * 1024;
+// Counters written since the last persist are lost on a crash; this bounds the undercount
+const PERSIST_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Meters bytes sent per [`DataCategory`] against the cellular plan and
+/// decides what to give up as the billing cycle's budget runs down.
+///
+/// Cheap to clone; all clones share the same counters.
+#[derive(Clone)]
+pub struct DataBudget {
+    config: BudgetConfig,
+    counters: Arc<Mutex<CycleCounters>>,
+    degradation: Arc<Mutex<Degradation>>,
+    /// False while traffic goes over depot Wi-Fi rather than the cellular plan.
+    metered: Arc<AtomicBool>,
+}
+
+impl DataBudget {
+    /// Loads persisted counters, starting from zero if they belong to an
+    /// earlier billing cycle or cannot be read.
+    pub fn load(config: &BudgetConfig) -> Self {
+        let current = cycle_start(Utc::now(), config.billing_cycle_day);
+        let counters = match std::fs::read(&config.state_path) {
+            Ok(bytes) => match serde_json::from_slice::<CycleCounters>(&bytes) {
+                Ok(counters) if counters.cycle_start == current => {
+                    info!(used_mb = counters.total() / MB, "📶 Data budget counters restored");
+                    counters
+                }
+                Ok(_) => {
+                    info!("📶 New billing cycle — data budget counters reset");
+                    CycleCounters::new(current)
+                }
+                Err(e) => {
+                    warn!(path=%config.state_path, error=%e, "Unreadable data budget state — starting from zero");
+                    CycleCounters::new(current)
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => CycleCounters::new(current),
+            Err(e) => {
+                warn!(path=%config.state_path, error=%e, "Failed to read data budget state — starting from zero");
+                CycleCounters::new(current)
+            }
+        };
+
+        Self {
+            config: config.clone(),
+            counters: Arc::new(Mutex::new(counters)),
+            degradation: Arc::new(Mutex::new(Degradation::NONE)),
+            metered: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// Adds `bytes` sent over the cellular link to `category`.
+    pub fn record(&self, category: DataCategory, bytes: usize) {
+        if !self.metered.load(Ordering::Relaxed) {
+            return;
+        }
+        let (used, percent) = {
+            let mut counters = self.current();
+            counters.add(category, bytes as u64);
+            (counters.get(category), self.used_percent(&counters))
+        };
+        metrics::gauge!("data_budget_used_bytes", "category" => category.as_str()).set(used as f64);
+        metrics::gauge!("data_budget_used_percent").set(percent as f64);
+    }
+
+    /// Stops or resumes metering, e.g. while the truck is on depot Wi-Fi.
+    pub fn set_metered(&self, metered: bool) {
+        self.metered.store(metered, Ordering::Relaxed);
+    }
+
+    /// Current degradation; logs when it changes.
+    pub fn degradation(&self) -> Degradation {
+        let next = {
+            let counters = self.current();
+            self.evaluate(&counters)
+        };
+
+        let mut last = self.degradation.lock();
+        if *last != next {
+            if next.is_degraded() {
+                warn!(
+                    defer_ota = next.defer_ota,
+                    drop_video = next.drop_video,
+                    sensor_every_nth = next.sensor_every_nth,
+                    "📉 Data budget running low — degrading"
+                );
+            } else {
+                info!("📶 Data budget restored — full service");
+            }
+            metrics::gauge!("data_budget_degraded").set(if next.is_degraded() { 1.0 } else { 0.0 });
+            *last = next;
+        }
+        next
+    }
+
+    /// Usage for the heartbeat.
+    pub fn usage(&self) -> truck_protocol::events::DataUsage {
+        let counters = self.current();
+        truck_protocol::events::DataUsage {
+            cycle_start: counters.cycle_start,
+            budget_bytes: self.config.monthly_mb * MB,
+            telemetry_bytes: counters.telemetry_bytes,
+            camera_bytes: counters.camera_bytes,
+            ota_bytes: counters.ota_bytes,
+        }
+    }
+
+    pub fn persist(&self) -> std::io::Result<()> {
+        let snapshot = self.current().clone();
+        let path = Path::new(&self.config.state_path);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Write then rename so a power cut never leaves a torn file
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&snapshot)?)?;
+        std::fs::rename(&tmp, path)
+    }
+
+    /// Persists the counters every minute for as long as the agent runs.
+    pub fn spawn_persister(&self) {
+        let budget = self.clone();
+        tokio::spawn(async move {
+            let mut tick = interval(PERSIST_INTERVAL);
+            loop {
+                tick.tick().await;
+                if let Err(e) = budget.persist() {
+                    error!(error=%e, "Failed to persist data budget counters");
+                }
+            }
+        });
+    }
+
+    /// Counters for the current cycle, rolling over first if a new one began.
+    fn current(&self) -> MutexGuard<'_, CycleCounters> {
+        let mut counters = self.counters.lock();
+        let start = cycle_start(Utc::now(), self.config.billing_cycle_day);
+        if counters.cycle_start != start {
+            info!(previous_used_mb = counters.total() / MB, "📶 New billing cycle — data budget counters reset");
+            *counters = CycleCounters::new(start);
+        }
+        counters
+    }
+
+    fn used_percent(&self, counters: &CycleCounters) -> f32 {
+        if self.config.monthly_mb == 0 {
+            return 0.0;
+        }
+        counters.total() as f32 * 100.0 / (self.config.monthly_mb * MB) as f32
+    }
+
+    fn evaluate(&self, counters: &CycleCounters) -> Degradation {
+        if !self.config.enable {
+            return Degradation::NONE;
+        }
+
+        let percent = self.used_percent(counters);
+        let over_cap = |cap_mb: u64, category| cap_mb > 0 && counters.get(category) >= cap_mb * MB;
+
+        Degradation {
+            defer_ota: percent >= self.config.defer_ota_at_percent
+                || over_cap(self.config.ota_mb, DataCategory::Ota),
+            drop_video: percent >= self.config.drop_video_at_percent
+                || over_cap(self.config.camera_mb, DataCategory::Camera),
+            sensor_every_nth: if percent >= self.config.reduce_sensor_rate_at_percent {
+                self.config.reduced_sensor_every_nth.max(1)
+            } else {
+                1
+            },
+        }
+    }
+}
+
+/// Start (unix seconds, UTC midnight) of the billing cycle containing `now`.
+/// Days past the 28th are clamped so every month has the cycle day.
+fn cycle_start(now: DateTime<Utc>, billing_cycle_day: u32) -> i64 {
+    let day = billing_cycle_day.clamp(1, 28);
+    let (year, month) = if now.day() >= day {
+        (now.year(), now.month())
+    } else if now.month() == 1 {
+        (now.year() - 1, 12)
+    } else {
+        (now.year(), now.month() - 1)
+    };
+    Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap().timestamp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cycle_start() {
+        let at = |y, m, d| Utc.with_ymd_and_hms(y, m, d, 12, 0, 0).unwrap();
+        assert_eq!(cycle_start(at(2026, 3, 10), 15), at(2026, 2, 15).timestamp() - 12 * 3600);
+        assert_eq!(cycle_start(at(2026, 1, 5), 15), at(2025, 12, 15).timestamp() - 12 * 3600);
+        assert_eq!(cycle_start(at(2026, 3, 31), 31), at(2026, 3, 28).timestamp() - 12 * 3600);
+    }
+
+    #[test]
+    fn test_degrades_as_budget_runs_down_and_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = BudgetConfig {
+            monthly_mb: 100,
+            camera_mb: 0,
+            state_path: dir.path().join("budget.json").to_string_lossy().to_string(),
+            ..BudgetConfig::default()
+        };
+
+        let budget = DataBudget::load(&config);
+        budget.record(DataCategory::Telemetry, 50 * MB as usize);
+        assert_eq!(budget.degradation(), Degradation::NONE);
+
+        budget.record(DataCategory::Ota, 15 * MB as usize);
+        let d = budget.degradation();
+        assert!(d.defer_ota && !d.drop_video);
+
+        budget.record(DataCategory::Telemetry, 30 * MB as usize);
+        let d = budget.degradation();
+        assert!(d.drop_video && d.sensor_every_nth > 1);
+
+        budget.persist().unwrap();
+        let restored = DataBudget::load(&config);
+        assert_eq!(restored.usage(), budget.usage());
+        assert_eq!(restored.degradation(), d);
+    }
+}
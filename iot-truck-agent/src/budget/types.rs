@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+/// Traffic category metered against the cellular data plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataCategory {
+    /// Batches on MQTT or the HTTP fallback.
+    Telemetry,
+    /// Camera blob uploads.
+    Camera,
+    /// Update downloads.
+    Ota,
+}
+
+impl DataCategory {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DataCategory::Telemetry => "telemetry",
+            DataCategory::Camera => "camera",
+            DataCategory::Ota => "ota",
+        }
+    }
+}
+
+/// Counters for one billing cycle, persisted so a reboot does not reset them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CycleCounters {
+    /// Unix seconds at which the cycle began.
+    pub cycle_start: i64,
+    pub telemetry_bytes: u64,
+    pub camera_bytes: u64,
+    pub ota_bytes: u64,
+}
+
+impl CycleCounters {
+    pub fn new(cycle_start: i64) -> Self {
+        Self {
+            cycle_start,
+            ..Default::default()
+        }
+    }
+
+    pub fn get(&self, category: DataCategory) -> u64 {
+        match category {
+            DataCategory::Telemetry => self.telemetry_bytes,
+            DataCategory::Camera => self.camera_bytes,
+            DataCategory::Ota => self.ota_bytes,
+        }
+    }
+
+    pub fn add(&mut self, category: DataCategory, bytes: u64) {
+        let counter = match category {
+            DataCategory::Telemetry => &mut self.telemetry_bytes,
+            DataCategory::Camera => &mut self.camera_bytes,
+            DataCategory::Ota => &mut self.ota_bytes,
+        };
+        *counter = counter.saturating_add(bytes);
+    }
+
+    pub fn total(&self) -> u64 {
+        self.telemetry_bytes + self.camera_bytes + self.ota_bytes
+    }
+}
+
+/// What the agent gives up to stay within the plan. Telemetry itself is never
+/// cut off: it carries the safety events the budget exists to protect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Degradation {
+    pub defer_ota: bool,
+    pub drop_video: bool,
+    /// Send one bulk sensor sample in this many; 1 sends all.
+    pub sensor_every_nth: u32,
+}
+
+impl Degradation {
+    pub const NONE: Degradation = Degradation {
+        defer_ota: false,
+        drop_video: false,
+        sensor_every_nth: 1,
+    };
+
+    pub fn is_degraded(&self) -> bool {
+        *self != Self::NONE
+    }
+}
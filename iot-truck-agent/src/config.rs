@@ -28,6 +28,14 @@ pub struct Config {
     pub enable_hot_reload: bool,
 
     pub mqtt: MqttConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub stream: StreamConfig,
+    #[serde(default)]
+    pub budget: BudgetConfig,
+    #[serde(default)]
+    pub depot: DepotConfig,
     pub sensors: SensorsConfig,
     pub camera: CameraConfig,
     pub storage: StorageConfig,
@@ -41,6 +49,243 @@ pub struct MqttConfig {
     pub topic_prefix: String,
     pub qos: u8,
     pub keep_alive: u64,
+
+    /// Seconds to wait for a server ack before resending a batch.
+    #[serde(default = "default_ack_timeout_sec")]
+    pub ack_timeout_sec: u64,
+    /// Resends of rejected events before they are given up on.
+    #[serde(default = "default_max_ack_retries")]
+    pub max_ack_retries: u32,
+
+    /// Batch encoding on the MQTT link: `json` or `cbor`.
+    #[serde(default)]
+    pub encoding: truck_protocol::Encoding,
+    /// Batch encoding for the HTTP fallback.
+    #[serde(default)]
+    pub http_encoding: truck_protocol::Encoding,
+    /// Larger batches go over the HTTP fallback instead; brokers cap message size.
+    #[serde(default = "default_max_payload_kb")]
+    pub max_payload_kb: usize,
+
+    #[serde(default)]
+    pub tls: TlsConfig,
+
+    /// `auto` tries MQTT 5 and falls back to 3.1.1; `v5` or `v3` pin the version.
+    #[serde(default)]
+    pub version: truck_protocol::MqttVersion,
+    /// MQTT 5: the broker drops bulk telemetry undelivered after this long; 0 never.
+    #[serde(default = "default_bulk_expiry_sec")]
+    pub bulk_expiry_sec: u32,
+    /// MQTT 5 topic aliases per connection; 0 always sends full topics.
+    #[serde(default = "default_topic_alias_max")]
+    pub topic_alias_max: u16,
+}
+
+/// Mutual TLS to the broker. Only `ca_path` is trusted, so a certificate
+/// from any public CA is refused; the device authenticates with its own
+/// certificate, which is rotated in place by the `RotateCertificate` command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsConfig {
+    pub enable: bool,
+    pub ca_path: String,
+    pub cert_path: String,
+    pub key_path: String,
+    /// Health warning this many days before the device certificate expires...
+    pub expiry_warning_days: u32,
+    /// ...and a critical alert from this many days.
+    pub expiry_critical_days: u32,
+    /// A rotated certificate the broker does not accept by then is rolled back.
+    pub rotation_timeout_sec: u64,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            ca_path: "/etc/truck-agent/tls/ca.pem".to_string(),
+            cert_path: "/var/lib/truck-agent/tls/device.pem".to_string(),
+            key_path: "/var/lib/truck-agent/tls/device.key".to_string(),
+            expiry_warning_days: 30,
+            expiry_critical_days: 7,
+            rotation_timeout_sec: 60,
+        }
+    }
+}
+
+/// HTTP fallback for batches and the upload path for camera blobs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpConfig {
+    pub base_url: String,
+    pub timeout_sec: u64,
+    /// Blob upload chunk size; a dropped connection costs at most one chunk.
+    pub upload_chunk_kb: usize,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "https://api.yourcompany.com".to_string(),
+            timeout_sec: 30,
+            upload_chunk_kb: 256,
+        }
+    }
+}
+
+/// Lane scheduling: critical events are always sent at once, high-priority
+/// events in small batches, bulk traffic only on a usable link.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamConfig {
+    pub high_batch_events: usize,
+    pub high_flush_ms: u64,
+    /// Below this estimated bandwidth, sensor bulk and camera blobs wait.
+    pub bulk_min_bandwidth_kbps: f32,
+    /// Bulk batches held back beyond this are spilled to the WAL.
+    pub max_bulk_backlog: usize,
+    /// Acked event ids remembered across restarts so WAL replay skips them.
+    #[serde(default = "default_dedup_window")]
+    pub dedup_window: usize,
+    #[serde(default)]
+    pub sampling: SamplingConfig,
+}
+
+fn default_dedup_window() -> usize {
+    100_000
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            high_batch_events: 10,
+            high_flush_ms: 100,
+            bulk_min_bandwidth_kbps: 64.0,
+            max_bulk_backlog: 50,
+            dedup_window: default_dedup_window(),
+            sampling: SamplingConfig::default(),
+        }
+    }
+}
+
+/// Bulk sensor publish rates by link quality. A link is fair or poor once
+/// any one of its thresholds is crossed; a good link sends everything.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SamplingConfig {
+    pub enable: bool,
+    pub fair_bandwidth_kbps: f32,
+    pub fair_latency_ms: f32,
+    pub fair_loss_percent: f32,
+    pub poor_bandwidth_kbps: f32,
+    pub poor_latency_ms: f32,
+    pub poor_loss_percent: f32,
+    pub fair: SensorIntervals,
+    pub poor: SensorIntervals,
+}
+
+/// Least time between two sent readings of a sensor; 0 sends every reading.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SensorIntervals {
+    pub gps_ms: u32,
+    pub obd_ms: u32,
+    pub imu_ms: u32,
+    pub tpms_ms: u32,
+    #[serde(default)]
+    pub j1939_ms: u32,
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            enable: true,
+            fair_bandwidth_kbps: 512.0,
+            fair_latency_ms: 500.0,
+            fair_loss_percent: 2.0,
+            poor_bandwidth_kbps: 128.0,
+            poor_latency_ms: 2000.0,
+            poor_loss_percent: 10.0,
+            fair: SensorIntervals {
+                gps_ms: 500,
+                obd_ms: 1000,
+                imu_ms: 200,
+                tpms_ms: 0,
+                j1939_ms: 1000,
+            },
+            poor: SensorIntervals {
+                gps_ms: 1000,
+                obd_ms: 5000,
+                imu_ms: 1000,
+                tpms_ms: 0,
+                j1939_ms: 5000,
+            },
+        }
+    }
+}
+
+/// Cellular data plan. Percentages are of `monthly_mb`; per-category caps
+/// of 0 mean the category is only bounded by the plan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetConfig {
+    pub enable: bool,
+    pub monthly_mb: u64,
+    /// Day of month (1-28) the plan resets, UTC.
+    pub billing_cycle_day: u32,
+    pub camera_mb: u64,
+    pub ota_mb: u64,
+    pub defer_ota_at_percent: f32,
+    pub drop_video_at_percent: f32,
+    pub reduce_sensor_rate_at_percent: f32,
+    /// Once sensor rate is reduced, one bulk sample in this many is sent.
+    pub reduced_sensor_every_nth: u32,
+    pub state_path: String,
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            enable: true,
+            monthly_mb: 2048,
+            billing_cycle_day: 1,
+            camera_mb: 1024,
+            ota_mb: 512,
+            defer_ota_at_percent: 60.0,
+            drop_video_at_percent: 80.0,
+            reduce_sensor_rate_at_percent: 90.0,
+            reduced_sensor_every_nth: 5,
+            state_path: "/var/lib/truck-agent/budget.json".to_string(),
+        }
+    }
+}
+
+/// Yard Wi-Fi. The truck is at the depot while `interface` is up, joined to
+/// `ssid` (empty accepts any) and `probe_endpoint` answers a TCP connect.
+/// There the WAL backlog and deferred camera clips are drained; on cellular
+/// they wait.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepotConfig {
+    pub enable: bool,
+    pub interface: String,
+    pub ssid: String,
+    /// `host:port`
+    pub probe_endpoint: String,
+    pub probe_interval_sec: u64,
+    pub probe_timeout_ms: u64,
+    /// WAL entries per backlog batch.
+    pub drain_batch_events: usize,
+    /// Hold bulk camera clips in the WAL until the depot link is up.
+    pub defer_camera_on_cellular: bool,
+}
+
+impl Default for DepotConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            interface: "wlan0".to_string(),
+            ssid: String::new(),
+            probe_endpoint: "depot-sync.local:443".to_string(),
+            probe_interval_sec: 30,
+            probe_timeout_ms: 2000,
+            drain_batch_events: 500,
+            defer_camera_on_cellular: true,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,6 +294,361 @@ pub struct SensorsConfig {
     pub obd_device: String,
     pub imu_device: String,
     pub sample_rate_hz: u32,
+
+    #[serde(default)]
+    pub gnss: GnssConfig,
+    #[serde(default)]
+    pub imu: ImuConfig,
+    #[serde(default)]
+    pub replay: ReplayConfig,
+    #[serde(default)]
+    pub tpms: TpmsConfig,
+    #[serde(default)]
+    pub j1939: J1939Config,
+    #[serde(default)]
+    pub obd: ObdConfig,
+    #[serde(default)]
+    pub obd_dtc: DtcConfig,
+    #[serde(default)]
+    pub fusion: FusionConfig,
+}
+
+/// GNSS receiver on `gps_device`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GnssConfig {
+    pub protocol: GnssProtocol,
+    pub baud_rate: u32,
+    /// Navigation rate set on the receiver in UBX mode.
+    pub update_rate_hz: u8,
+    /// The satellites in view are attached to one fix per interval.
+    pub sky_view_interval_sec: u64,
+}
+
+impl Default for GnssConfig {
+    fn default() -> Self {
+        Self {
+            protocol: GnssProtocol::Nmea,
+            baud_rate: 9600,
+            update_rate_hz: 1,
+            sky_view_interval_sec: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GnssProtocol {
+    /// GGA, RMC, GSA, GSV, VTG and GST sentences, as any receiver sends them.
+    #[default]
+    Nmea,
+    /// u-blox binary NAV-PVT, NAV-DOP and NAV-SAT; the receiver is configured
+    /// on open.
+    Ubx,
+}
+
+/// IMU on the `imu_device` I2C bus.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImuConfig {
+    pub chip: ImuChip,
+    /// 7-bit I2C address; the chip's usual one when unset.
+    pub address: Option<u8>,
+    /// Mounting learned on the first drive after installation. Delete the
+    /// file to calibrate again once the unit has been moved.
+    pub calibration_path: String,
+}
+
+impl Default for ImuConfig {
+    fn default() -> Self {
+        Self {
+            chip: ImuChip::Lis3dh,
+            address: None,
+            calibration_path: "/var/lib/truck-agent/imu_mounting.json".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImuChip {
+    /// Accelerometer only.
+    #[default]
+    Lis3dh,
+    Mpu6050,
+    /// Also the LSM6DS3TR-C.
+    Lsm6ds3,
+}
+
+/// Position filter over GNSS, IMU and wheel speed. IMU samples count once
+/// the mounting is calibrated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FusionConfig {
+    pub enable: bool,
+    pub publish_interval_ms: u64,
+    /// Events are held this long and processed in timestamp order, since
+    /// each sensor publishes from its own task.
+    pub reorder_ms: u64,
+    /// Without an accepted fix for longer, the output is dead-reckoned.
+    pub gnss_timeout_ms: u64,
+    /// Output stops after this long without a fix and resumes at the next.
+    pub max_dead_reckoning_sec: u64,
+    pub yaw_rate: YawRateSource,
+}
+
+impl Default for FusionConfig {
+    fn default() -> Self {
+        Self {
+            enable: true,
+            publish_interval_ms: 1000,
+            reorder_ms: 200,
+            gnss_timeout_ms: 3000,
+            max_dead_reckoning_sec: 600,
+            yaw_rate: YawRateSource::LateralAccel,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum YawRateSource {
+    /// `gyro_z` of a 6-axis IMU.
+    Gyro,
+    /// Lateral acceleration over speed, for accelerometer-only IMUs such as
+    /// the LIS3DH.
+    #[default]
+    LateralAccel,
+}
+
+/// ELM327 polling. The default catalogue is the six PIDs of every reading.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ObdConfig {
+    /// Skip catalogue PIDs the ECU does not list as supported (PIDs 00, 20, 40, 60).
+    pub discover_supported: bool,
+    /// Ask for up to six PIDs per request when the ECU answers that way (CAN only).
+    pub multi_pid: bool,
+    pub pids: Vec<PidSpec>,
+}
+
+impl Default for ObdConfig {
+    fn default() -> Self {
+        let pid = |pid, name: &str, bytes, formula: &str, interval_ms| PidSpec {
+            pid,
+            name: name.to_string(),
+            bytes,
+            formula: formula.to_string(),
+            interval_ms,
+        };
+        Self {
+            discover_supported: true,
+            multi_pid: true,
+            pids: vec![
+                pid(0x0C, "rpm", 2, "(256*A+B)/4", 0),
+                pid(0x0D, "speed_kmh", 1, "A", 0),
+                pid(0x04, "engine_load", 1, "A*100/255", 0),
+                pid(0x11, "throttle_pos", 1, "A*100/255", 0),
+                pid(0x05, "coolant_temp", 1, "A-40", 1000),
+                pid(0x2F, "fuel_level", 1, "A*100/255", 10000),
+            ],
+        }
+    }
+}
+
+/// One mode 01 PID.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PidSpec {
+    pub pid: u8,
+    /// rpm, speed_kmh, coolant_temp, fuel_level, engine_load and throttle_pos
+    /// fill the OBD reading's own fields; other names go to its `extra` map.
+    pub name: String,
+    /// Data bytes in the response, `A` to `D` in the formula.
+    pub bytes: u8,
+    pub formula: String,
+    /// Polled at most this often; 0 polls on every reading.
+    #[serde(default)]
+    pub interval_ms: u64,
+}
+
+/// OBD-II trouble code polling on the ELM327 adapter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DtcConfig {
+    pub enable: bool,
+    pub poll_interval_sec: u64,
+    /// Also read the mode 02 freeze frame while a stored code is present.
+    pub freeze_frame: bool,
+    /// Every remote clear is appended here as a JSON line.
+    pub audit_log: String,
+}
+
+impl Default for DtcConfig {
+    fn default() -> Self {
+        Self {
+            enable: true,
+            poll_interval_sec: 30,
+            freeze_frame: true,
+            audit_log: "/var/lib/truck-agent/dtc_audit.jsonl".to_string(),
+        }
+    }
+}
+
+/// Recordings played back in place of the devices; an empty path keeps the
+/// device for that sensor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReplayConfig {
+    /// Raw NMEA log.
+    pub gps: String,
+    /// ELM327 transcript.
+    pub obd: String,
+    /// CSV accelerometer capture.
+    pub imu: String,
+    /// Playback rate relative to the recording; 0 plays without pauses.
+    pub speed: f64,
+    /// Start over at the end of a recording instead of stopping the sensor.
+    pub repeat: bool,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            gps: String::new(),
+            obd: String::new(),
+            imu: String::new(),
+            speed: 1.0,
+            repeat: false,
+        }
+    }
+}
+
+/// Tire pressure monitoring, from the TPMS ECU on the J1939 bus or from a
+/// 433 MHz RF receiver on a serial port.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TpmsConfig {
+    pub source: TpmsSource,
+    pub can_interface: String,
+    /// Frames decoded as tire reports; the first match wins.
+    pub can_frames: Vec<TpmsFrameConfig>,
+    pub rf_device: String,
+    pub rf_baud_rate: u32,
+    /// RF sensor IDs and the tire each is fitted to.
+    pub rf_sensors: Vec<RfSensorConfig>,
+    /// How often the current set of tires is published.
+    pub publish_interval_ms: u64,
+    /// Tires not heard from for this long are dropped, e.g. after the
+    /// trailer is uncoupled.
+    pub stale_after_sec: u64,
+    /// Alert thresholds on top of whatever the TPMS flags itself.
+    pub low_pressure_psi: f32,
+    pub high_temperature_c: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TpmsSource {
+    #[default]
+    None,
+    Can,
+    Rf,
+}
+
+/// Frames whose identifier matches `id` under `mask`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TpmsFrameConfig {
+    pub id: u32,
+    #[serde(default = "default_can_mask")]
+    pub mask: u32,
+    /// Vehicle unit the frames report on: 0 is the tractor, 1 the first trailer.
+    #[serde(default)]
+    pub unit: u8,
+    pub decoder: TpmsDecoder,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TpmsDecoder {
+    /// J1939 PGN 65268 (TIRE), sent by most truck TPMS ECUs and trailer gateways.
+    J1939Tire,
+    /// One tire per frame at fixed byte offsets, for proprietary frames. The
+    /// location byte is encoded as in J1939: axle in the high nibble,
+    /// position in the low nibble.
+    Bytes {
+        location_byte: usize,
+        pressure_byte: usize,
+        pressure_kpa_per_bit: f32,
+        temperature_byte: usize,
+        temperature_offset_c: f32,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RfSensorConfig {
+    pub id: u32,
+    #[serde(default)]
+    pub unit: u8,
+    pub axle: u8,
+    pub position: u8,
+}
+
+impl Default for TpmsConfig {
+    fn default() -> Self {
+        Self {
+            source: TpmsSource::None,
+            can_interface: "can0".to_string(),
+            can_frames: vec![TpmsFrameConfig {
+                id: 0x18FE_F400, // PGN 65268 from any source address
+                mask: 0x03FF_FF00,
+                unit: 0,
+                decoder: TpmsDecoder::J1939Tire,
+            }],
+            rf_device: "/dev/ttyUSB2".to_string(),
+            rf_baud_rate: 19200,
+            rf_sensors: Vec::new(),
+            publish_interval_ms: 5000,
+            stale_after_sec: 900,
+            low_pressure_psi: 80.0,
+            high_temperature_c: 85.0,
+        }
+    }
+}
+
+/// SAE J1939 on the truck's CAN bus.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct J1939Config {
+    pub enable: bool,
+    pub interface: String,
+    /// Address claimed on the bus. If an ECU with a lower NAME claims it,
+    /// an arbitrary-address-capable agent moves to a free one in 128–247.
+    pub preferred_address: u8,
+    /// NAME fields; give every truck's agent its own identity number.
+    pub identity_number: u32,
+    pub manufacturer_code: u16,
+    pub function: u8,
+    pub arbitrary_address_capable: bool,
+    pub publish_interval_ms: u64,
+    /// How often engine hours, fuel used and distance are requested; ECUs
+    /// only send them when asked.
+    pub request_interval_sec: u64,
+}
+
+impl Default for J1939Config {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            interface: "can0".to_string(),
+            preferred_address: 0x80,
+            identity_number: 0,
+            manufacturer_code: 0,
+            function: 0,
+            arbitrary_address_capable: true,
+            publish_interval_ms: 1000,
+            request_interval_sec: 60,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,6 +664,23 @@ pub struct StorageConfig {
     pub wal_path: String,
     pub max_wal_size_mb: u64,
     pub checkpoint_interval_sec: u64,
+
+    #[serde(default)]
+    pub enable_encryption: bool,
+    /// Keystore for WAL encryption keys; keep it off the WAL volume if possible.
+    #[serde(default = "default_wal_key_dir")]
+    pub wal_key_dir: String,
+
+    /// Acked IMU/OBD samples older than this are folded into per-window summaries.
+    /// Nothing is acked while the truck is offline, so compaction frees no
+    /// space then; `max_wal_size_mb` has to hold the whole offline period raw.
+    #[serde(default = "default_compact_after_hours")]
+    pub compact_after_hours: u64,
+    #[serde(default = "default_compaction_window_sec")]
+    pub compaction_window_sec: u64,
+    /// Raw samples this close to an alert or ML event are always kept.
+    #[serde(default = "default_preserve_around_event_sec")]
+    pub preserve_around_event_sec: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -74,6 +691,16 @@ pub struct AlertsConfig {
 }
 
 fn default_true() -> bool { true }
+fn default_can_mask() -> u32 { 0x1FFF_FFFF }
+fn default_wal_key_dir() -> String { "/var/lib/truck-agent/keys".to_string() }
+fn default_compact_after_hours() -> u64 { 2 }
+fn default_compaction_window_sec() -> u64 { 60 }
+fn default_preserve_around_event_sec() -> u64 { 30 }
+fn default_ack_timeout_sec() -> u64 { 60 }
+fn default_max_ack_retries() -> u32 { 5 }
+fn default_max_payload_kb() -> usize { 256 }
+fn default_bulk_expiry_sec() -> u32 { 600 }
+fn default_topic_alias_max() -> u16 { 8 }
 
 impl Default for Config {
     fn default() -> Self {
@@ -87,12 +714,33 @@ impl Default for Config {
                 topic_prefix: "truck".to_string(),
                 qos: 1,
                 keep_alive: 30,
+                ack_timeout_sec: default_ack_timeout_sec(),
+                max_ack_retries: default_max_ack_retries(),
+                encoding: truck_protocol::Encoding::Json,
+                http_encoding: truck_protocol::Encoding::Json,
+                max_payload_kb: default_max_payload_kb(),
+                tls: TlsConfig::default(),
+                version: truck_protocol::MqttVersion::Auto,
+                bulk_expiry_sec: default_bulk_expiry_sec(),
+                topic_alias_max: default_topic_alias_max(),
             },
+            http: HttpConfig::default(),
+            stream: StreamConfig::default(),
+            budget: BudgetConfig::default(),
+            depot: DepotConfig::default(),
             sensors: SensorsConfig {
                 gps_device: "/dev/ttyUSB0".to_string(),
                 obd_device: "/dev/ttyUSB1".to_string(),
                 imu_device: "/dev/i2c-1".to_string(),
                 sample_rate_hz: 10,
+                gnss: GnssConfig::default(),
+                imu: ImuConfig::default(),
+                replay: ReplayConfig::default(),
+                tpms: TpmsConfig::default(),
+                j1939: J1939Config::default(),
+                obd: ObdConfig::default(),
+                obd_dtc: DtcConfig::default(),
+                fusion: FusionConfig::default(),
             },
             camera: CameraConfig {
                 devices: vec!["/dev/video0".to_string()],
@@ -104,6 +752,11 @@ impl Default for Config {
                 wal_path: "/var/lib/truck-agent/wal".to_string(),
                 max_wal_size_mb: 1024,
                 checkpoint_interval_sec: 300,
+                enable_encryption: false,
+                wal_key_dir: default_wal_key_dir(),
+                compact_after_hours: default_compact_after_hours(),
+                compaction_window_sec: default_compaction_window_sec(),
+                preserve_around_event_sec: default_preserve_around_event_sec(),
             },
             alerts: AlertsConfig {
                 enable_local_alerts: true,
@@ -1,14 +1,24 @@
 use crate::health::types::{AlertInfo, AlertSeverity, HealthAction, ActionType};
 use crate::health::config::HealthConfig;
+use crate::wal::retention::RetentionRequest;
+use tokio::sync::mpsc;
 use tracing::{error, warn};
 
 pub struct DiskPressureManager {
     config: HealthConfig,
+    wal_retention: Option<mpsc::Sender<RetentionRequest>>,
 }
 
 impl DiskPressureManager {
     pub fn new(config: HealthConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            wal_retention: None,
+        }
+    }
+
+    pub fn set_wal_retention(&mut self, trigger: mpsc::Sender<RetentionRequest>) {
+        self.wal_retention = Some(trigger);
     }
 
     pub fn check_disk_pressure(&self, disk_percent: f32) -> (Vec<AlertInfo>, Vec<HealthAction>) {
@@ -27,14 +37,25 @@ impl DiskPressureManager {
             });
 
             if disk_percent > self.config.disk.wal_rotate_early_at_percent {
+                // Free enough WAL to get back under the early-rotation mark, plus headroom
+                let over = disk_percent - self.config.disk.wal_rotate_early_at_percent;
+                let request = RetentionRequest {
+                    free_fraction: ((over + 5.0) / 100.0).min(0.5),
+                    reason: format!("disk_pressure_{:.0}pct", disk_percent),
+                };
+                let success = match &self.wal_retention {
+                    Some(trigger) => trigger.try_send(request).is_ok(),
+                    None => false,
+                };
+
                 actions.push(HealthAction {
                     action_id: format!("wal-rotate-{}", chrono::Utc::now().timestamp_nanos()),
                     action_type: ActionType::RotateWalEarly,
                     target_module: "wal".to_string(),
                     parameters: serde_json::json!({"reason": "disk_pressure"}),
                     executed_at: chrono::Utc::now().timestamp_nanos() as u64,
-                    success: false,
-                    message: "Forcing WAL checkpoint due to disk pressure".to_string(),
+                    success,
+                    message: "Evicting WAL entries by priority due to disk pressure".to_string(),
                 });
             }
 
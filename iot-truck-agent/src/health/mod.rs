@@ -45,6 +45,7 @@ pub struct HealthManager {
     adaptive_controller: AdaptiveController,
     alert_manager: AlertManager,
     snapshotter: HealthSnapshotter,
+    reported_alerts: std::sync::Mutex<Vec<crate::health::types::AlertInfo>>,
     tx: broadcast::Sender<StreamEvent>,
     config: Config,
     mqtt_client: Option<Arc<rumqttc::AsyncClient>>,
@@ -78,12 +79,18 @@ impl HealthManager {
             adaptive_controller,
             alert_manager,
             snapshotter,
+            reported_alerts: std::sync::Mutex::new(Vec::new()),
             tx,
             config,
             mqtt_client,
         })
     }
 
+    /// Lets disk pressure handling evict WAL entries directly.
+    pub fn set_wal_retention(&mut self, trigger: tokio::sync::mpsc::Sender<crate::wal::retention::RetentionRequest>) {
+        self.disk_pressure_manager.set_wal_retention(trigger);
+    }
+
     pub fn get_task_supervisor(&self) -> &TaskSupervisor {
         &self.task_supervisor
     }
@@ -92,6 +99,11 @@ impl HealthManager {
         &mut self.task_supervisor
     }
 
+    /// Queues alerts raised outside the health loop (e.g. WAL recovery) for the next `HealthEvent`.
+    pub fn report_alerts(&self, alerts: Vec<crate::health::types::AlertInfo>) {
+        self.reported_alerts.lock().unwrap().extend(alerts);
+    }
+
     pub async fn start_monitoring(&self) -> Result<(), Box<dyn std::error::Error>> {
         let interval = Duration::from_millis(self.config.health.interval_ms);
         info!(interval=?interval, "⏱️  Starting comprehensive health monitoring loop");
@@ -166,6 +178,7 @@ impl HealthManager {
         all_alerts.extend(task_alerts);
         all_alerts.extend(thermal_alerts);
         all_alerts.extend(disk_alerts);
+        all_alerts.extend(self.reported_alerts.lock().unwrap().drain(..));
 
         // Process alerts (debounce, log, trigger local)
         let processed_alerts = self.alert_manager.process_alerts(all_alerts);
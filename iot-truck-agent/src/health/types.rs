@@ -191,6 +191,7 @@ pub struct HealthAction {
     pub action_id: String,
     pub action_type: ActionType,
     pub target_module: String,
+    #[serde(with = "crate::wal::json_string")]
     pub parameters: serde_json::Value,
     pub executed_at: u64,
     pub success: bool,
@@ -1,4 +1,5 @@
 pub mod alert;
+pub mod budget;
 pub mod camera;
 pub mod config;
 pub mod health;
@@ -1,9 +1,11 @@
+use clap::{Parser, Subcommand};
 use iot_truck_agent::telemetry;
 use std::sync::Arc;
 use tokio;
 use tracing::{Level, info};
 
 mod alert;
+mod budget;
 mod camera;
 mod config;
 mod health;
@@ -14,8 +16,32 @@ mod stream;
 mod supervisor;
 mod wal;
 
+#[derive(Parser)]
+#[command(name = "iot-truck-agent", version, about = "Truck IoT edge agent")]
+struct Cli {
+    #[arg(long, default_value = "config/agent.toml")]
+    config: String,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Inspect or repair the local WAL (agent must be stopped)
+    Wal(wal::cli::WalArgs),
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+
+    // Maintenance commands run instead of the agent
+    if let Some(Command::Wal(args)) = cli.command {
+        let config = config::Config::load_from_file(&cli.config)?;
+        return wal::cli::run(args, &config.storage).await;
+    }
+
     // Initialize logging and metrics
     telemetry::init_tracing();
     telemetry::init_metrics("0.0.0.0:9090".parse().unwrap())?;
@@ -23,7 +49,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("🚛 Starting Truck IoT Agent — COMPLETE SYSTEM");
 
     // Load configuration
-    let config_path = "config/agent.toml";
+    let config_path = cli.config.as_str();
     let config = config::Config::load_from_file(config_path).map_err(|e| {
         tracing::error!(error = %e, "❌ Failed to load config — CRASHING");
         e
@@ -41,7 +67,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let (stream_tx, stream_rx) = tokio::sync::broadcast::channel(1000);
     let (alert_tx, alert_rx) = tokio::sync::broadcast::channel(1000);
     let (ota_command_tx, ota_command_rx) = tokio::sync::mpsc::channel(100);
-    let (ota_response_tx, _ota_response_rx) = tokio::sync::mpsc::channel(100);
+    let (ota_response_tx, ota_response_rx) = tokio::sync::mpsc::channel(100);
+    let (dtc_clear_tx, dtc_clear_rx) = tokio::sync::mpsc::channel(4);
 
     // Create shared resource usage for ML and health modules
     let resource_usage = Arc::new(tokio::sync::RwLock::new(health::types::ResourceUsage {
@@ -66,9 +93,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let wal_manager = wal::WalManager::new(&config, resource_usage.clone()).await?;
     let wal_manager_clone = wal_manager.clone();
 
-    // Initialize Stream Manager
+    // Cellular data budget, shared by everything that sends over the link
+    let data_budget = budget::DataBudget::load(&config.budget);
+    data_budget.spawn_persister();
+
+    // TLS identity towards the broker, rotated by remote command
+    let device_identity = stream::auth::DeviceIdentity::new(&config.mqtt.tls, &config.device_id);
+    if device_identity.is_enabled() {
+        if let Err(e) = device_identity.recover() {
+            tracing::error!(error = %e, "❌ Device TLS identity unusable");
+        }
+    }
+
+    // Initialize Stream Manager; remote commands and their responses also use its MQTT link
     info!("📡 Initializing Stream Manager");
-    let mut stream_manager = stream::StreamManager::new(&config, wal_manager.clone()).await?;
+    let mut stream_manager = stream::StreamManager::new(
+        &config,
+        wal_manager.clone(),
+        data_budget.clone(),
+        device_identity.clone(),
+        ota_command_tx,
+        ota_response_rx,
+    )
+    .await?;
     let stream_manager_clone = stream_manager.clone();
 
     // Initialize ML Edge Manager
@@ -79,8 +126,42 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Initialize Health Manager
     info!("🏥 Initializing Health Manager");
-    let health_manager = health::HealthManager::new(config.clone(), health_tx.clone()).await?;
+    let mut health_manager = health::HealthManager::new(config.clone(), health_tx.clone()).await?;
+    health_manager.report_alerts(wal_manager.recovery_alerts());
+    health_manager.set_wal_retention(wal_manager.retention_trigger());
     let health_manager_clone = health_manager.clone();
+
+    // Tell the server about unacked WAL data lost to retention
+    if let Some(mut reports) = wal_manager.take_retention_reports() {
+        let stream_manager = stream_manager.clone();
+        let device_id = config.device_id.clone();
+        tokio::spawn(async move {
+            let mut sequence_number = 1;
+            while let Some(stats) = reports.recv().await {
+                let Some(loss) = stats.to_data_loss() else {
+                    continue;
+                };
+                let event = stream::types::StreamEvent::new_data_loss(loss, &device_id, sequence_number);
+                sequence_number += 1;
+                if let Err(e) = stream_manager.send_event(event).await {
+                    tracing::error!(error = %e, "Failed to queue WAL data loss report for streaming");
+                }
+            }
+        });
+    }
+
+    // Warn well before the device certificate expires and the truck goes dark
+    {
+        let health_manager = health_manager.clone();
+        let device_identity = device_identity.clone();
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(std::time::Duration::from_secs(3600));
+            loop {
+                tick.tick().await;
+                health_manager.report_alerts(device_identity.expiry_alerts());
+            }
+        });
+    }
     let task_supervisor = health_manager.get_task_supervisor_mut();
 
     // Initialize Alert Manager
@@ -103,6 +184,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         network_health.clone(),
         ota_command_rx,
         ota_response_tx,
+        data_budget.clone(),
+        device_identity.clone(),
+        dtc_clear_tx,
     )
     .await?;
     let ota_manager_clone = ota_manager.clone();
@@ -133,7 +217,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let sensor_monitor_clone = sensor_monitor.clone();
 
     tokio::spawn(async move {
-        if let Err(e) = sensors::start_sensor_engine(&config_clone1, sensor_tx_clone1).await {
+        if let Err(e) = sensors::start_sensor_engine(&config_clone1, sensor_tx_clone1, dtc_clear_rx).await {
             tracing::error!(error = %e, "Sensor engine crashed");
             supervisor_manager.emergency_shutdown().await;
         }
@@ -323,16 +407,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let ml_manager = ml_manager_clone1;
         let alert_manager = alert_manager_clone1;
         let sensor_monitor = sensor_monitor_clone;
-        let sequence_number = std::sync::atomic::AtomicU64::new(1);
+        let mut last_seq = 0u64;
 
         while let Ok(event) = rx.recv().await {
+            // The WAL entry and the stream event share the sequence number, so
+            // the server's ack finds the entry; nanoseconds stay unique across restarts
+            let seq = (chrono::Utc::now().timestamp_nanos() as u64).max(last_seq + 1);
+            last_seq = seq;
+
             // Write to WAL
-            if let Err(e) = wal_manager.write_sensor(event.clone()).await {
+            if let Err(e) = wal_manager.write_sensor(event.clone(), seq).await {
                 tracing::error!(error = %e, "Failed to write sensor to WAL");
             }
 
             // Send to streamer
-            let seq = sequence_number.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
             if let Err(e) = stream_manager
                 .send_event(stream::types::StreamEvent::new_sensor(
                     event.clone(),
@@ -1,7 +1,7 @@
 use crate::camera::types::CameraFrame;
 use crate::ml_edge::types::SensorContext;
-use crate::sensors::types::{SensorEvent, SensorValues};
-use chrono::Utc;
+use crate::sensors::types::{GpsData, ImuData, ObdData, SensorEvent};
+use chrono::{DateTime, Timelike, Utc};
 use std::collections::VecDeque;
 
 pub struct SensorFusion {
@@ -24,13 +24,22 @@ impl SensorFusion {
         self.sensor_buffer.push_back(event);
     }
 
+    /// Removes and returns the events stamped before `cutoff`, oldest first.
+    /// Sources publish from their own tasks, so the buffer is only roughly in
+    /// timestamp order.
+    pub fn drain_before(&mut self, cutoff: DateTime<Utc>) -> Vec<SensorEvent> {
+        self.sensor_buffer.make_contiguous().sort_by_key(|e| e.timestamp);
+        let settled = self.sensor_buffer.partition_point(|e| e.timestamp < cutoff);
+        self.sensor_buffer.drain(..settled).collect()
+    }
+
     pub fn get_context_for_frame(&self, frame: &CameraFrame) -> Option<SensorContext> {
         let frame_time = frame.timestamp;
 
         // Find sensor events closest to frame time
-        let mut closest_gps = None;
-        let mut closest_obd = None;
-        let mut closest_imu = None;
+        let mut closest_gps: Option<(i64, GpsData)> = None;
+        let mut closest_obd: Option<(i64, ObdData)> = None;
+        let mut closest_imu: Option<(i64, ImuData)> = None;
 
         for event in &self.sensor_buffer {
             let event_time = event.timestamp;
@@ -43,33 +52,18 @@ impl SensorFusion {
 
             match &event.values {
                 crate::sensors::types::SensorValues::Gps(gps) => {
-                    if closest_gps.is_none()
-                        || (event_time - frame_time).num_milliseconds().abs()
-                            < (closest_gps.unwrap().0.timestamp - frame_time)
-                                .num_milliseconds()
-                                .abs()
-                    {
-                        closest_gps = Some((event.clone(), gps.clone()));
+                    if closest_gps.as_ref().is_none_or(|(diff, _)| time_diff < *diff) {
+                        closest_gps = Some((time_diff, gps.clone()));
                     }
                 }
                 crate::sensors::types::SensorValues::Obd(obd) => {
-                    if closest_obd.is_none()
-                        || (event_time - frame_time).num_milliseconds().abs()
-                            < (closest_obd.unwrap().0.timestamp - frame_time)
-                                .num_milliseconds()
-                                .abs()
-                    {
-                        closest_obd = Some((event.clone(), obd.clone()));
+                    if closest_obd.as_ref().is_none_or(|(diff, _)| time_diff < *diff) {
+                        closest_obd = Some((time_diff, obd.clone()));
                     }
                 }
                 crate::sensors::types::SensorValues::Imu(imu) => {
-                    if closest_imu.is_none()
-                        || (event_time - frame_time).num_milliseconds().abs()
-                            < (closest_imu.unwrap().0.timestamp - frame_time)
-                                .num_milliseconds()
-                                .abs()
-                    {
-                        closest_imu = Some((event.clone(), imu.clone()));
+                    if closest_imu.as_ref().is_none_or(|(diff, _)| time_diff < *diff) {
+                        closest_imu = Some((time_diff, imu.clone()));
                     }
                 }
                 _ => {}
@@ -10,6 +10,7 @@ use tracing::{error, info, warn};
 pub mod types;
 pub mod error;
 pub mod engine;
+pub mod fusion;
 pub mod models;
 pub mod preprocess;
 pub mod postprocess;
@@ -1,12 +1,22 @@
 use crate::ota::types::{RemoteCommand, CommandResponse, CommandStatus};
-use crate::ota::error::Result;
+use crate::ota::error::{OtaError, Result};
+use crate::sensors::obd::dtc::ClearRequest;
+use crate::stream::auth::DeviceIdentity;
 use std::process::Command;
+use tokio::sync::{mpsc, oneshot};
 use tokio::time::{sleep, Duration};
 use tracing::{info, warn};
 
-pub struct CommandExecutor;
+pub struct CommandExecutor {
+    identity: DeviceIdentity,
+    dtc_clear: mpsc::Sender<ClearRequest>,
+}
 
 impl CommandExecutor {
+    pub fn new(identity: DeviceIdentity, dtc_clear: mpsc::Sender<ClearRequest>) -> Self {
+        Self { identity, dtc_clear }
+    }
+
     pub async fn execute_command(&self, command: &RemoteCommand) -> CommandResponse {
         info!(command_id=%command.command_id, command_type=%command.command_type, "⚡ Executing remote command");
 
@@ -35,6 +45,12 @@ impl CommandExecutor {
             crate::ota::types::CommandType::FlushWAL => {
                 self.execute_flush_wal().await
             }
+            crate::ota::types::CommandType::RotateCertificate => {
+                self.execute_rotate_certificate(command).await
+            }
+            crate::ota::types::CommandType::ClearDtcs => {
+                self.execute_clear_dtcs(command).await
+            }
         };
 
         match result {
@@ -114,4 +130,53 @@ impl CommandExecutor {
         // In production, signal WAL to flush
         Ok(serde_json::json!({"status": "WAL flushed"}))
     }
-}
\ No newline at end of file
+
+    async fn execute_rotate_certificate(&self, command: &RemoteCommand) -> Result<serde_json::Value> {
+        if !self.identity.is_enabled() {
+            return Err(OtaError::CommandFailed("MQTT TLS is not enabled".to_string()));
+        }
+
+        match command.parameters.get("action").and_then(|a| a.as_str()) {
+            Some("create_csr") => {
+                info!("🔑 Generating device key and CSR");
+                let csr = self.identity.create_csr().await
+                    .map_err(|e| OtaError::CommandFailed(e.to_string()))?;
+                Ok(serde_json::json!({"status": "csr created", "csr": csr}))
+            }
+            Some("install") => {
+                let Some(certificate) = command.parameters.get("certificate").and_then(|c| c.as_str()) else {
+                    return Err(OtaError::CommandFailed("No certificate specified".to_string()));
+                };
+                info!("🔐 Installing rotated device certificate");
+                let expires_at = self.identity.install_certificate(certificate).await
+                    .map_err(|e| OtaError::CommandFailed(e.to_string()))?;
+                Ok(serde_json::json!({"status": "certificate rotated", "expires_at": expires_at}))
+            }
+            other => {
+                warn!(action=?other, "Unknown certificate rotation action");
+                Err(OtaError::CommandFailed("action must be create_csr or install".to_string()))
+            }
+        }
+    }
+
+    async fn execute_clear_dtcs(&self, command: &RemoteCommand) -> Result<serde_json::Value> {
+        let Some(requested_by) = command.issued_by.clone() else {
+            return Err(OtaError::CommandFailed("ClearDtcs needs the issuing operator for the audit log".to_string()));
+        };
+        info!(requested_by=%requested_by, "🧹 Clearing OBD-II trouble codes");
+        let (reply, result) = oneshot::channel();
+        let request = ClearRequest {
+            command_id: command.command_id.clone(),
+            requested_by,
+            reply,
+        };
+        self.dtc_clear
+            .send(request)
+            .await
+            .map_err(|_| OtaError::CommandFailed("No OBD adapter is connected".to_string()))?;
+        result
+            .await
+            .map_err(|_| OtaError::CommandFailed("OBD reader stopped before clearing".to_string()))?
+            .map_err(OtaError::CommandFailed)
+    }
+}
@@ -1,7 +1,10 @@
+use crate::budget::DataBudget;
 use crate::config::Config;
 use crate::health::types::NetworkHealth;
-use crate::ota::error::Result;
+use crate::ota::error::{OtaError, Result};
 use crate::ota::types::{CommandResponse, OtaStatus, OtaUpdate, RemoteCommand};
+use crate::sensors::obd::dtc::ClearRequest;
+use crate::stream::auth::DeviceIdentity;
 use tokio::sync::mpsc;
 use tokio::time::{Duration, sleep};
 use tracing::{error, info, warn};
@@ -31,6 +34,9 @@ pub struct OtaManager {
     command_rx: mpsc::Receiver<RemoteCommand>,
     command_tx: mpsc::Sender<CommandResponse>,
     device_id: String,
+    budget: DataBudget,
+    identity: DeviceIdentity,
+    dtc_clear: mpsc::Sender<ClearRequest>,
 }
 
 impl OtaManager {
@@ -39,6 +45,9 @@ impl OtaManager {
         network_health: std::sync::Arc<tokio::sync::RwLock<NetworkHealth>>,
         command_rx: mpsc::Receiver<RemoteCommand>,
         command_tx: mpsc::Sender<CommandResponse>,
+        budget: DataBudget,
+        identity: DeviceIdentity,
+        dtc_clear: mpsc::Sender<ClearRequest>,
     ) -> Result<Self> {
         let device_id = config.device_id.clone();
 
@@ -50,6 +59,9 @@ impl OtaManager {
             command_rx,
             command_tx,
             device_id,
+            budget,
+            identity,
+            dtc_clear,
         })
     }
 
@@ -63,7 +75,7 @@ impl OtaManager {
                 Some(command) = self.command_rx.recv() => {
                     metrics::counter!("remote_commands_total").increment(1);
 
-                    let executor = crate::ota::command_executor::CommandExecutor;
+                    let executor = crate::ota::command_executor::CommandExecutor::new(self.identity.clone(), self.dtc_clear.clone());
                     let response = executor.execute_command(&command).await;
 
                     if let Err(e) = self.command_tx.send(response.clone()).await {
@@ -94,6 +106,12 @@ impl OtaManager {
     pub async fn apply_update(&self, update: OtaUpdate) -> Result<OtaStatus> {
         metrics::counter!("ota_updates_total").increment(1);
 
+        // Safety fixes go out regardless; everything else waits for the next billing cycle
+        if update.priority != crate::ota::types::UpdatePriority::Critical && self.budget.degradation().defer_ota {
+            warn!(update_id=%update.update_id, priority=?update.priority, "📶 Data budget low — deferring update");
+            return Err(OtaError::BandwidthLimitExceeded);
+        }
+
         let bandwidth_manager = crate::ota::bandwidth_manager::BandwidthManager::new(
             self.network_health.clone(),
             1000, // 1 Mbps max
@@ -110,6 +128,7 @@ impl OtaManager {
         let downloader = crate::ota::updater::download::BandwidthAwareDownloader::new(
             bandwidth_manager.get_max_download_bandwidth().await,
             &self.config.storage.wal_path,
+            self.budget.clone(),
         );
 
         let file_path = downloader.download_update(&update).await?;
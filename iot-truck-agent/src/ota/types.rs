@@ -71,6 +71,9 @@ pub struct RemoteCommand {
     pub issued_at: u64,
     pub deadline: Option<u64>,
     pub requires_ack: bool,
+    /// Operator the server authenticated when the command was issued.
+    #[serde(default)]
+    pub issued_by: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -83,6 +86,12 @@ pub enum CommandType {
     RunHealthCheck,
     CaptureSnapshot,
     FlushWAL,
+    /// `{"action": "create_csr"}` returns a CSR for a new device key;
+    /// `{"action": "install", "certificate": "<PEM>"}` swaps the signed certificate in.
+    RotateCertificate,
+    /// Clears OBD-II trouble codes and freeze frame; refused unless the
+    /// command names the operator who issued it, for the truck's audit log.
+    ClearDtcs,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1,3 +1,4 @@
+use crate::budget::{types::DataCategory, DataBudget};
 use crate::ota::types::OtaUpdate;
 use crate::ota::error::Result;
 use reqwest::Client;
@@ -9,10 +10,11 @@ pub struct BandwidthAwareDownloader {
     client: Client,
     max_bandwidth_kbps: u32,
     temp_dir: String,
+    budget: DataBudget,
 }
 
 impl BandwidthAwareDownloader {
-    pub fn new(max_bandwidth_kbps: u32, temp_dir: &str) -> Self {
+    pub fn new(max_bandwidth_kbps: u32, temp_dir: &str, budget: DataBudget) -> Self {
         let client = Client::builder()
             .timeout(std::time::Duration::from_secs(300))
             .build()
@@ -22,6 +24,7 @@ impl BandwidthAwareDownloader {
             client,
             max_bandwidth_kbps,
             temp_dir: temp_dir.to_string(),
+            budget,
         }
     }
 
@@ -44,6 +47,7 @@ impl BandwidthAwareDownloader {
         while let Some(item) = stream.next().await {
             let chunk = item?;
             downloaded += chunk.len() as u64;
+            self.budget.record(DataCategory::Ota, chunk.len());
 
             // Bandwidth throttling
             if self.max_bandwidth_kbps > 0 {
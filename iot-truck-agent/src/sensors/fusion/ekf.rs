@@ -0,0 +1,185 @@
+//! Extended Kalman filter on a local east/north plane. The state is the
+//! offset from the plane's origin (m), speed along the heading (m/s), heading
+//! clockwise from north (rad) and the bias of the yaw rate input (rad/s).
+
+use std::f64::consts::PI;
+
+pub const EAST: usize = 0;
+pub const NORTH: usize = 1;
+pub const SPEED: usize = 2;
+pub const HEADING: usize = 3;
+pub const YAW_BIAS: usize = 4;
+const DIM: usize = 5;
+
+const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+/// Process noise, as spectral densities: the variance each state gains per
+/// second of prediction is the square.
+pub struct ProcessNoise {
+    pub position: f64, // m/√s, wheel slip and model error
+    pub accel: f64,    // m/s²/√s
+    pub yaw_rate: f64, // rad/s/√s
+    pub yaw_bias: f64, // rad/s/√s
+}
+
+#[derive(Debug, Clone)]
+pub struct Ekf {
+    pub x: [f64; DIM],
+    pub p: [[f64; DIM]; DIM],
+}
+
+impl Ekf {
+    /// Starts at a position with its variance (m²), speed, and heading with
+    /// its variance (rad²).
+    pub fn new(east: f64, north: f64, var_position: f64, speed: f64, heading: f64, var_heading: f64) -> Self {
+        let mut p = [[0.0; DIM]; DIM];
+        p[EAST][EAST] = var_position;
+        p[NORTH][NORTH] = var_position;
+        // A fix's speed may belong to an older sentence
+        p[SPEED][SPEED] = 15.0f64.powi(2);
+        p[HEADING][HEADING] = var_heading;
+        p[YAW_BIAS][YAW_BIAS] = 0.01f64.powi(2);
+        Self {
+            x: [east, north, speed, wrap(heading), 0.0],
+            p,
+        }
+    }
+
+    /// Advances the state by `dt` seconds under a forward acceleration
+    /// (m/s²) and a clockwise yaw rate (rad/s), both held over the step.
+    pub fn predict(&mut self, dt: f64, accel: f64, yaw_rate: f64, noise: &ProcessNoise) {
+        if dt <= 0.0 {
+            return;
+        }
+        let x = self.x;
+        let turn = (yaw_rate - x[YAW_BIAS]) * dt;
+        // Heading halfway through the step, so turns do not drift outwards
+        let (sin, cos) = (x[HEADING] + turn / 2.0).sin_cos();
+        let distance = x[SPEED] * dt;
+
+        self.x[EAST] += distance * sin;
+        self.x[NORTH] += distance * cos;
+        // Trucks do not reverse far enough to matter
+        self.x[SPEED] = (x[SPEED] + accel * dt).max(0.0);
+        self.x[HEADING] = wrap(x[HEADING] + turn);
+
+        let mut f = identity();
+        f[EAST][SPEED] = dt * sin;
+        f[EAST][HEADING] = distance * cos;
+        f[EAST][YAW_BIAS] = -distance * cos * dt / 2.0;
+        f[NORTH][SPEED] = dt * cos;
+        f[NORTH][HEADING] = -distance * sin;
+        f[NORTH][YAW_BIAS] = distance * sin * dt / 2.0;
+        f[HEADING][YAW_BIAS] = -dt;
+
+        let mut p = mul_transposed(&mul(&f, &self.p), &f);
+        p[EAST][EAST] += noise.position.powi(2) * dt;
+        p[NORTH][NORTH] += noise.position.powi(2) * dt;
+        p[SPEED][SPEED] += noise.accel.powi(2) * dt;
+        p[HEADING][HEADING] += noise.yaw_rate.powi(2) * dt;
+        p[YAW_BIAS][YAW_BIAS] += noise.yaw_bias.powi(2) * dt;
+        self.p = p;
+    }
+
+    /// Innovation of a direct measurement of state `i` and its variance.
+    pub fn innovation(&self, i: usize, z: f64, var: f64) -> (f64, f64) {
+        let y = z - self.x[i];
+        let y = if i == HEADING { wrap(y) } else { y };
+        (y, self.p[i][i] + var)
+    }
+
+    /// Corrects the state with a direct measurement of state `i`.
+    pub fn update(&mut self, i: usize, z: f64, var: f64) {
+        let (y, s) = self.innovation(i, z, var);
+        let gain: [f64; DIM] = std::array::from_fn(|r| self.p[r][i] / s);
+        let row = self.p[i];
+        for (r, k) in gain.iter().enumerate() {
+            self.x[r] += k * y;
+            for (p, p_i) in self.p[r].iter_mut().zip(row) {
+                *p -= k * p_i;
+            }
+        }
+        self.x[HEADING] = wrap(self.x[HEADING]);
+        self.x[SPEED] = self.x[SPEED].max(0.0);
+        // Keep P symmetric against rounding
+        for r in 0..DIM {
+            for c in r + 1..DIM {
+                let mean = (self.p[r][c] + self.p[c][r]) / 2.0;
+                self.p[r][c] = mean;
+                self.p[c][r] = mean;
+            }
+        }
+    }
+
+    /// Moves the plane's origin by (`east`, `north`) metres.
+    pub fn shift_origin(&mut self, east: f64, north: f64) {
+        self.x[EAST] -= east;
+        self.x[NORTH] -= north;
+    }
+}
+
+/// Tangent plane for the few kilometres around its origin; the caller moves
+/// the origin along as the truck drives.
+#[derive(Debug, Clone, Copy)]
+pub struct LocalPlane {
+    lat: f64,
+    lon: f64,
+    metres_per_deg_lat: f64,
+    metres_per_deg_lon: f64,
+}
+
+impl LocalPlane {
+    pub fn new(lat: f64, lon: f64) -> Self {
+        let metres_per_deg_lat = EARTH_RADIUS_M * PI / 180.0;
+        Self {
+            lat,
+            lon,
+            metres_per_deg_lat,
+            metres_per_deg_lon: metres_per_deg_lat * lat.to_radians().cos(),
+        }
+    }
+
+    pub fn to_local(self, lat: f64, lon: f64) -> (f64, f64) {
+        let mut d_lon = lon - self.lon;
+        if d_lon > 180.0 {
+            d_lon -= 360.0;
+        } else if d_lon < -180.0 {
+            d_lon += 360.0;
+        }
+        (d_lon * self.metres_per_deg_lon, (lat - self.lat) * self.metres_per_deg_lat)
+    }
+
+    pub fn to_geodetic(self, east: f64, north: f64) -> (f64, f64) {
+        let lat = self.lat + north / self.metres_per_deg_lat;
+        let mut lon = self.lon + east / self.metres_per_deg_lon;
+        if lon > 180.0 {
+            lon -= 360.0;
+        } else if lon < -180.0 {
+            lon += 360.0;
+        }
+        (lat, lon)
+    }
+}
+
+/// Angle in (-π, π].
+pub fn wrap(angle: f64) -> f64 {
+    let wrapped = angle.rem_euclid(2.0 * PI);
+    if wrapped > PI {
+        wrapped - 2.0 * PI
+    } else {
+        wrapped
+    }
+}
+
+fn identity() -> [[f64; DIM]; DIM] {
+    std::array::from_fn(|r| std::array::from_fn(|c| if r == c { 1.0 } else { 0.0 }))
+}
+
+fn mul(a: &[[f64; DIM]; DIM], b: &[[f64; DIM]; DIM]) -> [[f64; DIM]; DIM] {
+    std::array::from_fn(|r| std::array::from_fn(|c| (0..DIM).map(|k| a[r][k] * b[k][c]).sum()))
+}
+
+/// `a * bᵀ`
+fn mul_transposed(a: &[[f64; DIM]; DIM], b: &[[f64; DIM]; DIM]) -> [[f64; DIM]; DIM] {
+    std::array::from_fn(|r| std::array::from_fn(|c| (0..DIM).map(|k| a[r][k] * b[c][k]).sum()))
+}
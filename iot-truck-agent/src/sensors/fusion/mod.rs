@@ -0,0 +1,485 @@
+//! Position fusion for tunnels and urban canyons: GNSS fixes, IMU and wheel
+//! speed from OBD-II or J1939 go through an [`ekf::Ekf`], and the estimate is
+//! published as `Position` readings beside the raw GPS ones.
+
+use crate::config::{FusionConfig, YawRateSource};
+use crate::ml_edge::fusion::SensorFusion;
+use crate::sensors::types::{
+    GnssFixType, GpsData, ImuData, PositionData, PositionSource, SensorEvent, SensorType, SensorValues,
+};
+use ekf::{Ekf, LocalPlane, ProcessNoise, EAST, HEADING, NORTH, SPEED};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{info, warn};
+
+pub mod ekf;
+
+const GRAVITY: f64 = 9.80665;
+/// Below this speed (m/s) GNSS course and lateral acceleration say nothing
+/// about heading.
+const MIN_COURSE_SPEED: f64 = 3.0;
+/// Receivers send GGA and RMC for the same epoch; the second must not count
+/// as another fix.
+const SAME_EPOCH_MS: i64 = 500;
+/// Fixes further out than this (normalised innovation over both axes) are
+/// taken as multipath, until this many in a row say otherwise.
+const GATE: f64 = 16.0;
+const MAX_REJECTED_FIXES: u32 = 5;
+/// IMU samples older than this no longer drive the prediction.
+const IMU_TIMEOUT_MS: i64 = 1000;
+/// The plane is re-centred when the truck is this far from its origin.
+const RECENTRE_M: f64 = 5000.0;
+const REORDER_BUFFER: usize = 1024;
+
+const WITH_GYRO: ProcessNoise = ProcessNoise {
+    position: 0.1,
+    accel: 0.5,
+    yaw_rate: 0.005,
+    yaw_bias: 0.0005,
+};
+// Lateral acceleration also picks up road camber and mounting tilt
+const WITH_ACCELEROMETER: ProcessNoise = ProcessNoise {
+    yaw_rate: 0.02,
+    ..WITH_GYRO
+};
+const WITHOUT_IMU: ProcessNoise = ProcessNoise {
+    position: 0.1,
+    accel: 1.0,
+    yaw_rate: 0.1,
+    yaw_bias: 0.0005,
+};
+
+struct Track {
+    ekf: Ekf,
+    plane: LocalPlane,
+}
+
+pub struct PositionFusion {
+    config: FusionConfig,
+    track: Option<Track>,
+    /// Time the state is at, ms since the epoch.
+    state_ms: i64,
+    imu: Option<(i64, ImuData)>,
+    last_wheel_ms: Option<i64>,
+    /// An ECU without the speed PID reports 0 for ever, so OBD speed only
+    /// counts once it has moved.
+    obd_speed_seen: bool,
+    last_fix: Option<(i64, f64, f64)>,
+    rejected: u32,
+    last_publish_ms: Option<i64>,
+}
+
+impl PositionFusion {
+    pub fn new(config: &FusionConfig) -> Self {
+        Self {
+            config: config.clone(),
+            track: None,
+            state_ms: 0,
+            imu: None,
+            last_wheel_ms: None,
+            obd_speed_seen: false,
+            last_fix: None,
+            rejected: 0,
+            last_publish_ms: None,
+        }
+    }
+
+    /// Feeds one event, in timestamp order. Returns the estimate when one is
+    /// due; nothing is published before the first fix.
+    pub fn process(&mut self, event: &SensorEvent) -> Option<PositionData> {
+        let now = event.timestamp.timestamp_millis();
+        match &event.values {
+            SensorValues::Gps(gps) => {
+                self.advance(now);
+                self.on_gnss(now, gps);
+            }
+            // Until the mounting is calibrated the axes are the chip's
+            SensorValues::Imu(imu) if imu.longitudinal_g.is_some() => {
+                self.advance(now);
+                self.imu = Some((now, imu.clone()));
+            }
+            SensorValues::Obd(obd) => {
+                self.obd_speed_seen |= obd.speed_kmh > 0;
+                if self.obd_speed_seen {
+                    self.advance(now);
+                    self.on_wheel_speed(now, obd.speed_kmh as f64 / 3.6);
+                }
+            }
+            SensorValues::J1939(j1939) => {
+                if let Some(speed_kmh) = j1939.wheel_speed_kmh {
+                    self.advance(now);
+                    self.on_wheel_speed(now, speed_kmh as f64 / 3.6);
+                }
+            }
+            _ => return None,
+        }
+        self.publish(now)
+    }
+
+    /// Predicts the state forward to `now` with the latest IMU sample.
+    fn advance(&mut self, now: i64) {
+        let Some(track) = self.track.as_mut() else {
+            return;
+        };
+        if now <= self.state_ms {
+            return; // late event, applied to the current state
+        }
+        let dt = (now - self.state_ms) as f64 / 1000.0;
+        self.state_ms = now;
+
+        let imu = self.imu.as_ref().filter(|(at, _)| now - at <= IMU_TIMEOUT_MS);
+        let Some((_, imu)) = imu else {
+            track.ekf.predict(dt, 0.0, 0.0, &WITHOUT_IMU);
+            return;
+        };
+        let speed = track.ekf.x[SPEED];
+        // Heading turns clockwise; the IMU's z axis points up
+        let (yaw_rate, noise) = match self.config.yaw_rate {
+            YawRateSource::Gyro => (-(imu.gyro_z as f64).to_radians(), &WITH_GYRO),
+            YawRateSource::LateralAccel if speed > MIN_COURSE_SPEED => {
+                (-(imu.lateral_g.unwrap_or(0.0) as f64) * GRAVITY / speed, &WITH_ACCELEROMETER)
+            }
+            YawRateSource::LateralAccel => (0.0, &WITH_ACCELEROMETER),
+        };
+        track.ekf.predict(dt, imu.longitudinal_g.unwrap_or(0.0) as f64 * GRAVITY, yaw_rate, noise);
+    }
+
+    fn on_gnss(&mut self, now: i64, gps: &GpsData) {
+        if !usable(gps) {
+            return;
+        }
+        let same_epoch = self.last_fix.is_some_and(|(at, lat, lon)| {
+            now - at < SAME_EPOCH_MS && lat == gps.latitude && lon == gps.longitude
+        });
+
+        let Some(track) = self.track.as_mut() else {
+            self.start(now, gps);
+            return;
+        };
+        let speed = gps.speed_kmh as f64 / 3.6;
+
+        if !same_epoch {
+            let (east, north) = track.plane.to_local(gps.latitude, gps.longitude);
+            let var = fix_variance(gps);
+            let (y_east, s_east) = track.ekf.innovation(EAST, east, var);
+            let (y_north, s_north) = track.ekf.innovation(NORTH, north, var);
+            if y_east.powi(2) / s_east + y_north.powi(2) / s_north > GATE {
+                self.rejected += 1;
+                metrics::counter!("position_fixes_rejected_total").increment(1);
+                if self.rejected >= MAX_REJECTED_FIXES {
+                    warn!(rejected = self.rejected, "GNSS fixes keep disagreeing with the position filter — restarting it");
+                    self.start(now, gps);
+                }
+                return;
+            }
+            track.ekf.update(EAST, east, var);
+            track.ekf.update(NORTH, north, var);
+            self.rejected = 0;
+            self.last_fix = Some((now, gps.latitude, gps.longitude));
+        }
+
+        // NMEA readings carry the speed and course of the last RMC, which
+        // after an outage is the invalid one's zero; the gate drops those
+        gated_update(&mut track.ekf, SPEED, speed, 0.2f64.powi(2));
+        if speed > MIN_COURSE_SPEED {
+            let sigma = (0.5 / speed).atan();
+            gated_update(&mut track.ekf, HEADING, (gps.heading as f64).to_radians(), sigma.powi(2));
+        }
+
+        let (east, north) = (track.ekf.x[EAST], track.ekf.x[NORTH]);
+        if east.hypot(north) > RECENTRE_M {
+            let (lat, lon) = track.plane.to_geodetic(east, north);
+            track.plane = LocalPlane::new(lat, lon);
+            track.ekf.shift_origin(east, north);
+        }
+    }
+
+    /// (Re)starts the filter at a fix.
+    fn start(&mut self, now: i64, gps: &GpsData) {
+        let speed = gps.speed_kmh as f64 / 3.6;
+        let (heading, var_heading) = if speed > MIN_COURSE_SPEED {
+            ((gps.heading as f64).to_radians(), 5f64.to_radians().powi(2))
+        } else {
+            (0.0, std::f64::consts::PI.powi(2))
+        };
+        self.track = Some(Track {
+            ekf: Ekf::new(0.0, 0.0, fix_variance(gps), speed, heading, var_heading),
+            plane: LocalPlane::new(gps.latitude, gps.longitude),
+        });
+        self.state_ms = now;
+        self.last_fix = Some((now, gps.latitude, gps.longitude));
+        self.rejected = 0;
+        info!(lat = gps.latitude, lon = gps.longitude, "🧭 Position fusion started at GNSS fix");
+    }
+
+    fn on_wheel_speed(&mut self, now: i64, speed: f64) {
+        self.last_wheel_ms = Some(now);
+        if let Some(track) = self.track.as_mut() {
+            // 1 km/h resolution on OBD-II, a few percent of tire wear
+            track.ekf.update(SPEED, speed, (0.3 + 0.02 * speed).powi(2));
+        }
+    }
+
+    fn publish(&mut self, now: i64) -> Option<PositionData> {
+        let track = self.track.as_ref()?;
+        if self
+            .last_publish_ms
+            .is_some_and(|last| now - last < self.config.publish_interval_ms as i64)
+        {
+            return None;
+        }
+        let gnss_age_ms = self.last_fix.map_or(0, |(at, _, _)| (now - at).max(0));
+        if gnss_age_ms > self.config.max_dead_reckoning_sec as i64 * 1000 {
+            warn!(gnss_age_ms, "🧭 No GNSS fix for too long — position fusion stops until the next one");
+            self.track = None;
+            return None;
+        }
+        self.last_publish_ms = Some(now);
+
+        let recent = |at: Option<i64>| at.is_some_and(|at| now - at <= self.config.gnss_timeout_ms as i64);
+        let source = if gnss_age_ms > self.config.gnss_timeout_ms as i64 {
+            PositionSource::DeadReckoned
+        } else if recent(self.imu.as_ref().map(|(at, _)| *at)) || recent(self.last_wheel_ms) {
+            PositionSource::Fused
+        } else {
+            PositionSource::Gnss
+        };
+
+        let (x, p) = (&track.ekf.x, &track.ekf.p);
+        let (latitude, longitude) = track.plane.to_geodetic(x[EAST], x[NORTH]);
+        let position = PositionData {
+            latitude,
+            longitude,
+            speed_kmh: (x[SPEED] * 3.6) as f32,
+            heading: x[HEADING].to_degrees().rem_euclid(360.0) as f32,
+            covariance_m2: [p[EAST][EAST] as f32, p[NORTH][NORTH] as f32, p[EAST][NORTH] as f32],
+            speed_std_kmh: (p[SPEED][SPEED].sqrt() * 3.6) as f32,
+            heading_std_deg: p[HEADING][HEADING].sqrt().to_degrees() as f32,
+            source,
+            gnss_age_ms: gnss_age_ms.min(u32::MAX as i64) as u32,
+        };
+        metrics::gauge!("position_std_m").set((p[EAST][EAST] + p[NORTH][NORTH]).sqrt());
+        Some(position)
+    }
+}
+
+/// A fix the filter can use. The receiver's own dead reckoning would be
+/// counted twice.
+fn usable(gps: &GpsData) -> bool {
+    gps.fix_quality > 0
+        && !matches!(gps.fix_type, Some(GnssFixType::NoFix | GnssFixType::DeadReckoning))
+        && (gps.latitude != 0.0 || gps.longitude != 0.0)
+}
+
+fn gated_update(ekf: &mut Ekf, i: usize, z: f64, var: f64) {
+    let (y, s) = ekf.innovation(i, z, var);
+    if y.powi(2) / s <= GATE {
+        ekf.update(i, z, var);
+    }
+}
+
+/// Per-axis variance (m²) of a fix, from its reported accuracy or HDOP.
+fn fix_variance(gps: &GpsData) -> f64 {
+    let drms = gps
+        .horizontal_accuracy_m
+        .map(f64::from)
+        .or(gps.hdop.map(|hdop| hdop as f64 * 5.0))
+        .unwrap_or(10.0);
+    (drms.powi(2) / 2.0).max(1.0)
+}
+
+/// Runs the filter over the sensor channel and publishes its estimates on
+/// it, until the channel closes.
+pub async fn run(config: FusionConfig, tx: broadcast::Sender<SensorEvent>) {
+    let mut rx = tx.subscribe();
+    let mut fusion = PositionFusion::new(&config);
+    let mut buffer = SensorFusion::new(REORDER_BUFFER);
+    let hold = chrono::Duration::milliseconds(config.reorder_ms as i64);
+    let mut newest = None;
+    info!(interval_ms = config.publish_interval_ms, yaw_rate = ?config.yaw_rate, "🧭 Position fusion running");
+
+    loop {
+        let event = match rx.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!(skipped, "Position fusion fell behind the sensors");
+                metrics::counter!("sensor_errors_total", "sensor" => "position").increment(skipped);
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+        if event.sensor_type == SensorType::Position {
+            continue;
+        }
+        newest = newest.max(Some(event.timestamp));
+        buffer.add_sensor_event(event);
+
+        let Some(newest) = newest else { continue };
+        for event in buffer.drain_before(newest - hold) {
+            let Some(position) = fusion.process(&event) else {
+                continue;
+            };
+            let reading = SensorEvent {
+                sensor_id: "fusion".to_string(),
+                sensor_type: SensorType::Position,
+                timestamp: event.timestamp,
+                values: SensorValues::Position(position),
+                raw_payload: None,
+            };
+            if tx.send(reading).is_err() {
+                warn!("Sensor channel receiver dropped — no consumers");
+            }
+            metrics::counter!("sensor_events_total", "sensor" => "position").increment(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ObdConfig;
+    use crate::sensors::replay::{elm327::Elm327Replay, imu_csv::ImuCsvReplay, nmea::NmeaReplay};
+    use crate::sensors::SensorSource;
+    use std::f64::consts::FRAC_PI_2;
+    use std::io::Write;
+
+    const START: (f64, f64) = (37.7749, -122.4194);
+    const SPEED: f64 = 20.0; // 72 km/h
+    const TUNNEL_SEC: (u32, u32) = (40, 80);
+    // A right turn through 90° inside the tunnel
+    const TURN_SEC: (f64, f64) = (50.0, 60.0);
+    const TURN_RATE: f64 = FRAC_PI_2 / (TURN_SEC.1 - TURN_SEC.0);
+    const GYRO_BIAS_DPS: f64 = 0.02;
+
+    /// East, north and heading of the truck `t` seconds in.
+    fn truth(t: f64) -> (f64, f64, f64) {
+        let radius = SPEED / TURN_RATE;
+        let turn_start = SPEED * TURN_SEC.0;
+        if t < TURN_SEC.0 {
+            (SPEED * t, 0.0, FRAC_PI_2)
+        } else if t < TURN_SEC.1 {
+            let turned = TURN_RATE * (t - TURN_SEC.0);
+            (turn_start + radius * turned.sin(), radius * (turned.cos() - 1.0), FRAC_PI_2 + turned)
+        } else {
+            (turn_start + radius, -radius - SPEED * (t - TURN_SEC.1), FRAC_PI_2 * 2.0)
+        }
+    }
+
+    fn sentence(body: String) -> String {
+        let checksum = body.bytes().fold(0u8, |acc, b| acc ^ b);
+        format!("${}*{:02X}\n", body, checksum)
+    }
+
+    fn coordinates(lat: f64, lon: f64) -> String {
+        let minutes = |deg: f64| (deg.trunc(), deg.fract() * 60.0);
+        let ((lat_deg, lat_min), (lon_deg, lon_min)) = (minutes(lat.abs()), minutes(lon.abs()));
+        let (ns, ew) = (if lat < 0.0 { 'S' } else { 'N' }, if lon < 0.0 { 'W' } else { 'E' });
+        format!("{:02}{:08.5},{},{:03}{:08.5},{}", lat_deg, lat_min, ns, lon_deg, lon_min, ew)
+    }
+
+    /// NMEA log, 10 Hz IMU capture with a gyro and ELM327 speed transcript
+    /// of a two-minute drive, GNSS lost in the tunnel.
+    fn recordings() -> [tempfile::NamedTempFile; 3] {
+        let plane = LocalPlane::new(START.0, START.1);
+        let mut nmea = String::new();
+        let mut transcript = String::new();
+        for sec in 0..=120u32 {
+            let time = format!("12{:02}{:02}.00", sec / 60, sec % 60);
+            if sec > TUNNEL_SEC.0 && sec < TUNNEL_SEC.1 {
+                nmea += &sentence(format!("GPGGA,{},,,,,0,00,99.9,,M,,M,,", time));
+                nmea += &sentence(format!("GPRMC,{},V,,,,,,,010124,,,N", time));
+            } else {
+                let (east, north, heading) = truth(sec as f64);
+                let (lat, lon) = plane.to_geodetic(east, north);
+                nmea += &sentence(format!("GPGGA,{},{},1,09,0.9,12.0,M,,M,,", time, coordinates(lat, lon)));
+                nmea += &sentence(format!(
+                    "GPRMC,{},A,{},{:.2},{:.1},010124,,,A",
+                    time,
+                    coordinates(lat, lon),
+                    SPEED * 3.6 / 1.852,
+                    heading.to_degrees()
+                ));
+            }
+            transcript += &format!("[{}.000] 41 0D {:02X}\n", sec, (SPEED * 3.6) as u8);
+        }
+
+        let mut imu = "timestamp_ms,accel_x,accel_y,accel_z,gyro_x,gyro_y,gyro_z\n".to_string();
+        for tick in 0..=1200u32 {
+            let t = tick as f64 / 10.0;
+            let turning = (TURN_SEC.0..TURN_SEC.1).contains(&t);
+            // Right turn: acceleration to the right, negative rotation about z
+            let (accel_y, gyro_z) = if turning {
+                (-SPEED * TURN_RATE / GRAVITY, -TURN_RATE.to_degrees())
+            } else {
+                (0.0, 0.0)
+            };
+            imu += &format!("{},0.0,{:.5},1.0,0.0,0.0,{:.4}\n", tick * 100, accel_y, gyro_z + GYRO_BIAS_DPS);
+        }
+
+        [nmea, imu, transcript].map(|text| {
+            let mut file = tempfile::NamedTempFile::new().unwrap();
+            file.write_all(text.as_bytes()).unwrap();
+            file
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_dead_reckons_through_a_tunnel() {
+        let [nmea, imu, transcript] = recordings();
+        let path = |file: &tempfile::NamedTempFile| file.path().to_str().unwrap().to_string();
+        let sources: Vec<Box<dyn SensorSource>> = vec![
+            Box::new(NmeaReplay::open(&path(&nmea), 1.0, false, 10).await.unwrap()),
+            Box::new(ImuCsvReplay::open(&path(&imu), 1.0, false).await.unwrap()),
+            Box::new(Elm327Replay::open(&path(&transcript), 1.0, false, 1, &ObdConfig::default().pids).await.unwrap()),
+        ];
+
+        // Each recording played in its own time, merged the way the live
+        // pipeline does it
+        let epoch = chrono::Utc::now();
+        let mut buffer = SensorFusion::new(4096);
+        for mut source in sources {
+            let start = tokio::time::Instant::now();
+            while let Some(reading) = source.next_reading().await.unwrap() {
+                buffer.add_sensor_event(SensorEvent {
+                    sensor_id: source.sensor_id().to_string(),
+                    sensor_type: reading.values.sensor_type(),
+                    timestamp: epoch + chrono::Duration::from_std(start.elapsed()).unwrap(),
+                    values: reading.values,
+                    raw_payload: None,
+                });
+            }
+        }
+
+        let config = FusionConfig {
+            yaw_rate: YawRateSource::Gyro,
+            ..Default::default()
+        };
+        let mut fusion = PositionFusion::new(&config);
+        let plane = LocalPlane::new(START.0, START.1);
+        let mut published = 0;
+        for event in buffer.drain_before(epoch + chrono::Duration::days(1)) {
+            let Some(position) = fusion.process(&event) else {
+                continue;
+            };
+            published += 1;
+            let t = (event.timestamp - epoch).num_milliseconds() as f64 / 1000.0;
+            let (east, north) = plane.to_local(position.latitude, position.longitude);
+            let (true_east, true_north, _) = truth(t);
+            let error = (east - true_east).hypot(north - true_north);
+
+            let (entered, left) = (TUNNEL_SEC.0 as f64, TUNNEL_SEC.1 as f64);
+            if t == 0.0 {
+                // The first fix comes before any IMU sample
+                assert_eq!(position.source, PositionSource::Gnss);
+            } else if t <= entered || t >= left + 1.0 {
+                assert_eq!(position.source, PositionSource::Fused, "at {}s", t);
+                assert!(error < 3.0, "{:.1} m off at {}s with GNSS", error, t);
+            } else if t > entered + 3.0 && t < left {
+                assert_eq!(position.source, PositionSource::DeadReckoned, "at {}s", t);
+                assert!(error < 15.0, "{:.1} m off at {}s in the tunnel", error, t);
+                // The covariance owns up to the drift
+                assert!(error < 3.0 * position.horizontal_std_m() as f64, "{:.1} m off at {}s", error, t);
+            }
+        }
+        assert!(published >= 118, "{} positions published", published);
+    }
+}
@@ -0,0 +1,168 @@
+use crate::config::{GnssConfig, GnssProtocol};
+use crate::sensors::types::{SensorType, SensorValues};
+use crate::sensors::{Reading, SensorSource, SourceResult};
+use sentences::NmeaState;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio_serial::{SerialPortBuilderExt, SerialStream};
+use tracing::{error, info, warn};
+use ubx::{FrameParser, UbxState};
+
+pub mod sentences;
+pub mod ubx;
+
+/// GNSS receiver on a serial port, read as NMEA sentences or as u-blox UBX
+/// frames.
+pub struct GpsReader {
+    device_path: String,
+    config: GnssConfig,
+    port: BufReader<SerialStream>,
+    buf: String,
+    nmea: NmeaState,
+    ubx: UbxState,
+    frames: FrameParser,
+}
+
+impl GpsReader {
+    pub async fn open(device_path: &str, config: &GnssConfig) -> SourceResult<Self> {
+        let port = open_port(device_path, config).await?;
+        info!(device=%device_path, protocol=?config.protocol, "📡 GPS reader started");
+        Ok(Self {
+            device_path: device_path.to_string(),
+            config: config.clone(),
+            port: BufReader::new(port),
+            buf: String::new(),
+            nmea: NmeaState::new(config.sky_view_interval_sec),
+            ubx: UbxState::new(config.sky_view_interval_sec),
+            frames: FrameParser::default(),
+        })
+    }
+
+    /// Next fix from NMEA sentences; `None` once the device is gone.
+    async fn read_nmea(&mut self) -> SourceResult<Option<Reading>> {
+        loop {
+            self.buf.clear();
+            if self.port.read_line(&mut self.buf).await? == 0 {
+                return Ok(None);
+            }
+
+            let sentence = self.buf.trim_end();
+            match self.nmea.feed(sentence) {
+                Ok(Some((gps, _))) => {
+                    return Ok(Some(Reading {
+                        values: SensorValues::Gps(gps),
+                        raw_payload: Some(sentence.to_string()),
+                    }))
+                }
+                Ok(None) => {} // Ignore other sentences
+                Err(e) => {
+                    // Log malformed but don't crash
+                    metrics::counter!("sensor_errors_total", "sensor" => "gps").increment(1);
+                    warn!(sentence=%sentence, error=%e, "Malformed NMEA sentence");
+                }
+            }
+        }
+    }
+
+    /// Next NAV-PVT fix; `None` once the device is gone.
+    async fn read_ubx(&mut self) -> SourceResult<Option<Reading>> {
+        let mut chunk = [0u8; 512];
+        loop {
+            while let Some(frame) = self.frames.next_frame() {
+                if let Some(gps) = self.ubx.on_frame(&frame) {
+                    return Ok(Some(Reading {
+                        values: SensorValues::Gps(gps),
+                        raw_payload: None,
+                    }));
+                }
+            }
+            let n = self.port.read(&mut chunk).await?;
+            if n == 0 {
+                return Ok(None);
+            }
+            self.frames.push(&chunk[..n]);
+        }
+    }
+
+    async fn reconnect(&mut self) {
+        // EOF — device disconnected
+        warn!(device=%self.device_path, "GPS device disconnected");
+        metrics::gauge!("sensor_status", "sensor" => "gps").set(0.0);
+        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+        match open_port(&self.device_path, &self.config).await {
+            Ok(port) => {
+                self.port = BufReader::new(port);
+                self.frames = FrameParser::default();
+                info!(device=%self.device_path, "✅ GPS device reconnected");
+                metrics::gauge!("sensor_status", "sensor" => "gps").set(1.0);
+            }
+            Err(e) => error!(device=%self.device_path, error=%e, "Failed to reconnect GPS"),
+        }
+    }
+}
+
+/// Opens the port and, in UBX mode, sets the receiver up. Done again on every
+/// reconnect: a receiver without backup power forgets its configuration.
+async fn open_port(device_path: &str, config: &GnssConfig) -> SourceResult<SerialStream> {
+    let mut port = tokio_serial::new(device_path, config.baud_rate)
+        .open_native_async()
+        .map_err(|e| format!("Failed to open GPS device {}: {}", device_path, e))?;
+    if config.protocol == GnssProtocol::Ubx {
+        for message in ubx::configure(config.update_rate_hz, config.sky_view_interval_sec) {
+            port.write_all(&message).await?;
+        }
+    }
+    Ok(port)
+}
+
+/// Lets the sky view through once per interval of fix time, so it rides on
+/// a fraction of the readings.
+pub struct SkyViewGate {
+    interval_ms: u64,
+    last_ms: Option<u64>,
+}
+
+impl SkyViewGate {
+    pub fn new(interval_sec: u64) -> Self {
+        Self {
+            interval_ms: interval_sec * 1000,
+            last_ms: None,
+        }
+    }
+
+    /// `now_ms` is the fix time: NMEA time of day or UBX time of week. A
+    /// jump backwards (midnight, week rollover) opens the gate.
+    pub fn due(&mut self, now_ms: u64) -> bool {
+        if self
+            .last_ms
+            .is_some_and(|last| now_ms >= last && now_ms - last < self.interval_ms)
+        {
+            return false;
+        }
+        self.last_ms = Some(now_ms);
+        true
+    }
+}
+
+#[async_trait::async_trait]
+impl SensorSource for GpsReader {
+    fn sensor_id(&self) -> &str {
+        &self.device_path
+    }
+
+    fn sensor_type(&self) -> SensorType {
+        SensorType::Gps
+    }
+
+    async fn next_reading(&mut self) -> SourceResult<Option<Reading>> {
+        loop {
+            let reading = match self.config.protocol {
+                GnssProtocol::Nmea => self.read_nmea().await?,
+                GnssProtocol::Ubx => self.read_ubx().await?,
+            };
+            match reading {
+                Some(reading) => return Ok(Some(reading)),
+                None => self.reconnect().await,
+            }
+        }
+    }
+}
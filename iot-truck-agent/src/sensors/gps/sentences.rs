@@ -0,0 +1,234 @@
+//! NMEA 0183 beyond the position fix: GSA (fix type, DOPs and the satellites
+//! used), GSV (satellites in view), VTG (course and speed) and GST (position
+//! error). [`NmeaState`] folds them into the GGA and RMC fixes.
+
+use super::SkyViewGate;
+use crate::sensors::types::{GnssFixType, GnssSystem, GpsData, SatelliteInView};
+use crate::sensors::SourceResult;
+use chrono::{NaiveTime, Timelike};
+use nmea::sentences::{GnssType, GsaMode2, GsvData, RmcStatusOfFix};
+use nmea::ParseResult;
+use std::collections::BTreeMap;
+
+const KNOTS_TO_KMH: f32 = 1.852;
+
+pub struct NmeaState {
+    /// Latest value of every field, updated by whichever sentence has it.
+    gps: GpsData,
+    /// PRNs in the solution, from the current run of GSA sentences (one per
+    /// system on multi-GNSS receivers).
+    used: Vec<u32>,
+    in_gsa_run: bool,
+    /// Last complete GSV cycle per system.
+    sky: BTreeMap<GnssSystem, Vec<SatelliteInView>>,
+    /// Cycle being received: system, next sentence number, satellites so far.
+    partial: Option<(GnssSystem, u16, Vec<SatelliteInView>)>,
+    sky_view: SkyViewGate,
+}
+
+impl NmeaState {
+    pub fn new(sky_view_interval_sec: u64) -> Self {
+        Self {
+            gps: GpsData::default(),
+            used: Vec::new(),
+            in_gsa_run: false,
+            sky: BTreeMap::new(),
+            partial: None,
+            sky_view: SkyViewGate::new(sky_view_interval_sec),
+        }
+    }
+
+    /// Feeds one sentence. GGA and RMC return a fix with the sentence's UTC
+    /// time; fields the sentence lacks come from the latest one that has
+    /// them. `Ok(None)` for every other sentence.
+    pub fn feed(&mut self, sentence: &str) -> SourceResult<Option<(GpsData, Option<NaiveTime>)>> {
+        if let Some([lat, lon, alt]) = parse_gst(sentence) {
+            self.in_gsa_run = false;
+            // Both axes combined (DRMS)
+            self.gps.horizontal_accuracy_m = lat.zip(lon).map(|(lat, lon)| lat.hypot(lon));
+            self.gps.vertical_accuracy_m = alt;
+            return Ok(None);
+        }
+
+        let parsed = nmea::parse_str(sentence).map_err(|e| format!("{:?}", e))?;
+        if !matches!(parsed, ParseResult::GSA(_)) {
+            self.in_gsa_run = false;
+        }
+        let fix_time = match parsed {
+            ParseResult::GGA(gga) => {
+                self.gps.latitude = gga.latitude.unwrap_or(0.0);
+                self.gps.longitude = gga.longitude.unwrap_or(0.0);
+                self.gps.altitude = gga.altitude.unwrap_or(0.0);
+                self.gps.satellites = gga.fix_satellites.unwrap_or(0) as u8;
+                self.gps.fix_quality = gga.fix_type.map_or(0, |t| t as u8);
+                if gga.hdop.is_some() {
+                    self.gps.hdop = gga.hdop;
+                }
+                gga.fix_time
+            }
+            ParseResult::RMC(rmc) => {
+                self.gps.latitude = rmc.lat.unwrap_or(0.0);
+                self.gps.longitude = rmc.lon.unwrap_or(0.0);
+                self.gps.speed_kmh = rmc.speed_over_ground.unwrap_or(0.0) * KNOTS_TO_KMH;
+                self.gps.heading = rmc.true_course.unwrap_or(0.0);
+                if rmc.status_of_fix == RmcStatusOfFix::Invalid {
+                    self.gps.fix_quality = 0;
+                } else if self.gps.fix_quality == 0 {
+                    self.gps.fix_quality = 1;
+                }
+                rmc.fix_time
+            }
+            ParseResult::GSA(gsa) => {
+                if !self.in_gsa_run {
+                    self.used.clear();
+                    self.in_gsa_run = true;
+                }
+                self.used.extend(gsa.fix_sats_prn.iter().copied());
+                self.gps.fix_type = Some(match gsa.mode2 {
+                    GsaMode2::NoFix => GnssFixType::NoFix,
+                    GsaMode2::Fix2D => GnssFixType::Fix2d,
+                    GsaMode2::Fix3D => GnssFixType::Fix3d,
+                });
+                self.gps.pdop = gsa.pdop;
+                self.gps.hdop = gsa.hdop;
+                self.gps.vdop = gsa.vdop;
+                return Ok(None);
+            }
+            ParseResult::GSV(gsv) => {
+                self.on_gsv(&gsv);
+                return Ok(None);
+            }
+            ParseResult::VTG(vtg) => {
+                if let Some(knots) = vtg.speed_over_ground {
+                    self.gps.speed_kmh = knots * KNOTS_TO_KMH;
+                }
+                if let Some(course) = vtg.true_course {
+                    self.gps.heading = course;
+                }
+                return Ok(None);
+            }
+            _ => return Ok(None),
+        };
+
+        let mut gps = self.gps.clone();
+        if let Some(time) = fix_time {
+            let time_ms = time.num_seconds_from_midnight() as u64 * 1000 + time.nanosecond() as u64 / 1_000_000;
+            if self.sky.values().any(|s| !s.is_empty()) && self.sky_view.due(time_ms) {
+                gps.satellites_in_view = self.sky_view();
+            }
+        }
+        Ok(Some((gps, fix_time)))
+    }
+
+    fn on_gsv(&mut self, gsv: &GsvData) {
+        let system = match gsv.gnss_type {
+            GnssType::Gps => GnssSystem::Gps,
+            GnssType::Glonass => GnssSystem::Glonass,
+            GnssType::Galileo => GnssSystem::Galileo,
+            GnssType::Beidou => GnssSystem::Beidou,
+            _ => return,
+        };
+        if gsv.sentence_num == 1 {
+            self.partial = Some((system, 1, Vec::new()));
+        }
+        // A lost sentence drops the cycle; the previous one stays
+        let Some((_, next, satellites)) = self
+            .partial
+            .as_mut()
+            .filter(|(s, next, _)| *s == system && *next == gsv.sentence_num)
+        else {
+            self.partial = None;
+            return;
+        };
+        satellites.extend(gsv.sats_info.iter().flatten().map(|s| SatelliteInView {
+            system,
+            prn: s.prn() as u16,
+            elevation_deg: s.elevation().map(|e| e as i8),
+            azimuth_deg: s.azimuth().map(|a| a as u16),
+            snr_dbhz: s.snr().map(|snr| snr as u8),
+            used: false,
+        }));
+        *next += 1;
+        if gsv.sentence_num == gsv.number_of_sentences {
+            if let Some((system, _, satellites)) = self.partial.take() {
+                self.sky.insert(system, satellites);
+            }
+        }
+    }
+
+    /// Satellites in view, marked with whether the last GSA lists them. GSA
+    /// only carries PRNs, so systems with overlapping numbers may mark each
+    /// other's satellites.
+    fn sky_view(&self) -> Vec<SatelliteInView> {
+        self.sky
+            .values()
+            .flatten()
+            .map(|s| SatelliteInView {
+                used: self.used.contains(&(s.prn as u32)),
+                ..s.clone()
+            })
+            .collect()
+    }
+}
+
+/// Latitude, longitude and altitude error (1-sigma, metres) from a GST
+/// sentence: `$GPGST,time,rms,major,minor,orientation,lat,lon,alt*cs`.
+/// `None` for other sentences and bad checksums.
+fn parse_gst(sentence: &str) -> Option<[Option<f32>; 3]> {
+    let (body, checksum) = sentence.strip_prefix('$')?.split_once('*')?;
+    if body.get(2..6)? != "GST," {
+        return None;
+    }
+    let sum = body.bytes().fold(0u8, |acc, b| acc ^ b);
+    if u8::from_str_radix(checksum.trim(), 16).ok()? != sum {
+        return None;
+    }
+    let fields: Vec<&str> = body.split(',').collect();
+    let sigma = |i: usize| fields.get(i).and_then(|f| f.parse().ok());
+    Some([sigma(6), sigma(7), sigma(8)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folds_quality_sentences_into_fixes() {
+        let mut state = NmeaState::new(10);
+        let mut feed = |sentence: &str| state.feed(sentence).unwrap().map(|(gps, _)| gps);
+
+        let gga = feed("$GPGGA,123519.00,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*69").unwrap();
+        assert_eq!((gga.satellites, gga.hdop, gga.fix_type), (8, Some(0.9), None));
+        assert!(gga.satellites_in_view.is_empty());
+
+        for sentence in [
+            "$GPGSA,A,3,04,05,09,12,24,,,,,,,,2.5,1.3,2.1*39",
+            "$GPGSV,2,1,08,04,45,120,42,05,30,200,38,09,60,045,45,12,15,300,30*75",
+            "$GPGSV,2,2,08,24,70,090,47,25,05,010,,29,10,250,,31,20,180,22*7D",
+            "$GPVTG,054.7,T,034.4,M,005.5,N,010.2,K*48",
+            "$GPGST,123519.00,1.2,2.0,1.5,45.0,1.6,1.2,2.8*60",
+        ] {
+            assert!(feed(sentence).is_none());
+        }
+
+        let rmc = feed("$GPRMC,123520.00,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*4E").unwrap();
+        assert_eq!(rmc.fix_type, Some(GnssFixType::Fix3d));
+        assert_eq!((rmc.pdop, rmc.hdop, rmc.vdop), (Some(2.5), Some(1.3), Some(2.1)));
+        assert!((rmc.horizontal_accuracy_m.unwrap() - 2.0).abs() < 1e-5);
+        assert_eq!(rmc.vertical_accuracy_m, Some(2.8));
+        // RMC's own speed, GGA's altitude and satellite count
+        assert!((rmc.speed_kmh - 41.48).abs() < 0.01);
+        assert_eq!((rmc.altitude, rmc.satellites), (545.4, 8));
+
+        assert_eq!(rmc.satellites_in_view.len(), 8);
+        let used: Vec<u16> = rmc.satellites_in_view.iter().filter(|s| s.used).map(|s| s.prn).collect();
+        assert_eq!(used, [4, 5, 9, 12, 24]);
+        let untracked = rmc.satellites_in_view.iter().find(|s| s.prn == 25).unwrap();
+        assert_eq!((untracked.snr_dbhz, untracked.elevation_deg), (None, Some(5)));
+
+        // The sky view waits out its interval
+        let next = feed("$GPRMC,123521.00,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*4F").unwrap();
+        assert!(next.satellites_in_view.is_empty());
+        assert!(state.feed("$GPGST,123519.00,1.2,2.0,1.5,45.0,1.6,1.2,2.8*61").is_err());
+    }
+}
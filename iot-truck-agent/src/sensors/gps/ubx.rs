@@ -0,0 +1,340 @@
+//! u-blox UBX binary protocol: NAV-PVT for the fix, NAV-DOP and NAV-SAT for
+//! its quality, and the configuration that turns them on. Configuration uses
+//! the CFG-RATE and CFG-MSG messages, which u-blox 6 to 9 receivers accept.
+
+use super::SkyViewGate;
+use crate::sensors::types::{GnssFixType, GnssSystem, GpsData, SatelliteInView};
+use tracing::warn;
+
+pub const SYNC: [u8; 2] = [0xB5, 0x62];
+
+const CLASS_NAV: u8 = 0x01;
+const CLASS_ACK: u8 = 0x05;
+const CLASS_CFG: u8 = 0x06;
+const CLASS_NMEA: u8 = 0xF0;
+
+const NAV_DOP: u8 = 0x04;
+const NAV_PVT: u8 = 0x07;
+const NAV_SAT: u8 = 0x35;
+const ACK_NAK: u8 = 0x00;
+const CFG_MSG: u8 = 0x01;
+const CFG_RATE: u8 = 0x08;
+
+/// NMEA output on by default: GGA, GLL, GSA, GSV, RMC, VTG.
+const NMEA_DEFAULTS: [u8; 6] = [0x00, 0x01, 0x02, 0x03, 0x04, 0x05];
+
+const NAV_PVT_LEN: usize = 92;
+const NAV_DOP_LEN: usize = 18;
+/// NAV-SAT with 255 satellites is the longest message asked for.
+const MAX_PAYLOAD: usize = 8 + 12 * 255;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub class: u8,
+    pub id: u8,
+    pub payload: Vec<u8>,
+}
+
+/// 8-bit Fletcher checksum over class, id, length and payload.
+fn checksum(bytes: &[u8]) -> [u8; 2] {
+    let (mut a, mut b) = (0u8, 0u8);
+    for byte in bytes {
+        a = a.wrapping_add(*byte);
+        b = b.wrapping_add(a);
+    }
+    [a, b]
+}
+
+pub fn frame(class: u8, id: u8, payload: &[u8]) -> Vec<u8> {
+    let mut out = SYNC.to_vec();
+    out.extend_from_slice(&[class, id]);
+    out.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+    out.extend_from_slice(payload);
+    let ck = checksum(&out[2..]);
+    out.extend_from_slice(&ck);
+    out
+}
+
+/// Sets the navigation rate, asks for NAV-DOP and NAV-PVT every epoch and
+/// NAV-SAT once per sky view interval, and turns the default NMEA output off.
+pub fn configure(update_rate_hz: u8, sky_view_interval_sec: u64) -> Vec<Vec<u8>> {
+    let measurement_ms = 1000 / update_rate_hz.max(1) as u16;
+    let [lo, hi] = measurement_ms.to_le_bytes();
+    // One solution per measurement, aligned to GPS time
+    let mut messages = vec![frame(CLASS_CFG, CFG_RATE, &[lo, hi, 1, 0, 1, 0])];
+
+    let sat_every = (update_rate_hz as u64 * sky_view_interval_sec).clamp(1, 255) as u8;
+    for (id, rate) in [(NAV_DOP, 1), (NAV_PVT, 1), (NAV_SAT, sat_every)] {
+        messages.push(frame(CLASS_CFG, CFG_MSG, &[CLASS_NAV, id, rate]));
+    }
+    for id in NMEA_DEFAULTS {
+        messages.push(frame(CLASS_CFG, CFG_MSG, &[CLASS_NMEA, id, 0]));
+    }
+    messages
+}
+
+/// Pulls UBX frames out of the receiver's byte stream, skipping whatever
+/// else is on the line (NMEA text, noise, frames with a bad checksum).
+#[derive(Default)]
+pub struct FrameParser {
+    buf: Vec<u8>,
+}
+
+impl FrameParser {
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    pub fn next_frame(&mut self) -> Option<Frame> {
+        loop {
+            let Some(start) = self.buf.windows(2).position(|w| w == SYNC) else {
+                // Keep a trailing first sync byte for the next push
+                let keep = usize::from(self.buf.last() == Some(&SYNC[0]));
+                self.buf.drain(..self.buf.len() - keep);
+                return None;
+            };
+            self.buf.drain(..start);
+            if self.buf.len() < 6 {
+                return None;
+            }
+            let len = u16::from_le_bytes([self.buf[4], self.buf[5]]) as usize;
+            if len > MAX_PAYLOAD {
+                self.buf.drain(..2);
+                continue;
+            }
+            if self.buf.len() < 8 + len {
+                return None;
+            }
+            if self.buf[6 + len..8 + len] != checksum(&self.buf[2..6 + len]) {
+                metrics::counter!("sensor_errors_total", "sensor" => "gps").increment(1);
+                self.buf.drain(..2);
+                continue;
+            }
+            let frame = Frame {
+                class: self.buf[2],
+                id: self.buf[3],
+                payload: self.buf[6..6 + len].to_vec(),
+            };
+            self.buf.drain(..8 + len);
+            return Some(frame);
+        }
+    }
+}
+
+/// Builds fixes from the NAV messages of each epoch. u-blox sends an epoch's
+/// messages in id order, so NAV-DOP arrives before NAV-PVT and NAV-SAT after
+/// it, to be attached to a later fix.
+pub struct UbxState {
+    /// HDOP and VDOP from the last NAV-DOP.
+    dop: Option<(f32, f32)>,
+    sky: Vec<SatelliteInView>,
+    sky_view: SkyViewGate,
+}
+
+impl UbxState {
+    pub fn new(sky_view_interval_sec: u64) -> Self {
+        Self {
+            dop: None,
+            sky: Vec::new(),
+            sky_view: SkyViewGate::new(sky_view_interval_sec),
+        }
+    }
+
+    /// Returns the fix on NAV-PVT.
+    pub fn on_frame(&mut self, frame: &Frame) -> Option<GpsData> {
+        let p = &frame.payload;
+        match (frame.class, frame.id) {
+            (CLASS_NAV, NAV_DOP) if p.len() >= NAV_DOP_LEN => {
+                self.dop = Some((u16_at(p, 12) as f32 / 100.0, u16_at(p, 10) as f32 / 100.0));
+                None
+            }
+            (CLASS_NAV, NAV_SAT) => {
+                self.sky = parse_nav_sat(p);
+                None
+            }
+            (CLASS_NAV, NAV_PVT) if p.len() >= NAV_PVT_LEN => Some(self.nav_pvt(p)),
+            (CLASS_ACK, ACK_NAK) => {
+                warn!(class = ?p.first(), id = ?p.get(1), "GNSS receiver rejected a UBX configuration message");
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn nav_pvt(&mut self, p: &[u8]) -> GpsData {
+        let flags = p[21];
+        let fix_ok = flags & 0x01 != 0;
+        let differential = flags & 0x02 != 0;
+        // Same scale as GGA quality: 4 RTK fixed, 5 RTK float
+        let fix_quality = match (fix_ok, flags >> 6) {
+            (false, _) => 0,
+            (true, 2) => 4,
+            (true, 1) => 5,
+            (true, _) if differential => 2,
+            (true, _) => 1,
+        };
+        let fix_type = match p[20] {
+            1 => GnssFixType::DeadReckoning,
+            2 => GnssFixType::Fix2d,
+            3 | 4 => GnssFixType::Fix3d,
+            _ => GnssFixType::NoFix,
+        };
+
+        let itow_ms = u32_at(p, 0) as u64;
+        let satellites_in_view = if !self.sky.is_empty() && self.sky_view.due(itow_ms) {
+            self.sky.clone()
+        } else {
+            Vec::new()
+        };
+        GpsData {
+            latitude: i32_at(p, 28) as f64 * 1e-7,
+            longitude: i32_at(p, 24) as f64 * 1e-7,
+            altitude: i32_at(p, 36) as f32 / 1000.0, // above mean sea level, as in GGA
+            speed_kmh: i32_at(p, 60) as f32 * 0.0036,
+            heading: i32_at(p, 64) as f32 * 1e-5,
+            satellites: p[23],
+            fix_quality,
+            fix_type: Some(fix_type),
+            hdop: self.dop.map(|(h, _)| h),
+            vdop: self.dop.map(|(_, v)| v),
+            pdop: Some(u16_at(p, 76) as f32 / 100.0),
+            horizontal_accuracy_m: Some(u32_at(p, 40) as f32 / 1000.0),
+            vertical_accuracy_m: Some(u32_at(p, 44) as f32 / 1000.0),
+            satellites_in_view,
+        }
+    }
+}
+
+fn parse_nav_sat(p: &[u8]) -> Vec<SatelliteInView> {
+    let count = p.get(5).copied().unwrap_or(0) as usize;
+    p.get(8..)
+        .unwrap_or_default()
+        .chunks_exact(12)
+        .take(count)
+        .filter_map(|sv| {
+            let system = match sv[0] {
+                0 => GnssSystem::Gps,
+                1 => GnssSystem::Sbas,
+                2 => GnssSystem::Galileo,
+                3 => GnssSystem::Beidou,
+                5 => GnssSystem::Qzss,
+                6 => GnssSystem::Glonass,
+                7 => GnssSystem::Navic,
+                _ => return None,
+            };
+            let elevation = sv[3] as i8;
+            // Azimuth is only valid with an elevation in range
+            let known = (-90..=90).contains(&elevation);
+            Some(SatelliteInView {
+                system,
+                prn: sv[1] as u16,
+                elevation_deg: known.then_some(elevation),
+                azimuth_deg: known.then(|| i16::from_le_bytes([sv[4], sv[5]]).max(0) as u16),
+                snr_dbhz: (sv[2] > 0).then_some(sv[2]),
+                used: u32_at(sv, 8) & 0x08 != 0,
+            })
+        })
+        .collect()
+}
+
+fn u16_at(p: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([p[at], p[at + 1]])
+}
+
+fn u32_at(p: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([p[at], p[at + 1], p[at + 2], p[at + 3]])
+}
+
+fn i32_at(p: &[u8], at: usize) -> i32 {
+    u32_at(p, at) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nav_pvt(itow_ms: u32) -> Vec<u8> {
+        let mut p = vec![0u8; NAV_PVT_LEN];
+        let mut put = |at: usize, bytes: &[u8]| p[at..at + bytes.len()].copy_from_slice(bytes);
+        put(0, &itow_ms.to_le_bytes());
+        put(20, &[3, 0x01, 0, 12]); // 3D, gnssFixOK, 12 satellites
+        put(24, &113_516_666i32.to_le_bytes());
+        put(28, &481_173_000i32.to_le_bytes());
+        put(36, &545_400i32.to_le_bytes());
+        put(40, &1_800u32.to_le_bytes());
+        put(44, &2_900u32.to_le_bytes());
+        put(60, &11_389i32.to_le_bytes());
+        put(64, &8_440_000i32.to_le_bytes());
+        put(76, &170u16.to_le_bytes());
+        frame(CLASS_NAV, NAV_PVT, &p)
+    }
+
+    #[test]
+    fn test_decodes_nav_frames_from_mixed_stream() {
+        // The well-known 1 Hz CFG-RATE
+        let config = configure(1, 10);
+        assert_eq!(config[0], [0xB5, 0x62, 0x06, 0x08, 0x06, 0x00, 0xE8, 0x03, 0x01, 0x00, 0x01, 0x00, 0x01, 0x39]);
+        assert_eq!(config[3], frame(CLASS_CFG, CFG_MSG, &[CLASS_NAV, NAV_SAT, 10]));
+
+        let mut dop = vec![0u8; NAV_DOP_LEN];
+        dop[10..12].copy_from_slice(&210u16.to_le_bytes());
+        dop[12..14].copy_from_slice(&130u16.to_le_bytes());
+        let mut sat = vec![0, 0, 0, 0, 1, 2, 0, 0];
+        sat.extend_from_slice(&[0, 5, 38, 30, 200, 0, 0, 0, 0x0C, 0, 0, 0]);
+        sat.extend_from_slice(&[2, 11, 0, 42, 14, 1, 0, 0, 0x01, 0, 0, 0]);
+        let mut corrupt = nav_pvt(100_500);
+        *corrupt.last_mut().unwrap() ^= 0xFF;
+
+        let mut stream = b"$GPGGA,123519.00,,,,,0,00,,,,,,,*4B\r\n".to_vec();
+        stream.extend(frame(CLASS_NAV, NAV_DOP, &dop));
+        stream.extend(nav_pvt(100_000));
+        stream.extend(corrupt);
+        stream.extend(frame(CLASS_NAV, NAV_SAT, &sat));
+        stream.extend(nav_pvt(101_000));
+        stream.extend(nav_pvt(102_000));
+
+        let mut parser = FrameParser::default();
+        let mut state = UbxState::new(10);
+        let mut fixes = Vec::new();
+        // Split mid-frame, as serial reads do
+        for chunk in stream.chunks(50) {
+            parser.push(chunk);
+            while let Some(frame) = parser.next_frame() {
+                fixes.extend(state.on_frame(&frame));
+            }
+        }
+
+        assert_eq!(fixes.len(), 3);
+        let fix = &fixes[0];
+        assert!((fix.latitude - 48.1173).abs() < 1e-9 && (fix.longitude - 11.3516666).abs() < 1e-9);
+        assert_eq!((fix.altitude, fix.satellites, fix.fix_quality), (545.4, 12, 1));
+        assert!((fix.speed_kmh - 41.0).abs() < 0.01 && (fix.heading - 84.4).abs() < 1e-3);
+        assert_eq!(fix.fix_type, Some(GnssFixType::Fix3d));
+        assert_eq!((fix.hdop, fix.vdop, fix.pdop), (Some(1.3), Some(2.1), Some(1.7)));
+        assert_eq!((fix.horizontal_accuracy_m, fix.vertical_accuracy_m), (Some(1.8), Some(2.9)));
+        assert!(fix.satellites_in_view.is_empty());
+
+        assert_eq!(
+            fixes[1].satellites_in_view,
+            [
+                SatelliteInView {
+                    system: GnssSystem::Gps,
+                    prn: 5,
+                    elevation_deg: Some(30),
+                    azimuth_deg: Some(200),
+                    snr_dbhz: Some(38),
+                    used: true,
+                },
+                SatelliteInView {
+                    system: GnssSystem::Galileo,
+                    prn: 11,
+                    elevation_deg: Some(42),
+                    azimuth_deg: Some(270),
+                    snr_dbhz: None,
+                    used: false,
+                },
+            ]
+        );
+        assert!(fixes[2].satellites_in_view.is_empty());
+    }
+}
@@ -0,0 +1,262 @@
+//! Mounting calibration. The unit can sit in the cab at any angle, so the
+//! rotation from chip axes to the truck's is learned on the first drive:
+//! gravity gives "up" while the truck stands still, and acceleration or
+//! braking in a straight line gives "forward", its sign taken from the
+//! change in vehicle speed.
+
+use super::chips::Sample;
+use super::{add, cross, dot, norm, scale, sub, unit};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::Path;
+use tracing::warn;
+
+const GRAVITY: f64 = 9.80665;
+/// Samples further from the running mean end a stationary window.
+const STILL_TOLERANCE_G: f64 = 0.05;
+const STILL_TOLERANCE_DPS: f64 = 1.0;
+const STILL_MS: i64 = 3000;
+/// Speed changes are taken over this much history, since OBD-II reports
+/// whole km/h.
+const SPEED_BASELINE_MS: i64 = 1500;
+const MIN_SPEED_BASELINE_MS: i64 = 500;
+/// Speed readings older than this say nothing about the current sample.
+const SPEED_TIMEOUT_MS: i64 = 1500;
+/// About 0.1 g.
+const MIN_SPEED_CHANGE: f64 = 1.0;
+const MIN_HORIZONTAL_G: f64 = 0.08;
+/// Turning faster than this, the horizontal acceleration is not along the
+/// truck.
+const MAX_TURN_DPS: f64 = 3.0;
+/// Horizontal acceleration this much beyond the speed change is mostly
+/// cornering (accelerometer-only chips cannot see the turn).
+const MAX_EXCESS: f64 = 2.0;
+const FORWARD_SAMPLES: u32 = 300;
+/// Length of the summed forward vectors over the sum of their lengths;
+/// lower means the samples disagreed and are collected again.
+const MIN_AGREEMENT: f64 = 0.8;
+
+/// How the chip sits in the truck. The rows of `rotation` are the truck's
+/// forward, left and up axes in chip coordinates.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Mounting {
+    pub rotation: [[f64; 3]; 3],
+    /// Gyro output at rest in chip axes (deg/s); zero without a gyro.
+    pub gyro_bias: [f64; 3],
+}
+
+impl Mounting {
+    /// `None` when there is no file yet; an unreadable one is logged and
+    /// calibrated over.
+    pub fn load(path: &str) -> Option<Self> {
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!(path=%path, error=%e, "Failed to read IMU mounting — calibrating again");
+                return None;
+            }
+        };
+        serde_json::from_slice(&bytes)
+            .map_err(|e| warn!(path=%path, error=%e, "Unreadable IMU mounting — calibrating again"))
+            .ok()
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let path = Path::new(path);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Write, sync and rename so a power cut leaves the old file or the new
+        // one, never a torn or empty one
+        let tmp = path.with_extension("tmp");
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(&serde_json::to_vec_pretty(self)?)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, path)?;
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        std::fs::File::open(dir)?.sync_all()
+    }
+
+    /// Chip-axis vector in truck axes.
+    pub fn rotate(&self, v: [f64; 3]) -> [f64; 3] {
+        self.rotation.map(|row| dot(row, v))
+    }
+
+    /// Gyro reading in truck axes, bias removed.
+    pub fn rotate_gyro(&self, gyro: [f64; 3]) -> [f64; 3] {
+        self.rotate(sub(gyro, self.gyro_bias))
+    }
+}
+
+/// Stationary samples collected so far.
+struct StillWindow {
+    since_ms: i64,
+    count: f64,
+    accel_sum: [f64; 3],
+    gyro_sum: [f64; 3],
+}
+
+#[derive(Default)]
+pub struct Calibrator {
+    still: Option<StillWindow>,
+    /// Unit vector up and gyro bias, both in chip axes.
+    level: Option<([f64; 3], [f64; 3])>,
+    speeds: VecDeque<(i64, f64)>,
+    forward_sum: [f64; 3],
+    forward_length: f64,
+    forward_samples: u32,
+}
+
+impl Calibrator {
+    /// Vehicle speed (m/s) from one source, in time order.
+    pub fn on_speed(&mut self, at_ms: i64, speed: f64) {
+        self.speeds.push_back((at_ms, speed));
+        while self.speeds.front().is_some_and(|(t, _)| at_ms - t > SPEED_BASELINE_MS) {
+            self.speeds.pop_front();
+        }
+    }
+
+    /// Feeds one sample; returns the mounting once it is known.
+    pub fn on_sample(&mut self, at_ms: i64, sample: &Sample) -> Option<Mounting> {
+        match self.level {
+            None => {
+                self.on_still_sample(at_ms, sample);
+                None
+            }
+            Some((up, gyro_bias)) => self.on_forward_sample(at_ms, sample, up, gyro_bias),
+        }
+    }
+
+    fn on_still_sample(&mut self, at_ms: i64, sample: &Sample) {
+        let gyro = sample.gyro.unwrap_or([0.0; 3]);
+        let moving = self.speed_at(at_ms).is_some_and(|(speed, _)| speed > 0.5);
+        let still = self.still.as_ref().is_some_and(|w| {
+            norm(sub(sample.accel, scale(w.accel_sum, 1.0 / w.count))) < STILL_TOLERANCE_G
+                && norm(sub(gyro, scale(w.gyro_sum, 1.0 / w.count))) < STILL_TOLERANCE_DPS
+        });
+        if moving {
+            self.still = None;
+            return;
+        }
+        let window = match self.still.as_mut() {
+            Some(window) if still => window,
+            _ => self.still.insert(StillWindow {
+                since_ms: at_ms,
+                count: 0.0,
+                accel_sum: [0.0; 3],
+                gyro_sum: [0.0; 3],
+            }),
+        };
+        window.count += 1.0;
+        window.accel_sum = add(window.accel_sum, sample.accel);
+        window.gyro_sum = add(window.gyro_sum, gyro);
+
+        if at_ms - window.since_ms >= STILL_MS {
+            let up = unit(window.accel_sum);
+            let gyro_bias = scale(window.gyro_sum, 1.0 / window.count);
+            self.level = Some((up, gyro_bias));
+        }
+    }
+
+    fn on_forward_sample(&mut self, at_ms: i64, sample: &Sample, up: [f64; 3], gyro_bias: [f64; 3]) -> Option<Mounting> {
+        let (_, speed_change) = self.speed_at(at_ms)?;
+        if speed_change.abs() < MIN_SPEED_CHANGE {
+            return None;
+        }
+        let horizontal = sub(sample.accel, scale(up, dot(sample.accel, up)));
+        let length = norm(horizontal);
+        if length < MIN_HORIZONTAL_G || length * GRAVITY > MAX_EXCESS * speed_change.abs() {
+            return None;
+        }
+        if sample.gyro.is_some_and(|g| dot(sub(g, gyro_bias), up).abs() > MAX_TURN_DPS) {
+            return None;
+        }
+
+        // Braking pushes the chip backwards
+        self.forward_sum = add(self.forward_sum, scale(horizontal, speed_change.signum()));
+        self.forward_length += length;
+        self.forward_samples += 1;
+        if self.forward_samples < FORWARD_SAMPLES {
+            return None;
+        }
+
+        let agreement = norm(self.forward_sum) / self.forward_length;
+        let forward = unit(sub(self.forward_sum, scale(up, dot(self.forward_sum, up))));
+        self.forward_sum = [0.0; 3];
+        self.forward_length = 0.0;
+        self.forward_samples = 0;
+        if agreement < MIN_AGREEMENT {
+            warn!(agreement, "IMU forward axis inconsistent — collecting again");
+            return None;
+        }
+        Some(Mounting {
+            rotation: [forward, cross(up, forward), up],
+            gyro_bias,
+        })
+    }
+
+    /// Latest speed and its rate of change (m/s²), if recent enough.
+    fn speed_at(&self, at_ms: i64) -> Option<(f64, f64)> {
+        let (&(first_ms, first), &(last_ms, last)) = (self.speeds.front()?, self.speeds.back()?);
+        if at_ms - last_ms > SPEED_TIMEOUT_MS || last_ms - first_ms < MIN_SPEED_BASELINE_MS {
+            return None;
+        }
+        Some((last, (last - first) * 1000.0 / (last_ms - first_ms) as f64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_learns_a_tilted_mounting_from_a_drive() {
+        // Chip on a bracket: rows are the truck's forward, left and up axes
+        let up = unit([0.3, -0.5, 0.81]);
+        let forward = unit(sub([-0.6, -0.7, 0.1], scale(up, dot([-0.6, -0.7, 0.1], up))));
+        let truth = [forward, cross(up, forward), up];
+        let gyro_bias = [0.8, -0.3, 1.5];
+        let to_chip = |v: [f64; 3]| add(add(scale(truth[0], v[0]), scale(truth[1], v[1])), scale(truth[2], v[2]));
+
+        let mut calibrator = Calibrator::default();
+        let mut speed = 0.0;
+        let mut learned = None;
+        for tick in 0..3000 {
+            let t = tick as f64 / 100.0;
+            // Parked, then pulling away at 0.15 g, then a bend taken at speed
+            let (longitudinal, lateral, yaw_dps) = match t {
+                t if t < 5.0 => (0.0, 0.0, 0.0),
+                t if t < 15.0 => (0.15, 0.0, 0.0),
+                _ => (0.0, 0.25, 6.0),
+            };
+            speed += longitudinal * GRAVITY * 0.01;
+            if tick % 10 == 0 {
+                // OBD-II whole km/h at 10 Hz
+                calibrator.on_speed(tick * 10, (speed * 3.6).round() / 3.6);
+            }
+            let vibration = 0.01 * (t * 83.0).sin();
+            let sample = Sample {
+                accel: to_chip([longitudinal + vibration, lateral, 1.0 - vibration]),
+                gyro: Some(add(to_chip([0.0, 0.0, yaw_dps]), gyro_bias)),
+            };
+            if let Some(mounting) = calibrator.on_sample(tick * 10, &sample) {
+                learned = Some((t, mounting));
+                break;
+            }
+        }
+
+        let (at, mounting) = learned.expect("mounting never learned");
+        assert!((5.0..15.0).contains(&at), "learned at {} s", at);
+        for (row, expected) in mounting.rotation.iter().zip(truth) {
+            assert!(dot(*row, expected) > 0.999, "{:?} vs {:?}", row, expected);
+        }
+        assert!(norm(sub(mounting.gyro_bias, gyro_bias)) < 0.01);
+        let braking = mounting.rotate(to_chip([-0.4, 0.0, 1.0]));
+        assert!((braking[0] + 0.4).abs() < 0.01 && braking[1].abs() < 0.01);
+    }
+}
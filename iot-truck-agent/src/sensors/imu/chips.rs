@@ -0,0 +1,196 @@
+//! Register-level I2C drivers. Each reads acceleration (g) and, where the
+//! chip has a gyro, angular rate (deg/s), in the chip's own axes. Every chip
+//! is set to about 100 Hz, ±4 g and (gyros) ±500 deg/s.
+
+use crate::config::ImuChip;
+use crate::sensors::SourceResult;
+use embedded_hal::i2c::I2c;
+use std::fmt::Debug;
+
+pub struct Sample {
+    pub accel: [f64; 3],
+    pub gyro: Option<[f64; 3]>,
+}
+
+pub trait Chip: Send {
+    fn read(&mut self) -> SourceResult<Sample>;
+}
+
+/// Probes and configures `chip` at `address`, or at its usual address.
+pub fn open<I>(i2c: I, chip: ImuChip, address: Option<u8>) -> SourceResult<Box<dyn Chip>>
+where
+    I: I2c + Send + 'static,
+    I::Error: Debug,
+{
+    Ok(match chip {
+        ImuChip::Lis3dh => Box::new(Lis3dh::open(Bus::new(i2c, address.unwrap_or(0x18)))?),
+        ImuChip::Mpu6050 => Box::new(Mpu6050::open(Bus::new(i2c, address.unwrap_or(0x68)))?),
+        ImuChip::Lsm6ds3 => Box::new(Lsm6ds3::open(Bus::new(i2c, address.unwrap_or(0x6A)))?),
+    })
+}
+
+struct Bus<I> {
+    i2c: I,
+    address: u8,
+}
+
+impl<I: I2c> Bus<I>
+where
+    I::Error: Debug,
+{
+    fn new(i2c: I, address: u8) -> Self {
+        Self { i2c, address }
+    }
+
+    fn write(&mut self, register: u8, value: u8) -> SourceResult<()> {
+        self.i2c
+            .write(self.address, &[register, value])
+            .map_err(|e| format!("I2C write to 0x{:02X} failed: {:?}", self.address, e).into())
+    }
+
+    fn read(&mut self, register: u8, buf: &mut [u8]) -> SourceResult<()> {
+        self.i2c
+            .write_read(self.address, &[register], buf)
+            .map_err(|e| format!("I2C read from 0x{:02X} failed: {:?}", self.address, e).into())
+    }
+
+    /// Fails unless the identity register holds one of `expected`, so a
+    /// wrong `chip` setting is caught at open rather than read as garbage.
+    fn expect_id(&mut self, register: u8, expected: &[u8], name: &str) -> SourceResult<()> {
+        let mut id = [0u8];
+        self.read(register, &mut id)?;
+        if !expected.contains(&id[0]) {
+            return Err(format!("No {} at 0x{:02X} (WHO_AM_I 0x{:02X})", name, self.address, id[0]).into());
+        }
+        Ok(())
+    }
+}
+
+struct Lis3dh<I> {
+    bus: Bus<I>,
+}
+
+impl<I: I2c> Lis3dh<I>
+where
+    I::Error: Debug,
+{
+    const WHO_AM_I: u8 = 0x0F;
+    const CTRL_REG1: u8 = 0x20;
+    const CTRL_REG4: u8 = 0x23;
+    const OUT_X_L: u8 = 0x28;
+    /// Register address bit for auto-increment.
+    const INCREMENT: u8 = 0x80;
+    /// ±4 g, high resolution: 2 mg per 12-bit count.
+    const G_PER_COUNT: f64 = 0.002;
+
+    fn open(mut bus: Bus<I>) -> SourceResult<Self> {
+        bus.expect_id(Self::WHO_AM_I, &[0x33], "LIS3DH")?;
+        bus.write(Self::CTRL_REG1, 0x57)?; // 100 Hz, x/y/z on
+        bus.write(Self::CTRL_REG4, 0x98)?; // block update, ±4 g, high resolution
+        Ok(Self { bus })
+    }
+}
+
+impl<I: I2c + Send> Chip for Lis3dh<I>
+where
+    I::Error: Debug,
+{
+    fn read(&mut self) -> SourceResult<Sample> {
+        let mut raw = [0u8; 6];
+        self.bus.read(Self::OUT_X_L | Self::INCREMENT, &mut raw)?;
+        // Left-justified 12-bit values
+        let axis = |i: usize| (i16::from_le_bytes([raw[2 * i], raw[2 * i + 1]]) >> 4) as f64 * Self::G_PER_COUNT;
+        Ok(Sample {
+            accel: [axis(0), axis(1), axis(2)],
+            gyro: None,
+        })
+    }
+}
+
+struct Mpu6050<I> {
+    bus: Bus<I>,
+}
+
+impl<I: I2c> Mpu6050<I>
+where
+    I::Error: Debug,
+{
+    const SMPLRT_DIV: u8 = 0x19;
+    const CONFIG: u8 = 0x1A;
+    const GYRO_CONFIG: u8 = 0x1B;
+    const ACCEL_CONFIG: u8 = 0x1C;
+    const ACCEL_XOUT_H: u8 = 0x3B;
+    const PWR_MGMT_1: u8 = 0x6B;
+    const WHO_AM_I: u8 = 0x75;
+    const COUNTS_PER_G: f64 = 8192.0;
+    const COUNTS_PER_DPS: f64 = 65.5;
+
+    fn open(mut bus: Bus<I>) -> SourceResult<Self> {
+        // 0x68 whatever the AD0 pin says; the register-compatible MPU-6500 answers 0x70
+        bus.expect_id(Self::WHO_AM_I, &[0x68, 0x70], "MPU-6050")?;
+        bus.write(Self::PWR_MGMT_1, 0x01)?; // awake, clocked from the x gyro
+        bus.write(Self::CONFIG, 0x03)?; // 44 Hz low-pass, 1 kHz internal rate
+        bus.write(Self::SMPLRT_DIV, 9)?; // 1 kHz / (1 + 9) = 100 Hz
+        bus.write(Self::GYRO_CONFIG, 0x08)?; // ±500 deg/s
+        bus.write(Self::ACCEL_CONFIG, 0x08)?; // ±4 g
+        Ok(Self { bus })
+    }
+}
+
+impl<I: I2c + Send> Chip for Mpu6050<I>
+where
+    I::Error: Debug,
+{
+    fn read(&mut self) -> SourceResult<Sample> {
+        // Accelerometer, temperature, gyro; big-endian
+        let mut raw = [0u8; 14];
+        self.bus.read(Self::ACCEL_XOUT_H, &mut raw)?;
+        let word = |i: usize| i16::from_be_bytes([raw[2 * i], raw[2 * i + 1]]) as f64;
+        Ok(Sample {
+            accel: [0, 1, 2].map(|i| word(i) / Self::COUNTS_PER_G),
+            gyro: Some([4, 5, 6].map(|i| word(i) / Self::COUNTS_PER_DPS)),
+        })
+    }
+}
+
+struct Lsm6ds3<I> {
+    bus: Bus<I>,
+}
+
+impl<I: I2c> Lsm6ds3<I>
+where
+    I::Error: Debug,
+{
+    const WHO_AM_I: u8 = 0x0F;
+    const CTRL1_XL: u8 = 0x10;
+    const CTRL2_G: u8 = 0x11;
+    const CTRL3_C: u8 = 0x12;
+    const OUTX_L_G: u8 = 0x22;
+    const G_PER_COUNT: f64 = 0.000_122;
+    const DPS_PER_COUNT: f64 = 0.017_5;
+
+    fn open(mut bus: Bus<I>) -> SourceResult<Self> {
+        // LSM6DS3, LSM6DS3TR-C
+        bus.expect_id(Self::WHO_AM_I, &[0x69, 0x6A], "LSM6DS3")?;
+        bus.write(Self::CTRL3_C, 0x44)?; // block update, auto-increment
+        bus.write(Self::CTRL1_XL, 0x48)?; // 104 Hz, ±4 g
+        bus.write(Self::CTRL2_G, 0x44)?; // 104 Hz, ±500 deg/s
+        Ok(Self { bus })
+    }
+}
+
+impl<I: I2c + Send> Chip for Lsm6ds3<I>
+where
+    I::Error: Debug,
+{
+    fn read(&mut self) -> SourceResult<Sample> {
+        // Gyro, then accelerometer; little-endian
+        let mut raw = [0u8; 12];
+        self.bus.read(Self::OUTX_L_G, &mut raw)?;
+        let word = |i: usize| i16::from_le_bytes([raw[2 * i], raw[2 * i + 1]]) as f64;
+        Ok(Sample {
+            accel: [3, 4, 5].map(|i| word(i) * Self::G_PER_COUNT),
+            gyro: Some([0, 1, 2].map(|i| word(i) * Self::DPS_PER_COUNT)),
+        })
+    }
+}
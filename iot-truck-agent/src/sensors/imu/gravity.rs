@@ -0,0 +1,146 @@
+//! Gravity in truck axes, tracked through road grade and body roll. The gyro
+//! turns the estimate with the truck; whenever the truck is not manoeuvring
+//! the accelerometer pulls it back, slowly enough that a long brake or curve
+//! barely leaks in. Without a gyro only the second half is possible, so the
+//! estimate lags grade changes by a few more seconds.
+//!
+//! The agent may start mid-brake or mid-curve, so the first estimate waits
+//! for a steady stretch of about 1 g, close to vertical and without turning;
+//! until then there is no gravity-free acceleration to alert on.
+
+use super::{add, cross, norm, scale, sub};
+
+/// Below this much acceleration beside gravity the truck counts as cruising.
+const QUIET_G: f64 = 0.05;
+/// Time constants (s) of the pull towards the accelerometer.
+const TAU_WITH_GYRO: f64 = 2.0;
+const TAU_WITHOUT_GYRO: f64 = 10.0;
+const TAU_MANOEUVRING: f64 = 60.0;
+/// Longer gaps are not integrated.
+const MAX_STEP_MS: i64 = 1000;
+/// Length of the steady stretch the first estimate is taken from.
+const SEED_MS: i64 = 2000;
+const SEED_TURN_DPS: f64 = 1.0;
+/// Horizontal part of gravity on a 10% grade or cross slope; anything more
+/// at start-up is the truck accelerating.
+const SEED_MAX_TILT_G: f64 = 0.1;
+
+#[derive(Default)]
+pub struct GravityFilter {
+    gravity: Option<[f64; 3]>,
+    seed: Option<Seed>,
+    last_ms: i64,
+}
+
+/// Steady samples collected before the first estimate.
+struct Seed {
+    since_ms: i64,
+    count: f64,
+    accel_sum: [f64; 3],
+}
+
+impl GravityFilter {
+    /// Takes acceleration (g) and bias-free angular rate (deg/s) in truck
+    /// axes, and returns the acceleration with gravity removed once the
+    /// filter has settled.
+    pub fn update(&mut self, at_ms: i64, accel: [f64; 3], gyro: Option<[f64; 3]>) -> Option<[f64; 3]> {
+        let Some(mut gravity) = self.gravity else {
+            self.seed(at_ms, accel, gyro);
+            return None;
+        };
+        let dt = (at_ms - self.last_ms).clamp(0, MAX_STEP_MS) as f64 / 1000.0;
+        self.last_ms = at_ms;
+
+        if let Some(gyro) = gyro {
+            // Gravity stays put while the truck turns under it
+            let rate = gyro.map(f64::to_radians);
+            gravity = sub(gravity, scale(cross(rate, gravity), dt));
+        }
+        let quiet = norm(sub(accel, gravity)) < QUIET_G;
+        let tau = match (quiet, gyro.is_some()) {
+            (false, _) => TAU_MANOEUVRING,
+            (true, true) => TAU_WITH_GYRO,
+            (true, false) => TAU_WITHOUT_GYRO,
+        };
+        gravity = add(gravity, scale(sub(accel, gravity), (dt / tau).min(1.0)));
+        self.gravity = Some(gravity);
+        Some(sub(accel, gravity))
+    }
+
+    fn seed(&mut self, at_ms: i64, accel: [f64; 3], gyro: Option<[f64; 3]>) {
+        // Braking, cornering or a bump shows up as a tilt, a magnitude off 1 g or a turn
+        if (norm(accel) - 1.0).abs() >= QUIET_G
+            || accel[0].hypot(accel[1]) >= SEED_MAX_TILT_G
+            || gyro.is_some_and(|g| norm(g) >= SEED_TURN_DPS)
+        {
+            self.seed = None;
+            return;
+        }
+        let steady = self
+            .seed
+            .as_ref()
+            .is_some_and(|s| norm(sub(accel, scale(s.accel_sum, 1.0 / s.count))) < QUIET_G);
+        let seed = match self.seed.as_mut() {
+            Some(seed) if steady => seed,
+            _ => self.seed.insert(Seed {
+                since_ms: at_ms,
+                count: 0.0,
+                accel_sum: [0.0; 3],
+            }),
+        };
+        seed.count += 1.0;
+        seed.accel_sum = add(seed.accel_sum, accel);
+
+        if at_ms - seed.since_ms >= SEED_MS {
+            self.gravity = Some(scale(seed.accel_sum, 1.0 / seed.count));
+            self.last_ms = at_ms;
+            self.seed = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_removes_gravity_through_a_grade_change_and_braking() {
+        let mut filter = GravityFilter::default();
+        // Level, then up onto a 6% grade between 5 s and 7 s
+        let final_pitch = 0.06f64.atan();
+        let mut worst: f64 = 0.0;
+        for tick in 0..1500 {
+            let t = tick as f64 / 100.0;
+            let pitch = (t.clamp(5.0, 7.0) - 5.0) / 2.0 * final_pitch;
+            let pitch_rate = if (5.0..7.0).contains(&t) { final_pitch / 2.0 } else { 0.0 };
+            let braking = if (10.0..13.0).contains(&t) { -0.4 } else { 0.0 };
+
+            // Nose up is a negative turn about the left axis
+            let accel = [pitch.sin() + braking, 0.0, pitch.cos()];
+            let gyro = [0.0, -pitch_rate.to_degrees(), 0.0];
+            if let Some(linear) = filter.update(tick * 10, accel, Some(gyro)) {
+                worst = worst.max((linear[0] - braking).abs()).max(linear[2].abs());
+            }
+        }
+        assert!(worst < 0.03, "worst error {} g", worst);
+    }
+
+    #[test]
+    fn test_waits_for_a_steady_stretch_when_started_mid_brake() {
+        let mut filter = GravityFilter::default();
+        let mut first = None;
+        let mut worst: f64 = 0.0;
+        for tick in 0..800 {
+            let braking = if tick < 300 { -0.3 } else { 0.0 };
+            let Some(linear) = filter.update(tick * 10, [braking, 0.0, 1.0], Some([0.0; 3])) else {
+                assert!(first.is_none(), "settled filter went quiet at tick {}", tick);
+                continue;
+            };
+            first.get_or_insert(tick);
+            worst = worst.max((linear[0] - braking).abs()).max(linear[2].abs());
+        }
+        // Nothing while braking, then two seconds after it ended
+        assert_eq!(first, Some(501));
+        assert!(worst < 0.01, "worst error {} g", worst);
+    }
+}
@@ -0,0 +1,190 @@
+//! IMU on I2C: an accelerometer-only LIS3DH, or an MPU-6050 or LSM6DS3 with
+//! a gyro. Readings are in chip axes until the mounting is calibrated, then
+//! in the truck's, with gravity removed from the longitudinal, lateral and
+//! vertical acceleration.
+
+use crate::config::ImuConfig;
+use crate::sensors::types::{ImuData, SensorEvent, SensorType, SensorValues};
+use crate::sensors::{Reading, SensorSource, SourceResult};
+use calibration::{Calibrator, Mounting};
+use chips::{Chip, Sample};
+use chrono::Utc;
+use gravity::GravityFilter;
+use linux_embedded_hal::I2cdev;
+use tokio::sync::broadcast::{self, error::TryRecvError};
+use tracing::{error, info};
+
+pub mod calibration;
+pub mod chips;
+pub mod gravity;
+
+/// Read at 100 Hz.
+pub struct ImuReader {
+    device_path: String,
+    calibration_path: String,
+    chip: Box<dyn Chip>,
+    tick: tokio::time::Interval,
+    mounting: Option<Mounting>,
+    calibrator: Calibrator,
+    /// Every sensor's events, for the vehicle speed; dropped once calibrated.
+    events: Option<broadcast::Receiver<SensorEvent>>,
+    /// The calibrator follows one speed: the first sensor to report movement.
+    speed_source: Option<String>,
+    gravity: GravityFilter,
+}
+
+impl ImuReader {
+    pub fn open(device_path: &str, config: &ImuConfig, events: broadcast::Receiver<SensorEvent>) -> SourceResult<Self> {
+        let i2c_dev = I2cdev::new(device_path)
+            .map_err(|e| format!("Failed to open I2C device {}: {}", device_path, e))?;
+        let chip = chips::open(i2c_dev, config.chip, config.address)?;
+
+        let mounting = Mounting::load(&config.calibration_path);
+        if mounting.is_some() {
+            info!(device=%device_path, chip=?config.chip, "🌀 IMU reader started");
+        } else {
+            info!(
+                device=%device_path,
+                chip=?config.chip,
+                "🌀 IMU reader started — mounting not calibrated yet: park for a few seconds, then accelerate and brake in a straight line"
+            );
+        }
+
+        Ok(Self {
+            device_path: device_path.to_string(),
+            calibration_path: config.calibration_path.clone(),
+            chip,
+            tick: tokio::time::interval(tokio::time::Duration::from_millis(10)), // 100 Hz
+            events: mounting.is_none().then_some(events),
+            mounting,
+            calibrator: Calibrator::default(),
+            speed_source: None,
+            gravity: GravityFilter::default(),
+        })
+    }
+
+    /// Passes the speeds published since the last sample to the calibrator.
+    fn drain_speeds(&mut self) {
+        let Some(events) = self.events.as_mut() else {
+            return;
+        };
+        loop {
+            let event = match events.try_recv() {
+                Ok(event) => event,
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(TryRecvError::Empty | TryRecvError::Closed) => return,
+            };
+            let speed_kmh = match &event.values {
+                SensorValues::Obd(obd) => obd.speed_kmh as f32,
+                SensorValues::J1939(j1939) => match j1939.wheel_speed_kmh {
+                    Some(speed_kmh) => speed_kmh,
+                    None => continue,
+                },
+                SensorValues::Gps(gps) if gps.fix_quality > 0 => gps.speed_kmh,
+                _ => continue,
+            };
+            // An ECU without the speed PID reports 0 for ever
+            if self.speed_source.is_none() && speed_kmh > 0.0 {
+                self.speed_source = Some(event.sensor_id.clone());
+            }
+            if self.speed_source.as_ref() == Some(&event.sensor_id) {
+                self.calibrator
+                    .on_speed(event.timestamp.timestamp_millis(), speed_kmh as f64 / 3.6);
+            }
+        }
+    }
+
+    fn process(&mut self, at_ms: i64, sample: Sample) -> ImuData {
+        if self.mounting.is_none() {
+            self.drain_speeds();
+            if let Some(mounting) = self.calibrator.on_sample(at_ms, &sample) {
+                info!(rotation=?mounting.rotation, gyro_bias=?mounting.gyro_bias, "🧭 IMU mounting calibrated");
+                if let Err(e) = mounting.save(&self.calibration_path) {
+                    error!(path=%self.calibration_path, error=%e, "Failed to save IMU mounting");
+                }
+                self.mounting = Some(mounting);
+                self.events = None;
+            }
+        }
+
+        let Some(mounting) = &self.mounting else {
+            let gyro = sample.gyro.unwrap_or([0.0; 3]);
+            return ImuData {
+                accel_x: sample.accel[0] as f32,
+                accel_y: sample.accel[1] as f32,
+                accel_z: sample.accel[2] as f32,
+                gyro_x: gyro[0] as f32,
+                gyro_y: gyro[1] as f32,
+                gyro_z: gyro[2] as f32,
+                longitudinal_g: None,
+                lateral_g: None,
+                vertical_g: None,
+            };
+        };
+        let accel = mounting.rotate(sample.accel);
+        let gyro = sample.gyro.map(|g| mounting.rotate_gyro(g));
+        let linear = self.gravity.update(at_ms, accel, gyro);
+        let gyro = gyro.unwrap_or([0.0; 3]);
+        ImuData {
+            accel_x: accel[0] as f32,
+            accel_y: accel[1] as f32,
+            accel_z: accel[2] as f32,
+            gyro_x: gyro[0] as f32,
+            gyro_y: gyro[1] as f32,
+            gyro_z: gyro[2] as f32,
+            longitudinal_g: linear.map(|l| l[0] as f32),
+            lateral_g: linear.map(|l| l[1] as f32),
+            vertical_g: linear.map(|l| l[2] as f32),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SensorSource for ImuReader {
+    fn sensor_id(&self) -> &str {
+        &self.device_path
+    }
+
+    fn sensor_type(&self) -> SensorType {
+        SensorType::Imu
+    }
+
+    async fn next_reading(&mut self) -> SourceResult<Option<Reading>> {
+        self.tick.tick().await;
+        let sample = self.chip.read()?;
+        let imu = self.process(Utc::now().timestamp_millis(), sample);
+
+        Ok(Some(Reading {
+            values: SensorValues::Imu(imu),
+            raw_payload: None,
+        }))
+    }
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn add(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: [f64; 3], k: f64) -> [f64; 3] {
+    a.map(|v| v * k)
+}
+
+fn norm(a: [f64; 3]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+fn unit(a: [f64; 3]) -> [f64; 3] {
+    scale(a, 1.0 / norm(a))
+}
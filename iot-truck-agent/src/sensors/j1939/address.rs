@@ -0,0 +1,107 @@
+//! Address claim (SAE J1939-81). The agent claims an address before it sends
+//! requests or takes part in connection-mode transfers, answers requests for
+//! claimed addresses, and moves to a free address in the self-configurable
+//! range when an ECU with a lower NAME claims the same one.
+
+use super::{J1939Frame, GLOBAL, NULL_ADDRESS};
+use crate::config::J1939Config;
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+pub const ADDRESS_CLAIMED: u32 = 60928;
+pub const REQUEST: u32 = 59904;
+
+const DYNAMIC_RANGE: std::ops::RangeInclusive<u8> = 128..=247;
+
+/// 64-bit NAME from the configured fields: arbitrary address capable,
+/// on-highway industry group, function, manufacturer code, identity number.
+fn name(config: &J1939Config) -> u64 {
+    (config.arbitrary_address_capable as u64) << 63
+        | 1 << 60
+        | (config.function as u64) << 40
+        | ((config.manufacturer_code & 0x7FF) as u64) << 21
+        | (config.identity_number & 0x1F_FFFF) as u64
+}
+
+pub struct AddressClaimer {
+    name: u64,
+    arbitrary: bool,
+    address: u8,
+    /// Addresses other ECUs hold, with their NAMEs.
+    peers: HashMap<u8, u64>,
+}
+
+impl AddressClaimer {
+    pub fn new(config: &J1939Config) -> Self {
+        Self {
+            name: name(config),
+            arbitrary: config.arbitrary_address_capable,
+            address: config.preferred_address,
+            peers: HashMap::new(),
+        }
+    }
+
+    /// Our address, or `None` after failing to claim one.
+    pub fn address(&self) -> Option<u8> {
+        (self.address != NULL_ADDRESS).then_some(self.address)
+    }
+
+    /// Address Claimed for our current address (Cannot Claim from the null
+    /// address if we have none).
+    pub fn claim(&self) -> J1939Frame {
+        J1939Frame {
+            priority: 6,
+            pgn: ADDRESS_CLAIMED,
+            source: self.address,
+            destination: GLOBAL,
+            data: self.name.to_le_bytes().to_vec(),
+        }
+    }
+
+    /// Handles claims and requests from the bus; returns the frame to answer with.
+    pub fn on_frame(&mut self, frame: &J1939Frame) -> Option<J1939Frame> {
+        match frame.pgn {
+            ADDRESS_CLAIMED => {
+                let their_name = u64::from_le_bytes(frame.data.get(..8)?.try_into().ok()?);
+                self.peers.retain(|_, name| *name != their_name);
+                if frame.source != NULL_ADDRESS {
+                    self.peers.insert(frame.source, their_name);
+                }
+                if frame.source != self.address || their_name == self.name {
+                    return None;
+                }
+                if their_name > self.name {
+                    // We win; restate the claim
+                    return Some(self.claim());
+                }
+                self.lose();
+                Some(self.claim())
+            }
+            REQUEST if frame.destination == GLOBAL || frame.destination == self.address => {
+                let requested = frame.data.get(..3)?;
+                (u32::from_le_bytes([requested[0], requested[1], requested[2], 0]) == ADDRESS_CLAIMED)
+                    .then(|| self.claim())
+            }
+            _ => None,
+        }
+    }
+
+    fn lose(&mut self) {
+        let lost = self.address;
+        let free = if self.arbitrary {
+            DYNAMIC_RANGE.find(|a| *a != lost && !self.peers.contains_key(a))
+        } else {
+            None
+        };
+        match free {
+            Some(address) => {
+                info!(lost, address, "🔀 J1939 address taken by a higher priority ECU, moving");
+                self.address = address;
+            }
+            None => {
+                warn!(lost, "J1939 address lost and no free address to claim");
+                self.address = NULL_ADDRESS;
+            }
+        }
+    }
+}
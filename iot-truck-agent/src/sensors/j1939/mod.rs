@@ -0,0 +1,280 @@
+//! SAE J1939 on the truck's CAN bus, for heavy-duty vehicles where OBD-II
+//! over ELM327 reports little.
+//!
+//! The reader claims an address, reassembles multi-packet transfers, asks
+//! for the PGNs ECUs only send on request, and publishes the latest decoded
+//! values on a fixed interval.
+
+use crate::config::J1939Config;
+use crate::sensors::types::{SensorType, SensorValues};
+use crate::sensors::{Reading, SensorSource, SourceResult};
+use address::AddressClaimer;
+use pgn::J1939State;
+use socketcan::tokio::CanSocket;
+use socketcan::{CanFrame, EmbeddedFrame, ExtendedId, Frame};
+use tokio::time::{interval, interval_at, Duration, Instant, Interval, MissedTickBehavior};
+use tracing::info;
+use transport::Transport;
+
+pub mod address;
+pub mod pgn;
+pub mod transport;
+
+pub const GLOBAL: u8 = 0xFF;
+pub const NULL_ADDRESS: u8 = 0xFE;
+
+/// Time a claim must stand unchallenged before the address is used.
+const CLAIM_SETTLE: Duration = Duration::from_millis(250);
+
+/// A J1939 message: one CAN frame, or a transfer reassembled from several.
+#[derive(Debug, Clone, PartialEq)]
+pub struct J1939Frame {
+    pub priority: u8,
+    pub pgn: u32,
+    pub source: u8,
+    /// `GLOBAL` for broadcast (PDU2) PGNs.
+    pub destination: u8,
+    pub data: Vec<u8>,
+}
+
+impl J1939Frame {
+    pub fn from_can(id: u32, data: &[u8]) -> Self {
+        let dp = (id >> 24) & 0x03;
+        let pf = (id >> 16) & 0xFF;
+        let ps = (id >> 8) & 0xFF;
+        // PDU1 formats carry a destination address in PS, PDU2 a group extension
+        let (pgn, destination) = if pf < 240 {
+            (dp << 16 | pf << 8, ps as u8)
+        } else {
+            (dp << 16 | pf << 8 | ps, GLOBAL)
+        };
+        Self {
+            priority: ((id >> 26) & 0x07) as u8,
+            pgn,
+            source: (id & 0xFF) as u8,
+            destination,
+            data: data.to_vec(),
+        }
+    }
+
+    pub fn can_id(&self) -> u32 {
+        let pgn = if (self.pgn >> 8) & 0xFF < 240 {
+            self.pgn & 0x3_FF00 | self.destination as u32
+        } else {
+            self.pgn
+        };
+        (self.priority as u32) << 26 | pgn << 8 | self.source as u32
+    }
+
+    fn to_can(&self) -> Option<CanFrame> {
+        CanFrame::new(ExtendedId::new(self.can_id())?, &self.data)
+    }
+}
+
+pub struct J1939Reader {
+    interface: String,
+    socket: CanSocket,
+    claimer: AddressClaimer,
+    transport: Transport,
+    state: J1939State,
+    publish: Interval,
+    requests: Interval,
+}
+
+impl J1939Reader {
+    pub async fn open(config: &J1939Config) -> SourceResult<Self> {
+        let socket = CanSocket::open(&config.interface)
+            .map_err(|e| format!("Failed to open CAN interface {}: {}", config.interface, e))?;
+
+        let mut publish = interval(Duration::from_millis(config.publish_interval_ms.max(1)));
+        publish.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut requests = interval_at(
+            Instant::now() + CLAIM_SETTLE,
+            Duration::from_secs(config.request_interval_sec.max(1)),
+        );
+        requests.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        let reader = Self {
+            interface: config.interface.clone(),
+            socket,
+            claimer: AddressClaimer::new(config),
+            transport: Transport::default(),
+            state: J1939State::default(),
+            publish,
+            requests,
+        };
+        reader.send(&reader.claimer.claim()).await?;
+
+        info!(interface=%config.interface, address=config.preferred_address, "🚛 J1939 reader started");
+        Ok(reader)
+    }
+
+    async fn send(&self, frame: &J1939Frame) -> SourceResult<()> {
+        let can = frame.to_can().ok_or("J1939 frame does not fit in a CAN frame")?;
+        self.socket.write_frame(can).await?;
+        Ok(())
+    }
+
+    async fn handle(&mut self, frame: J1939Frame) -> SourceResult<()> {
+        if let Some(reply) = self.claimer.on_frame(&frame) {
+            self.send(&reply).await?;
+        }
+
+        let mut replies = Vec::new();
+        let message = match frame.pgn {
+            transport::TP_CM | transport::TP_DT => self.transport.on_frame(&frame, self.claimer.address(), &mut replies),
+            _ => Some(frame),
+        };
+        for reply in &replies {
+            self.send(reply).await?;
+        }
+        if let Some(message) = message {
+            self.state.apply(&message);
+        }
+        Ok(())
+    }
+
+    /// Requests the on-request PGNs from every ECU.
+    async fn request_on_demand(&self) -> SourceResult<()> {
+        let Some(source) = self.claimer.address() else {
+            return Ok(()); // may not transmit without an address
+        };
+        for pgn in pgn::ON_REQUEST {
+            self.send(&J1939Frame {
+                priority: 6,
+                pgn: address::REQUEST,
+                source,
+                destination: GLOBAL,
+                data: pgn.to_le_bytes()[..3].to_vec(),
+            })
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl SensorSource for J1939Reader {
+    fn sensor_id(&self) -> &str {
+        &self.interface
+    }
+
+    fn sensor_type(&self) -> SensorType {
+        SensorType::J1939
+    }
+
+    async fn next_reading(&mut self) -> SourceResult<Option<Reading>> {
+        loop {
+            tokio::select! {
+                frame = self.socket.read_frame() => {
+                    // J1939 uses extended identifiers only
+                    if let CanFrame::Data(frame) = frame? {
+                        if frame.is_extended() {
+                            self.handle(J1939Frame::from_can(frame.raw_id(), frame.data())).await?;
+                        }
+                    }
+                }
+                _ = self.requests.tick() => self.request_on_demand().await?,
+                _ = self.publish.tick() => {
+                    if let Some(data) = self.state.snapshot() {
+                        return Ok(Some(Reading {
+                            values: SensorValues::J1939(data),
+                            raw_payload: None,
+                        }));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::types::J1939Fault;
+
+    // candump -L of an engine ECU (0x00) and a body controller (0x21) that
+    // also claims 0x80, with a lower NAME than the agent's
+    const RECORDING: &str = "\
+(1700000000.000100) vcan0 18EEFF21#0100000000000010
+(1700000000.000200) vcan0 0CF00400#F07D7DE02EFFFFFF
+(1700000000.000300) vcan0 18FEF100#FF0058FFFFFFFFFF
+(1700000000.000400) vcan0 18FEF200#8A02FFFFFFFFFFFF
+(1700000000.000500) vcan0 18FEEE00#82FF402FFFFFFFFF
+(1700000000.000600) vcan0 18FEE500#80C40300FFFFFFFF
+(1700000000.000700) vcan0 18FEE900#FFFFFFFF21A10700
+(1700000000.000800) vcan0 18FEC100#0F15AF09FFFFFFFF
+(1700000000.000900) vcan0 18EEFF80#0100000000000010
+(1700000000.001000) vcan0 1CECFF00#200A0002FFCAFE00
+(1700000000.001100) vcan0 1CEBFF00#0104FF6E000003B3
+(1700000000.001200) vcan0 1CEBFF00#020C1001FFFFFFFF";
+
+    fn replay_line(line: &str) -> CanFrame {
+        let frame = line.split_whitespace().nth(2).unwrap();
+        let (id, data) = frame.split_once('#').unwrap();
+        let data: Vec<u8> = (0..data.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&data[i..i + 2], 16).unwrap())
+            .collect();
+        CanFrame::new(ExtendedId::new(u32::from_str_radix(id, 16).unwrap()).unwrap(), &data).unwrap()
+    }
+
+    // Run with `cargo test -- --ignored` after creating a virtual CAN interface:
+    //   ip link add dev vcan0 type vcan && ip link set up vcan0
+    #[tokio::test]
+    #[ignore = "needs the vcan0 interface"]
+    async fn test_decodes_recorded_bus_traffic_from_vcan() {
+        let bus = CanSocket::open("vcan0").expect("vcan0 interface");
+
+        let config = J1939Config {
+            enable: true,
+            interface: "vcan0".to_string(),
+            publish_interval_ms: 100,
+            ..J1939Config::default()
+        };
+        let mut reader = J1939Reader::open(&config).await.unwrap();
+        let claim = J1939Frame::from_can(0x18EE_FF80, &[]);
+        let first = bus.read_frame().await.unwrap();
+        assert_eq!(J1939Frame::from_can(first.raw_id(), &[]), claim);
+
+        for line in RECORDING.lines() {
+            bus.write_frame(replay_line(line)).await.unwrap();
+        }
+
+        let reading = tokio::time::timeout(Duration::from_secs(2), reader.next_reading())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let SensorValues::J1939(data) = reading.values else {
+            panic!("not a J1939 reading");
+        };
+        assert_eq!(data.engine_speed_rpm, Some(1500.0));
+        assert_eq!(data.wheel_speed_kmh, Some(88.0));
+        assert!((data.fuel_rate_lph.unwrap() - 32.5).abs() < 1e-3);
+        assert_eq!(data.coolant_temp_c, Some(90.0));
+        assert_eq!(data.oil_temp_c, Some(105.0));
+        assert!((data.engine_hours.unwrap() - 12345.6).abs() < 1e-6);
+        assert_eq!(data.total_fuel_used_l, Some(250000.5));
+        assert!((data.odometer_km.unwrap() - 812345.675).abs() < 1e-6);
+        assert!(data.amber_warning_lamp && !data.red_stop_lamp);
+        assert_eq!(
+            data.active_faults,
+            [
+                J1939Fault { source_address: 0x00, spn: 110, fmi: 0, occurrence_count: 3 },
+                J1939Fault { source_address: 0x00, spn: 3251, fmi: 16, occurrence_count: 1 },
+            ]
+        );
+
+        // Lost 0x80 to the body controller's lower NAME: re-claimed at 0x81
+        let mut moved = None;
+        while moved.is_none() {
+            let frame = bus.read_frame().await.unwrap();
+            let frame = J1939Frame::from_can(frame.raw_id(), frame.data());
+            if frame.pgn == address::ADDRESS_CLAIMED {
+                moved = Some(frame.source);
+            }
+        }
+        assert_eq!(moved, Some(0x81));
+    }
+}
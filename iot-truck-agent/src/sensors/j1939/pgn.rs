@@ -0,0 +1,130 @@
+//! Decoders for the PGNs the agent reports. Scaling per SAE J1939-71;
+//! values an ECU marks as error or not available leave the last good value.
+
+use super::J1939Frame;
+use crate::sensors::types::{J1939Data, J1939Fault};
+use std::collections::BTreeMap;
+use tokio::time::{Duration, Instant};
+
+pub const EEC1: u32 = 61444; // Electronic Engine Controller 1
+pub const CCVS: u32 = 65265; // Cruise Control/Vehicle Speed
+pub const LFE: u32 = 65266; // Fuel Economy
+pub const LFC: u32 = 65257; // Fuel Consumption
+pub const VDHR: u32 = 65217; // High Resolution Vehicle Distance
+pub const VD: u32 = 65248; // Vehicle Distance
+pub const HOURS: u32 = 65253; // Engine Hours, Revolutions
+pub const ET1: u32 = 65262; // Engine Temperature 1
+pub const DM1: u32 = 65226; // Active Diagnostic Trouble Codes
+
+/// PGNs ECUs only send when asked.
+pub const ON_REQUEST: [u32; 3] = [HOURS, LFC, VDHR];
+
+/// ECUs repeat DM1 every second while a fault is active and may stop
+/// sending it once all clear.
+const DM1_STALE: Duration = Duration::from_secs(3);
+
+/// Latest values seen on the bus.
+#[derive(Default)]
+pub struct J1939State {
+    data: J1939Data,
+    seen: bool,
+    high_res_distance: bool,
+    /// DM1 per source address: lamps (amber, red stop), faults, when received.
+    dm1: BTreeMap<u8, (bool, bool, Vec<J1939Fault>, Instant)>,
+}
+
+impl J1939State {
+    /// Applies a single-frame or reassembled message. Returns false for PGNs
+    /// the agent does not decode.
+    pub fn apply(&mut self, frame: &J1939Frame) -> bool {
+        let d = &frame.data;
+        let data = &mut self.data;
+        match frame.pgn {
+            EEC1 => set(&mut data.engine_speed_rpm, u16_at(d, 3).map(|v| v as f32 * 0.125)),
+            CCVS => set(&mut data.wheel_speed_kmh, u16_at(d, 1).map(|v| v as f32 / 256.0)),
+            LFE => set(&mut data.fuel_rate_lph, u16_at(d, 0).map(|v| v as f32 * 0.05)),
+            LFC => set(&mut data.total_fuel_used_l, u32_at(d, 4).map(|v| v as f64 * 0.5)),
+            HOURS => set(&mut data.engine_hours, u32_at(d, 0).map(|v| v as f64 * 0.05)),
+            ET1 => {
+                set(&mut data.coolant_temp_c, u8_at(d, 0).map(|v| v as f32 - 40.0));
+                set(&mut data.oil_temp_c, u16_at(d, 2).map(|v| v as f32 * 0.03125 - 273.0));
+            }
+            VDHR => {
+                if let Some(meters) = u32_at(d, 0).map(|v| v as f64 * 5.0) {
+                    self.high_res_distance = true;
+                    data.odometer_km = Some(meters / 1000.0);
+                }
+            }
+            VD if !self.high_res_distance => set(&mut data.odometer_km, u32_at(d, 4).map(|v| v as f64 * 0.125)),
+            VD => {}
+            DM1 => {
+                let Some((amber, red, faults)) = decode_dm1(frame.source, d) else {
+                    return false;
+                };
+                self.dm1.insert(frame.source, (amber, red, faults, Instant::now()));
+            }
+            _ => return false,
+        }
+        self.seen = true;
+        true
+    }
+
+    /// Current values, or `None` before anything was decoded.
+    pub fn snapshot(&mut self) -> Option<J1939Data> {
+        if !self.seen {
+            return None;
+        }
+        let now = Instant::now();
+        self.dm1.retain(|_, (.., received)| now.duration_since(*received) < DM1_STALE);
+
+        let mut data = self.data.clone();
+        for (amber, red, faults, _) in self.dm1.values() {
+            data.amber_warning_lamp |= amber;
+            data.red_stop_lamp |= red;
+            data.active_faults.extend(faults.iter().cloned());
+        }
+        Some(data)
+    }
+}
+
+fn set<T>(field: &mut Option<T>, value: Option<T>) {
+    if value.is_some() {
+        *field = value;
+    }
+}
+
+// 0xFB and up (0xFB00, 0xFB000000) flag error, reserved or not available
+fn u8_at(d: &[u8], at: usize) -> Option<u8> {
+    d.get(at).copied().filter(|v| *v <= 0xFA)
+}
+
+fn u16_at(d: &[u8], at: usize) -> Option<u16> {
+    let bytes = d.get(at..at + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]])).filter(|v| *v <= 0xFAFF)
+}
+
+fn u32_at(d: &[u8], at: usize) -> Option<u32> {
+    let bytes = d.get(at..at + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).filter(|v| *v <= 0xFAFF_FFFF)
+}
+
+/// Lamp status then four bytes per DTC (SPN conversion method 4). A single
+/// all-zero DTC means no active faults; all-ones DTCs are padding.
+fn decode_dm1(source: u8, d: &[u8]) -> Option<(bool, bool, Vec<J1939Fault>)> {
+    let lamps = *d.first()?;
+    let amber = (lamps >> 2) & 0b11 == 0b01;
+    let red = (lamps >> 4) & 0b11 == 0b01;
+
+    let faults = d
+        .get(2..)?
+        .chunks_exact(4)
+        .map(|dtc| J1939Fault {
+            source_address: source,
+            spn: dtc[0] as u32 | (dtc[1] as u32) << 8 | ((dtc[2] >> 5) as u32) << 16,
+            fmi: dtc[2] & 0x1F,
+            occurrence_count: dtc[3] & 0x7F,
+        })
+        .filter(|fault| fault.spn != 0 && fault.spn != 0x7_FFFF) // none, padding
+        .collect();
+    Some((amber, red, faults))
+}
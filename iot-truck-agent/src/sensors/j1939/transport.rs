@@ -0,0 +1,205 @@
+//! Transport protocol (SAE J1939-21) for messages over eight bytes: BAM
+//! broadcasts, and RTS/CTS transfers addressed to the agent, which it paces
+//! with clear-to-send windows and acknowledges at the end.
+
+use super::{J1939Frame, GLOBAL};
+use std::collections::HashMap;
+use tokio::time::{Duration, Instant};
+use tracing::debug;
+
+pub const TP_CM: u32 = 60416;
+pub const TP_DT: u32 = 60160;
+
+const RTS: u8 = 16;
+const CTS: u8 = 17;
+const END_OF_MSG_ACK: u8 = 19;
+const BAM: u8 = 32;
+const ABORT: u8 = 255;
+
+const ABORT_BAD_SEQUENCE: u8 = 7;
+
+/// T1/T2: longest gap between packets of a transfer.
+const PACKET_TIMEOUT: Duration = Duration::from_millis(1250);
+
+struct Session {
+    pgn: u32,
+    size: usize,
+    packets: u8,
+    data: Vec<u8>,
+    /// Wider than the sequence numbers so it can pass packet 255.
+    next: u16,
+    /// Last packet of the current CTS window; `None` for BAM.
+    window_end: Option<u16>,
+    max_per_cts: u8,
+    last_packet: Instant,
+}
+
+/// Transfers in progress, by (source, destination).
+#[derive(Default)]
+pub struct Transport {
+    sessions: HashMap<(u8, u8), Session>,
+}
+
+impl Transport {
+    /// Feeds a TP.CM or TP.DT frame. Returns the reassembled message once
+    /// complete; handshake frames to send go to `replies`.
+    pub fn on_frame(&mut self, frame: &J1939Frame, own: Option<u8>, replies: &mut Vec<J1939Frame>) -> Option<J1939Frame> {
+        let key = (frame.source, frame.destination);
+        match frame.pgn {
+            TP_CM => {
+                let d = frame.data.get(..8)?;
+                let pgn = u32::from_le_bytes([d[5], d[6], d[7], 0]);
+                let size = u16::from_le_bytes([d[1], d[2]]) as usize;
+                if matches!(d[0], BAM | RTS) && d[3] == 0 {
+                    debug!(source = frame.source, pgn, "J1939 transfer of zero packets ignored");
+                    return None;
+                }
+                match d[0] {
+                    BAM if frame.destination == GLOBAL => {
+                        self.sessions.insert(key, Session::new(pgn, size, d[3], None, 0));
+                    }
+                    RTS if own.is_some() && Some(frame.destination) == own => {
+                        let max_per_cts = if d[4] == 0xFF { d[3] } else { d[4].min(d[3]) };
+                        let mut session = Session::new(pgn, size, d[3], Some(0), max_per_cts.max(1));
+                        replies.push(session.clear_to_send(frame));
+                        self.sessions.insert(key, session);
+                    }
+                    ABORT => {
+                        self.sessions.remove(&key);
+                    }
+                    _ => {}
+                }
+                None
+            }
+            TP_DT => {
+                let d = frame.data.get(..8)?;
+                let session = self.sessions.get_mut(&key)?;
+                if session.last_packet.elapsed() > PACKET_TIMEOUT || d[0] as u16 != session.next {
+                    debug!(source = frame.source, pgn = session.pgn, "J1939 transfer dropped");
+                    if session.window_end.is_some() {
+                        replies.push(control(frame, [ABORT, ABORT_BAD_SEQUENCE, 0xFF, 0xFF, 0xFF], session.pgn));
+                    }
+                    self.sessions.remove(&key);
+                    return None;
+                }
+
+                session.data.extend_from_slice(&d[1..]);
+                session.next += 1;
+                session.last_packet = Instant::now();
+
+                if session.next > session.packets as u16 {
+                    let mut session = self.sessions.remove(&key)?;
+                    session.data.truncate(session.size);
+                    if session.window_end.is_some() {
+                        let [lo, hi] = (session.size as u16).to_le_bytes();
+                        replies.push(control(frame, [END_OF_MSG_ACK, lo, hi, session.packets, 0xFF], session.pgn));
+                    }
+                    return Some(J1939Frame {
+                        priority: frame.priority,
+                        pgn: session.pgn,
+                        source: frame.source,
+                        destination: frame.destination,
+                        data: session.data,
+                    });
+                }
+                if session.window_end.is_some_and(|end| session.next > end) {
+                    replies.push(session.clear_to_send(frame));
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Session {
+    fn new(pgn: u32, size: usize, packets: u8, window_end: Option<u16>, max_per_cts: u8) -> Self {
+        Self {
+            pgn,
+            size,
+            packets,
+            data: Vec::with_capacity(packets as usize * 7),
+            next: 1,
+            window_end,
+            max_per_cts,
+            last_packet: Instant::now(),
+        }
+    }
+
+    /// Opens the next window of packets; only called while packets remain.
+    fn clear_to_send(&mut self, from: &J1939Frame) -> J1939Frame {
+        let count = (self.max_per_cts as u16).min(self.packets as u16 + 1 - self.next);
+        self.window_end = Some(self.next + count - 1);
+        control(from, [CTS, count as u8, self.next as u8, 0xFF, 0xFF], self.pgn)
+    }
+}
+
+/// TP.CM back to the sender of `to`.
+fn control(to: &J1939Frame, head: [u8; 5], pgn: u32) -> J1939Frame {
+    let mut data = head.to_vec();
+    data.extend_from_slice(&pgn.to_le_bytes()[..3]);
+    J1939Frame {
+        priority: 7,
+        pgn: TP_CM,
+        source: to.destination,
+        destination: to.source,
+        data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(pgn: u32, source: u8, destination: u8, data: &[u8]) -> J1939Frame {
+        J1939Frame { priority: 7, pgn, source, destination, data: data.to_vec() }
+    }
+
+    #[test]
+    fn test_rts_cts_transfer_in_windows() {
+        let mut transport = Transport::default();
+        let mut replies = Vec::new();
+        // 16 bytes of DM2 (65227) from the engine, two packets per CTS
+        let rts = frame(TP_CM, 0x00, 0x80, &[RTS, 16, 0, 3, 2, 0xCB, 0xFE, 0x00]);
+        assert!(transport.on_frame(&rts, Some(0x80), &mut replies).is_none());
+        assert_eq!(replies.pop().unwrap().data, [CTS, 2, 1, 0xFF, 0xFF, 0xCB, 0xFE, 0x00]);
+
+        let packet = |seq: u8| frame(TP_DT, 0x00, 0x80, &[seq, seq, seq, seq, seq, seq, seq, seq]);
+        assert!(transport.on_frame(&packet(1), Some(0x80), &mut replies).is_none());
+        assert!(replies.is_empty());
+        assert!(transport.on_frame(&packet(2), Some(0x80), &mut replies).is_none());
+        assert_eq!(replies.pop().unwrap().data, [CTS, 1, 3, 0xFF, 0xFF, 0xCB, 0xFE, 0x00]);
+
+        let message = transport.on_frame(&packet(3), Some(0x80), &mut replies).unwrap();
+        assert_eq!((message.pgn, message.source), (65227, 0x00));
+        assert_eq!(message.data, [1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 3, 3]);
+        let ack = replies.pop().unwrap();
+        assert_eq!((ack.source, ack.destination), (0x80, 0x00));
+        assert_eq!(ack.data, [END_OF_MSG_ACK, 16, 0, 3, 0xFF, 0xCB, 0xFE, 0x00]);
+
+        // Not ours: no handshake
+        let rts = frame(TP_CM, 0x00, 0x81, &[RTS, 16, 0, 3, 2, 0xCB, 0xFE, 0x00]);
+        transport.on_frame(&rts, Some(0x80), &mut replies);
+        assert!(replies.is_empty());
+    }
+
+    #[test]
+    fn test_longest_bam_completes_and_empty_rts_is_ignored() {
+        let mut transport = Transport::default();
+        let mut replies = Vec::new();
+        // 1785 bytes in 255 packets, the most a transfer can carry
+        let bam = frame(TP_CM, 0x00, GLOBAL, &[BAM, 0xF9, 0x06, 255, 0xFF, 0xCB, 0xFE, 0x00]);
+        assert!(transport.on_frame(&bam, Some(0x80), &mut replies).is_none());
+        for seq in 1..255u8 {
+            let packet = frame(TP_DT, 0x00, GLOBAL, &[seq, 0, 0, 0, 0, 0, 0, 0]);
+            assert!(transport.on_frame(&packet, Some(0x80), &mut replies).is_none());
+        }
+        let last = frame(TP_DT, 0x00, GLOBAL, &[255, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(transport.on_frame(&last, Some(0x80), &mut replies).unwrap().data.len(), 1785);
+
+        let rts = frame(TP_CM, 0x00, 0x80, &[RTS, 0, 0, 0, 0xFF, 0xCB, 0xFE, 0x00]);
+        assert!(transport.on_frame(&rts, Some(0x80), &mut replies).is_none());
+        assert!(replies.is_empty());
+        assert!(transport.sessions.is_empty());
+    }
+}
@@ -1,11 +1,15 @@
-use crate::config::Config;
-use crate::sensors::types::SensorEvent;
-use tokio::sync::broadcast;
+use crate::config::{Config, TpmsSource};
+use crate::sensors::types::{SensorEvent, SensorType, SensorValues};
+use chrono::Utc;
+use tokio::sync::{broadcast, mpsc};
 use tracing::{error, info, warn};
 
+pub mod fusion;
 pub mod gps;
 pub mod imu;
+pub mod j1939;
 pub mod obd;
+pub mod replay;
 pub mod tpms;
 pub mod types;
 
@@ -14,67 +18,162 @@ metrics::describe_counter!("sensor_events_total", "Total sensor events emitted")
 metrics::describe_gauge!("sensor_status", "Sensor connectivity status (1=up, 0=down)");
 metrics::describe_counter!("sensor_errors_total", "Total sensor read errors");
 
+pub type SourceResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Produces the readings of one sensor, from a device or a recording.
+#[async_trait::async_trait]
+pub trait SensorSource: Send {
+    fn sensor_id(&self) -> &str;
+    fn sensor_type(&self) -> SensorType;
+    /// Waits for the next reading. `Ok(None)` means the source is finished,
+    /// e.g. a recording played to the end.
+    async fn next_reading(&mut self) -> SourceResult<Option<Reading>>;
+}
+
+/// One reading and, for sources that have it, the raw line it came from.
+pub struct Reading {
+    pub values: SensorValues,
+    pub raw_payload: Option<String>,
+}
+
 pub async fn start_sensor_engine(
     config: &Config,
     tx: broadcast::Sender<SensorEvent>,
+    dtc_clear: mpsc::Receiver<obd::dtc::ClearRequest>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     info!("🚀 Starting Sensor Ingestion Engine...");
 
-    // Start GPS if device path is configured
-    if !config.sensors.gps_device.is_empty() {
-        let gps_tx = tx.clone();
-        let gps_device = config.sensors.gps_device.clone();
-        tokio::spawn(async move {
-            if let Err(e) = gps::start_gps_reader(gps_device, gps_tx).await {
-                error!(sensor="gps", error=%e, "GPS reader failed");
-            }
-        });
+    if config.sensors.fusion.enable {
+        tokio::spawn(fusion::run(config.sensors.fusion.clone(), tx.clone()));
     }
 
-    // Start OBD if configured
-    if !config.sensors.obd_device.is_empty() {
-        let obd_tx = tx.clone();
-        let obd_device = config.sensors.obd_device.clone();
-        tokio::spawn(async move {
-            if let Err(e) = obd::start_obd_reader(obd_device, obd_tx).await {
-                error!(sensor="obd", error=%e, "OBD reader failed");
-            }
-        });
+    let sources = open_sources(config, &tx, dtc_clear).await;
+    let count = sources.len();
+    for source in sources {
+        tokio::spawn(run_source(source, tx.clone()));
     }
 
-    // Start IMU if configured
-    if !config.sensors.imu_device.is_empty() {
-        let imu_tx = tx.clone();
-        let imu_device = config.sensors.imu_device.clone();
-        tokio::spawn(async move {
-            if let Err(e) = imu::start_imu_reader(imu_device, imu_tx).await {
-                error!(sensor="imu", error=%e, "IMU reader failed");
+    info!("✅ Sensor engine started — monitoring {} sensors", count);
+    Ok(())
+}
+
+/// A recording configured for a sensor replaces its device. Clear requests
+/// only reach a real OBD adapter; with a recording they fail. The IMU reads
+/// the other sensors' speed from `tx` while it calibrates.
+async fn open_sources(
+    config: &Config,
+    tx: &broadcast::Sender<SensorEvent>,
+    dtc_clear: mpsc::Receiver<obd::dtc::ClearRequest>,
+) -> Vec<Box<dyn SensorSource>> {
+    let sensors = &config.sensors;
+    let replay = &sensors.replay;
+    let mut sources: Vec<Box<dyn SensorSource>> = Vec::new();
+
+    let gps: SourceResult<Option<Box<dyn SensorSource>>> = if !replay.gps.is_empty() {
+        replay::nmea::NmeaReplay::open(&replay.gps, replay.speed, replay.repeat, sensors.gnss.sky_view_interval_sec)
+            .await
+            .map(boxed)
+    } else if !sensors.gps_device.is_empty() {
+        gps::GpsReader::open(&sensors.gps_device, &sensors.gnss).await.map(boxed)
+    } else {
+        Ok(None)
+    };
+
+    let obd: SourceResult<Option<Box<dyn SensorSource>>> = if !replay.obd.is_empty() {
+        replay::elm327::Elm327Replay::open(&replay.obd, replay.speed, replay.repeat, sensors.sample_rate_hz, &sensors.obd.pids)
+            .await
+            .map(boxed)
+    } else if !sensors.obd_device.is_empty() {
+        obd::ObdReader::open(sensors, dtc_clear)
+            .await
+            .map(boxed)
+    } else {
+        Ok(None)
+    };
+
+    let imu: SourceResult<Option<Box<dyn SensorSource>>> = if !replay.imu.is_empty() {
+        replay::imu_csv::ImuCsvReplay::open(&replay.imu, replay.speed, replay.repeat)
+            .await
+            .map(boxed)
+    } else if !sensors.imu_device.is_empty() {
+        imu::ImuReader::open(&sensors.imu_device, &sensors.imu, tx.subscribe()).map(boxed)
+    } else {
+        Ok(None)
+    };
+
+    let tpms: SourceResult<Option<Box<dyn SensorSource>>> = match sensors.tpms.source {
+        TpmsSource::Can => tpms::can::CanTpmsReader::open(&sensors.tpms).map(boxed),
+        TpmsSource::Rf => tpms::rf::RfTpmsReader::open(&sensors.tpms).map(boxed),
+        TpmsSource::None => Ok(None),
+    };
+
+    let j1939: SourceResult<Option<Box<dyn SensorSource>>> = if sensors.j1939.enable {
+        j1939::J1939Reader::open(&sensors.j1939).await.map(boxed)
+    } else {
+        Ok(None)
+    };
+
+    for (sensor, opened) in [("gps", gps), ("obd", obd), ("imu", imu), ("tpms", tpms), ("j1939", j1939)] {
+        match opened {
+            Ok(Some(source)) => sources.push(source),
+            Ok(None) => {}
+            Err(e) => {
+                error!(sensor, error=%e, "Failed to open sensor source");
+                metrics::gauge!("sensor_status", "sensor" => sensor).set(0.0);
             }
-        });
+        }
     }
+    sources
+}
 
-    // TPMS — future
-    // if config has CAN or RF config → start_tpms_reader()
-
-    info!(
-        "✅ Sensor engine started — monitoring {} sensors",
-        active_sensor_count(config)
-    );
-    Ok(())
+fn boxed<S: SensorSource + 'static>(source: S) -> Option<Box<dyn SensorSource>> {
+    Some(Box::new(source))
 }
 
-fn active_sensor_count(config: &Config) -> usize {
-    let mut count = 0;
-    if !config.sensors.gps_device.is_empty() {
-        count += 1;
-    }
-    if !config.sensors.obd_device.is_empty() {
-        count += 1;
+/// Emits a source's readings until it finishes.
+async fn run_source(mut source: Box<dyn SensorSource>, tx: broadcast::Sender<SensorEvent>) {
+    let sensor = sensor_label(&source.sensor_type());
+    metrics::gauge!("sensor_status", "sensor" => sensor).set(1.0);
+
+    loop {
+        match source.next_reading().await {
+            Ok(Some(reading)) => {
+                let event = SensorEvent {
+                    sensor_id: source.sensor_id().to_string(),
+                    sensor_type: reading.values.sensor_type(),
+                    timestamp: Utc::now(),
+                    values: reading.values,
+                    raw_payload: reading.raw_payload,
+                };
+                if tx.send(event).is_err() {
+                    warn!("Sensor channel receiver dropped — no consumers");
+                }
+                metrics::counter!("sensor_events_total", "sensor" => sensor).increment(1);
+            }
+            Ok(None) => {
+                info!(sensor, sensor_id=%source.sensor_id(), "⏹️  Sensor source finished");
+                metrics::gauge!("sensor_status", "sensor" => sensor).set(0.0);
+                return;
+            }
+            Err(e) => {
+                error!(sensor, error=%e, "Sensor read error");
+                metrics::counter!("sensor_errors_total", "sensor" => sensor).increment(1);
+                tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            }
+        }
     }
-    if !config.sensors.imu_device.is_empty() {
-        count += 1;
+}
+
+fn sensor_label(sensor_type: &SensorType) -> &'static str {
+    match sensor_type {
+        SensorType::Gps => "gps",
+        SensorType::Obd => "obd",
+        SensorType::Imu => "imu",
+        SensorType::Tpms => "tpms",
+        SensorType::J1939 => "j1939",
+        SensorType::Dtc => "dtc",
+        SensorType::Position => "position",
     }
-    count
 }
 //In each module, add heartbeats: TODO
 // In sensor loop
@@ -0,0 +1,197 @@
+//! The mode 01 PIDs the OBD reader polls, from `[[sensors.obd.pids]]`.
+
+use super::elm::messages;
+use super::formula::Formula;
+use crate::config::PidSpec;
+use crate::sensors::types::ObdData;
+use std::collections::BTreeSet;
+use tokio::time::Duration;
+
+/// PIDs 00, 20, 40 and 60 answer with which of the next 32 are supported.
+pub const SUPPORT_BITMAPS: [u8; 4] = [0x00, 0x20, 0x40, 0x60];
+
+pub struct PidEntry {
+    pub pid: u8,
+    pub name: String,
+    pub bytes: usize,
+    pub interval: Duration,
+    formula: Formula,
+}
+
+pub struct PidCatalogue {
+    entries: Vec<PidEntry>,
+}
+
+impl PidCatalogue {
+    pub fn from_config(pids: &[PidSpec]) -> Result<Self, String> {
+        let mut entries: Vec<PidEntry> = Vec::with_capacity(pids.len());
+        for spec in pids {
+            let formula = Formula::parse(&spec.formula).map_err(|e| format!("OBD PID {}: {}", spec.name, e))?;
+            if !(1..=4).contains(&spec.bytes) || formula.bytes_read() > spec.bytes as usize {
+                return Err(format!("OBD PID {}: formula reads past its {} bytes", spec.name, spec.bytes));
+            }
+            if SUPPORT_BITMAPS.contains(&spec.pid) || entries.iter().any(|e| e.pid == spec.pid) {
+                return Err(format!("OBD PID 0x{:02X} ({}) is reserved or listed twice", spec.pid, spec.name));
+            }
+            entries.push(PidEntry {
+                pid: spec.pid,
+                name: spec.name.clone(),
+                bytes: spec.bytes as usize,
+                interval: Duration::from_millis(spec.interval_ms),
+                formula,
+            });
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn entries(&self) -> &[PidEntry] {
+        &self.entries
+    }
+
+    pub fn get(&self, pid: u8) -> Option<&PidEntry> {
+        self.entries.iter().find(|e| e.pid == pid)
+    }
+
+    /// Drops the PIDs not in `supported` and returns them.
+    pub fn retain_supported(&mut self, supported: &BTreeSet<u8>) -> Vec<PidEntry> {
+        let (kept, dropped): (Vec<_>, Vec<_>) = self.entries.drain(..).partition(|e| supported.contains(&e.pid));
+        self.entries = kept;
+        dropped
+    }
+
+    /// Decodes one PID's data into `obd`. Returns false for PIDs not in the
+    /// catalogue, with too few data bytes, or whose formula gives no finite
+    /// value (a division by zero, say), which JSON could not carry.
+    pub fn apply(&self, obd: &mut ObdData, pid: u8, data: &[u8]) -> bool {
+        let Some(entry) = self.get(pid) else {
+            return false;
+        };
+        let Some(value) = data.get(..entry.bytes).and_then(|d| entry.formula.eval(d)) else {
+            return false;
+        };
+        if !value.is_finite() {
+            return false;
+        }
+        // Casts saturate, so an out-of-range value pins at the field's limit
+        match entry.name.as_str() {
+            "rpm" => obd.rpm = value as u16,
+            "speed_kmh" => obd.speed_kmh = value as u8,
+            "coolant_temp" => obd.coolant_temp = value as i8,
+            "fuel_level" => obd.fuel_level = value as u8,
+            "engine_load" => obd.engine_load = value as u8,
+            "throttle_pos" => obd.throttle_pos = value as u8,
+            name => {
+                obd.extra.insert(name.to_string(), value);
+            }
+        }
+        true
+    }
+
+    /// Forgets a PID that went unanswered, so an engine switched off does
+    /// not keep reporting its last rpm and speed.
+    pub fn clear(&self, obd: &mut ObdData, pid: u8) {
+        let Some(entry) = self.get(pid) else {
+            return;
+        };
+        match entry.name.as_str() {
+            "rpm" => obd.rpm = 0,
+            "speed_kmh" => obd.speed_kmh = 0,
+            "coolant_temp" => obd.coolant_temp = 0,
+            "fuel_level" => obd.fuel_level = 0,
+            "engine_load" => obd.engine_load = 0,
+            "throttle_pos" => obd.throttle_pos = 0,
+            name => {
+                obd.extra.remove(name);
+            }
+        }
+    }
+
+    /// PIDs and their data in the mode 01 replies of a response. A reply to
+    /// a multi-PID request holds several, which the catalogue's byte lengths
+    /// split apart.
+    pub fn split_replies(&self, text: &str) -> Vec<(u8, Vec<u8>)> {
+        let mut out = Vec::new();
+        for message in messages(text) {
+            let Some((0x41, mut rest)) = message.payload.split_first().map(|(s, r)| (*s, r)) else {
+                continue;
+            };
+            while let Some((&pid, data)) = rest.split_first() {
+                let Some(len) = self.get(pid).map(|e| e.bytes) else {
+                    break; // unknown length, cannot find the next PID
+                };
+                let Some(value) = data.get(..len) else {
+                    break;
+                };
+                out.push((pid, value.to_vec()));
+                rest = &data[len..];
+            }
+        }
+        out
+    }
+}
+
+/// PIDs an ECU lists as supported in its reply to bitmap PID `base`: bit 7
+/// of the first byte is `base + 1`, bit 0 of the last `base + 32`.
+pub fn supported_in(base: u8, bitmap: &[u8]) -> Vec<u8> {
+    (0..32u8)
+        .filter(|bit| bitmap.get(*bit as usize / 8).is_some_and(|b| b & (0x80 >> (bit % 8)) != 0))
+        .map(|bit| base + bit + 1)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ObdConfig;
+
+    #[test]
+    fn test_decodes_multi_pid_reply_with_formulas() {
+        let mut pids = ObdConfig::default().pids;
+        pids.push(PidSpec {
+            pid: 0x42,
+            name: "module_voltage".to_string(),
+            bytes: 2,
+            formula: "(256*A+B)/1000".to_string(),
+            interval_ms: 0,
+        });
+        let catalogue = PidCatalogue::from_config(&pids).unwrap();
+
+        // RPM, speed, coolant and voltage over two CAN frames
+        let text = "7E8 10 0B 41 0C 1A F8 0D 3C\r7E8 21 05 7B 42 36 B0 00 00\r\r>";
+        let mut obd = ObdData::default();
+        for (pid, data) in catalogue.split_replies(text) {
+            assert!(catalogue.apply(&mut obd, pid, &data));
+        }
+        assert_eq!((obd.rpm, obd.speed_kmh, obd.coolant_temp), (1726, 60, 83));
+        assert_eq!(obd.extra["module_voltage"], 14.0);
+
+        assert_eq!(supported_in(0x20, &[0x00, 0x02, 0x00, 0x01]), [0x2F, 0x40]);
+
+        pids[0].formula = "(256*A+B)/4*C".to_string();
+        assert!(PidCatalogue::from_config(&pids).is_err());
+    }
+
+    #[test]
+    fn test_non_finite_values_skipped_and_unanswered_pids_cleared() {
+        let mut pids = ObdConfig::default().pids;
+        pids.push(PidSpec {
+            pid: 0x42,
+            name: "ratio".to_string(),
+            bytes: 2,
+            formula: "A/B".to_string(),
+            interval_ms: 0,
+        });
+        let catalogue = PidCatalogue::from_config(&pids).unwrap();
+
+        let mut obd = ObdData::default();
+        assert!(!catalogue.apply(&mut obd, 0x42, &[3, 0]));
+        assert!(!obd.extra.contains_key("ratio"));
+        assert!(catalogue.apply(&mut obd, 0x42, &[3, 2]));
+        assert!(catalogue.apply(&mut obd, 0x0C, &[0x1A, 0xF8]));
+
+        catalogue.clear(&mut obd, 0x0C);
+        catalogue.clear(&mut obd, 0x42);
+        assert_eq!(obd.rpm, 0);
+        assert!(obd.extra.is_empty());
+    }
+}
@@ -0,0 +1,321 @@
+//! OBD-II diagnostic trouble codes: stored (mode 03), pending (07) and
+//! permanent (0A) codes, the freeze frame (02), and clearing (04) on remote
+//! command. Everything here works on the adapter's text so it can be tested
+//! without one.
+
+use super::elm::messages;
+use crate::sensors::types::DiagnosticCode;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::oneshot;
+use tracing::error;
+
+pub const MODE_FREEZE_FRAME: u8 = 0x02;
+pub const MODE_STORED: u8 = 0x03;
+pub const MODE_CLEAR: u8 = 0x04;
+pub const MODE_PENDING: u8 = 0x07;
+pub const MODE_PERMANENT: u8 = 0x0A;
+
+/// Freeze frame PID holding the code that caused the snapshot.
+pub const PID_FREEZE_DTC: u8 = 0x02;
+
+const NEGATIVE_RESPONSE: u8 = 0x7F;
+const CONDITIONS_NOT_CORRECT: u8 = 0x22;
+
+/// Asks the OBD reader, which owns the adapter, to clear the codes.
+pub struct ClearRequest {
+    pub command_id: String,
+    /// Operator the server authenticated for the command; goes into the audit log.
+    pub requested_by: String,
+    pub reply: oneshot::Sender<Result<serde_json::Value, String>>,
+}
+
+/// SAE J2012 code from the two bytes an ECU reports, or `None` for the
+/// all-zero padding.
+pub fn decode(a: u8, b: u8) -> Option<String> {
+    if a == 0 && b == 0 {
+        return None;
+    }
+    let system = ['P', 'C', 'B', 'U'][(a >> 6) as usize];
+    Some(format!("{}{}{:X}{:02X}", system, (a >> 4) & 0x03, a & 0x0F, b))
+}
+
+pub fn describe(code: &str) -> Option<&'static str> {
+    DESCRIPTIONS
+        .binary_search_by_key(&code, |&(c, _)| c)
+        .ok()
+        .map(|i| DESCRIPTIONS[i].1)
+}
+
+pub fn diagnostic_code(code: String) -> DiagnosticCode {
+    DiagnosticCode {
+        description: describe(&code).map(str::to_string),
+        code,
+    }
+}
+
+/// Codes in the replies to a mode 03, 07 or 0A request, in order and once
+/// each when several ECUs report the same one.
+pub fn parse_codes(text: &str, mode: u8) -> Vec<String> {
+    let mut codes = Vec::new();
+    for message in messages(text) {
+        let Some((&service, data)) = message.payload.split_first() else {
+            continue;
+        };
+        if service != mode | 0x40 {
+            continue;
+        }
+        let data = if message.can { data.get(1..).unwrap_or_default() } else { data };
+        for pair in data.chunks_exact(2) {
+            if let Some(code) = decode(pair[0], pair[1]) {
+                if !codes.contains(&code) {
+                    codes.push(code);
+                }
+            }
+        }
+    }
+    codes
+}
+
+/// Data bytes of a freeze frame PID, from the reply to `02 <pid> 00`.
+pub fn parse_freeze_frame_pid(text: &str, pid: u8) -> Option<Vec<u8>> {
+    messages(text).into_iter().find_map(|m| match m.payload.as_slice() {
+        [service, answered, _frame, data @ ..] if *service == MODE_FREEZE_FRAME | 0x40 && *answered == pid => {
+            Some(data.to_vec())
+        }
+        _ => None,
+    })
+}
+
+/// Outcome of a mode 04 request.
+pub fn parse_clear(text: &str) -> Result<(), String> {
+    let messages = messages(text);
+    if messages.iter().any(|m| m.payload.first() == Some(&(MODE_CLEAR | 0x40))) {
+        return Ok(());
+    }
+    match messages.iter().find_map(|m| match m.payload.as_slice() {
+        [NEGATIVE_RESPONSE, MODE_CLEAR, nrc, ..] => Some(*nrc),
+        _ => None,
+    }) {
+        Some(CONDITIONS_NOT_CORRECT) => {
+            Err("ECU refused: conditions not correct (engine must be off, ignition on)".to_string())
+        }
+        Some(nrc) => Err(format!("ECU refused with response code 0x{:02X}", nrc)),
+        None => Err("no reply from the ECU".to_string()),
+    }
+}
+
+/// Runs `clear` only once `record` is on disk in the audit log, then
+/// appends its outcome, so codes are never cleared without a trace.
+pub async fn audited_clear(
+    path: &str,
+    mut record: serde_json::Value,
+    clear: impl std::future::Future<Output = Result<(), String>>,
+) -> Result<(), String> {
+    record["result"] = "requested".into();
+    append_audit(path, &record)
+        .await
+        .map_err(|e| format!("cannot write audit log {}, codes not cleared: {}", path, e))?;
+
+    let outcome = clear.await;
+    record["result"] = match &outcome {
+        Ok(()) => "cleared".to_string(),
+        Err(e) => format!("failed: {}", e),
+    }
+    .into();
+    // The request is already on record; a lost outcome line does not undo the clear
+    if let Err(e) = append_audit(path, &record).await {
+        error!(path, error=%e, "Failed to write DTC clear outcome to the audit log");
+    }
+    outcome
+}
+
+/// Appends one JSON line to the audit log and syncs it, and the directory
+/// entry of a new log, to disk.
+pub async fn append_audit(path: &str, record: &serde_json::Value) -> std::io::Result<()> {
+    let dir = std::path::Path::new(path).parent().filter(|d| !d.as_os_str().is_empty());
+    if let Some(dir) = dir {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let created = !tokio::fs::try_exists(path).await?;
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(format!("{}\n", record).as_bytes()).await?;
+    file.sync_data().await?;
+    if created {
+        tokio::fs::File::open(dir.unwrap_or(std::path::Path::new("."))).await?.sync_all().await?;
+    }
+    Ok(())
+}
+
+/// Generic (SAE J2012) codes, sorted for binary search. Manufacturer
+/// ranges (P1xxx, P3xxx, ...) differ per make and are left undescribed.
+const DESCRIPTIONS: &[(&str, &str)] = &[
+    ("B0001", "Driver frontal stage 1 deployment control"),
+    ("B0100", "Electronic frontal sensor 1"),
+    ("C0035", "Left front wheel speed sensor circuit"),
+    ("C0040", "Right front wheel speed sensor circuit"),
+    ("C0045", "Left rear wheel speed sensor circuit"),
+    ("C0050", "Right rear wheel speed sensor circuit"),
+    ("C0110", "Pump motor circuit"),
+    ("C0265", "EBCM motor relay circuit"),
+    ("P0087", "Fuel rail/system pressure too low"),
+    ("P0088", "Fuel rail/system pressure too high"),
+    ("P0100", "Mass or volume air flow circuit"),
+    ("P0101", "Mass or volume air flow circuit range/performance"),
+    ("P0102", "Mass or volume air flow circuit low input"),
+    ("P0103", "Mass or volume air flow circuit high input"),
+    ("P0106", "Manifold absolute pressure circuit range/performance"),
+    ("P0107", "Manifold absolute pressure circuit low input"),
+    ("P0108", "Manifold absolute pressure circuit high input"),
+    ("P0110", "Intake air temperature circuit"),
+    ("P0115", "Engine coolant temperature circuit"),
+    ("P0116", "Engine coolant temperature circuit range/performance"),
+    ("P0117", "Engine coolant temperature circuit low input"),
+    ("P0118", "Engine coolant temperature circuit high input"),
+    ("P0120", "Throttle position sensor A circuit"),
+    ("P0121", "Throttle position sensor A circuit range/performance"),
+    ("P0128", "Coolant thermostat below regulating temperature"),
+    ("P0130", "O2 sensor circuit (bank 1, sensor 1)"),
+    ("P0133", "O2 sensor circuit slow response (bank 1, sensor 1)"),
+    ("P0171", "System too lean (bank 1)"),
+    ("P0172", "System too rich (bank 1)"),
+    ("P0174", "System too lean (bank 2)"),
+    ("P0175", "System too rich (bank 2)"),
+    ("P0200", "Injector circuit"),
+    ("P0201", "Injector circuit, cylinder 1"),
+    ("P0202", "Injector circuit, cylinder 2"),
+    ("P0203", "Injector circuit, cylinder 3"),
+    ("P0204", "Injector circuit, cylinder 4"),
+    ("P0205", "Injector circuit, cylinder 5"),
+    ("P0206", "Injector circuit, cylinder 6"),
+    ("P0217", "Engine coolant over temperature condition"),
+    ("P0219", "Engine overspeed condition"),
+    ("P0234", "Turbocharger/supercharger overboost condition"),
+    ("P0299", "Turbocharger/supercharger underboost"),
+    ("P0300", "Random/multiple cylinder misfire detected"),
+    ("P0301", "Cylinder 1 misfire detected"),
+    ("P0302", "Cylinder 2 misfire detected"),
+    ("P0303", "Cylinder 3 misfire detected"),
+    ("P0304", "Cylinder 4 misfire detected"),
+    ("P0305", "Cylinder 5 misfire detected"),
+    ("P0306", "Cylinder 6 misfire detected"),
+    ("P0335", "Crankshaft position sensor A circuit"),
+    ("P0340", "Camshaft position sensor A circuit (bank 1)"),
+    ("P0380", "Glow plug/heater circuit A"),
+    ("P0401", "Exhaust gas recirculation flow insufficient"),
+    ("P0402", "Exhaust gas recirculation flow excessive"),
+    ("P0403", "Exhaust gas recirculation control circuit"),
+    ("P0404", "Exhaust gas recirculation control circuit range/performance"),
+    ("P0420", "Catalyst system efficiency below threshold (bank 1)"),
+    ("P0430", "Catalyst system efficiency below threshold (bank 2)"),
+    ("P0440", "Evaporative emission system"),
+    ("P0442", "Evaporative emission system leak detected (small leak)"),
+    ("P0455", "Evaporative emission system leak detected (large leak)"),
+    ("P0461", "Fuel level sensor A circuit range/performance"),
+    ("P0462", "Fuel level sensor A circuit low"),
+    ("P0463", "Fuel level sensor A circuit high"),
+    ("P0480", "Fan 1 control circuit"),
+    ("P0500", "Vehicle speed sensor A"),
+    ("P0501", "Vehicle speed sensor A range/performance"),
+    ("P0505", "Idle air control system"),
+    ("P0520", "Engine oil pressure sensor/switch circuit"),
+    ("P0521", "Engine oil pressure sensor/switch range/performance"),
+    ("P0524", "Engine oil pressure too low"),
+    ("P0562", "System voltage low"),
+    ("P0563", "System voltage high"),
+    ("P0600", "Serial communication link"),
+    ("P0601", "Internal control module memory checksum error"),
+    ("P0606", "Control module processor"),
+    ("P0700", "Transmission control system (MIL request)"),
+    ("P0705", "Transmission range sensor circuit"),
+    ("P0715", "Input/turbine speed sensor A circuit"),
+    ("P0720", "Output speed sensor circuit"),
+    ("P0730", "Incorrect gear ratio"),
+    ("P0740", "Torque converter clutch solenoid circuit"),
+    ("P2002", "Diesel particulate filter efficiency below threshold (bank 1)"),
+    ("P2004", "Intake manifold runner control stuck open (bank 1)"),
+    ("P200E", "Catalyst system over temperature (bank 1)"),
+    ("P2047", "Reductant injector circuit/open (bank 1, unit 1)"),
+    ("P20EE", "SCR NOx catalyst efficiency below threshold (bank 1)"),
+    ("P2135", "Throttle/pedal position sensor/switch A/B voltage correlation"),
+    ("P2263", "Turbocharger/supercharger boost system performance"),
+    ("P242F", "Diesel particulate filter restriction - ash accumulation"),
+    ("P2459", "Diesel particulate filter regeneration frequency"),
+    ("P2463", "Diesel particulate filter restriction - soot accumulation"),
+    ("U0001", "High speed CAN communication bus"),
+    ("U0100", "Lost communication with ECM/PCM A"),
+    ("U0101", "Lost communication with TCM"),
+    ("U0121", "Lost communication with anti-lock brake system control module"),
+    ("U0140", "Lost communication with body control module"),
+    ("U0155", "Lost communication with instrument panel cluster control module"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_codes_from_can_and_legacy_replies() {
+        // Engine ECU with four stored codes over two CAN frames, transmission
+        // ECU with one, and an adapter prompt left over
+        let can = "\
+7E8 10 0A 43 04 01 33 03 01\r\
+7E9 04 43 01 07 00\r\
+7E8 21 C0 35 82 05 00 00 00\r\r>";
+        assert_eq!(parse_codes(can, MODE_STORED), ["P0700", "P0133", "P0301", "U0035", "B0205"]);
+
+        // ISO 9141: three codes per line, zero padded, checksum at the end
+        let legacy = "SEARCHING...\r48 6B 10 43 03 01 00 00 00 00 C4\r\r>";
+        assert_eq!(parse_codes(legacy, MODE_STORED), ["P0301"]);
+        assert_eq!(parse_codes("NO DATA\r\r>", MODE_PERMANENT), Vec::<String>::new());
+
+        let frame = parse_freeze_frame_pid("7E8 05 42 02 00 03 01", PID_FREEZE_DTC).unwrap();
+        assert_eq!(decode(frame[0], frame[1]).as_deref(), Some("P0301"));
+
+        assert_eq!(parse_clear("7E8 01 44\r\r>"), Ok(()));
+        assert!(parse_clear("7E8 03 7F 04 22\r\r>").unwrap_err().contains("engine must be off"));
+
+        assert!(DESCRIPTIONS.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(describe("P0301"), Some("Cylinder 1 misfire detected"));
+        assert_eq!(describe("P1234"), None);
+    }
+
+    #[tokio::test]
+    async fn test_clear_runs_only_after_audit_record_is_written() {
+        let record = serde_json::json!({"command_id": "cmd-1", "requested_by": "ops@fleet", "stored": ["P0301"]});
+
+        // No audit log, no clear
+        let mut cleared = false;
+        let outcome = audited_clear("/dev/null/dtc_audit.jsonl", record.clone(), async {
+            cleared = true;
+            Ok(())
+        })
+        .await;
+        assert!(outcome.unwrap_err().contains("codes not cleared"));
+        assert!(!cleared);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit").join("dtc_audit.jsonl");
+        let path = path.to_str().unwrap();
+        let outcome = audited_clear(path, record, async {
+            // The request is on disk before the ECU is asked
+            let log = std::fs::read_to_string(path).unwrap();
+            assert!(log.contains("\"requested\""));
+            Err("no reply from the ECU".to_string())
+        })
+        .await;
+        assert!(outcome.is_err());
+
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["requested_by"], "ops@fleet");
+        assert_eq!(lines[1]["result"], "failed: no reply from the ECU");
+    }
+}
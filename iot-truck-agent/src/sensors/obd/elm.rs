@@ -0,0 +1,84 @@
+//! Framing of ELM327 responses with headers on (ATH1), shared by the PID
+//! and trouble code requests.
+
+use std::collections::HashMap;
+
+/// One ECU's reply, from the service byte on.
+#[derive(Debug, PartialEq)]
+pub struct Message {
+    /// CAN framing; CAN replies to modes 03, 07 and 0A lead with a code count.
+    pub can: bool,
+    pub payload: Vec<u8>,
+}
+
+/// Complete replies in an ELM327 response with headers on (ATH1). CAN
+/// replies are reassembled from their ISO-TP frames per ECU; other
+/// protocols carry three header bytes and a checksum around each line.
+pub fn messages(text: &str) -> Vec<Message> {
+    let mut out = Vec::new();
+    // Multi-frame CAN replies in progress, by header: length, bytes so far
+    let mut pending: HashMap<String, (usize, Vec<u8>)> = HashMap::new();
+
+    for line in text.split(['\r', '\n', '>']) {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some(first) = tokens.first() else {
+            continue;
+        };
+        // 11-bit headers print as one token (7E8), 29-bit ones as four (18 DA F1 10)
+        let (header, rest) = if first.len() == 3 {
+            (first.to_string(), &tokens[1..])
+        } else if tokens.len() > 4 && tokens[0] == "18" && tokens[1] == "DA" {
+            (tokens[..4].concat(), &tokens[4..])
+        } else {
+            (String::new(), &tokens[..])
+        };
+        // NO DATA, SEARCHING... and the like
+        let Some(bytes) = hex_bytes(rest) else {
+            continue;
+        };
+
+        if header.is_empty() {
+            if bytes.len() > 4 {
+                out.push(Message { can: false, payload: bytes[3..bytes.len() - 1].to_vec() });
+            }
+            continue;
+        }
+        let Some(&pci) = bytes.first() else {
+            continue;
+        };
+        match pci >> 4 {
+            // Single frame
+            0 => {
+                if let Some(payload) = bytes.get(1..=(pci & 0x0F) as usize) {
+                    out.push(Message { can: true, payload: payload.to_vec() });
+                }
+            }
+            // First frame: 12-bit length, then the start of the payload
+            1 if bytes.len() > 2 => {
+                let len = ((pci & 0x0F) as usize) << 8 | bytes[1] as usize;
+                pending.insert(header, (len, bytes[2..].to_vec()));
+            }
+            // Consecutive frame
+            2 => {
+                let Some((len, data)) = pending.get_mut(&header) else {
+                    continue;
+                };
+                data.extend_from_slice(&bytes[1..]);
+                if data.len() >= *len {
+                    let (len, mut payload) = pending.remove(&header).unwrap_or_default();
+                    payload.truncate(len);
+                    out.push(Message { can: true, payload });
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+fn hex_bytes(tokens: &[&str]) -> Option<Vec<u8>> {
+    tokens
+        .iter()
+        .map(|t| if t.len() == 2 { u8::from_str_radix(t, 16).ok() } else { None })
+        .collect()
+}
@@ -0,0 +1,117 @@
+//! PID formulas in the notation scan tools use: `(256*A+B)/4`, `A-40`,
+//! `A*100/255`. `A` to `D` are the data bytes; `+ - * /`, parentheses and
+//! decimal numbers are understood.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Formula {
+    Number(f64),
+    /// Data byte, 0 for `A`.
+    Byte(usize),
+    Neg(Box<Formula>),
+    Add(Box<Formula>, Box<Formula>),
+    Sub(Box<Formula>, Box<Formula>),
+    Mul(Box<Formula>, Box<Formula>),
+    Div(Box<Formula>, Box<Formula>),
+}
+
+impl Formula {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parser = Parser { chars: text.chars().filter(|c| !c.is_whitespace()).collect(), pos: 0 };
+        let formula = parser.expr()?;
+        match parser.peek() {
+            None => Ok(formula),
+            Some(c) => Err(format!("unexpected '{}' in formula {}", c, text)),
+        }
+    }
+
+    /// `None` if the formula reads a byte `data` does not have.
+    pub fn eval(&self, data: &[u8]) -> Option<f64> {
+        Some(match self {
+            Formula::Number(n) => *n,
+            Formula::Byte(i) => *data.get(*i)? as f64,
+            Formula::Neg(f) => -f.eval(data)?,
+            Formula::Add(a, b) => a.eval(data)? + b.eval(data)?,
+            Formula::Sub(a, b) => a.eval(data)? - b.eval(data)?,
+            Formula::Mul(a, b) => a.eval(data)? * b.eval(data)?,
+            Formula::Div(a, b) => a.eval(data)? / b.eval(data)?,
+        })
+    }
+
+    /// Number of data bytes the formula reads.
+    pub fn bytes_read(&self) -> usize {
+        match self {
+            Formula::Number(_) => 0,
+            Formula::Byte(i) => i + 1,
+            Formula::Neg(f) => f.bytes_read(),
+            Formula::Add(a, b) | Formula::Sub(a, b) | Formula::Mul(a, b) | Formula::Div(a, b) => {
+                a.bytes_read().max(b.bytes_read())
+            }
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn expr(&mut self) -> Result<Formula, String> {
+        let mut left = self.term()?;
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            self.pos += 1;
+            let right = Box::new(self.term()?);
+            left = if op == '+' { Formula::Add(Box::new(left), right) } else { Formula::Sub(Box::new(left), right) };
+        }
+        Ok(left)
+    }
+
+    fn term(&mut self) -> Result<Formula, String> {
+        let mut left = self.factor()?;
+        while let Some(op @ ('*' | '/')) = self.peek() {
+            self.pos += 1;
+            let right = Box::new(self.factor()?);
+            left = if op == '*' { Formula::Mul(Box::new(left), right) } else { Formula::Div(Box::new(left), right) };
+        }
+        Ok(left)
+    }
+
+    fn factor(&mut self) -> Result<Formula, String> {
+        match self.peek() {
+            Some('-') => {
+                self.pos += 1;
+                Ok(Formula::Neg(Box::new(self.factor()?)))
+            }
+            Some('(') => {
+                self.pos += 1;
+                let inner = self.expr()?;
+                if self.peek() != Some(')') {
+                    return Err("missing ')' in formula".to_string());
+                }
+                self.pos += 1;
+                Ok(inner)
+            }
+            Some(c @ 'A'..='D') => {
+                self.pos += 1;
+                Ok(Formula::Byte(c as usize - 'A' as usize))
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+                    self.pos += 1;
+                }
+                let number: String = self.chars[start..self.pos].iter().collect();
+                number
+                    .parse()
+                    .map(Formula::Number)
+                    .map_err(|_| format!("bad number {} in formula", number))
+            }
+            Some(c) => Err(format!("unexpected '{}' in formula", c)),
+            None => Err("formula ends early".to_string()),
+        }
+    }
+}
@@ -0,0 +1,478 @@
+use crate::config::{DtcConfig, SensorsConfig};
+use crate::sensors::types::{DiagnosticCode, DtcData, FreezeFrame, ObdData, SensorType, SensorValues};
+use crate::sensors::{Reading, SensorSource, SourceResult};
+use catalogue::PidCatalogue;
+use dtc::ClearRequest;
+use std::collections::BTreeSet;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant};
+use tokio_serial::{ClearBuffer, SerialPort, SerialPortBuilderExt, SerialStream};
+use tracing::{info, warn};
+
+pub mod catalogue;
+pub mod dtc;
+pub mod elm;
+pub mod formula;
+
+/// Monitor status: MIL and code count.
+const PID_MONITOR_STATUS: u8 = 0x01;
+
+/// Most PIDs one mode 01 request may carry (ISO 15765-4).
+const MAX_PIDS_PER_REQUEST: usize = 6;
+
+/// ELM327 adapter on a serial port, polled at the configured sample rate.
+/// Each catalogue PID is requested at its own rate and keeps its last value
+/// in between. Trouble codes are read on their own, slower schedule and
+/// reported only when they change.
+pub struct ObdReader {
+    device_path: String,
+    port: SerialStream,
+    tick: tokio::time::Interval,
+    catalogue: PidCatalogue,
+    /// When each catalogue entry is next due, in catalogue order.
+    due: Vec<Instant>,
+    /// PIDs per request; 1 for ECUs that do not answer multi-PID requests.
+    per_request: usize,
+    values: ObdData,
+    dtc: DtcConfig,
+    next_dtc_poll: Instant,
+    last_dtcs: Option<DtcData>,
+    clear_requests: mpsc::Receiver<ClearRequest>,
+}
+
+impl ObdReader {
+    pub async fn open(sensors: &SensorsConfig, clear_requests: mpsc::Receiver<ClearRequest>) -> SourceResult<Self> {
+        let device_path = &sensors.obd_device;
+        let port = tokio_serial::new(device_path, 38400) // ELM327 default
+            .open_native_async()
+            .map_err(|e| format!("Failed to open OBD device {}: {}", device_path, e))?;
+
+        info!(device=%device_path, "🔌 OBD reader started");
+        Self::with_port(device_path, port, sensors, clear_requests).await
+    }
+
+    /// Sets up the adapter on an open port and works out what to poll.
+    pub async fn with_port(
+        device_path: &str,
+        mut port: SerialStream,
+        sensors: &SensorsConfig,
+        clear_requests: mpsc::Receiver<ClearRequest>,
+    ) -> SourceResult<Self> {
+        // Initialize ELM327
+        initialize_elm327(&mut port).await?;
+
+        let mut catalogue = PidCatalogue::from_config(&sensors.obd.pids)?;
+        if sensors.obd.discover_supported {
+            match discover_supported(&mut port).await? {
+                Some(supported) => {
+                    for skipped in catalogue.retain_supported(&supported) {
+                        info!(pid=%format!("{:02X}", skipped.pid), name=%skipped.name, "OBD PID not supported by the vehicle, skipping");
+                    }
+                }
+                None => warn!(device=%device_path, "ECU lists no supported PIDs, polling the whole catalogue"),
+            }
+        }
+        let per_request = if sensors.obd.multi_pid && answers_multi_pid(&mut port, &catalogue).await? {
+            MAX_PIDS_PER_REQUEST
+        } else {
+            1
+        };
+        info!(pids=catalogue.entries().len(), per_request, "📋 OBD PID catalogue ready");
+
+        let period_ms = 1000 / sensors.sample_rate_hz.max(1) as u64;
+        Ok(Self {
+            device_path: device_path.to_string(),
+            port,
+            tick: tokio::time::interval(tokio::time::Duration::from_millis(period_ms)),
+            due: vec![Instant::now(); catalogue.entries().len()],
+            catalogue,
+            per_request,
+            values: ObdData::default(),
+            dtc: sensors.obd_dtc.clone(),
+            next_dtc_poll: Instant::now(),
+            last_dtcs: None,
+            clear_requests,
+        })
+    }
+
+    /// Requests the PIDs that are due, as few requests as the ECU allows.
+    /// Due PIDs left unanswered lose their last value.
+    async fn poll_due(&mut self) -> SourceResult<()> {
+        let now = Instant::now();
+        let due: Vec<usize> = (0..self.due.len()).filter(|i| self.due[*i] <= now).collect();
+        let mut answered = BTreeSet::new();
+        for chunk in due.chunks(self.per_request) {
+            let pids: String = chunk
+                .iter()
+                .map(|i| format!("{:02X}", self.catalogue.entries()[*i].pid))
+                .collect();
+            let response = query_all(&mut self.port, &format!("01{}", pids)).await?;
+            for (pid, data) in self.catalogue.split_replies(&response) {
+                if self.catalogue.apply(&mut self.values, pid, &data) {
+                    answered.insert(pid);
+                }
+            }
+        }
+        for i in due {
+            let entry = &self.catalogue.entries()[i];
+            if !answered.contains(&entry.pid) {
+                self.catalogue.clear(&mut self.values, entry.pid);
+            }
+            self.due[i] = now + entry.interval;
+        }
+        Ok(())
+    }
+
+    async fn read_dtcs(&mut self) -> SourceResult<DtcData> {
+        let mil_on = query_pid(&mut self.port, PID_MONITOR_STATUS)
+            .await?
+            .is_some_and(|data| data.first().is_some_and(|a| a & 0x80 != 0));
+        let mut dtcs = DtcData {
+            mil_on,
+            stored: self.read_codes(dtc::MODE_STORED).await?,
+            pending: self.read_codes(dtc::MODE_PENDING).await?,
+            permanent: self.read_codes(dtc::MODE_PERMANENT).await?,
+            freeze_frame: None,
+        };
+        if self.dtc.freeze_frame && !dtcs.stored.is_empty() {
+            dtcs.freeze_frame = self.read_freeze_frame().await?;
+        }
+        Ok(dtcs)
+    }
+
+    async fn read_codes(&mut self, mode: u8) -> SourceResult<Vec<DiagnosticCode>> {
+        let response = query_all(&mut self.port, &format!("{:02X}", mode)).await?;
+        Ok(dtc::parse_codes(&response, mode).into_iter().map(dtc::diagnostic_code).collect())
+    }
+
+    /// Frame 0: the PIDs of a regular reading as they were when the code set.
+    async fn read_freeze_frame(&mut self) -> SourceResult<Option<FreezeFrame>> {
+        let response = query_all(&mut self.port, &format!("02{:02X}00", dtc::PID_FREEZE_DTC)).await?;
+        let code = dtc::parse_freeze_frame_pid(&response, dtc::PID_FREEZE_DTC)
+            .and_then(|data| dtc::decode(*data.first()?, *data.get(1)?));
+        let Some(code) = code else {
+            return Ok(None);
+        };
+
+        let mut values = ObdData::default();
+        let pids: Vec<u8> = self.catalogue.entries().iter().map(|e| e.pid).collect();
+        for pid in pids {
+            let response = query_all(&mut self.port, &format!("02{:02X}00", pid)).await?;
+            if let Some(data) = dtc::parse_freeze_frame_pid(&response, pid) {
+                self.catalogue.apply(&mut values, pid, &data);
+            }
+        }
+        Ok(Some(FreezeFrame { code, values }))
+    }
+
+    /// Clears codes and freeze frame (mode 04). What is about to be cleared
+    /// and by whom is audited first; without that record nothing is cleared.
+    async fn clear_codes(&mut self, request: &ClearRequest) -> Result<serde_json::Value, String> {
+        let before = self.read_dtcs().await.map_err(|e| e.to_string())?;
+
+        let codes = |codes: &[DiagnosticCode]| codes.iter().map(|c| c.code.clone()).collect::<Vec<_>>();
+        let record = serde_json::json!({
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "command_id": request.command_id,
+            "requested_by": request.requested_by,
+            "device": self.device_path,
+            "mil_on": before.mil_on,
+            "stored": codes(&before.stored),
+            "pending": codes(&before.pending),
+            "permanent": codes(&before.permanent),
+        });
+        let port = &mut self.port;
+        let outcome = dtc::audited_clear(&self.dtc.audit_log, record.clone(), async move {
+            match query_all(port, &format!("{:02X}", dtc::MODE_CLEAR)).await {
+                Ok(response) => dtc::parse_clear(&response),
+                Err(e) => Err(e.to_string()),
+            }
+        })
+        .await;
+        info!(command_id=%request.command_id, requested_by=%request.requested_by, stored=?record["stored"], outcome=?outcome, "🧾 OBD-II trouble code clear");
+
+        outcome.map(|()| {
+            serde_json::json!({
+                "status": "codes cleared",
+                "cleared": record["stored"],
+                "pending_cleared": record["pending"],
+                // Only the ECU clears these, once it has verified the repair
+                "permanent_remaining": record["permanent"],
+            })
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl SensorSource for ObdReader {
+    fn sensor_id(&self) -> &str {
+        &self.device_path
+    }
+
+    fn sensor_type(&self) -> SensorType {
+        SensorType::Obd
+    }
+
+    async fn next_reading(&mut self) -> SourceResult<Option<Reading>> {
+        // Clear requests are served between readings
+        loop {
+            tokio::select! {
+                Some(request) = self.clear_requests.recv() => {
+                    let result = self.clear_codes(&request).await;
+                    let _ = request.reply.send(result);
+                    // Report what is left right away
+                    self.next_dtc_poll = Instant::now();
+                }
+                _ = self.tick.tick() => break,
+            }
+        }
+
+        if self.dtc.enable && Instant::now() >= self.next_dtc_poll {
+            self.next_dtc_poll = Instant::now() + Duration::from_secs(self.dtc.poll_interval_sec.max(1));
+            let dtcs = self.read_dtcs().await?;
+            if self.last_dtcs.as_ref() != Some(&dtcs) {
+                self.last_dtcs = Some(dtcs.clone());
+                return Ok(Some(Reading {
+                    values: SensorValues::Dtc(dtcs),
+                    raw_payload: None,
+                }));
+            }
+        }
+
+        self.poll_due().await?;
+        Ok(Some(Reading {
+            values: SensorValues::Obd(self.values.clone()),
+            raw_payload: None,
+        }))
+    }
+}
+
+async fn initialize_elm327(port: &mut SerialStream) -> SourceResult<()> {
+    // Reset
+    write_command(port, "ATZ\r").await?;
+    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+
+    // Echo off
+    write_command(port, "ATE0\r").await?;
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    // Set headers auto
+    write_command(port, "ATH1\r").await?;
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    // Set protocol auto
+    write_command(port, "ATSP0\r").await?;
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    info!("✅ ELM327 initialized");
+    Ok(())
+}
+
+async fn write_command(port: &mut SerialStream, cmd: &str) -> SourceResult<()> {
+    port.write_all(cmd.as_bytes()).await?;
+    port.flush().await?;
+    Ok(())
+}
+
+/// PIDs the ECUs list in their support bitmaps, or `None` if none answers
+/// PID 00.
+async fn discover_supported(port: &mut SerialStream) -> SourceResult<Option<BTreeSet<u8>>> {
+    let mut supported = BTreeSet::new();
+    for base in catalogue::SUPPORT_BITMAPS {
+        // Each bitmap's last bit says whether the next one exists
+        if base != 0 && !supported.contains(&base) {
+            break;
+        }
+        let response = query_all(port, &format!("01{:02X}", base)).await?;
+        let mut answered = false;
+        for message in elm::messages(&response) {
+            if let [0x41, pid, bitmap @ ..] = message.payload.as_slice() {
+                if *pid == base {
+                    supported.extend(catalogue::supported_in(base, bitmap));
+                    answered = true;
+                }
+            }
+        }
+        if base == 0 && !answered {
+            return Ok(None);
+        }
+    }
+    Ok(Some(supported))
+}
+
+/// Asks for the first two PIDs at once. ECUs that take one PID per request
+/// answer only the first, or not at all.
+async fn answers_multi_pid(port: &mut SerialStream, catalogue: &PidCatalogue) -> SourceResult<bool> {
+    let [first, second, ..] = catalogue.entries() else {
+        return Ok(false);
+    };
+    let response = query_all(port, &format!("01{:02X}{:02X}", first.pid, second.pid)).await?;
+    let answered: Vec<u8> = catalogue.split_replies(&response).into_iter().map(|(pid, _)| pid).collect();
+    Ok(answered.contains(&first.pid) && answered.contains(&second.pid))
+}
+
+/// Sends a request and collects the whole answer, up to the adapter's `>`
+/// prompt, for requests several ECUs or several frames answer.
+async fn query_all(port: &mut SerialStream, request: &str) -> SourceResult<String> {
+    // Drop the tail of an earlier answer, prompt included
+    port.clear(ClearBuffer::Input)?;
+    write_command(port, &format!("{}\r", request)).await?;
+
+    let mut buf = vec![0u8; 256];
+    let mut response = String::new();
+    let timeout = tokio::time::sleep(Duration::from_millis(1000));
+    tokio::pin!(timeout);
+
+    loop {
+        tokio::select! {
+            _ = &mut timeout => return Ok(response),
+            result = port.read(&mut buf) => {
+                let n = result?;
+                response.push_str(&String::from_utf8_lossy(&buf[..n]));
+                if response.trim_end().ends_with('>') {
+                    return Ok(response);
+                }
+            }
+        }
+    }
+}
+
+/// Requests one PID and returns its data bytes, or `None` on timeout.
+async fn query_pid(port: &mut SerialStream, pid: u8) -> SourceResult<Option<Vec<u8>>> {
+    write_command(port, &format!("01{:02X}\r", pid)).await?;
+
+    let mut buf = vec![0u8; 256];
+    let mut response = String::new();
+    let timeout = tokio::time::sleep(tokio::time::Duration::from_millis(200));
+    tokio::pin!(timeout);
+
+    loop {
+        tokio::select! {
+            _ = &mut timeout => {
+                return Ok(None); // Timeout
+            }
+            result = port.read(&mut buf) => {
+                let n = result?;
+                response.push_str(&String::from_utf8_lossy(&buf[..n]));
+                let answer = response
+                    .split(['\r', '\n'])
+                    .filter_map(parse_response)
+                    .find(|(answered, _)| *answered == pid);
+                if let Some((_, data)) = answer {
+                    return Ok(Some(data));
+                }
+            }
+        }
+    }
+}
+
+/// PID and data bytes of a mode 01 response line such as `41 0D 3C`, with or
+/// without a CAN header in front (`7E8 03 41 0D 3C`).
+pub fn parse_response(line: &str) -> Option<(u8, Vec<u8>)> {
+    let bytes: Vec<u8> = line
+        .split_whitespace()
+        .filter(|token| token.len() == 2)
+        .map(|token| u8::from_str_radix(token, 16))
+        .collect::<Result<_, _>>()
+        .ok()?;
+    let at = bytes.iter().position(|b| *b == 0x41)?;
+    let pid = *bytes.get(at + 1)?;
+    Some((pid, bytes[at + 2..].to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    /// One engine ECU on CAN that supports load, coolant, RPM, speed and fuel
+    /// level but not throttle, and takes multi-PID requests.
+    async fn mock_elm327(mut port: SerialStream, requests: Arc<Mutex<Vec<String>>>) {
+        let values: HashMap<u8, &[u8]> = [
+            (0x00, &[0x18, 0x18, 0x00, 0x01][..]),
+            (0x20, &[0x00, 0x02, 0x00, 0x00][..]),
+            (0x04, &[0x66][..]),
+            (0x05, &[0x7B][..]),
+            (0x0C, &[0x1A, 0xF8][..]),
+            (0x0D, &[0x3C][..]),
+            (0x2F, &[0x80][..]),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut buf = [0u8; 64];
+        let mut pending = String::new();
+        while let Ok(n @ 1..) = port.read(&mut buf).await {
+            pending.push_str(&String::from_utf8_lossy(&buf[..n]));
+            while let Some(end) = pending.find('\r') {
+                let request = pending.drain(..=end).collect::<String>().trim().to_string();
+                let reply = if request.starts_with("AT") { "OK".to_string() } else { answer(&request, &values) };
+                requests.lock().unwrap().push(request);
+                port.write_all(format!("{}\r\r>", reply).as_bytes()).await.unwrap();
+            }
+        }
+    }
+
+    /// Mode 01 reply as the adapter prints it with headers on.
+    fn answer(request: &str, values: &HashMap<u8, &[u8]>) -> String {
+        let bytes: Vec<u8> = (0..request.len())
+            .step_by(2)
+            .filter_map(|i| u8::from_str_radix(request.get(i..i + 2)?, 16).ok())
+            .collect();
+        let [0x01, pids @ ..] = bytes.as_slice() else {
+            return "NO DATA".to_string();
+        };
+        let mut payload = vec![0x41];
+        for pid in pids {
+            if let Some(data) = values.get(pid) {
+                payload.push(*pid);
+                payload.extend_from_slice(data);
+            }
+        }
+        if payload.len() == 1 {
+            return "NO DATA".to_string();
+        }
+
+        let hex = |bytes: &[u8]| bytes.iter().map(|b| format!(" {:02X}", b)).collect::<String>();
+        if payload.len() <= 7 {
+            return format!("7E8 {:02X}{}", payload.len(), hex(&payload));
+        }
+        let mut lines = vec![format!("7E8 10 {:02X}{}", payload.len(), hex(&payload[..6]))];
+        for (i, chunk) in payload[6..].chunks(7).enumerate() {
+            lines.push(format!("7E8 {:02X}{}", 0x21 + i, hex(chunk)));
+        }
+        lines.join("\r")
+    }
+
+    #[tokio::test]
+    async fn test_polls_supported_pids_from_mock_elm327() {
+        let (agent, adapter) = SerialStream::pair().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        tokio::spawn(mock_elm327(adapter, requests.clone()));
+
+        let mut sensors = Config::default().sensors;
+        sensors.obd_dtc.enable = false;
+        let (_clear_tx, clear_rx) = mpsc::channel(1);
+        let mut reader = ObdReader::with_port("pty", agent, &sensors, clear_rx).await.unwrap();
+        assert_eq!(reader.per_request, MAX_PIDS_PER_REQUEST);
+
+        let reading = reader.next_reading().await.unwrap().unwrap();
+        let SensorValues::Obd(obd) = reading.values else {
+            panic!("not an OBD reading");
+        };
+        assert_eq!(
+            (obd.rpm, obd.speed_kmh, obd.engine_load, obd.coolant_temp, obd.fuel_level, obd.throttle_pos),
+            (1726, 60, 40, 83, 50, 0)
+        );
+
+        // Throttle is not in the bitmap and the 0x20 bitmap ends the chain
+        let requests = requests.lock().unwrap().clone();
+        assert!(requests.iter().any(|r| r == "0120") && !requests.iter().any(|r| r == "0140"));
+        let asks_for = |r: &String, pid: &str| r.starts_with("01") && (2..r.len()).step_by(2).any(|i| &r[i..i + 2] == pid);
+        assert!(!requests.iter().any(|r| asks_for(r, "11")));
+        assert!(requests.iter().any(|r| r == "010C0D04052F"));
+    }
+}
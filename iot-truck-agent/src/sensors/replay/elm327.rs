@@ -0,0 +1,109 @@
+use super::{Pacer, ReplayFile};
+use crate::config::PidSpec;
+use crate::sensors::obd::catalogue::PidCatalogue;
+use crate::sensors::obd::parse_response;
+use crate::sensors::types::{ObdData, SensorType, SensorValues};
+use crate::sensors::{Reading, SensorSource, SourceResult};
+
+/// Transcript of an ELM327 session, one line per request or response,
+/// optionally stamped with seconds since the start: `[12.350] 41 0D 3C`.
+///
+/// Requests, prompts, AT replies and `NO DATA` are skipped, as are PIDs not
+/// in the catalogue. Responses are collected into one reading until a PID comes round again. Unstamped
+/// transcripts play at the configured sample rate.
+pub struct Elm327Replay {
+    path: String,
+    file: ReplayFile,
+    pacer: Pacer,
+    catalogue: PidCatalogue,
+    period_sec: f64,
+    frames: u64,
+    /// First response of the next reading, read while closing the last one.
+    carry: Option<(Option<f64>, u8, Vec<u8>)>,
+}
+
+impl Elm327Replay {
+    pub async fn open(path: &str, speed: f64, repeat: bool, sample_rate_hz: u32, pids: &[PidSpec]) -> SourceResult<Self> {
+        Ok(Self {
+            path: path.to_string(),
+            file: ReplayFile::open(path, repeat).await?,
+            pacer: Pacer::new(speed),
+            catalogue: PidCatalogue::from_config(pids)?,
+            period_sec: 1.0 / sample_rate_hz.max(1) as f64,
+            frames: 0,
+            carry: None,
+        })
+    }
+}
+
+/// Splits the `[seconds]` stamp off a transcript line.
+fn split_stamp(line: &str) -> (Option<f64>, &str) {
+    let line = line.trim();
+    if let Some(rest) = line.strip_prefix('[') {
+        if let Some((stamp, body)) = rest.split_once(']') {
+            return (stamp.trim().parse().ok(), body);
+        }
+    }
+    (None, line)
+}
+
+#[async_trait::async_trait]
+impl SensorSource for Elm327Replay {
+    fn sensor_id(&self) -> &str {
+        &self.path
+    }
+
+    fn sensor_type(&self) -> SensorType {
+        SensorType::Obd
+    }
+
+    async fn next_reading(&mut self) -> SourceResult<Option<Reading>> {
+        let mut obd = ObdData::default();
+        let mut seen: Vec<u8> = Vec::new();
+        let mut started_at = None;
+
+        if let Some((at, pid, data)) = self.carry.take() {
+            self.catalogue.apply(&mut obd, pid, &data);
+            seen.push(pid);
+            started_at = at;
+        }
+
+        loop {
+            let mut restarted = false;
+            let Some(line) = self.file.next_line(&mut restarted).await? else {
+                break;
+            };
+            if restarted {
+                self.pacer.restart();
+            }
+
+            let (at, body) = split_stamp(&line);
+            let Some((pid, data)) = parse_response(body) else {
+                continue;
+            };
+            if seen.contains(&pid) {
+                self.carry = Some((at, pid, data));
+                break;
+            }
+            if self.catalogue.apply(&mut obd, pid, &data) {
+                self.file.mark_yielded();
+                if seen.is_empty() {
+                    started_at = at;
+                }
+                seen.push(pid);
+            }
+        }
+
+        if seen.is_empty() {
+            return Ok(None);
+        }
+        let at = started_at.unwrap_or(self.frames as f64 * self.period_sec);
+        self.frames += 1;
+        self.pacer.wait(at).await;
+
+        Ok(Some(Reading {
+            values: SensorValues::Obd(obd),
+            raw_payload: None,
+        }))
+    }
+}
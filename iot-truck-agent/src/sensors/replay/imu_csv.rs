@@ -0,0 +1,115 @@
+use super::{Pacer, ReplayFile};
+use crate::sensors::types::{ImuData, SensorType, SensorValues};
+use crate::sensors::{Reading, SensorSource, SourceResult};
+use tracing::warn;
+
+/// Column positions from the capture's header row.
+struct Columns {
+    timestamp_ms: usize,
+    accel: [usize; 3],
+    gyro: [Option<usize>; 3],
+}
+
+impl Columns {
+    fn from_header(header: &str) -> SourceResult<Self> {
+        let names: Vec<&str> = header.split(',').map(str::trim).collect();
+        let find = |name: &str| names.iter().position(|n| *n == name);
+        let require = |name: &str| find(name).ok_or_else(|| format!("IMU capture has no {} column", name));
+        Ok(Self {
+            timestamp_ms: require("timestamp_ms")?,
+            accel: [require("accel_x")?, require("accel_y")?, require("accel_z")?],
+            gyro: [find("gyro_x"), find("gyro_y"), find("gyro_z")],
+        })
+    }
+
+    fn parse(&self, row: &str) -> Option<(f64, ImuData)> {
+        let fields: Vec<&str> = row.split(',').map(str::trim).collect();
+        let value = |i: usize| fields.get(i)?.parse::<f32>().ok();
+        let gyro = |i: Option<usize>| i.map_or(Some(0.0), value);
+        let timestamp_ms = fields.get(self.timestamp_ms)?.parse::<f64>().ok()?;
+        let accel = [value(self.accel[0])?, value(self.accel[1])?, value(self.accel[2])?];
+        Some((
+            timestamp_ms,
+            ImuData {
+                accel_x: accel[0],
+                accel_y: accel[1],
+                accel_z: accel[2],
+                gyro_x: gyro(self.gyro[0])?,
+                gyro_y: gyro(self.gyro[1])?,
+                gyro_z: gyro(self.gyro[2])?,
+                longitudinal_g: Some(accel[0]),
+                lateral_g: Some(accel[1]),
+                vertical_g: Some(accel[2] - 1.0),
+            },
+        ))
+    }
+}
+
+/// CSV accelerometer capture with a `timestamp_ms,accel_x,accel_y,accel_z`
+/// header (g), plus optional `gyro_x,gyro_y,gyro_z` columns (deg/s). The
+/// axes are taken to be the truck's on level road, so gravity is 1 g on z.
+pub struct ImuCsvReplay {
+    path: String,
+    file: ReplayFile,
+    pacer: Pacer,
+    columns: Columns,
+}
+
+impl ImuCsvReplay {
+    pub async fn open(path: &str, speed: f64, repeat: bool) -> SourceResult<Self> {
+        let mut file = ReplayFile::open(path, repeat).await?;
+        let header = file
+            .next_line(&mut false)
+            .await?
+            .ok_or_else(|| format!("IMU capture {} is empty", path))?;
+        Ok(Self {
+            path: path.to_string(),
+            file,
+            pacer: Pacer::new(speed),
+            columns: Columns::from_header(&header)?,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl SensorSource for ImuCsvReplay {
+    fn sensor_id(&self) -> &str {
+        &self.path
+    }
+
+    fn sensor_type(&self) -> SensorType {
+        SensorType::Imu
+    }
+
+    async fn next_reading(&mut self) -> SourceResult<Option<Reading>> {
+        loop {
+            let mut restarted = false;
+            let Some(row) = self.file.next_line(&mut restarted).await? else {
+                return Ok(None);
+            };
+            if restarted {
+                // The header again
+                self.pacer.restart();
+                continue;
+            }
+            if row.trim().is_empty() {
+                continue;
+            }
+
+            match self.columns.parse(&row) {
+                Some((timestamp_ms, imu)) => {
+                    self.file.mark_yielded();
+                    self.pacer.wait(timestamp_ms / 1000.0).await;
+                    return Ok(Some(Reading {
+                        values: SensorValues::Imu(imu),
+                        raw_payload: None,
+                    }));
+                }
+                None => {
+                    metrics::counter!("sensor_errors_total", "sensor" => "imu").increment(1);
+                    warn!(path=%self.path, row=%row, "Malformed row in IMU capture");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Sensor sources that play back recordings instead of reading devices.
+//!
+//! Each keeps the recording's own timing, divided by `speed`: 1.0 plays in
+//! real time, 10.0 ten times faster, 0 as fast as the pipeline takes it.
+
+use crate::sensors::SourceResult;
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+use tokio::time::Duration;
+use tracing::{error, info};
+
+pub mod elm327;
+pub mod imu_csv;
+pub mod nmea;
+
+/// Lines of a recording, starting over at the end if `repeat` is set.
+struct ReplayFile {
+    path: String,
+    repeat: bool,
+    lines: Lines<BufReader<File>>,
+    /// Whether the current pass has produced a reading.
+    pass_yielded: bool,
+}
+
+impl ReplayFile {
+    async fn open(path: &str, repeat: bool) -> SourceResult<Self> {
+        let file = File::open(path)
+            .await
+            .map_err(|e| format!("Failed to open recording {}: {}", path, e))?;
+        info!(path, repeat, "▶️  Replaying sensor recording");
+        Ok(Self {
+            path: path.to_string(),
+            repeat,
+            lines: BufReader::new(file).lines(),
+            pass_yielded: false,
+        })
+    }
+
+    /// Next line, or `None` at the end of a recording that does not repeat,
+    /// or that went a whole pass without a reading and would only spin.
+    /// `restarted` is set when the recording wrapped around.
+    async fn next_line(&mut self, restarted: &mut bool) -> SourceResult<Option<String>> {
+        if let Some(line) = self.lines.next_line().await? {
+            return Ok(Some(line));
+        }
+        if !self.repeat {
+            return Ok(None);
+        }
+        if !std::mem::take(&mut self.pass_yielded) {
+            error!(path=%self.path, "Recording has no readings — not repeating it");
+            return Ok(None);
+        }
+        let file = File::open(&self.path).await?;
+        self.lines = BufReader::new(file).lines();
+        *restarted = true;
+        Ok(self.lines.next_line().await?)
+    }
+
+    /// Records that the current pass produced a reading.
+    fn mark_yielded(&mut self) {
+        self.pass_yielded = true;
+    }
+}
+
+/// Sleeps out the recorded gaps between readings.
+struct Pacer {
+    speed: f64,
+    last_sec: Option<f64>,
+}
+
+impl Pacer {
+    fn new(speed: f64) -> Self {
+        Self { speed, last_sec: None }
+    }
+
+    /// Waits until the reading recorded at `at_sec` is due. Backwards jumps
+    /// (a new recording, a clock reset) are not waited for.
+    async fn wait(&mut self, at_sec: f64) {
+        let gap = self.last_sec.map_or(0.0, |last| at_sec - last);
+        self.last_sec = Some(at_sec);
+        if self.speed > 0.0 && gap > 0.0 {
+            tokio::time::sleep(Duration::from_secs_f64(gap / self.speed)).await;
+        }
+    }
+
+    fn restart(&mut self) {
+        self.last_sec = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::types::SensorValues;
+    use crate::sensors::SensorSource;
+    use std::io::Write;
+
+    fn recording(text: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+        file
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replays_recordings_with_their_timing() {
+        let nmea_log = recording(concat!(
+            "$GPGGA,120000.00,3746.494,N,12225.164,W,1,09,0.9,12.0,M,,M,,*5A\n",
+            "$GPGSV,1,1,00*79\n",
+            "$GPRMC,120001.00,A,3746.494,N,12225.164,W,30.0,90.0,010124,,,A*48\n",
+        ));
+        let mut gps = nmea::NmeaReplay::open(nmea_log.path().to_str().unwrap(), 1.0, false, 10).await.unwrap();
+        let start = tokio::time::Instant::now();
+        let first = gps.next_reading().await.unwrap().unwrap();
+        assert!(matches!(first.values, SensorValues::Gps(g) if g.satellites == 9));
+        let second = gps.next_reading().await.unwrap().unwrap();
+        assert!(matches!(second.values, SensorValues::Gps(g) if (g.speed_kmh - 55.56).abs() < 0.01));
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+        assert!(gps.next_reading().await.unwrap().is_none());
+
+        let transcript = recording(concat!(
+            "[0.000] >010C\n",
+            "[0.000] 41 0C 1A F8\n",
+            "[0.250] 7E8 03 41 0D 3C\n",
+            "NO DATA\n",
+            "[0.500] 41 0C 0F A0\n",
+        ));
+        let pids = crate::config::ObdConfig::default().pids;
+        let mut obd = elm327::Elm327Replay::open(transcript.path().to_str().unwrap(), 2.0, false, 10, &pids)
+            .await
+            .unwrap();
+        let start = tokio::time::Instant::now();
+        let frame = obd.next_reading().await.unwrap().unwrap();
+        assert!(matches!(frame.values, SensorValues::Obd(o) if o.rpm == 1726 && o.speed_kmh == 60));
+        let frame = obd.next_reading().await.unwrap().unwrap();
+        assert!(matches!(frame.values, SensorValues::Obd(o) if o.rpm == 1000 && o.speed_kmh == 0));
+        assert_eq!(start.elapsed(), Duration::from_millis(250));
+        assert!(obd.next_reading().await.unwrap().is_none());
+
+        let capture = recording("timestamp_ms,accel_x,accel_y,accel_z\n0,0.01,0.02,1.0\n10,-0.5,0.0,0.98\n");
+        let mut imu = imu_csv::ImuCsvReplay::open(capture.path().to_str().unwrap(), 0.0, true).await.unwrap();
+        let start = tokio::time::Instant::now();
+        for expected in [0.01, -0.5, 0.01] {
+            let reading = imu.next_reading().await.unwrap().unwrap();
+            assert!(matches!(reading.values, SensorValues::Imu(i) if i.accel_x == expected));
+        }
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_repeating_recording_without_readings_finishes() {
+        let capture = recording("timestamp_ms,accel_x,accel_y,accel_z\n");
+        let mut imu = imu_csv::ImuCsvReplay::open(capture.path().to_str().unwrap(), 0.0, true).await.unwrap();
+        assert!(imu.next_reading().await.unwrap().is_none());
+
+        let nmea_log = recording("$GPGSV,1,1,00*79\n$GPGSV,1,1,00*79\n");
+        let mut gps = nmea::NmeaReplay::open(nmea_log.path().to_str().unwrap(), 0.0, true, 10).await.unwrap();
+        assert!(gps.next_reading().await.unwrap().is_none());
+    }
+}
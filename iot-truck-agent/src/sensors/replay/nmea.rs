@@ -0,0 +1,95 @@
+use super::{Pacer, ReplayFile};
+use crate::sensors::gps::sentences::NmeaState;
+use crate::sensors::types::{SensorType, SensorValues};
+use crate::sensors::{Reading, SensorSource, SourceResult};
+use chrono::Timelike;
+use tracing::warn;
+
+const DAY_SEC: f64 = 86_400.0;
+
+/// Raw NMEA log as written by the receiver, paced by the sentences' fix times.
+pub struct NmeaReplay {
+    path: String,
+    file: ReplayFile,
+    pacer: Pacer,
+    sky_view_interval_sec: u64,
+    state: NmeaState,
+    last_fix_sec: Option<f64>,
+    day_offset_sec: f64,
+}
+
+impl NmeaReplay {
+    pub async fn open(path: &str, speed: f64, repeat: bool, sky_view_interval_sec: u64) -> SourceResult<Self> {
+        Ok(Self {
+            path: path.to_string(),
+            file: ReplayFile::open(path, repeat).await?,
+            pacer: Pacer::new(speed),
+            sky_view_interval_sec,
+            state: NmeaState::new(sky_view_interval_sec),
+            last_fix_sec: None,
+            day_offset_sec: 0.0,
+        })
+    }
+
+    /// Fix time as seconds since the start of the log's first day; NMEA only
+    /// carries the time of day, so a large backwards jump is taken as midnight.
+    fn log_time(&mut self, fix_sec: f64) -> f64 {
+        if let Some(last) = self.last_fix_sec {
+            if fix_sec - last < -DAY_SEC / 2.0 {
+                self.day_offset_sec += DAY_SEC;
+            }
+        }
+        self.last_fix_sec = Some(fix_sec);
+        self.day_offset_sec + fix_sec
+    }
+}
+
+#[async_trait::async_trait]
+impl SensorSource for NmeaReplay {
+    fn sensor_id(&self) -> &str {
+        &self.path
+    }
+
+    fn sensor_type(&self) -> SensorType {
+        SensorType::Gps
+    }
+
+    async fn next_reading(&mut self) -> SourceResult<Option<Reading>> {
+        loop {
+            let mut restarted = false;
+            let Some(line) = self.file.next_line(&mut restarted).await? else {
+                return Ok(None);
+            };
+            if restarted {
+                self.pacer.restart();
+                self.state = NmeaState::new(self.sky_view_interval_sec);
+                self.last_fix_sec = None;
+                self.day_offset_sec = 0.0;
+            }
+
+            let sentence = line.trim();
+            if sentence.is_empty() {
+                continue;
+            }
+            match self.state.feed(sentence) {
+                Ok(Some((gps, fix_time))) => {
+                    self.file.mark_yielded();
+                    if let Some(time) = fix_time {
+                        let fix_sec = time.num_seconds_from_midnight() as f64 + time.nanosecond() as f64 / 1e9;
+                        let at = self.log_time(fix_sec);
+                        self.pacer.wait(at).await;
+                    }
+                    return Ok(Some(Reading {
+                        values: SensorValues::Gps(gps),
+                        raw_payload: Some(sentence.to_string()),
+                    }));
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    metrics::counter!("sensor_errors_total", "sensor" => "gps").increment(1);
+                    warn!(path=%self.path, sentence=%sentence, error=%e, "Malformed NMEA sentence in recording");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use super::{kpa_to_psi, publish_interval, TireTable};
+use crate::config::{TpmsConfig, TpmsDecoder, TpmsFrameConfig};
+use crate::sensors::types::{SensorType, SensorValues, TirePosition, TireSensor};
+use crate::sensors::{Reading, SensorSource, SourceResult};
+use socketcan::tokio::CanSocket;
+use socketcan::{CanFrame, EmbeddedFrame, Frame};
+use tracing::info;
+
+/// TPMS ECUs on a SocketCAN interface: the tractor's own and any trailer
+/// gateways, told apart by the configured frame IDs.
+pub struct CanTpmsReader {
+    interface: String,
+    socket: CanSocket,
+    frames: Vec<TpmsFrameConfig>,
+    table: TireTable,
+    publish: tokio::time::Interval,
+}
+
+impl CanTpmsReader {
+    pub fn open(config: &TpmsConfig) -> SourceResult<Self> {
+        let socket = CanSocket::open(&config.can_interface)
+            .map_err(|e| format!("Failed to open CAN interface {}: {}", config.can_interface, e))?;
+
+        info!(interface=%config.can_interface, frames=config.can_frames.len(), "🛞 TPMS reader started on CAN");
+
+        Ok(Self {
+            interface: config.can_interface.clone(),
+            socket,
+            frames: config.can_frames.clone(),
+            table: TireTable::new(config),
+            publish: publish_interval(config),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl SensorSource for CanTpmsReader {
+    fn sensor_id(&self) -> &str {
+        &self.interface
+    }
+
+    fn sensor_type(&self) -> SensorType {
+        SensorType::Tpms
+    }
+
+    async fn next_reading(&mut self) -> SourceResult<Option<Reading>> {
+        loop {
+            tokio::select! {
+                frame = self.socket.read_frame() => {
+                    // Remote and error frames carry no tire data
+                    if let CanFrame::Data(frame) = frame? {
+                        if let Some(tire) = decode_frame(&self.frames, frame.raw_id(), frame.data()) {
+                            self.table.update(tire);
+                        }
+                    }
+                }
+                _ = self.publish.tick() => {
+                    if let Some(tpms) = self.table.snapshot() {
+                        return Ok(Some(Reading {
+                            values: SensorValues::Tpms(tpms),
+                            raw_payload: None,
+                        }));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Decodes a frame with the first configured decoder whose ID matches.
+fn decode_frame(frames: &[TpmsFrameConfig], id: u32, data: &[u8]) -> Option<TireSensor> {
+    let frame = frames.iter().find(|f| id & f.mask == f.id & f.mask)?;
+    match &frame.decoder {
+        TpmsDecoder::J1939Tire => decode_j1939_tire(frame.unit, data),
+        TpmsDecoder::Bytes {
+            location_byte,
+            pressure_byte,
+            pressure_kpa_per_bit,
+            temperature_byte,
+            temperature_offset_c,
+        } => {
+            let location = *data.get(*location_byte)?;
+            let pressure = *data.get(*pressure_byte)?;
+            let temperature = *data.get(*temperature_byte)?;
+            Some(TireSensor {
+                position: tire_location(frame.unit, location),
+                pressure_psi: kpa_to_psi(pressure as f32 * pressure_kpa_per_bit),
+                temperature_c: temperature as f32 + temperature_offset_c,
+                battery_percent: None,
+                alert: false,
+            })
+        }
+    }
+}
+
+/// J1939 PGN 65268 (TIRE): location, pressure at 4 kPa/bit, temperature at
+/// 1/32 °C/bit from -273 °C, tire status (SPN 1698) and pressure threshold
+/// detection (SPN 2587). Reports without pressure or temperature are dropped.
+fn decode_j1939_tire(unit: u8, data: &[u8]) -> Option<TireSensor> {
+    let [location, pressure, temp_lo, temp_hi, status, _, _, threshold] = *data else {
+        return None;
+    };
+    let temperature = u16::from_le_bytes([temp_lo, temp_hi]);
+    if pressure > 0xFA || temperature > 0xFAFF {
+        return None; // error or not available
+    }
+
+    let leaking = (status >> 2) & 0b11 == 0b01;
+    // Extreme over, over, under and extreme under pressure
+    let off_pressure = matches!(threshold >> 5, 0b000 | 0b001 | 0b011 | 0b100);
+
+    Some(TireSensor {
+        position: tire_location(unit, location),
+        pressure_psi: kpa_to_psi(pressure as f32 * 4.0),
+        temperature_c: temperature as f32 / 32.0 - 273.0,
+        battery_percent: None,
+        alert: leaking || off_pressure,
+    })
+}
+
+/// Axle in the high nibble, position from the left in the low nibble.
+fn tire_location(unit: u8, location: u8) -> TirePosition {
+    TirePosition {
+        unit,
+        axle: location >> 4,
+        position: location & 0x0F,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TpmsSource;
+    use socketcan::ExtendedId;
+
+    fn tire_frame(id: u32, location: u8, pressure_kpa: u16, temperature_c: f32) -> CanFrame {
+        let temperature = (((temperature_c + 273.0) * 32.0) as u16).to_le_bytes();
+        // Sensor enabled, no leak; threshold detection "no warning"
+        let data = [location, (pressure_kpa / 4) as u8, temperature[0], temperature[1], 0x01, 0xFF, 0xFF, 0x5F];
+        CanFrame::new(ExtendedId::new(id).unwrap(), &data).unwrap()
+    }
+
+    // Run with `cargo test -- --ignored` after creating a virtual CAN interface:
+    //   ip link add dev vcan0 type vcan && ip link set up vcan0
+    #[tokio::test]
+    #[ignore = "needs the vcan0 interface"]
+    async fn test_reads_tractor_and_trailer_tires_from_vcan() {
+        let bus = CanSocket::open("vcan0").expect("vcan0 interface");
+
+        let mut config = TpmsConfig {
+            source: TpmsSource::Can,
+            can_interface: "vcan0".to_string(),
+            publish_interval_ms: 50,
+            ..TpmsConfig::default()
+        };
+        // Trailer gateway at source address 0xC8, ahead of the catch-all
+        config.can_frames.insert(
+            0,
+            TpmsFrameConfig {
+                id: 0x18FE_F4C8,
+                mask: 0x03FF_FFFF,
+                unit: 1,
+                decoder: TpmsDecoder::J1939Tire,
+            },
+        );
+        let mut reader = CanTpmsReader::open(&config).unwrap();
+
+        for frame in [
+            tire_frame(0x18FE_F433, 0x00, 760, 40.0), // tractor steer, left
+            tire_frame(0x0CF0_0400, 0x13, 760, 40.0), // engine speed, not a tire
+            tire_frame(0x18FE_F433, 0x13, 760, 45.0), // tractor drive, right outer
+            tire_frame(0x18FE_F4C8, 0x12, 412, 30.0), // trailer rear, right inner, soft
+        ] {
+            bus.write_frame(frame).await.unwrap();
+        }
+
+        let reading = tokio::time::timeout(tokio::time::Duration::from_secs(2), reader.next_reading())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let SensorValues::Tpms(tpms) = reading.values else {
+            panic!("not a TPMS reading");
+        };
+        let positions: Vec<_> = tpms.tires.iter().map(|t| (t.position.unit, t.position.axle, t.position.position)).collect();
+        assert_eq!(positions, [(0, 0, 0), (0, 1, 3), (1, 1, 2)]);
+        assert!((tpms.tires[0].pressure_psi - 110.2).abs() < 0.1);
+        assert_eq!(tpms.tires[1].temperature_c, 45.0);
+        assert_eq!(tpms.tires.iter().map(|t| t.alert).collect::<Vec<_>>(), [false, false, true]);
+    }
+}
@@ -0,0 +1,64 @@
+//! Tire pressure monitoring over CAN (J1939 TPMS ECUs) or a serial 433 MHz
+//! RF receiver.
+//!
+//! Both links report one tire at a time, every few seconds to every few
+//! minutes. The readers keep the latest report per tire and publish the
+//! whole set, tractor and trailers, on a fixed interval.
+
+use crate::config::TpmsConfig;
+use crate::sensors::types::{TirePosition, TireSensor, TpmsData};
+use std::collections::BTreeMap;
+use tokio::time::{interval, Duration, Instant, Interval, MissedTickBehavior};
+
+pub mod can;
+pub mod rf;
+
+const KPA_PER_PSI: f32 = 6.894_757;
+
+fn kpa_to_psi(kpa: f32) -> f32 {
+    kpa / KPA_PER_PSI
+}
+
+/// Latest report per tire.
+struct TireTable {
+    tires: BTreeMap<TirePosition, (TireSensor, Instant)>,
+    stale_after: Duration,
+    low_pressure_psi: f32,
+    high_temperature_c: f32,
+}
+
+impl TireTable {
+    fn new(config: &TpmsConfig) -> Self {
+        Self {
+            tires: BTreeMap::new(),
+            stale_after: Duration::from_secs(config.stale_after_sec),
+            low_pressure_psi: config.low_pressure_psi,
+            high_temperature_c: config.high_temperature_c,
+        }
+    }
+
+    /// Records a report, raising its alert if it crosses the configured thresholds.
+    fn update(&mut self, mut tire: TireSensor) {
+        tire.alert |= tire.pressure_psi < self.low_pressure_psi || tire.temperature_c > self.high_temperature_c;
+        self.tires.insert(tire.position, (tire, Instant::now()));
+    }
+
+    /// Every tire heard from recently, or `None` if there are none.
+    fn snapshot(&mut self) -> Option<TpmsData> {
+        let now = Instant::now();
+        let stale_after = self.stale_after;
+        self.tires.retain(|_, (_, seen)| now.duration_since(*seen) < stale_after);
+        if self.tires.is_empty() {
+            return None;
+        }
+        Some(TpmsData {
+            tires: self.tires.values().map(|(tire, _)| tire.clone()).collect(),
+        })
+    }
+}
+
+fn publish_interval(config: &TpmsConfig) -> Interval {
+    let mut tick = interval(Duration::from_millis(config.publish_interval_ms.max(1)));
+    tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    tick
+}
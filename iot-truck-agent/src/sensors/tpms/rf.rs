@@ -0,0 +1,215 @@
+//! Serial 433 MHz TPMS receiver. Every sensor transmission it hears arrives
+//! as one binary frame:
+//!
+//! ```text
+//! 55 AA 0D  id:4  pressure:2  temperature  battery  status  xor
+//! ```
+//!
+//! `0D` is the frame length, the sensor ID and pressure (0.1 kPa) are
+//! big-endian, temperature is offset by -50 °C, battery is in percent, and
+//! `xor` covers every byte before it. Status bits: 0x01 fast leak, 0x02
+//! sensor fault, 0x04 battery low.
+
+use super::{kpa_to_psi, publish_interval, TireTable};
+use crate::config::TpmsConfig;
+use crate::sensors::types::{SensorType, SensorValues, TirePosition, TireSensor};
+use crate::sensors::{Reading, SensorSource, SourceResult};
+use std::collections::HashMap;
+use tokio::io::AsyncReadExt;
+use tokio_serial::{SerialPortBuilderExt, SerialStream};
+use tracing::{debug, error, info, warn};
+
+const SYNC: [u8; 2] = [0x55, 0xAA];
+const FRAME_LEN: usize = 13;
+const STATUS_FAST_LEAK: u8 = 0x01;
+const STATUS_SENSOR_FAULT: u8 = 0x02;
+
+/// One sensor transmission.
+#[derive(Debug, PartialEq)]
+struct RfReport {
+    sensor_id: u32,
+    pressure_kpa: f32,
+    temperature_c: f32,
+    battery_percent: u8,
+    status: u8,
+}
+
+pub struct RfTpmsReader {
+    device_path: String,
+    baud_rate: u32,
+    port: SerialStream,
+    buf: Vec<u8>,
+    /// Sensor ID to the tire it is fitted to.
+    sensors: HashMap<u32, TirePosition>,
+    table: TireTable,
+    publish: tokio::time::Interval,
+}
+
+impl RfTpmsReader {
+    pub fn open(config: &TpmsConfig) -> SourceResult<Self> {
+        let port = open_port(&config.rf_device, config.rf_baud_rate)?;
+        info!(device=%config.rf_device, sensors=config.rf_sensors.len(), "🛞 TPMS reader started on RF receiver");
+
+        let sensors = config
+            .rf_sensors
+            .iter()
+            .map(|s| (s.id, TirePosition { unit: s.unit, axle: s.axle, position: s.position }))
+            .collect();
+
+        Ok(Self {
+            device_path: config.rf_device.clone(),
+            baud_rate: config.rf_baud_rate,
+            port,
+            buf: Vec::new(),
+            sensors,
+            table: TireTable::new(config),
+            publish: publish_interval(config),
+        })
+    }
+
+    fn record(&mut self, report: RfReport) {
+        let Some(position) = self.sensors.get(&report.sensor_id) else {
+            // Neighbouring vehicles' sensors are heard too
+            debug!(sensor_id = %format!("{:08X}", report.sensor_id), "Ignoring unknown TPMS sensor");
+            return;
+        };
+        self.table.update(TireSensor {
+            position: *position,
+            pressure_psi: kpa_to_psi(report.pressure_kpa),
+            temperature_c: report.temperature_c,
+            battery_percent: Some(report.battery_percent),
+            alert: report.status & (STATUS_FAST_LEAK | STATUS_SENSOR_FAULT) != 0,
+        });
+    }
+}
+
+fn open_port(device_path: &str, baud_rate: u32) -> SourceResult<SerialStream> {
+    tokio_serial::new(device_path, baud_rate)
+        .open_native_async()
+        .map_err(|e| format!("Failed to open TPMS receiver {}: {}", device_path, e).into())
+}
+
+#[async_trait::async_trait]
+impl SensorSource for RfTpmsReader {
+    fn sensor_id(&self) -> &str {
+        &self.device_path
+    }
+
+    fn sensor_type(&self) -> SensorType {
+        SensorType::Tpms
+    }
+
+    async fn next_reading(&mut self) -> SourceResult<Option<Reading>> {
+        let mut chunk = [0u8; 64];
+        loop {
+            tokio::select! {
+                read = self.port.read(&mut chunk) => {
+                    let n = read?;
+                    if n == 0 {
+                        // EOF — receiver unplugged
+                        warn!(device=%self.device_path, "TPMS receiver disconnected");
+                        metrics::gauge!("sensor_status", "sensor" => "tpms").set(0.0);
+                        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                        match open_port(&self.device_path, self.baud_rate) {
+                            Ok(port) => {
+                                self.port = port;
+                                self.buf.clear();
+                                info!(device=%self.device_path, "✅ TPMS receiver reconnected");
+                                metrics::gauge!("sensor_status", "sensor" => "tpms").set(1.0);
+                            }
+                            Err(e) => error!(device=%self.device_path, error=%e, "Failed to reconnect TPMS receiver"),
+                        }
+                        continue;
+                    }
+                    self.buf.extend_from_slice(&chunk[..n]);
+                    while let Some(report) = take_frame(&mut self.buf) {
+                        self.record(report);
+                    }
+                }
+                _ = self.publish.tick() => {
+                    if let Some(tpms) = self.table.snapshot() {
+                        return Ok(Some(Reading {
+                            values: SensorValues::Tpms(tpms),
+                            raw_payload: None,
+                        }));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Takes the next valid frame off the front of `buf`, skipping noise and
+/// frames that fail the checksum. Returns `None` until a whole frame is in.
+fn take_frame(buf: &mut Vec<u8>) -> Option<RfReport> {
+    loop {
+        let Some(start) = buf.windows(2).position(|w| w == SYNC) else {
+            // Keep a trailing 0x55 that may start the next frame
+            let keep = usize::from(buf.last() == Some(&SYNC[0]));
+            buf.drain(..buf.len() - keep);
+            return None;
+        };
+        buf.drain(..start);
+        if buf.len() < FRAME_LEN {
+            return None;
+        }
+
+        let frame = &buf[..FRAME_LEN];
+        let checksum = frame[..FRAME_LEN - 1].iter().fold(0u8, |acc, b| acc ^ b);
+        if frame[2] as usize != FRAME_LEN || checksum != frame[FRAME_LEN - 1] {
+            metrics::counter!("sensor_errors_total", "sensor" => "tpms").increment(1);
+            buf.drain(..1);
+            continue;
+        }
+
+        let report = RfReport {
+            sensor_id: u32::from_be_bytes([frame[3], frame[4], frame[5], frame[6]]),
+            pressure_kpa: u16::from_be_bytes([frame[7], frame[8]]) as f32 / 10.0,
+            temperature_c: frame[9] as f32 - 50.0,
+            battery_percent: frame[10],
+            status: frame[11],
+        };
+        buf.drain(..FRAME_LEN);
+        return Some(report);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(sensor_id: u32, pressure_dkpa: u16, temperature: u8, status: u8) -> Vec<u8> {
+        let mut frame = vec![0x55, 0xAA, FRAME_LEN as u8];
+        frame.extend_from_slice(&sensor_id.to_be_bytes());
+        frame.extend_from_slice(&pressure_dkpa.to_be_bytes());
+        frame.extend_from_slice(&[temperature, 90, status]);
+        frame.push(frame.iter().fold(0u8, |acc, b| acc ^ b));
+        frame
+    }
+
+    #[test]
+    fn test_resyncs_past_noise_and_bad_checksums() {
+        let mut corrupt = frame(0x1A2B_3C4D, 7600, 90, 0);
+        corrupt[8] ^= 0xFF;
+        let good = frame(0x1A2B_3C4D, 7600, 90, STATUS_FAST_LEAK);
+
+        let mut buf = vec![0x00, 0x55, 0x13];
+        buf.extend(corrupt);
+        buf.extend(&good[..6]);
+        assert_eq!(take_frame(&mut buf), None);
+
+        buf.extend(&good[6..]);
+        let report = take_frame(&mut buf).unwrap();
+        assert_eq!(
+            report,
+            RfReport {
+                sensor_id: 0x1A2B_3C4D,
+                pressure_kpa: 760.0,
+                temperature_c: 40.0,
+                battery_percent: 90,
+                status: STATUS_FAST_LEAK,
+            }
+        );
+        assert!(buf.is_empty());
+    }
+}
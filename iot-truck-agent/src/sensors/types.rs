@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +18,9 @@ pub enum SensorType {
     Obd,
     Imu,
     Tpms,
+    J1939,
+    Dtc,
+    Position,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,10 +29,29 @@ pub enum SensorValues {
     Obd(ObdData),
     Imu(ImuData),
     Tpms(TpmsData),
+    J1939(J1939Data),
+    Dtc(DtcData),
+    Position(PositionData),
+}
+
+impl SensorValues {
+    /// Type of the event carrying these values. Mostly the source's own, but
+    /// the OBD reader also reports trouble codes.
+    pub fn sensor_type(&self) -> SensorType {
+        match self {
+            SensorValues::Gps(_) => SensorType::Gps,
+            SensorValues::Obd(_) => SensorType::Obd,
+            SensorValues::Imu(_) => SensorType::Imu,
+            SensorValues::Tpms(_) => SensorType::Tpms,
+            SensorValues::J1939(_) => SensorType::J1939,
+            SensorValues::Dtc(_) => SensorType::Dtc,
+            SensorValues::Position(_) => SensorType::Position,
+        }
+    }
 }
 
 // --- GPS ---
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GpsData {
     pub latitude: f64,
     pub longitude: f64,
@@ -37,10 +60,46 @@ pub struct GpsData {
     pub heading: f32,
     pub satellites: u8,
     pub fix_quality: u8,
+    pub fix_type: Option<GnssFixType>,
+    pub hdop: Option<f32>,
+    pub vdop: Option<f32>,
+    pub pdop: Option<f32>,
+    pub horizontal_accuracy_m: Option<f32>, // 1-sigma
+    pub vertical_accuracy_m: Option<f32>,
+    pub satellites_in_view: Vec<SatelliteInView>, // only every sky_view_interval_sec
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum GnssFixType {
+    NoFix,
+    DeadReckoning,
+    Fix2d,
+    Fix3d,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GnssSystem {
+    Gps,
+    Sbas,
+    Galileo,
+    Beidou,
+    Qzss,
+    Glonass,
+    Navic,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SatelliteInView {
+    pub system: GnssSystem,
+    pub prn: u16,
+    pub elevation_deg: Option<i8>,
+    pub azimuth_deg: Option<u16>,
+    pub snr_dbhz: Option<u8>, // None while not tracked
+    pub used: bool,
 }
 
 // --- OBD-II ---
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct ObdData {
     pub rpm: u16,
     pub speed_kmh: u8,
@@ -48,36 +107,123 @@ pub struct ObdData {
     pub fuel_level: u8, // 0-100%
     pub engine_load: u8,
     pub throttle_pos: u8,
+    pub extra: BTreeMap<String, f64>, // catalogue PIDs by name; always written, WAL records are bincode
 }
 
-// --- IMU (Accelerometer) ---
+// --- IMU (Accelerometer, Gyroscope) ---
+// Chip axes until the mounting is calibrated, then the truck's: x forward,
+// y left, z up
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImuData {
     pub accel_x: f32, // g-force
     pub accel_y: f32,
     pub accel_z: f32,
-    pub gyro_x: f32,  // deg/s (future)
+    pub gyro_x: f32,  // deg/s, 0 on accelerometer-only chips
     pub gyro_y: f32,
     pub gyro_z: f32,
+    pub longitudinal_g: Option<f32>, // gravity removed; None until calibrated and settled
+    pub lateral_g: Option<f32>,
+    pub vertical_g: Option<f32>,
 }
 
 // --- TPMS (Tire Pressure) ---
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TpmsData {
-    pub front_left: TireSensor,
-    pub front_right: TireSensor,
-    pub rear_left: TireSensor,
-    pub rear_right: TireSensor,
+    pub tires: Vec<TireSensor>, // tractor first, front axle to rear
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TireSensor {
+    pub position: TirePosition,
     pub pressure_psi: f32,
     pub temperature_c: f32,
-    pub battery_percent: u8,
+    pub battery_percent: Option<u8>, // not reported over J1939
     pub alert: bool,
 }
 
+/// Unit 0 is the tractor, 1 the first trailer. Axles count from the front,
+/// positions from the left-most tire (J1939 tire location).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TirePosition {
+    pub unit: u8,
+    pub axle: u8,
+    pub position: u8,
+}
+
+// --- J1939 (heavy-duty CAN) ---
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct J1939Data {
+    pub engine_speed_rpm: Option<f32>, // None until an ECU reports it
+    pub wheel_speed_kmh: Option<f32>,
+    pub fuel_rate_lph: Option<f32>,
+    pub total_fuel_used_l: Option<f64>,
+    pub odometer_km: Option<f64>,
+    pub engine_hours: Option<f64>,
+    pub coolant_temp_c: Option<f32>,
+    pub oil_temp_c: Option<f32>,
+    pub amber_warning_lamp: bool, // lit on any ECU
+    pub red_stop_lamp: bool,
+    pub active_faults: Vec<J1939Fault>, // DM1 of every ECU
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct J1939Fault {
+    pub source_address: u8,
+    pub spn: u32,
+    pub fmi: u8,
+    pub occurrence_count: u8,
+}
+
+// --- OBD-II trouble codes ---
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DtcData {
+    pub mil_on: bool,
+    pub stored: Vec<DiagnosticCode>,    // mode 03
+    pub pending: Vec<DiagnosticCode>,   // mode 07
+    pub permanent: Vec<DiagnosticCode>, // mode 0A
+    pub freeze_frame: Option<FreezeFrame>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DiagnosticCode {
+    pub code: String, // e.g. P0301
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FreezeFrame {
+    pub code: String,
+    pub values: ObdData,
+}
+
+// --- Fused position ---
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PositionData {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub speed_kmh: f32,
+    pub heading: f32,            // degrees clockwise from true north
+    pub covariance_m2: [f32; 3], // east var, north var, east-north cov
+    pub speed_std_kmh: f32,
+    pub heading_std_deg: f32,
+    pub source: PositionSource,
+    pub gnss_age_ms: u32, // since the filter last accepted a fix
+}
+
+impl PositionData {
+    /// DRMS of the position, in metres.
+    pub fn horizontal_std_m(&self) -> f32 {
+        (self.covariance_m2[0] + self.covariance_m2[1]).max(0.0).sqrt()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PositionSource {
+    Gnss,         // no IMU or wheel speed to fuse
+    Fused,        // GNSS within the timeout, IMU and wheel speed between fixes
+    DeadReckoned, // no usable fix for longer than the timeout
+}
+
 impl fmt::Display for SensorEvent {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -0,0 +1,283 @@
+use crate::stream::types::Batch;
+use std::collections::HashMap;
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn};
+use truck_protocol::{AckStatus, BatchAck, RejectedEvent};
+
+/// Tracks batches handed to a transport until the server acknowledges them.
+///
+/// Delivery at the transport layer (MQTT PUBACK/PUBCOMP, HTTP 200) only means
+/// the broker has the bytes; an event is safe to drop from the WAL once it is
+/// listed as accepted in a [`BatchAck`].
+pub struct AckTracker {
+    in_flight: HashMap<String, InFlight>,
+    max_retries: u32,
+}
+
+struct InFlight {
+    batch: Batch,
+    sent_at: Instant,
+    /// When the send over the cellular link started; not reset by a timeout.
+    link_sent_at: Option<Instant>,
+    attempts: u32,
+}
+
+/// What to do after applying an ack.
+#[derive(Debug, Default)]
+pub struct AckResolution {
+    /// Event ids now durably stored server-side.
+    pub acked: Vec<String>,
+    /// Events to put back on the wire.
+    pub retransmit: Option<Batch>,
+    /// Events the server refused permanently, or that ran out of retries.
+    pub dropped: Vec<RejectedEvent>,
+    /// Wire size of the batch settled and how long the link took to ack it;
+    /// `None` for a batch that did not go over the cellular link.
+    pub round_trip: Option<(usize, Duration)>,
+}
+
+impl AckTracker {
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            in_flight: HashMap::new(),
+            max_retries,
+        }
+    }
+
+    /// Registers a batch just sent over the cellular link.
+    pub fn track(&mut self, batch: Batch) {
+        self.track_sent(batch, Some(Instant::now()));
+    }
+
+    /// Registers a batch whose ack came back with the send: `link_sent_at`
+    /// is when that send started, or `None` if it did not use the cellular link.
+    pub fn track_sent(&mut self, batch: Batch, link_sent_at: Option<Instant>) {
+        // A retransmission was settled by its ack; its events carry the count
+        let attempts = match self.in_flight.get(&batch.batch_id) {
+            Some(flight) => flight.attempts + 1,
+            None => 1 + batch.events.iter().map(|e| e.metadata.retry_count).max().unwrap_or(0),
+        };
+
+        self.in_flight.insert(
+            batch.batch_id.clone(),
+            InFlight {
+                batch,
+                sent_at: Instant::now(),
+                link_sent_at,
+                attempts,
+            },
+        );
+        metrics::gauge!("stream_batches_in_flight").set(self.in_flight.len() as f64);
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    pub fn resolve(&mut self, ack: &BatchAck) -> AckResolution {
+        let Some(flight) = self.in_flight.remove(&ack.batch_id) else {
+            // Late or repeated ack for a batch we already settled
+            return AckResolution {
+                acked: ack.accepted.clone(),
+                ..Default::default()
+            };
+        };
+        metrics::gauge!("stream_batches_in_flight").set(self.in_flight.len() as f64);
+
+        let mut resolution = AckResolution {
+            acked: ack.accepted.clone(),
+            round_trip: flight.link_sent_at.map(|at| (flight.batch.size_bytes, at.elapsed())),
+            ..Default::default()
+        };
+
+        match ack.status {
+            AckStatus::Success | AckStatus::Duplicate => {
+                if ack.accepted.is_empty() {
+                    // Duplicate of a batch we never saw acked: trust the server
+                    resolution.acked = flight.batch.events.iter().map(|e| e.event_id.clone()).collect();
+                }
+                info!(batch_id=%ack.batch_id, status=?ack.status, events=resolution.acked.len(), "✅ Batch acknowledged");
+            }
+            AckStatus::Partial | AckStatus::Failed => {
+                let retry: Vec<&str> = ack.retry_ids().collect();
+                let exhausted = flight.attempts > self.max_retries;
+
+                let mut events = Vec::new();
+                for event in flight.batch.events.iter() {
+                    if !retry.contains(&event.event_id.as_str()) {
+                        continue;
+                    }
+                    if exhausted {
+                        resolution.dropped.push(RejectedEvent {
+                            event_id: event.event_id.clone(),
+                            reason: format!("gave up after {} attempts", flight.attempts),
+                            retryable: false,
+                        });
+                    } else {
+                        let mut event = event.clone();
+                        event.metadata.retry_count += 1;
+                        events.push(event);
+                    }
+                }
+                resolution
+                    .dropped
+                    .extend(ack.rejected.iter().filter(|r| !r.retryable).cloned());
+
+                warn!(
+                    batch_id=%ack.batch_id,
+                    accepted=ack.accepted.len(),
+                    retry=events.len(),
+                    dropped=resolution.dropped.len(),
+                    "⚠️  Batch partially acknowledged"
+                );
+
+                if !events.is_empty() {
+                    let mut batch = flight.batch;
+                    batch.events = events;
+                    resolution.retransmit = Some(batch);
+                }
+            }
+        }
+
+        resolution
+    }
+
+    /// Batches that have waited longer than `timeout` for an ack.
+    ///
+    /// Their clock is restarted so each timeout yields a batch only once.
+    pub fn expired(&mut self, timeout: Duration) -> Vec<Batch> {
+        let now = Instant::now();
+        self.in_flight
+            .values_mut()
+            .filter(|f| now.duration_since(f.sent_at) > timeout)
+            .map(|f| {
+                f.sent_at = now;
+                f.batch.clone()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::types::{
+        CommandResponseData, CommandStatus, CompressionType, EventMetadata, EventPayload, EventPriority, EventType,
+        QoSLevel, StreamEvent,
+    };
+
+    fn event(id: &str) -> StreamEvent {
+        StreamEvent {
+            event_id: id.to_string(),
+            event_type: EventType::CommandResponse,
+            payload: EventPayload::CommandResponse(CommandResponseData {
+                command_id: "cmd-1".to_string(),
+                status: CommandStatus::Success,
+                message: String::new(),
+                data: None,
+            }),
+            timestamp: 0,
+            priority: EventPriority::Medium,
+            metadata: EventMetadata {
+                device_id: "TRK-0001".to_string(),
+                truck_id: "TRK-0001".to_string(),
+                sequence_number: 0,
+                retry_count: 0,
+                source_module: "test".to_string(),
+                requires_ack: true,
+                qos: QoSLevel::AtLeastOnce,
+                encryption: None,
+            },
+        }
+    }
+
+    fn batch(ids: &[&str]) -> Batch {
+        Batch {
+            batch_id: "batch-1".to_string(),
+            events: ids.iter().map(|id| event(id)).collect(),
+            created_at: 0,
+            size_bytes: 0,
+            compression_ratio: 1.0,
+            priority: EventPriority::Medium,
+            estimated_latency_ms: 0.0,
+            sensor_encoding: CompressionType::None,
+            sample_intervals: Default::default(),
+        }
+    }
+
+    fn ack(accepted: &[&str], retry: &[&str]) -> BatchAck {
+        let rejected = retry
+            .iter()
+            .map(|id| RejectedEvent {
+                event_id: id.to_string(),
+                reason: "storage busy".to_string(),
+                retryable: true,
+            })
+            .collect();
+        BatchAck::new(
+            "TRK-0001",
+            "batch-1",
+            accepted.iter().map(|id| id.to_string()).collect(),
+            rejected,
+            1,
+            0,
+        )
+    }
+
+    #[test]
+    fn test_retransmissions_count_towards_the_retry_limit() {
+        let mut tracker = AckTracker::new(2);
+        tracker.track(batch(&["evt-1", "evt-2"]));
+
+        let resolution = tracker.resolve(&ack(&["evt-1"], &["evt-2"]));
+        assert_eq!(resolution.acked, vec!["evt-1"]);
+        let mut retry = resolution.retransmit.expect("evt-2 should be retried");
+        assert_eq!(retry.events.len(), 1);
+        assert_eq!(tracker.in_flight(), 0);
+
+        // Sent again and failed again, until the attempts run out
+        for attempt in 2..=3 {
+            tracker.track(retry);
+            let resolution = tracker.resolve(&ack(&[], &["evt-2"]));
+            match resolution.retransmit {
+                Some(batch) if attempt == 2 => retry = batch,
+                None if attempt == 3 => {
+                    assert_eq!(resolution.dropped.len(), 1);
+                    assert_eq!(resolution.dropped[0].reason, "gave up after 3 attempts");
+                    return;
+                }
+                other => panic!("attempt {}: unexpected retransmit {:?}", attempt, other.map(|b| b.events.len())),
+            }
+        }
+        unreachable!();
+    }
+
+    #[test]
+    fn test_duplicate_ack_releases_the_whole_batch() {
+        let mut tracker = AckTracker::new(2);
+        tracker.track(batch(&["evt-1", "evt-2"]));
+
+        let mut duplicate = ack(&[], &[]);
+        duplicate.status = AckStatus::Duplicate;
+        let resolution = tracker.resolve(&duplicate);
+        assert_eq!(resolution.acked, vec!["evt-1", "evt-2"]);
+        assert!(resolution.retransmit.is_none() && resolution.dropped.is_empty());
+
+        // Repeated ack after the batch was settled
+        assert_eq!(tracker.resolve(&ack(&["evt-1"], &[])).acked, vec!["evt-1"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_round_trip_reported_only_for_link_batches() {
+        let mut tracker = AckTracker::new(2);
+        let mut sent = batch(&["evt-1"]);
+        sent.size_bytes = 4096;
+        tracker.track(sent.clone());
+        tokio::time::advance(Duration::from_millis(300)).await;
+        assert_eq!(tracker.resolve(&ack(&["evt-1"], &[])).round_trip, Some((4096, Duration::from_millis(300))));
+
+        // Sent over depot Wi-Fi
+        tracker.track_sent(sent, None);
+        assert_eq!(tracker.resolve(&ack(&["evt-1"], &[])).round_trip, None);
+    }
+}
@@ -0,0 +1,377 @@
+use crate::config::TlsConfig;
+use crate::health::types::{AlertInfo, AlertSeverity};
+use crate::stream::error::{Result, StreamError};
+use rumqttc::Transport;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Duration;
+use tracing::{error, info, warn};
+
+/// Asks the MQTT connection to reconnect with the credentials now on disk;
+/// answered with whether the broker accepted them.
+pub type ReloadRequest = oneshot::Sender<bool>;
+
+const DAY_SECS: i64 = 24 * 3600;
+
+/// The device's TLS identity towards the broker: CA-pinned transport,
+/// certificate expiry alerts and on-device rotation.
+///
+/// Rotation is two remote commands. `create_csr` generates a new key that
+/// never leaves the device and returns a CSR for the fleet CA to sign;
+/// `install_certificate` swaps the signed certificate in, reconnects, and
+/// restores the previous pair if the broker refuses the new one.
+#[derive(Clone)]
+pub struct DeviceIdentity {
+    config: TlsConfig,
+    device_id: String,
+    reload_tx: mpsc::Sender<ReloadRequest>,
+    reload_rx: Arc<parking_lot::Mutex<Option<mpsc::Receiver<ReloadRequest>>>>,
+    rotation: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl DeviceIdentity {
+    pub fn new(config: &TlsConfig, device_id: &str) -> Self {
+        let (reload_tx, reload_rx) = mpsc::channel(1);
+        Self {
+            config: config.clone(),
+            device_id: device_id.to_string(),
+            reload_tx,
+            reload_rx: Arc::new(parking_lot::Mutex::new(Some(reload_rx))),
+            rotation: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enable
+    }
+
+    /// Reconnect requests for the MQTT connection; can be taken once.
+    pub fn take_reload_requests(&self) -> Option<mpsc::Receiver<ReloadRequest>> {
+        self.reload_rx.lock().take()
+    }
+
+    /// Makes sure the certificate and key on disk belong together. A rotation
+    /// or rollback interrupted between its two file replacements leaves a
+    /// mismatched pair the broker would refuse; the previous pair is restored then.
+    pub fn recover(&self) -> Result<()> {
+        let cert_path = PathBuf::from(&self.config.cert_path);
+        let key_path = PathBuf::from(&self.config.key_path);
+        if is_pair(&cert_path, &key_path) {
+            return Ok(());
+        }
+        if !is_pair(&previous(&cert_path), &previous(&key_path)) {
+            return Err(StreamError::CertificateError(
+                "device certificate does not match its key and there is no previous pair".to_string(),
+            ));
+        }
+
+        warn!("Device certificate does not match its key — restoring the previous pair");
+        metrics::counter!("mqtt_cert_rotation_rollbacks_total").increment(1);
+        restore_previous(&cert_path, &key_path)
+    }
+
+    /// TLS transport trusting only the configured CA, authenticating with the
+    /// device certificate currently on disk.
+    pub fn transport(&self) -> Result<Transport> {
+        let ca = read(&self.config.ca_path)?;
+        let cert = read(&self.config.cert_path)?;
+        let key = read(&self.config.key_path)?;
+        Ok(Transport::tls(ca, Some((cert, key)), None))
+    }
+
+    /// Expiry of the installed device certificate, unix seconds.
+    pub fn expires_at(&self) -> Result<i64> {
+        not_after(&read(&self.config.cert_path)?)
+    }
+
+    /// Alerts for the next `HealthEvent` while the certificate nears expiry.
+    pub fn expiry_alerts(&self) -> Vec<AlertInfo> {
+        if !self.config.enable {
+            return Vec::new();
+        }
+
+        let (severity, message) = match self.expires_at() {
+            Ok(expires_at) => {
+                let days_left = (expires_at - chrono::Utc::now().timestamp()) / DAY_SECS;
+                metrics::gauge!("mqtt_cert_expiry_days").set(days_left as f64);
+                if days_left < self.config.expiry_critical_days as i64 {
+                    (AlertSeverity::Critical, format!("Device certificate expires in {} days", days_left))
+                } else if days_left < self.config.expiry_warning_days as i64 {
+                    (AlertSeverity::Warning, format!("Device certificate expires in {} days", days_left))
+                } else {
+                    return Vec::new();
+                }
+            }
+            Err(e) => (AlertSeverity::Critical, format!("Device certificate unreadable: {}", e)),
+        };
+
+        vec![AlertInfo {
+            alert_id: format!("mqtt-cert-{}", chrono::Utc::now().timestamp_nanos()),
+            alert_type: "mqtt_cert_expiry".to_string(),
+            severity,
+            message,
+            triggered_at: chrono::Utc::now().timestamp_nanos() as u64,
+            source: "stream".to_string(),
+            recommended_action: "Rotate the device certificate".to_string(),
+        }]
+    }
+
+    /// Generates a new key pair beside the current one and returns a CSR (PEM)
+    /// for it. The current credentials stay in use until a certificate is installed.
+    pub async fn create_csr(&self) -> Result<String> {
+        let _rotation = self.rotation.lock().await;
+
+        let mut params = rcgen::CertificateParams::new(Vec::new());
+        params.distinguished_name = rcgen::DistinguishedName::new();
+        params.distinguished_name.push(rcgen::DnType::CommonName, self.device_id.clone());
+        let request = rcgen::Certificate::from_params(params).map_err(cert_error)?;
+
+        let csr = request.serialize_request_pem().map_err(cert_error)?;
+        write_private(&pending(&self.config.key_path), request.serialize_private_key_pem().as_bytes())?;
+
+        info!(device_id=%self.device_id, "🔑 New device key generated — CSR ready for signing");
+        Ok(csr)
+    }
+
+    /// Installs a certificate signed for the key from [`Self::create_csr`] and
+    /// reconnects with it. If the broker does not accept it within
+    /// `rotation_timeout_sec`, the previous certificate and key are restored.
+    /// Returns the new certificate's expiry.
+    pub async fn install_certificate(&self, cert_pem: &str) -> Result<i64> {
+        let _rotation = self.rotation.lock().await;
+
+        let pending_key = pending(&self.config.key_path);
+        let key_pem = std::fs::read_to_string(&pending_key)
+            .map_err(|_| StreamError::CertificateError("no pending key — create a CSR first".to_string()))?;
+        let expires_at = check_matches_key(cert_pem.as_bytes(), &key_pem)?;
+
+        // Keep the working pair so a rejected certificate can be undone
+        let cert_path = PathBuf::from(&self.config.cert_path);
+        let key_path = PathBuf::from(&self.config.key_path);
+        copy_private(&cert_path, &previous(&cert_path))?;
+        copy_private(&key_path, &previous(&key_path))?;
+
+        // Each file is replaced by rename, so neither is ever seen half-written;
+        // a crash between the two leaves a mismatch that `recover` undoes
+        write_private(&pending(&cert_path), cert_pem.as_bytes())?;
+        std::fs::rename(&pending_key, &key_path)?;
+        std::fs::rename(pending(&cert_path), &cert_path)?;
+        sync_dir(&cert_path)?;
+        sync_dir(&key_path)?;
+
+        if self.reload().await {
+            info!(expires_at, "🔐 Device certificate rotated");
+            metrics::counter!("mqtt_cert_rotations_total").increment(1);
+            return Ok(expires_at);
+        }
+
+        warn!("Broker refused the rotated certificate — rolling back");
+        metrics::counter!("mqtt_cert_rotation_rollbacks_total").increment(1);
+        restore_previous(&cert_path, &key_path)?;
+        if !self.reload().await {
+            error!("MQTT still not connected after restoring the previous certificate");
+        }
+        Err(StreamError::CertificateError("broker did not accept the new certificate".to_string()))
+    }
+
+    async fn reload(&self) -> bool {
+        let (done_tx, done_rx) = oneshot::channel();
+        if self.reload_tx.send(done_tx).await.is_err() {
+            return false;
+        }
+        let timeout = Duration::from_secs(self.config.rotation_timeout_sec);
+        matches!(tokio::time::timeout(timeout, done_rx).await, Ok(Ok(true)))
+    }
+}
+
+/// Checks `cert_pem` is a currently valid certificate for `key_pem`; returns its expiry.
+fn check_matches_key(cert_pem: &[u8], key_pem: &str) -> Result<i64> {
+    let (_, pem) = x509_parser::pem::parse_x509_pem(cert_pem).map_err(cert_error)?;
+    let cert = pem.parse_x509().map_err(cert_error)?;
+    if !certifies(&cert, key_pem)? {
+        return Err(StreamError::CertificateError("certificate does not match the pending key".to_string()));
+    }
+    if !cert.validity().is_valid() {
+        return Err(StreamError::CertificateError("certificate is not currently valid".to_string()));
+    }
+    Ok(cert.validity().not_after.timestamp())
+}
+
+fn certifies(cert: &x509_parser::certificate::X509Certificate, key_pem: &str) -> Result<bool> {
+    let key = rcgen::KeyPair::from_pem(key_pem).map_err(cert_error)?;
+    Ok(cert.public_key().raw == key.public_key_der().as_slice())
+}
+
+/// Whether both files exist and the certificate is for the key; validity is
+/// not checked, an expired pair is still better than a mismatched one.
+fn is_pair(cert_path: &Path, key_path: &Path) -> bool {
+    let (Ok(cert_pem), Ok(key_pem)) = (std::fs::read(cert_path), std::fs::read_to_string(key_path)) else {
+        return false;
+    };
+    let Ok((_, pem)) = x509_parser::pem::parse_x509_pem(&cert_pem) else {
+        return false;
+    };
+    match pem.parse_x509() {
+        Ok(cert) => matches!(certifies(&cert, &key_pem), Ok(true)),
+        Err(_) => false,
+    }
+}
+
+/// Copies the previous pair back over the current one. The `.previous` files
+/// are kept, so a restore cut short is simply repeated by `recover`.
+fn restore_previous(cert_path: &Path, key_path: &Path) -> Result<()> {
+    for path in [key_path, cert_path] {
+        let staged = with_suffix(path, "restoring");
+        copy_private(&previous(path), &staged)?;
+        std::fs::rename(&staged, path)?;
+        sync_dir(path)?;
+    }
+    Ok(())
+}
+
+fn not_after(cert_pem: &[u8]) -> Result<i64> {
+    let (_, pem) = x509_parser::pem::parse_x509_pem(cert_pem).map_err(cert_error)?;
+    let cert = pem.parse_x509().map_err(cert_error)?;
+    Ok(cert.validity().not_after.timestamp())
+}
+
+fn read(path: &str) -> Result<Vec<u8>> {
+    std::fs::read(path).map_err(|e| StreamError::CertificateError(format!("{}: {}", path, e)))
+}
+
+fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Makes renames into `path`'s directory durable.
+fn sync_dir(path: &Path) -> Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    std::fs::File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+fn copy_private(from: &Path, to: &Path) -> Result<()> {
+    write_private(to, &std::fs::read(from)?)
+}
+
+fn pending(path: impl AsRef<Path>) -> PathBuf {
+    with_suffix(path.as_ref(), "pending")
+}
+
+fn previous(path: impl AsRef<Path>) -> PathBuf {
+    with_suffix(path.as_ref(), "previous")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn cert_error(e: impl std::fmt::Display) -> StreamError {
+    StreamError::CertificateError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A self-signed certificate and its key, both PEM.
+    fn generate_pair() -> (String, String) {
+        let key = rcgen::KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256).unwrap();
+        let key_pem = key.serialize_pem();
+        let mut params = rcgen::CertificateParams::new(vec!["truck-7".to_string()]);
+        params.key_pair = Some(key);
+        let cert_pem = rcgen::Certificate::from_params(params).unwrap().serialize_pem().unwrap();
+        (cert_pem, key_pem)
+    }
+
+    fn identity_in(dir: &Path) -> DeviceIdentity {
+        let config = TlsConfig {
+            enable: true,
+            cert_path: dir.join("device.pem").to_string_lossy().into_owned(),
+            key_path: dir.join("device.key").to_string_lossy().into_owned(),
+            rotation_timeout_sec: 1,
+            ..TlsConfig::default()
+        };
+        DeviceIdentity::new(&config, "truck-7")
+    }
+
+    #[test]
+    fn test_certificate_must_match_pending_key() {
+        let (cert_pem, key_pem) = generate_pair();
+
+        assert!(check_matches_key(cert_pem.as_bytes(), &key_pem).unwrap() > chrono::Utc::now().timestamp());
+
+        let other = rcgen::KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256).unwrap();
+        assert!(check_matches_key(cert_pem.as_bytes(), &other.serialize_pem()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rejected_certificate_rolls_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let identity = identity_in(dir.path());
+        let cert_path = PathBuf::from(&identity.config.cert_path);
+        let key_path = PathBuf::from(&identity.config.key_path);
+
+        let (old_cert, old_key) = generate_pair();
+        std::fs::write(&cert_path, &old_cert).unwrap();
+        std::fs::write(&key_path, &old_key).unwrap();
+        let (new_cert, new_key) = generate_pair();
+        std::fs::write(pending(&key_path), &new_key).unwrap();
+
+        // The broker refuses the new certificate and accepts the old one again
+        let mut reloads = identity.take_reload_requests().unwrap();
+        let broker = tokio::spawn(async move {
+            reloads.recv().await.unwrap().send(false).unwrap();
+            reloads.recv().await.unwrap().send(true).unwrap();
+        });
+
+        assert!(identity.install_certificate(&new_cert).await.is_err());
+        broker.await.unwrap();
+        assert_eq!(std::fs::read_to_string(&cert_path).unwrap(), old_cert);
+        assert_eq!(std::fs::read_to_string(&key_path).unwrap(), old_key);
+    }
+
+    #[test]
+    fn test_recover_restores_previous_pair_after_interrupted_swap() {
+        let dir = tempfile::tempdir().unwrap();
+        let identity = identity_in(dir.path());
+        let cert_path = PathBuf::from(&identity.config.cert_path);
+        let key_path = PathBuf::from(&identity.config.key_path);
+
+        let (old_cert, old_key) = generate_pair();
+        std::fs::write(previous(&cert_path), &old_cert).unwrap();
+        std::fs::write(previous(&key_path), &old_key).unwrap();
+        // Power lost after the new key was renamed in but before the certificate
+        let (_, new_key) = generate_pair();
+        std::fs::write(&cert_path, &old_cert).unwrap();
+        std::fs::write(&key_path, &new_key).unwrap();
+
+        identity.recover().unwrap();
+        assert!(is_pair(&cert_path, &key_path));
+        assert_eq!(std::fs::read_to_string(&key_path).unwrap(), old_key);
+
+        // A consistent pair is left alone
+        identity.recover().unwrap();
+        assert_eq!(std::fs::read_to_string(&cert_path).unwrap(), old_cert);
+    }
+}
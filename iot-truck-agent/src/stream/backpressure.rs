@@ -13,8 +13,11 @@ pub async fn init_wal_manager(wal_manager: WalManager) {
 pub async fn notify_wal_ack(event_id: &str) {
     let wal = WAL_MANAGER.read().await;
     if let Some(wal_manager) = wal.as_ref() {
-        // In production, call wal_manager.mark_acked(event_id)
-        tracing::trace!(event_id, "🗑️  WAL entry acknowledged");
+        match wal_manager.mark_acked(event_id).await {
+            Ok(()) => tracing::trace!(event_id, "🗑️  WAL entry acknowledged"),
+            // The entry stays unacked and is resent after replay — never lost
+            Err(e) => tracing::error!(event_id, error=%e, "Failed to mark WAL entry acked"),
+        }
     }
 }
 
@@ -25,7 +28,7 @@ pub async fn buffer_to_wal(event: crate::stream::types::StreamEvent) -> Result<(
         match event.event_type {
             crate::stream::types::EventType::Sensor => {
                 if let crate::stream::types::EventPayload::Sensor(sensor_event) = event.payload {
-                    wal_manager.write_sensor(sensor_event).await?;
+                    wal_manager.write_sensor(sensor_event, event.metadata.sequence_number).await?;
                 }
             }
             crate::stream::types::EventType::CameraMeta => {
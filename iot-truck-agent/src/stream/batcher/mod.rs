@@ -1,142 +1,220 @@
 use crate::stream::types::{StreamEvent, Batch, EventPriority};
 use tokio::sync::mpsc;
-use tokio::time::{sleep, Duration};
-use tracing::{info, warn};
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tracing::debug;
 use std::collections::VecDeque;
 
+pub mod priority;
+
+pub use priority::{Lane, LaneQueues};
+
+/// Groups events into batches per [`Lane`]: critical events go out alone and
+/// at once, high-priority events in small batches flushed every
+/// `high_flush_ms`, everything else by size, count and timeout.
 pub struct IntelligentBatcher {
     max_batch_size_bytes: usize,
     max_batch_events: usize,
     batch_timeout_ms: u64,
-    priority_queues: [VecDeque<StreamEvent>; 4], // Critical, High, Medium, Low
+    high_max_events: usize,
+    high_flush_ms: u64,
+    high_queue: VecDeque<StreamEvent>,
+    bulk_queue: VecDeque<StreamEvent>,
+    bulk_size: usize,
 }
 
 impl IntelligentBatcher {
-    pub fn new(max_size: usize, max_events: usize, timeout_ms: u64) -> Self {
+    pub fn new(max_size: usize, max_events: usize, timeout_ms: u64, high_max_events: usize, high_flush_ms: u64) -> Self {
         Self {
             max_batch_size_bytes: max_size,
             max_batch_events: max_events,
             batch_timeout_ms: timeout_ms,
-            priority_queues: [
-                VecDeque::new(), // Critical
-                VecDeque::new(), // High
-                VecDeque::new(), // Medium
-                VecDeque::new(), // Low
-            ],
+            high_max_events: high_max_events.max(1),
+            high_flush_ms,
+            high_queue: VecDeque::new(),
+            bulk_queue: VecDeque::new(),
+            bulk_size: 0,
         }
     }
 
     pub async fn start_batcher(
+        mut self,
         mut rx: mpsc::Receiver<StreamEvent>,
         batch_tx: mpsc::Sender<Batch>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut timeout = tokio::time::sleep(Duration::from_millis(1000));
+        let mut high_tick = interval(Duration::from_millis(self.high_flush_ms.max(1)));
+        let mut bulk_tick = interval(Duration::from_millis(self.batch_timeout_ms.max(1)));
+        high_tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        bulk_tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
 
         loop {
-            tokio::select! {
-                Some(event) = rx.recv() => {
-                    let priority_index = event.priority as usize;
-                    if priority_index < 4 {
-                        self.priority_queues[priority_index].push_back(event);
-                    }
-                    
-                    // Try to create batch immediately for critical events
-                    if event.priority == EventPriority::Critical {
-                        if let Some(batch) = self.try_create_batch() {
-                            if batch_tx.send(batch).await.is_err() {
-                                return Err("Batch channel closed".into());
-                            }
-                        }
-                    }
+            let batch = tokio::select! {
+                event = rx.recv() => {
+                    let Some(event) = event else {
+                        return Ok(());
+                    };
+                    self.accept(event)
                 }
-                _ = &mut timeout => {
-                    if let Some(batch) = self.try_create_batch() {
-                        if batch_tx.send(batch).await.is_err() {
-                            return Err("Batch channel closed".into());
-                        }
-                    }
-                    timeout = tokio::time::sleep(Duration::from_millis(self.batch_timeout_ms));
+                _ = high_tick.tick() => self.flush_high(),
+                _ = bulk_tick.tick() => self.flush_bulk(),
+            };
+
+            if let Some(batch) = batch {
+                debug!(batch_id=%batch.batch_id, lane=Lane::of_batch(&batch).as_str(), events=batch.events.len(), "Batch ready");
+                if batch_tx.send(batch).await.is_err() {
+                    return Err("Batch channel closed".into());
                 }
             }
         }
     }
 
-    fn try_create_batch(&mut self) -> Option<Batch> {
-        let mut events = Vec::new();
-        let mut current_size = 0;
-        let mut highest_priority = EventPriority::Low;
-
-        // Always include critical events
-        while let Some(event) = self.priority_queues[0].pop_front() {
-            let event_size = event.size_bytes();
-            if current_size + event_size > self.max_batch_size_bytes && !events.is_empty() {
-                break;
-            }
-            if events.len() >= self.max_batch_events && !events.is_empty() {
-                break;
+    /// Queues `event` in its lane and returns a batch if that filled one.
+    fn accept(&mut self, event: StreamEvent) -> Option<Batch> {
+        match Lane::of(&event) {
+            Lane::Critical => Some(Self::build_batch(vec![event], Lane::Critical)),
+            Lane::Priority => {
+                self.high_queue.push_back(event);
+                if self.high_queue.len() >= self.high_max_events { self.flush_high() } else { None }
             }
-            events.push(event);
-            current_size += event_size;
-            highest_priority = EventPriority::Critical;
-        }
-
-        // Include high priority if space
-        if current_size < self.max_batch_size_bytes / 2 {
-            while let Some(event) = self.priority_queues[1].pop_front() {
-                let event_size = event.size_bytes();
-                if current_size + event_size > self.max_batch_size_bytes {
-                    break;
-                }
-                if events.len() >= self.max_batch_events {
-                    break;
-                }
-                events.push(event);
-                current_size += event_size;
-                if highest_priority > EventPriority::High {
-                    highest_priority = EventPriority::High;
+            Lane::Bulk => {
+                self.bulk_size += event.size_bytes();
+                self.bulk_queue.push_back(event);
+                if self.bulk_size >= self.max_batch_size_bytes || self.bulk_queue.len() >= self.max_batch_events {
+                    self.flush_bulk()
+                } else {
+                    None
                 }
             }
         }
+    }
 
-        // Include medium/low if space and not time-critical
-        if !events.is_empty() && events[0].priority > EventPriority::High {
-            while current_size < self.max_batch_size_bytes / 4 && events.len() < self.max_batch_events {
-                let event = if !self.priority_queues[2].is_empty() {
-                    self.priority_queues[2].pop_front()
-                } else if !self.priority_queues[3].is_empty() {
-                    self.priority_queues[3].pop_front()
-                } else {
-                    break;
-                };
+    fn flush_high(&mut self) -> Option<Batch> {
+        if self.high_queue.is_empty() {
+            return None;
+        }
+        let n = self.high_queue.len().min(self.high_max_events);
+        let events: Vec<_> = self.high_queue.drain(..n).collect();
+        Some(Self::build_batch(events, Lane::Priority))
+    }
 
-                if let Some(event) = event {
-                    let event_size = event.size_bytes();
-                    events.push(event);
-                    current_size += event_size;
-                }
+    fn flush_bulk(&mut self) -> Option<Batch> {
+        let mut events = Vec::new();
+        let mut current_size = 0;
+        while let Some(event) = self.bulk_queue.front() {
+            let event_size = event.size_bytes();
+            if !events.is_empty()
+                && (current_size + event_size > self.max_batch_size_bytes || events.len() >= self.max_batch_events)
+            {
+                break;
             }
+            current_size += event_size;
+            events.push(self.bulk_queue.pop_front().unwrap());
         }
+        self.bulk_size -= current_size;
 
         if events.is_empty() {
             return None;
         }
+        Some(Self::build_batch(events, Lane::Bulk))
+    }
 
+    pub(crate) fn build_batch(events: Vec<StreamEvent>, lane: Lane) -> Batch {
         let batch_id = format!("batch-{}", chrono::Utc::now().timestamp_nanos());
         let created_at = chrono::Utc::now().timestamp_nanos() as u64;
 
-        // Compress batch
-        let uncompressed = serde_json::to_vec(&events)?;
-        let compressed = zstd::encode_all(&uncompressed[..], 3)?;
-        let compression_ratio = uncompressed.len() as f32 / compressed.len() as f32;
+        // Batch priority identifies the lane downstream (see `Lane::of_batch`)
+        let priority = match lane {
+            Lane::Critical => EventPriority::Critical,
+            Lane::Priority => EventPriority::High,
+            Lane::Bulk => events.iter().map(|e| e.priority.clone()).min()
+                .unwrap_or(EventPriority::Low)
+                .max(EventPriority::Medium),
+        };
 
-        Some(Batch {
+        let raw_size: usize = events.iter().map(|e| e.size_bytes()).sum();
+        // Compression estimate is only worth its cost for bulk batches
+        let (size_bytes, compression_ratio) = match lane {
+            Lane::Bulk => {
+                let uncompressed = serde_json::to_vec(&events).unwrap_or_default();
+                match zstd::encode_all(&uncompressed[..], 3) {
+                    Ok(compressed) if !compressed.is_empty() => {
+                        (compressed.len(), uncompressed.len() as f32 / compressed.len() as f32)
+                    }
+                    _ => (raw_size, 1.0),
+                }
+            }
+            _ => (raw_size, 1.0),
+        };
+
+        Batch {
             batch_id,
             events,
             created_at,
-            size_bytes: compressed.len(),
+            size_bytes,
             compression_ratio,
-            priority: highest_priority,
+            priority,
             estimated_latency_ms: 0.0, // Will be filled by network monitor
-        })
+            sensor_encoding: crate::stream::types::CompressionType::None,
+            sample_intervals: Default::default(),
+        }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::types::{CommandResponseData, CommandStatus, EventMetadata, EventPayload, EventType, QoSLevel};
+
+    fn event(seq: u64, priority: EventPriority) -> StreamEvent {
+        StreamEvent {
+            event_id: format!("evt-{}", seq),
+            event_type: EventType::CommandResponse,
+            payload: EventPayload::CommandResponse(CommandResponseData {
+                command_id: "cmd-1".to_string(),
+                status: CommandStatus::Success,
+                message: String::new(),
+                data: None,
+            }),
+            timestamp: 0,
+            priority,
+            metadata: EventMetadata {
+                device_id: "TRK-0001".to_string(),
+                truck_id: "TRK-0001".to_string(),
+                sequence_number: seq,
+                retry_count: 0,
+                source_module: "test".to_string(),
+                requires_ack: true,
+                qos: QoSLevel::AtLeastOnce,
+                encryption: None,
+            },
+        }
+    }
+
+    fn ids(batch: &Batch) -> Vec<&str> {
+        batch.events.iter().map(|e| e.event_id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_events_batched_per_lane() {
+        // 512-byte command responses: bulk flushes on the third one by size
+        let mut batcher = IntelligentBatcher::new(1500, 100, 1000, 2, 100);
+
+        assert!(batcher.accept(event(1, EventPriority::Medium)).is_none());
+        assert!(batcher.accept(event(2, EventPriority::High)).is_none());
+
+        // Critical goes out alone, ahead of anything queued
+        let critical = batcher.accept(event(3, EventPriority::Critical)).unwrap();
+        assert_eq!((ids(&critical), Lane::of_batch(&critical)), (vec!["evt-3"], Lane::Critical));
+
+        let high = batcher.accept(event(4, EventPriority::High)).unwrap();
+        assert_eq!((ids(&high), Lane::of_batch(&high)), (vec!["evt-2", "evt-4"], Lane::Priority));
+
+        assert!(batcher.accept(event(5, EventPriority::Low)).is_none());
+        let bulk = batcher.accept(event(6, EventPriority::Low)).unwrap();
+        assert_eq!((ids(&bulk), Lane::of_batch(&bulk)), (vec!["evt-1", "evt-5"], Lane::Bulk));
+
+        // The timeout flush picks up the rest
+        assert!(batcher.flush_high().is_none());
+        assert_eq!(ids(&batcher.flush_bulk().unwrap()), vec!["evt-6"]);
+        assert_eq!(batcher.bulk_size, 0);
+    }
+}
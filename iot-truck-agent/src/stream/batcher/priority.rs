@@ -0,0 +1,184 @@
+use crate::stream::types::{Batch, EventPayload, EventPriority, StreamEvent};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Backoff after a failed send doubles from this up to `MAX_BACKOFF`
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(32);
+
+/// Scheduling lane. Lanes are batched and sent independently so a safety
+/// alert never waits behind sensor bulk or camera blobs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lane {
+    /// Sent one event at a time, immediately, with QoS 2 on the critical topic.
+    Critical,
+    /// High-priority events (ML detections) in small, quickly flushed batches.
+    Priority,
+    /// Sensor data, heartbeats and camera blobs; sent when bandwidth allows.
+    Bulk,
+}
+
+impl Lane {
+    pub const ALL: [Lane; 3] = [Lane::Critical, Lane::Priority, Lane::Bulk];
+
+    pub fn of(event: &StreamEvent) -> Self {
+        if event.is_critical() {
+            Lane::Critical
+        } else if matches!(event.payload, EventPayload::CameraBlob { .. }) {
+            Lane::Bulk
+        } else if event.priority == EventPriority::High {
+            Lane::Priority
+        } else {
+            Lane::Bulk
+        }
+    }
+
+    /// Lane a batch was built for; the batcher sets `priority` to match.
+    pub fn of_batch(batch: &Batch) -> Self {
+        match batch.priority {
+            EventPriority::Critical => Lane::Critical,
+            EventPriority::High => Lane::Priority,
+            EventPriority::Medium | EventPriority::Low => Lane::Bulk,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Lane::Critical => "critical",
+            Lane::Priority => "priority",
+            Lane::Bulk => "bulk",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Batches waiting to be sent, one queue per lane. Failed sends back off per
+/// lane, so a lane that keeps failing does not hold up the others.
+#[derive(Default)]
+pub struct LaneQueues {
+    queues: [VecDeque<Batch>; 3],
+    failures: [u32; 3],
+    retry_at: [Option<Instant>; 3],
+}
+
+impl LaneQueues {
+    pub fn push_back(&mut self, batch: Batch) {
+        self.queues[Lane::of_batch(&batch).index()].push_back(batch);
+    }
+
+    /// Requeues a batch that failed to send ahead of the rest of its lane.
+    pub fn push_front(&mut self, batch: Batch) {
+        self.queues[Lane::of_batch(&batch).index()].push_front(batch);
+    }
+
+    /// Next batch to send, strictly by lane. Bulk is skipped unless
+    /// `bulk_allowed`, and lanes still backing off are skipped.
+    pub fn pop_next(&mut self, bulk_allowed: bool, now: Instant) -> Option<Batch> {
+        Lane::ALL
+            .into_iter()
+            .filter(|lane| bulk_allowed || *lane != Lane::Bulk)
+            .filter(|lane| self.retry_at[lane.index()].is_none_or(|at| at <= now))
+            .find_map(|lane| self.queues[lane.index()].pop_front())
+    }
+
+    /// Holds `lane` back after a failed send; returns the delay.
+    pub fn send_failed(&mut self, lane: Lane, now: Instant) -> Duration {
+        let failures = &mut self.failures[lane.index()];
+        *failures += 1;
+        let delay = BASE_BACKOFF.saturating_mul(1 << (*failures - 1).min(16)).min(MAX_BACKOFF);
+        self.retry_at[lane.index()] = Some(now + delay);
+        delay
+    }
+
+    pub fn send_succeeded(&mut self, lane: Lane) {
+        self.failures[lane.index()] = 0;
+        self.retry_at[lane.index()] = None;
+    }
+
+    pub fn len(&self, lane: Lane) -> usize {
+        self.queues[lane.index()].len()
+    }
+
+    /// Drops queued bulk batches for which `keep` returns false.
+    pub fn retain_bulk(&mut self, keep: impl FnMut(&Batch) -> bool) {
+        self.queues[Lane::Bulk.index()].retain(keep);
+    }
+
+    /// Removes the oldest bulk batches beyond `max`, for the caller to spill to the WAL.
+    pub fn trim_bulk(&mut self, max: usize) -> Vec<Batch> {
+        let bulk = &mut self.queues[Lane::Bulk.index()];
+        let excess = bulk.len().saturating_sub(max);
+        bulk.drain(..excess).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::types::CompressionType;
+
+    fn batch(id: &str, priority: EventPriority) -> Batch {
+        Batch {
+            batch_id: id.to_string(),
+            events: Vec::new(),
+            created_at: 0,
+            size_bytes: 0,
+            compression_ratio: 1.0,
+            priority,
+            estimated_latency_ms: 0.0,
+            sensor_encoding: CompressionType::None,
+            sample_intervals: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_critical_first_and_bulk_held_back() {
+        let mut lanes = LaneQueues::default();
+        lanes.push_back(batch("bulk-1", EventPriority::Medium));
+        lanes.push_back(batch("bulk-2", EventPriority::Low));
+        lanes.push_back(batch("high-1", EventPriority::High));
+        lanes.push_back(batch("crit-1", EventPriority::Critical));
+
+        let now = Instant::now();
+        assert_eq!(lanes.pop_next(false, now).unwrap().batch_id, "crit-1");
+        assert_eq!(lanes.pop_next(false, now).unwrap().batch_id, "high-1");
+        // Poor link: bulk waits
+        assert!(lanes.pop_next(false, now).is_none());
+        assert_eq!(lanes.len(Lane::Bulk), 2);
+
+        let spilled = lanes.trim_bulk(1);
+        assert_eq!(spilled[0].batch_id, "bulk-1");
+        assert_eq!(lanes.pop_next(true, now).unwrap().batch_id, "bulk-2");
+    }
+
+    #[test]
+    fn test_failing_lane_backs_off_alone() {
+        let mut lanes = LaneQueues::default();
+        let now = Instant::now();
+        lanes.push_back(batch("crit-1", EventPriority::Critical));
+        lanes.push_back(batch("bulk-1", EventPriority::Medium));
+
+        // The critical topic is refused: requeue and back off that lane only
+        let failed = lanes.pop_next(true, now).unwrap();
+        assert_eq!(lanes.send_failed(Lane::Critical, now), Duration::from_secs(1));
+        lanes.push_front(failed);
+        lanes.push_back(batch("crit-2", EventPriority::Critical));
+        assert_eq!(lanes.pop_next(true, now).unwrap().batch_id, "bulk-1");
+        assert!(lanes.pop_next(true, now).is_none());
+
+        // Consecutive failures double the delay up to the cap
+        assert_eq!(lanes.send_failed(Lane::Critical, now), Duration::from_secs(2));
+        for _ in 0..10 {
+            lanes.send_failed(Lane::Critical, now);
+        }
+        assert_eq!(lanes.send_failed(Lane::Critical, now), MAX_BACKOFF);
+
+        let later = now + MAX_BACKOFF;
+        assert_eq!(lanes.pop_next(true, later).unwrap().batch_id, "crit-1");
+        lanes.send_succeeded(Lane::Critical);
+        assert_eq!(lanes.pop_next(true, now).unwrap().batch_id, "crit-2");
+    }
+}
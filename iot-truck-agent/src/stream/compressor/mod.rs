@@ -1,15 +1,27 @@
-use crate::stream::types::{StreamEvent, CompressionType};
+use crate::stream::types::{Batch, StreamEvent, CompressionType};
 
 pub struct AdaptiveCompressor;
 
 impl AdaptiveCompressor {
+    /// Column-encode sensor readings once there are enough of them to share
+    /// timestamps and deltas; see `truck_protocol::codec`.
+    pub fn choose_sensor_encoding(batch: &mut Batch) {
+        let sensor_events = batch
+            .events
+            .iter()
+            .filter(|e| matches!(e.payload, crate::stream::types::EventPayload::Sensor(_)))
+            .count();
+        batch.sensor_encoding = if sensor_events >= 2 {
+            CompressionType::Delta
+        } else {
+            CompressionType::None
+        };
+    }
+
     pub fn compress_event(event: &mut StreamEvent, network_quality: &crate::stream::types::NetworkQuality) -> Result<(), Box<dyn std::error::Error>> {
         match &mut event.payload {
-            crate::stream::types::EventPayload::Sensor(sensor_event) => {
-                // Use delta encoding for sensor data
-                let compressed = Self::delta_encode_sensor(sensor_event)?;
-                event.payload = crate::stream::types::EventPayload::Sensor(compressed);
-                event.metadata.encryption = None; // Delta encoding not encrypted
+            crate::stream::types::EventPayload::Sensor(_) => {
+                // Encoded per batch in `choose_sensor_encoding`
             }
             crate::stream::types::EventPayload::CameraBlob { data, compression_type, .. } => {
                 // Choose compression based on network quality
@@ -30,12 +42,6 @@ impl AdaptiveCompressor {
                 }
                 // Otherwise leave as is
             }
-            crate::stream::types::EventPayload::Ml(ml_event) => {
-                // Compress ML events with Zstd
-                let json = serde_json::to_vec(ml_event)?;
-                let compressed = zstd::encode_all(&json[..], 3)?;
-                event.payload = crate::stream::types::EventPayload::Ml(serde_json::from_slice(&compressed)?);
-            }
             _ => {
                 // Use Zstd for other event types
                 let json = serde_json::to_vec(&event.payload)?;
@@ -47,12 +53,6 @@ impl AdaptiveCompressor {
         Ok(())
     }
 
-    fn delta_encode_sensor(sensor_event: &crate::sensors::types::SensorEvent) -> Result<crate::sensors::types::SensorEvent, Box<dyn std::error::Error>> {
-        // In production, implement delta encoding against previous values
-        // For now, return as is
-        Ok(sensor_event.clone())
-    }
-
     fn compress_to_h264( &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
         // In production, use ffmpeg or v4l2 m2m
         // For now, return as is
@@ -0,0 +1,136 @@
+use crate::stream::types::Batch;
+use sled::transaction::{ConflictableTransactionResult, TransactionError};
+use sled::{Db, Transactional, Tree};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::{debug, info};
+
+const DELIVERED_TREE: &str = "dedup_delivered";
+const ORDER_TREE: &str = "dedup_order";
+
+/// Remembers the ids of the last `capacity` events the server acknowledged,
+/// in a sled database next to the WAL, so events replayed from the WAL after
+/// a crash are not sent a second time.
+///
+/// Two trees: `dedup_delivered` maps event id to insertion order, and
+/// `dedup_order` maps order back to event id so the oldest can be evicted.
+pub struct EventDeduplicator {
+    db: Db,
+    delivered: Tree,
+    order: Tree,
+    len: AtomicUsize,
+    capacity: usize,
+}
+
+impl EventDeduplicator {
+    pub fn open(path: &str, capacity: usize) -> Result<Self, sled::Error> {
+        let db = sled::open(path)?;
+        let delivered = db.open_tree(DELIVERED_TREE)?;
+        let order = db.open_tree(ORDER_TREE)?;
+        let len = order.len();
+        info!(remembered = len, capacity, "🧾 Event dedup window loaded");
+
+        Ok(Self {
+            db,
+            delivered,
+            order,
+            len: AtomicUsize::new(len),
+            capacity: capacity.max(1),
+        })
+    }
+
+    pub fn is_delivered(&self, event_id: &str) -> bool {
+        self.delivered.contains_key(event_id).unwrap_or(false)
+    }
+
+    /// Records acknowledged events, evicting the oldest beyond capacity.
+    ///
+    /// Each id is added to and evicted from both trees in one transaction, so
+    /// a crash never leaves an id remembered without its place in the order.
+    pub fn mark_delivered<'a>(&self, event_ids: impl IntoIterator<Item = &'a str>) -> Result<(), sled::Error> {
+        for event_id in event_ids {
+            if self.delivered.contains_key(event_id)? {
+                continue;
+            }
+            let seq = self.db.generate_id()?.to_be_bytes();
+            let inserted = (&self.delivered, &self.order)
+                .transaction(|(delivered, order)| -> ConflictableTransactionResult<bool, sled::Error> {
+                    if delivered.get(event_id)?.is_some() {
+                        return Ok(false);
+                    }
+                    delivered.insert(event_id, &seq[..])?;
+                    order.insert(&seq[..], event_id)?;
+                    Ok(true)
+                })
+                .map_err(storage_error)?;
+            if inserted {
+                self.len.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        while self.len.load(Ordering::Relaxed) > self.capacity {
+            let Some((seq, event_id)) = self.order.first()? else {
+                break;
+            };
+            let evicted = (&self.delivered, &self.order)
+                .transaction(|(delivered, order)| -> ConflictableTransactionResult<bool, sled::Error> {
+                    if order.remove(seq.clone())?.is_none() {
+                        return Ok(false);
+                    }
+                    delivered.remove(event_id.clone())?;
+                    Ok(true)
+                })
+                .map_err(storage_error)?;
+            if evicted {
+                self.len.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes already-delivered events from `batch` and returns their ids.
+    pub fn filter_batch(&self, batch: &mut Batch) -> Vec<String> {
+        let mut duplicates = Vec::new();
+        batch.events.retain(|event| {
+            if self.is_delivered(&event.event_id) {
+                duplicates.push(event.event_id.clone());
+                false
+            } else {
+                true
+            }
+        });
+        if !duplicates.is_empty() {
+            debug!(batch_id=%batch.batch_id, count = duplicates.len(), "Skipped events already delivered");
+            metrics::counter!("stream_duplicates_skipped_total").increment(duplicates.len() as u64);
+        }
+        duplicates
+    }
+}
+
+fn storage_error(e: TransactionError<sled::Error>) -> sled::Error {
+    match e {
+        TransactionError::Abort(e) | TransactionError::Storage(e) => e,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_survives_reopen_and_evicts_oldest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+
+        {
+            let dedup = EventDeduplicator::open(path, 3).unwrap();
+            dedup.mark_delivered(["evt-1", "evt-2", "evt-3"]).unwrap();
+            dedup.mark_delivered(["evt-2", "evt-4"]).unwrap();
+        }
+
+        let dedup = EventDeduplicator::open(path, 3).unwrap();
+        assert!(!dedup.is_delivered("evt-1"));
+        for id in ["evt-2", "evt-3", "evt-4"] {
+            assert!(dedup.is_delivered(id), "{} should be remembered", id);
+        }
+    }
+}
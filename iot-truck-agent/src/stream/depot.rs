@@ -0,0 +1,273 @@
+use crate::budget::DataBudget;
+use crate::config::{Config, DepotConfig};
+use crate::stream::batcher::Lane;
+use crate::stream::http::{self, BlobUploader, HttpStreamer};
+use crate::stream::types::{
+    Batch, CompressionType, EventMetadata, EventPayload, EventPriority, EventType, QoSLevel, StreamEvent,
+};
+use crate::wal::types::{EntryPayload, WalEntry};
+use std::net::{IpAddr, SocketAddrV4, SocketAddrV6};
+use std::sync::Arc;
+use tokio::net::TcpSocket;
+use tokio::sync::watch;
+use tokio::time::{interval, timeout, Duration};
+use tracing::{debug, info, warn};
+
+/// Watches for the depot Wi-Fi link.
+///
+/// Testable without Wi-Fi: point `interface` at a dummy or veth interface
+/// and `probe_endpoint` at something listening behind it.
+pub struct DepotMonitor {
+    config: DepotConfig,
+}
+
+impl DepotMonitor {
+    pub fn new(config: &DepotConfig) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+
+    /// Probes every `probe_interval_sec`; the receiver holds true while the
+    /// truck is at the depot. Traffic sent there is not metered against the
+    /// cellular plan.
+    pub fn spawn(self, budget: DataBudget) -> watch::Receiver<bool> {
+        let (tx, rx) = watch::channel(false);
+        if !self.config.enable {
+            return rx;
+        }
+
+        tokio::spawn(async move {
+            let mut tick = interval(Duration::from_secs(self.config.probe_interval_sec.max(1)));
+            loop {
+                tick.tick().await;
+                let at_depot = self.probe().await;
+                if at_depot == *tx.borrow() {
+                    continue;
+                }
+                if at_depot {
+                    info!(interface=%self.config.interface, "🏠 Depot link up — draining backlog at full speed");
+                } else {
+                    info!(interface=%self.config.interface, "📶 Depot link lost — bulk backlog waits for the next visit");
+                }
+                budget.set_metered(!at_depot);
+                metrics::gauge!("stream_depot_link").set(if at_depot { 1.0 } else { 0.0 });
+                if tx.send(at_depot).is_err() {
+                    return;
+                }
+            }
+        });
+        rx
+    }
+
+    /// Whether the depot interface is up, on the right network, and the
+    /// probe endpoint answers through it.
+    pub async fn probe(&self) -> bool {
+        interface_up(&self.config.interface) && self.ssid_matches().await && self.endpoint_reachable().await
+    }
+
+    async fn ssid_matches(&self) -> bool {
+        if self.config.ssid.is_empty() {
+            return true;
+        }
+        let output = tokio::process::Command::new("iwgetid")
+            .arg(&self.config.interface)
+            .arg("--raw")
+            .output()
+            .await;
+        match output {
+            Ok(output) => String::from_utf8_lossy(&output.stdout).trim() == self.config.ssid,
+            Err(e) => {
+                debug!(error=%e, "Cannot read SSID");
+                false
+            }
+        }
+    }
+
+    async fn endpoint_reachable(&self) -> bool {
+        let probe_timeout = Duration::from_millis(self.config.probe_timeout_ms);
+        let addr = match timeout(probe_timeout, tokio::net::lookup_host(&self.config.probe_endpoint)).await {
+            Ok(Ok(mut addrs)) => match addrs.next() {
+                Some(addr) => addr,
+                None => return false,
+            },
+            _ => return false,
+        };
+
+        let socket = match if addr.is_ipv4() { TcpSocket::new_v4() } else { TcpSocket::new_v6() } {
+            Ok(socket) => socket,
+            Err(_) => return false,
+        };
+        // Only a connect through the depot interface counts. Binding needs
+        // CAP_NET_RAW; without it the depot link is never trusted
+        if let Err(e) = socket.bind_device(Some(self.config.interface.as_bytes())) {
+            warn!(interface=%self.config.interface, error=%e, "Cannot bind depot probe to its interface — not treating it as the depot link");
+            return false;
+        }
+        matches!(timeout(probe_timeout, socket.connect(addr)).await, Ok(Ok(_)))
+    }
+}
+
+/// HTTP clients the backlog drain sends through, bound to the depot
+/// interface's address so depot-only traffic never leaves over cellular.
+pub struct DrainClients {
+    interface: String,
+    base_url: String,
+    device_id: String,
+    encoding: truck_protocol::Encoding,
+    chunk_kb: usize,
+    timeout_sec: u64,
+    budget: DataBudget,
+}
+
+/// Clients for one depot visit.
+pub struct DrainLink {
+    pub batches: HttpStreamer,
+    pub blobs: Arc<BlobUploader>,
+}
+
+impl DrainClients {
+    pub fn new(config: &Config, budget: DataBudget) -> Self {
+        Self {
+            interface: config.depot.interface.clone(),
+            base_url: config.http.base_url.clone(),
+            device_id: config.device_id.clone(),
+            encoding: config.mqtt.http_encoding,
+            chunk_kb: config.http.upload_chunk_kb,
+            timeout_sec: config.http.timeout_sec,
+            budget,
+        }
+    }
+
+    /// Clients bound to the interface's current address; an error if it has none.
+    pub fn bind(&self) -> Result<DrainLink, String> {
+        let address = interface_address(&self.interface)
+            .ok_or_else(|| format!("{} has no address to bind to", self.interface))?;
+        Ok(DrainLink {
+            batches: HttpStreamer::new(
+                &http::batch_url(&self.base_url, &self.device_id),
+                &self.device_id,
+                self.encoding,
+                self.timeout_sec,
+                Some(address),
+                self.budget.clone(),
+            ),
+            blobs: Arc::new(BlobUploader::new(
+                &self.base_url,
+                &self.device_id,
+                self.chunk_kb,
+                self.timeout_sec,
+                Some(address),
+                self.budget.clone(),
+            )),
+        })
+    }
+}
+
+/// Address of `interface`, IPv4 preferred.
+fn interface_address(interface: &str) -> Option<IpAddr> {
+    let mut v6 = None;
+    for ifaddr in nix::ifaddrs::getifaddrs().ok()? {
+        if ifaddr.interface_name != interface {
+            continue;
+        }
+        let Some(address) = ifaddr.address else {
+            continue;
+        };
+        if let Some(sin) = address.as_sockaddr_in() {
+            return Some(IpAddr::V4(*SocketAddrV4::from(*sin).ip()));
+        }
+        if let Some(sin6) = address.as_sockaddr_in6() {
+            v6 = v6.or(Some(IpAddr::V6(*SocketAddrV6::from(*sin6).ip())));
+        }
+    }
+    v6
+}
+
+/// Whether `batch` is backlog from the depot drain rather than live traffic.
+pub fn is_backlog(batch: &Batch) -> bool {
+    Lane::of_batch(batch) == Lane::Bulk && batch.events.iter().all(is_backlog_event)
+}
+
+pub fn is_backlog_event(event: &StreamEvent) -> bool {
+    event.metadata.source_module == "wal"
+}
+
+/// Dummy and loopback interfaces report `unknown` rather than `up`.
+fn interface_up(interface: &str) -> bool {
+    match std::fs::read_to_string(format!("/sys/class/net/{}/operstate", interface)) {
+        Ok(state) => matches!(state.trim(), "up" | "unknown"),
+        Err(_) => false,
+    }
+}
+
+/// Stream event for an unacked WAL entry, or `None` for entries that never
+/// leave the truck.
+pub fn backlog_event(event_id: String, entry: WalEntry) -> Option<StreamEvent> {
+    let (event_type, payload) = match entry.payload {
+        EntryPayload::Sensor(e) => (EventType::Sensor, EventPayload::Sensor(e)),
+        EntryPayload::CameraMeta(m) => (EventType::CameraMeta, EventPayload::CameraMeta(m)),
+        EntryPayload::CameraBlob { blob_id, data, .. } => (
+            EventType::CameraBlob,
+            EventPayload::CameraBlob {
+                blob_id,
+                data,
+                compression_type: CompressionType::None,
+            },
+        ),
+        EntryPayload::Ml(e) => (EventType::Ml, EventPayload::Ml(e)),
+        EntryPayload::Health(e) => (EventType::Health, EventPayload::Health(e)),
+        // Local bookkeeping, compactor summaries, and entries we hold no key for
+        _ => return None,
+    };
+
+    Some(StreamEvent {
+        event_id,
+        event_type,
+        payload,
+        timestamp: entry.timestamp,
+        // Backlog never competes with live traffic
+        priority: EventPriority::Low,
+        metadata: EventMetadata {
+            device_id: entry.metadata.device_id,
+            truck_id: entry.metadata.truck_id,
+            sequence_number: entry.metadata.sequence_number,
+            retry_count: 0,
+            source_module: "wal".to_string(),
+            requires_ack: true,
+            qos: QoSLevel::AtLeastOnce,
+            encryption: None,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_probe_needs_interface_and_endpoint() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = DepotConfig {
+            enable: true,
+            interface: "lo".to_string(),
+            probe_endpoint: listener.local_addr().unwrap().to_string(),
+            ..DepotConfig::default()
+        };
+        // Without CAP_NET_RAW the probe cannot bind to the interface and must refuse
+        let can_bind = TcpSocket::new_v4().unwrap().bind_device(Some(b"lo")).is_ok();
+        assert_eq!(DepotMonitor::new(&config).probe().await, can_bind);
+
+        let missing = DepotConfig {
+            interface: "depot-missing0".to_string(),
+            ..config
+        };
+        assert!(!DepotMonitor::new(&missing).probe().await);
+    }
+
+    #[test]
+    fn test_drain_binds_to_interface_address() {
+        assert_eq!(interface_address("lo"), Some(IpAddr::from([127, 0, 0, 1])));
+        assert_eq!(interface_address("depot-missing0"), None);
+    }
+}
@@ -5,18 +5,33 @@ pub enum StreamError {
     #[error("MQTT error: {0}")]
     MqttError(#[from] rumqttc::ClientError),
 
+    #[error("MQTT 5 error: {0}")]
+    Mqtt5Error(#[from] rumqttc::v5::ClientError),
+
+    #[error("MQTT connection failed: {0}")]
+    ConnectFailed(String),
+
     #[error("HTTP error: {0}")]
     HttpError(#[from] reqwest::Error),
 
     #[error("Serialization error: {0}")]
     SerializeError(#[from] serde_json::Error),
 
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Protocol error: {0}")]
+    ProtocolError(#[from] truck_protocol::ProtocolError),
+
     #[error("Network timeout")]
     Timeout,
 
     #[error("Authentication failed")]
     AuthFailed,
 
+    #[error("Certificate error: {0}")]
+    CertificateError(String),
+
     #[error("Server rejected batch: {0}")]
     ServerRejected(String),
 
@@ -0,0 +1,122 @@
+use crate::budget::{types::DataCategory, DataBudget};
+use crate::stream::error::{Result, StreamError};
+use crate::stream::types::CompressionType;
+use reqwest::{Client, StatusCode};
+use std::net::IpAddr;
+use tracing::{debug, info, warn};
+use truck_protocol::upload::{self, UploadStatus};
+
+/// Resumable chunked upload of camera blobs, which are too large for MQTT.
+///
+/// Every attempt starts by asking the server how much it already holds, so an
+/// upload cut off by a dropped link or an agent restart continues where it
+/// stopped. Uploads are keyed by event id; repeating a finished one is a no-op.
+pub struct BlobUploader {
+    client: Client,
+    base_url: String,
+    device_id: String,
+    chunk_size: usize,
+    budget: DataBudget,
+}
+
+impl BlobUploader {
+    /// `local_address` binds every connection to that source address.
+    pub fn new(
+        base_url: &str,
+        device_id: &str,
+        chunk_kb: usize,
+        timeout_sec: u64,
+        local_address: Option<IpAddr>,
+        budget: DataBudget,
+    ) -> Self {
+        let client = Client::builder()
+            .timeout(std::time::Duration::from_secs(timeout_sec))
+            .local_address(local_address)
+            .build()
+            .unwrap();
+
+        Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            device_id: device_id.to_string(),
+            chunk_size: chunk_kb.max(1) * 1024,
+            budget,
+        }
+    }
+
+    pub async fn upload(&self, event_id: &str, data: &[u8], compression: &CompressionType) -> Result<UploadStatus> {
+        let url = format!("{}{}", self.base_url, upload::blob_path(&self.device_id, event_id));
+        let total = data.len() as u64;
+
+        let mut offset = match self.status(&url).await? {
+            Some(status) if status.is_complete() => return Ok(status),
+            Some(status) if status.total_size == total => status.offset,
+            _ => 0,
+        };
+        if offset > 0 {
+            info!(event_id, offset, total, "⏯️ Resuming blob upload");
+        }
+
+        loop {
+            let end = (offset as usize + self.chunk_size).min(data.len());
+            let response = self
+                .client
+                .put(&url)
+                .header(upload::HEADER_UPLOAD_OFFSET, offset)
+                .header(upload::HEADER_UPLOAD_LENGTH, total)
+                .header(upload::HEADER_BLOB_FORMAT, blob_format(compression))
+                .body(data[offset as usize..end].to_vec())
+                .send()
+                .await?;
+            self.budget.record(DataCategory::Camera, end - offset as usize);
+
+            let http_status = response.status();
+            if http_status != StatusCode::OK && http_status != StatusCode::CONFLICT {
+                let body = response.text().await.unwrap_or_default();
+                return Err(StreamError::ServerRejected(format!("{}: {}", http_status, body)));
+            }
+
+            let status: UploadStatus = serde_json::from_slice(&response.bytes().await?)?;
+            if status.is_complete() {
+                info!(event_id, bytes = total, key = ?status.stored_key, "📦 Blob uploaded");
+                metrics::counter!("blob_uploads_total").increment(1);
+                metrics::counter!("blob_upload_bytes_total").increment(total);
+                return Ok(status);
+            }
+            if http_status == StatusCode::CONFLICT {
+                // Server holds a different amount than we assumed; continue from its offset
+                warn!(event_id, sent = offset, server = status.offset, "Blob upload offset out of sync — resyncing");
+                metrics::counter!("blob_upload_resyncs_total").increment(1);
+            }
+            if status.offset > total || (http_status == StatusCode::OK && status.offset <= offset) {
+                return Err(StreamError::ServerRejected(format!(
+                    "upload of {} stalled at offset {}",
+                    event_id, status.offset
+                )));
+            }
+
+            debug!(event_id, offset = status.offset, total, "Blob chunk accepted");
+            offset = status.offset;
+        }
+    }
+
+    async fn status(&self, url: &str) -> Result<Option<UploadStatus>> {
+        let response = self.client.get(url).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(StreamError::ServerRejected(response.status().to_string()));
+        }
+        Ok(Some(serde_json::from_slice(&response.bytes().await?)?))
+    }
+}
+
+fn blob_format(compression: &CompressionType) -> &'static str {
+    match compression {
+        CompressionType::None => "jpeg",
+        CompressionType::H264 => "h264",
+        CompressionType::Zstd => "zst",
+        CompressionType::Delta => "bin",
+    }
+}
@@ -0,0 +1,81 @@
+use crate::budget::{types::DataCategory, DataBudget};
+use crate::stream::error::{Result, StreamError};
+use crate::stream::types::Batch;
+use reqwest::Client;
+use std::net::IpAddr;
+use tracing::info;
+use truck_protocol::{BatchAck, Encoding};
+
+pub mod fallback;
+
+pub use fallback::BlobUploader;
+
+/// HTTP fallback used while MQTT is down. The server answers each POST with
+/// the same [`BatchAck`] it would otherwise publish on the ack topic.
+pub struct HttpStreamer {
+    client: Client,
+    url: String,
+    device_id: String,
+    encoding: Encoding,
+    budget: DataBudget,
+}
+
+/// Batch endpoint for `device_id` under the server's `base_url`.
+pub fn batch_url(base_url: &str, device_id: &str) -> String {
+    format!("{}/ingest/batch/{}", base_url.trim_end_matches('/'), device_id)
+}
+
+impl HttpStreamer {
+    /// `local_address` binds every connection to that source address.
+    pub fn new(
+        url: &str,
+        device_id: &str,
+        encoding: Encoding,
+        timeout_sec: u64,
+        local_address: Option<IpAddr>,
+        budget: DataBudget,
+    ) -> Self {
+        let client = Client::builder()
+            .timeout(std::time::Duration::from_secs(timeout_sec))
+            .local_address(local_address)
+            .build()
+            .unwrap();
+
+        Self {
+            client,
+            url: url.to_string(),
+            device_id: device_id.to_string(),
+            encoding,
+            budget,
+        }
+    }
+
+    pub async fn send_batch(&self, batch: &Batch) -> Result<BatchAck> {
+        let payload = crate::stream::wire::to_envelope(batch, &self.device_id).encode_as(self.encoding)?;
+        let sent_bytes = payload.len();
+
+        let response = self
+            .client
+            .post(&self.url)
+            .header("Content-Type", self.encoding.content_type())
+            .header("Accept", self.encoding.content_type())
+            .body(payload)
+            .send()
+            .await?;
+        self.budget.record(DataCategory::Telemetry, sent_bytes);
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(StreamError::ServerRejected(format!("{}: {}", status, body)));
+        }
+
+        let ack = BatchAck::decode(&response.bytes().await?)?;
+        info!(batch_id=%ack.batch_id, status=?ack.status, "📨 HTTP batch acknowledged");
+
+        metrics::counter!("http_batches_sent_total").increment(1);
+        metrics::counter!("http_events_sent_total").increment(batch.events.len() as u64);
+
+        Ok(ack)
+    }
+}
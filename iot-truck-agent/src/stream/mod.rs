@@ -1,25 +1,39 @@
-use crate::config::Config;
+use crate::budget::DataBudget;
+use crate::config::{Config, DepotConfig};
+use crate::stream::ack::AckTracker;
+use crate::stream::auth::DeviceIdentity;
 use crate::stream::backpressure;
-use crate::stream::batcher::IntelligentBatcher;
+use crate::stream::batcher::{IntelligentBatcher, Lane, LaneQueues};
 use crate::stream::compressor::AdaptiveCompressor;
-use crate::stream::http::HttpStreamer;
+use crate::stream::deduplicator::EventDeduplicator;
+use crate::stream::depot::{DepotMonitor, DrainClients};
+use crate::stream::http::{BlobUploader, HttpStreamer};
 use crate::stream::mqtt::MqttStreamer;
-use crate::stream::types::{Ack, Batch, StreamEvent};
+use crate::stream::sampling::SamplingPolicy;
+use crate::stream::types::{Batch, EventPayload, StreamEvent};
+use crate::ota::types::{CommandResponse, RemoteCommand};
 use crate::wal::WalManager;
-use tokio::sync::mpsc;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{mpsc, watch, Mutex};
 use tokio::time::{Duration, sleep};
 use tracing::{error, info, warn};
+use truck_protocol::BatchAck;
 
+pub mod ack;
 pub mod auth;
 pub mod backpressure;
 pub mod batcher;
 pub mod compressor;
 pub mod deduplicator;
+pub mod depot;
 pub mod error;
 pub mod http;
 pub mod monitor;
 pub mod mqtt;
+pub mod sampling;
 pub mod types;
+pub mod wire;
 
 // Metrics
 metrics::describe_counter!("batches_created_total", "Total batches created");
@@ -34,23 +48,82 @@ metrics::describe_counter!("stream_retries_total", "Total retries");
 metrics::describe_counter!("stream_errors_total", "Total stream errors");
 metrics::describe_gauge!("network_latency_ms", "Network latency in ms");
 metrics::describe_gauge!("network_packet_loss_percent", "Network packet loss percent");
+metrics::describe_gauge!("stream_batches_in_flight", "Batches sent but not yet acked by the server");
+metrics::describe_counter!("stream_events_acked_total", "Events acked by the server");
+metrics::describe_counter!("stream_retransmits_total", "Batches resent after a partial ack or ack timeout");
+metrics::describe_counter!("stream_events_dropped_total", "Events the server rejected permanently");
+metrics::describe_histogram!("stream_lane_latency_ms", "Time from event capture to publish, per lane");
+metrics::describe_gauge!("stream_lane_queue_depth", "Batches waiting to be sent, per lane");
+metrics::describe_counter!("stream_bulk_spilled_total", "Bulk batches spilled to the WAL while the link was too slow");
+metrics::describe_counter!("stream_events_shed_for_budget_total", "Bulk events held back in the WAL to save cellular data");
+metrics::describe_counter!("blob_uploads_total", "Camera blobs uploaded over HTTP");
+metrics::describe_counter!("blob_upload_bytes_total", "Camera blob bytes uploaded over HTTP");
+metrics::describe_counter!("blob_upload_resyncs_total", "Blob uploads resumed from a server-reported offset");
+metrics::describe_counter!("blob_upload_failures_total", "Blob uploads given up on and returned to the WAL");
+metrics::describe_gauge!("mqtt_protocol_version", "MQTT protocol in use (4 = 3.1.1, 5 = 5.0)");
+metrics::describe_gauge!("mqtt_cert_expiry_days", "Days until the device certificate expires");
+metrics::describe_counter!("mqtt_cert_rotations_total", "Device certificates rotated");
+metrics::describe_counter!("mqtt_cert_rotation_rollbacks_total", "Rotated certificates rolled back after the broker refused them");
+metrics::describe_counter!("stream_duplicates_skipped_total", "Replayed events skipped because the server already acked them");
+metrics::describe_counter!("stream_events_downsampled_total", "Bulk sensor readings held back in the WAL by link-quality sampling");
+metrics::describe_gauge!("stream_sampling_grade", "Link grade used for sensor sampling (0 = good, 1 = fair, 2 = poor)");
+metrics::describe_gauge!("stream_depot_link", "1 while the depot Wi-Fi link is up");
+metrics::describe_counter!("stream_backlog_events_total", "Unacked WAL entries queued for sending at the depot");
+metrics::describe_counter!("stream_clips_deferred_total", "Bulk camera clips left in the WAL until the depot link is up");
+
+// Camera blobs waiting for the uploader; beyond this they go back to the WAL
+const BLOB_QUEUE_DEPTH: usize = 32;
+const BLOB_UPLOAD_RETRIES: u32 = 5;
+// Backlog batches queued ahead at the depot; each ack makes room for the next
+const BACKLOG_PIPELINE: usize = 4;
+// WAL reads per refill, so a stretch of acked entries cannot stall the loop
+const BACKLOG_READS_PER_REFILL: usize = 8;
+
+/// Position of the depot backlog drain in the WAL.
+struct BacklogCursor {
+    next_seq: u64,
+    /// Last entry written before the depot link came up; later entries are live traffic.
+    end_seq: u64,
+}
 
 pub struct StreamManager {
     batch_rx: mpsc::Receiver<Batch>,
+    ack_rx: mpsc::Receiver<BatchAck>,
+    command_response_rx: mpsc::Receiver<CommandResponse>,
+    ack_tracker: Mutex<AckTracker>,
+    ack_timeout: Duration,
     wal_manager: WalManager,
     device_id: String,
     mqtt_streamer: MqttStreamer,
     http_streamer: HttpStreamer,
-    network_monitor: crate::stream::monitor::NetworkMonitor,
+    blob_tx: mpsc::Sender<StreamEvent>,
+    deduplicator: Arc<EventDeduplicator>,
+    bulk_min_bandwidth_kbps: f32,
+    max_bulk_backlog: usize,
+    budget: DataBudget,
+    sampling: SamplingPolicy,
+    depot: DepotConfig,
+    at_depot: watch::Receiver<bool>,
+    backlog: Mutex<Option<BacklogCursor>>,
+    drain_clients: DrainClients,
+    /// Backlog batches go out through this, bound to the depot interface, or not at all.
+    drain_batches: Mutex<Option<HttpStreamer>>,
+    /// The same for backlog camera clips, read by the blob uploader.
+    drain_blobs: watch::Sender<Option<Arc<BlobUploader>>>,
 }
 
 impl StreamManager {
     pub async fn new(
         config: &Config,
         wal_manager: WalManager,
+        budget: DataBudget,
+        identity: DeviceIdentity,
+        command_tx: mpsc::Sender<RemoteCommand>,
+        command_response_rx: mpsc::Receiver<CommandResponse>,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         let (event_tx, event_rx) = mpsc::channel(1000);
         let (batch_tx, batch_rx) = mpsc::channel(100);
+        let (ack_tx, ack_rx) = mpsc::channel(100);
 
         // Initialize WAL for backpressure
         backpressure::init_wal_manager(wal_manager.clone()).await;
@@ -60,6 +133,8 @@ impl StreamManager {
             256 * 1024, // 256KB max batch
             100,        // 100 events max
             1000,       // 1s timeout
+            config.stream.high_batch_events,
+            config.stream.high_flush_ms,
         );
         tokio::spawn(async move {
             if let Err(e) = batcher.start_batcher(event_rx, batch_tx).await {
@@ -68,123 +143,385 @@ impl StreamManager {
         });
 
         // Start MQTT client
-        let mut mqtt_streamer = MqttStreamer::new(
-            &config.mqtt.broker_url,
-            &config.mqtt.client_id,
+        let mqtt_streamer = MqttStreamer::new(
+            &config.mqtt,
             &config.device_id,
+            budget.clone(),
+            identity,
+            ack_tx,
+            command_tx,
         )
         .await?;
 
         // Start HTTP fallback
         let http_streamer = HttpStreamer::new(
-            &format!(
-                "https://api.yourcompany.com/v1/telemetry/{}",
-                config.device_id
-            ),
-            30,
+            &crate::stream::http::batch_url(&config.http.base_url, &config.device_id),
+            &config.device_id,
+            config.mqtt.http_encoding,
+            config.http.timeout_sec,
+            None,
+            budget.clone(),
+        );
+
+        // Remembers acked events across restarts so WAL replay does not resend them
+        let deduplicator = Arc::new(EventDeduplicator::open(
+            &format!("{}/dedup", config.storage.wal_path),
+            config.stream.dedup_window,
+        )?);
+
+        // Camera blobs never go on MQTT; a separate worker uploads them in chunks
+        let (blob_tx, blob_rx) = mpsc::channel(BLOB_QUEUE_DEPTH);
+        let blob_uploader = BlobUploader::new(
+            &config.http.base_url,
+            &config.device_id,
+            config.http.upload_chunk_kb,
+            config.http.timeout_sec,
+            None,
+            budget.clone(),
         );
+        let (drain_blobs, drain_blobs_rx) = watch::channel(None);
+        tokio::spawn(run_blob_uploads(blob_uploader, drain_blobs_rx, blob_rx, deduplicator.clone()));
 
-        // Start network monitor
-        let network_monitor =
-            crate::stream::monitor::NetworkMonitor::new(mqtt_streamer.get_connection_quality());
+        // Yard Wi-Fi gets the WAL backlog and deferred camera clips
+        let at_depot = DepotMonitor::new(&config.depot).spawn(budget.clone());
+        if config.stream.sampling.enable && !config.depot.enable {
+            warn!("Link-quality sampling is on without the depot drain — thinned readings stay in the WAL but are never backfilled");
+        }
 
         info!("✅ Stream manager initialized with intelligent batching and compression");
 
         Ok(Self {
             batch_rx,
+            ack_rx,
+            command_response_rx,
+            ack_tracker: Mutex::new(AckTracker::new(config.mqtt.max_ack_retries)),
+            ack_timeout: Duration::from_secs(config.mqtt.ack_timeout_sec),
             wal_manager,
             device_id: config.device_id.clone(),
             mqtt_streamer,
             http_streamer,
-            network_monitor,
+            blob_tx,
+            deduplicator,
+            bulk_min_bandwidth_kbps: config.stream.bulk_min_bandwidth_kbps,
+            max_bulk_backlog: config.stream.max_bulk_backlog,
+            budget,
+            sampling: SamplingPolicy::new(&config.stream.sampling),
+            depot: config.depot.clone(),
+            at_depot,
+            backlog: Mutex::new(None),
+            drain_clients: DrainClients::new(config, budget.clone()),
+            drain_batches: Mutex::new(None),
+            drain_blobs,
         })
     }
 
     pub async fn start_streaming_loop(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let mut pending_batches = std::collections::VecDeque::new();
+        let mut lanes = LaneQueues::default();
+        let mut at_depot = self.at_depot.clone();
 
         loop {
             tokio::select! {
                 Some(batch) = self.batch_rx.recv() => {
-                    pending_batches.push_back(batch);
+                    lanes.push_back(batch);
+                }
+                Ok(()) = at_depot.changed() => {
+                    let arrived = *at_depot.borrow_and_update();
+                    self.start_backlog(arrived).await;
+                    if !arrived {
+                        // Still unacked in the WAL; the next depot visit sends them
+                        lanes.retain_bulk(|batch| !depot::is_backlog(batch));
+                    }
+                }
+                Some(ack) = self.ack_rx.recv() => {
+                    self.apply_ack(&ack, &mut lanes).await;
+                }
+                Some(response) = self.command_response_rx.recv() => {
+                    if let Err(e) = self.mqtt_streamer.send_command_response(&response).await {
+                        warn!(command_id=%response.command_id, error=%e, "Failed to publish command response");
+                    }
                 }
                 _ = sleep(Duration::from_secs(1)) => {
-                    if !pending_batches.is_empty() {
-                        let batch = pending_batches.pop_front().unwrap();
-                        let result = self.send_with_retry_and_compress(batch).await;
-
-                        match result {
-                            Ok(ack) => {
-                                info!(batch_id=%ack.batch_id, "✅ Batch sent successfully");
-                                // Process ACK
-                                self.mqtt_streamer.process_ack(&ack.batch_id).await?;
-                            }
-                            Err(e) => {
-                                error!(error=%e, "❌ Failed to send batch — requeuing");
-                                pending_batches.push_front(batch); // retry later
-                                metrics::counter!("stream_errors_total").increment(1);
-                            }
-                        }
+                    // Nothing heard back from the server in time: put it on the wire again
+                    for batch in self.ack_tracker.lock().await.expired(self.ack_timeout) {
+                        warn!(batch_id=%batch.batch_id, "⏰ No server ack — resending batch");
+                        metrics::counter!("stream_retransmits_total").increment(1);
+                        self.mqtt_streamer.link_monitor().batch_timed_out();
+                        lanes.push_back(batch);
+                    }
+                }
+            }
+
+            self.refill_backlog(&mut lanes).await;
+            self.drain_lanes(&mut lanes).await;
+        }
+    }
+
+    /// Arriving at the depot starts a pass over the whole WAL; leaving it
+    /// abandons the pass. Entries acked meanwhile are skipped on the next one.
+    /// Nothing is drained unless the drain can be bound to the depot interface.
+    async fn start_backlog(&self, at_depot: bool) {
+        let mut drain = None;
+        if at_depot {
+            match (self.wal_manager.last_sequence(), self.drain_clients.bind()) {
+                (Ok(end_seq), Ok(link)) => drain = Some((BacklogCursor { next_seq: 0, end_seq }, link)),
+                (Err(e), _) => error!(error=%e, "Cannot read WAL position — backlog not drained"),
+                (_, Err(e)) => error!(error=%e, "Cannot bind the drain to the depot interface — backlog not drained"),
+            }
+        }
+
+        let (cursor, link) = drain.unzip();
+        let (batches, blobs) = link.map(|link| (link.batches, link.blobs)).unzip();
+        *self.backlog.lock().await = cursor;
+        *self.drain_batches.lock().await = batches;
+        self.drain_blobs.send_replace(blobs);
+    }
+
+    /// Keeps a few backlog batches queued in the bulk lane while at the depot.
+    async fn refill_backlog(&self, lanes: &mut LaneQueues) {
+        let mut backlog = self.backlog.lock().await;
+        let mut reads = 0;
+        // Camera clips in the backlog go to the uploader; leave it room for them
+        while lanes.len(Lane::Bulk) < BACKLOG_PIPELINE
+            && self.blob_tx.capacity() >= BLOB_QUEUE_DEPTH / 2
+            && reads < BACKLOG_READS_PER_REFILL
+        {
+            reads += 1;
+            let Some(cursor) = backlog.as_mut() else {
+                return;
+            };
+            if cursor.next_seq > cursor.end_seq {
+                info!("✅ Depot backlog drained");
+                *backlog = None;
+                return;
+            }
+
+            let (entries, next_seq) = match self
+                .wal_manager
+                .backlog(cursor.next_seq, cursor.end_seq, self.depot.drain_batch_events)
+                .await
+            {
+                Ok(read) => read,
+                Err(e) => {
+                    error!(error=%e, seq=cursor.next_seq, "Failed to read WAL backlog");
+                    return;
+                }
+            };
+            cursor.next_seq = next_seq;
+
+            let events: Vec<StreamEvent> = entries
+                .into_iter()
+                .filter_map(|(event_id, entry)| depot::backlog_event(event_id, entry))
+                .collect();
+            if events.is_empty() {
+                continue;
+            }
+            metrics::counter!("stream_backlog_events_total").increment(events.len() as u64);
+            lanes.push_back(IntelligentBatcher::build_batch(events, Lane::Bulk));
+        }
+    }
+
+    /// Sends queued batches strictly by lane until the queues are empty or a
+    /// send fails. Bulk only goes out while the link is fast enough; the
+    /// oldest held-back bulk batches are spilled to the WAL.
+    async fn drain_lanes(&self, lanes: &mut LaneQueues) {
+        let bulk_allowed = *self.at_depot.borrow()
+            || self.mqtt_streamer.get_connection_quality().await.bandwidth_kbps >= self.bulk_min_bandwidth_kbps;
+
+        for batch in lanes.trim_bulk(self.max_bulk_backlog) {
+            warn!(batch_id=%batch.batch_id, events=batch.events.len(), "🐢 Bulk backlog full on slow link — spilling batch to WAL");
+            metrics::counter!("stream_bulk_spilled_total").increment(1);
+            for event in batch.events {
+                if let Err(e) = backpressure::buffer_to_wal(event).await {
+                    error!(error=%e, "Failed to buffer event to WAL");
+                }
+            }
+        }
+
+        while let Some(batch) = lanes.pop_next(bulk_allowed, Instant::now()) {
+            let lane = Lane::of_batch(&batch);
+            match self.send_batch(batch.clone()).await {
+                Ok(ack) => {
+                    lanes.send_succeeded(lane);
+                    record_lane_latency(lane, &batch);
+                    match ack {
+                        // HTTP fallback answers with the ack inline
+                        Some(ack) => self.apply_ack(&ack, lanes).await,
+                        None => tracing::debug!(batch_id=%batch.batch_id, lane=lane.as_str(), "📨 Batch published — awaiting server ack"),
                     }
                 }
+                Err(e) => {
+                    // Other lanes carry on; this one is retried once its backoff ends
+                    let delay = lanes.send_failed(lane, Instant::now());
+                    error!(error=%e, lane=lane.as_str(), "❌ Failed to send batch — retrying in {:?}", delay);
+                    lanes.push_front(batch);
+                    metrics::counter!("stream_errors_total").increment(1);
+                    metrics::counter!("stream_retries_total").increment(1);
+                }
+            }
+
+            // Pick up anything batched meanwhile so a new critical event goes next
+            while let Ok(batch) = self.batch_rx.try_recv() {
+                lanes.push_back(batch);
             }
         }
+
+        for lane in Lane::ALL {
+            metrics::gauge!("stream_lane_queue_depth", "lane" => lane.as_str()).set(lanes.len(lane) as f64);
+        }
+    }
+
+    /// Applies a server ack: acked events are released from the WAL, retryable
+    /// rejections are queued again, permanent rejections are logged and dropped.
+    async fn apply_ack(&self, ack: &BatchAck, lanes: &mut LaneQueues) {
+        if ack.device_id != self.device_id {
+            warn!(device_id=%ack.device_id, batch_id=%ack.batch_id, "Ignoring ack addressed to another device");
+            return;
+        }
+
+        let resolution = self.ack_tracker.lock().await.resolve(ack);
+        if let Some((bytes, round_trip)) = resolution.round_trip {
+            self.mqtt_streamer.link_monitor().batch_acked(bytes, round_trip);
+        }
+
+        if let Err(e) = self.deduplicator.mark_delivered(resolution.acked.iter().map(String::as_str)) {
+            error!(error=%e, "Failed to record delivered events");
+        }
+        for event_id in &resolution.acked {
+            backpressure::notify_wal_ack(event_id).await;
+        }
+        metrics::counter!("stream_events_acked_total").increment(resolution.acked.len() as u64);
+
+        for rejected in &resolution.dropped {
+            error!(event_id=%rejected.event_id, reason=%rejected.reason, "❌ Event rejected by server — dropping");
+        }
+        metrics::counter!("stream_events_dropped_total").increment(resolution.dropped.len() as u64);
+
+        if let Some(batch) = resolution.retransmit {
+            metrics::counter!("stream_retransmits_total").increment(1);
+            lanes.push_front(batch);
+        }
     }
 
-    async fn send_with_retry_and_compress(
+    /// Sends a batch over MQTT, or HTTP if that fails, and registers it as
+    /// awaiting an ack. Returns the ack when the transport delivers it
+    /// synchronously (HTTP); MQTT acks arrive on `ack_rx`. Retrying is left to
+    /// the caller's lane backoff.
+    async fn send_batch(
         &self,
         mut batch: Batch,
-    ) -> Result<Ack, Box<dyn std::error::Error>> {
-        let network_quality = self.network_monitor.get_quality().await;
+    ) -> Result<Option<BatchAck>, Box<dyn std::error::Error>> {
+        // Replayed from the WAL after a crash, but the server already has them
+        for event_id in self.deduplicator.filter_batch(&mut batch) {
+            backpressure::notify_wal_ack(&event_id).await;
+        }
+        let backlog = depot::is_backlog(&batch);
+        let network_quality = self.mqtt_streamer.get_connection_quality().await;
+        let at_depot = *self.at_depot.borrow();
+        if !at_depot {
+            // Full-rate readings held back here stay unacked in the WAL for backfill
+            self.sampling.apply(&mut batch, &network_quality);
+            self.defer_camera_clips(&mut batch);
+        }
+        self.apply_data_budget(&mut batch, at_depot);
 
         // Compress events based on network quality
         for event in &mut batch.events {
             AdaptiveCompressor::compress_event(event, &network_quality)?;
         }
+        AdaptiveCompressor::choose_sensor_encoding(&mut batch);
 
-        let mut retry_count = 0;
-        let max_retries = 5;
+        self.divert_camera_blobs(&mut batch).await;
+        if batch.events.is_empty() {
+            return Ok(None);
+        }
 
-        loop {
-            // Try MQTT first
-            if self.mqtt_streamer.is_connected() {
-                match self.mqtt_streamer.send_batch(batch.clone()).await {
-                    Ok(ack) => {
-                        metrics::counter!("stream_retries_total").increment(retry_count as u64);
-                        return Ok(ack);
-                    }
-                    Err(e) => {
-                        warn!(error=%e, "MQTT send failed — trying HTTP fallback");
-                    }
-                }
-            }
+        // Backlog only goes out bound to the depot interface; after leaving
+        // the depot it stays unacked in the WAL for the next visit
+        if backlog {
+            let drain = self.drain_batches.lock().await;
+            let Some(drain) = drain.as_ref() else {
+                return Ok(None);
+            };
+            let ack = drain.send_batch(&batch).await?;
+            // Depot Wi-Fi says nothing about the cellular link
+            self.ack_tracker.lock().await.track_sent(batch, None);
+            return Ok(Some(ack));
+        }
 
-            // Fallback to HTTP
-            match self.http_streamer.send_batch(batch.clone()).await {
-                Ok(ack) => {
-                    metrics::counter!("stream_retries_total").increment(retry_count as u64);
-                    return Ok(ack);
+        // Try MQTT first
+        if self.mqtt_streamer.is_connected() {
+            match self.mqtt_streamer.send_batch(&batch).await {
+                Ok(()) => {
+                    self.ack_tracker.lock().await.track(batch);
+                    return Ok(None);
                 }
                 Err(e) => {
-                    error!(error=%e, "HTTP send failed");
+                    warn!(error=%e, "MQTT send failed — trying HTTP fallback");
                 }
             }
+        }
 
-            // Retry with backoff
-            if retry_count >= max_retries {
-                // Buffer to WAL
-                for event in batch.events {
-                    if let Err(e) = backpressure::buffer_to_wal(event).await {
-                        error!(error=%e, "Failed to buffer event to WAL");
-                    }
-                }
-                return Err("Max retries exceeded — buffered to WAL".into());
+        // Fallback to HTTP
+        let sent_at = tokio::time::Instant::now();
+        let ack = self.http_streamer.send_batch(&batch).await?;
+        self.ack_tracker.lock().await.track_sent(batch, Some(sent_at));
+        Ok(Some(ack))
+    }
+
+    /// Sheds bulk traffic the data plan can no longer afford and stamps
+    /// heartbeats with current usage. Shed events are never acked, so they stay
+    /// in the WAL, out of reach of the compactor, until the depot drain sends
+    /// them. Depot Wi-Fi is not on the plan, so nothing is shed there.
+    fn apply_data_budget(&self, batch: &mut Batch, at_depot: bool) {
+        let degradation = self.budget.degradation();
+
+        if !at_depot && Lane::of_batch(batch) == Lane::Bulk && degradation.is_degraded() {
+            let before = batch.events.len();
+            let every_nth = degradation.sensor_every_nth.max(1) as u64;
+            batch.events.retain(|e| match &e.payload {
+                EventPayload::CameraBlob { .. } => !degradation.drop_video,
+                EventPayload::Sensor(_) => e.metadata.sequence_number % every_nth == 0,
+                _ => true,
+            });
+            let shed = before - batch.events.len();
+            metrics::counter!("stream_events_shed_for_budget_total").increment(shed as u64);
+        }
+
+        for event in &mut batch.events {
+            if let EventPayload::Heartbeat(heartbeat) = &mut event.payload {
+                heartbeat.data_usage = Some(self.budget.usage());
             }
+        }
+    }
+
+    /// Leaves bulk camera clips in the WAL while on cellular; the depot
+    /// backlog drain uploads them.
+    fn defer_camera_clips(&self, batch: &mut Batch) {
+        if !self.depot.enable || !self.depot.defer_camera_on_cellular || Lane::of_batch(batch) != Lane::Bulk {
+            return;
+        }
+        let before = batch.events.len();
+        batch.events.retain(|e| !matches!(e.payload, EventPayload::CameraBlob { .. }));
+        metrics::counter!("stream_clips_deferred_total").increment((before - batch.events.len()) as u64);
+    }
 
-            retry_count += 1;
-            let delay = Duration::from_secs(2u64.pow(retry_count));
-            warn!(retry_count, delay=?delay, "⏳ Retrying in {:?}", delay);
-            tokio::time::sleep(delay).await;
+    /// Moves camera blobs out of the batch and onto the upload queue. Each blob
+    /// is released from the WAL once its upload completes, not by a batch ack.
+    async fn divert_camera_blobs(&self, batch: &mut Batch) {
+        let (blobs, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut batch.events)
+            .into_iter()
+            .partition(|e| matches!(e.payload, EventPayload::CameraBlob { .. }));
+        batch.events = rest;
+
+        for blob in blobs {
+            if let Err(mpsc::error::TrySendError::Full(blob) | mpsc::error::TrySendError::Closed(blob)) =
+                self.blob_tx.try_send(blob)
+            {
+                warn!(event_id=%blob.event_id, "Blob upload queue full — buffering to WAL");
+                if let Err(e) = backpressure::buffer_to_wal(blob).await {
+                    error!(error=%e, "Failed to buffer blob to WAL");
+                }
+            }
         }
     }
 
@@ -196,3 +533,69 @@ impl StreamManager {
         Ok(())
     }
 }
+
+fn record_lane_latency(lane: Lane, batch: &Batch) {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64;
+    // Blobs are only queued for upload at this point, not sent
+    for event in batch.events.iter().filter(|e| !matches!(e.payload, EventPayload::CameraBlob { .. })) {
+        let latency_ms = now.saturating_sub(event.timestamp) as f64 / 1_000_000.0;
+        metrics::histogram!("stream_lane_latency_ms", "lane" => lane.as_str()).record(latency_ms);
+    }
+}
+
+async fn run_blob_uploads(
+    uploader: BlobUploader,
+    drain: watch::Receiver<Option<Arc<BlobUploader>>>,
+    mut blob_rx: mpsc::Receiver<StreamEvent>,
+    deduplicator: Arc<EventDeduplicator>,
+) {
+    while let Some(event) = blob_rx.recv().await {
+        let EventPayload::CameraBlob { data, compression_type, .. } = &event.payload else {
+            continue;
+        };
+
+        // Backlog clips only go out through the depot interface; without it they wait in the WAL
+        let backlog = depot::is_backlog_event(&event);
+        let drain_uploader = drain.borrow().clone();
+        let uploader = match (backlog, drain_uploader.as_deref()) {
+            (false, _) => &uploader,
+            (true, Some(drain_uploader)) => drain_uploader,
+            (true, None) => continue,
+        };
+
+        let mut retry_count = 0;
+        loop {
+            match uploader.upload(&event.event_id, data, compression_type).await {
+                Ok(_) => {
+                    if let Err(e) = deduplicator.mark_delivered([event.event_id.as_str()]) {
+                        error!(error=%e, "Failed to record delivered blob");
+                    }
+                    backpressure::notify_wal_ack(&event.event_id).await;
+                    break;
+                }
+                Err(e) if retry_count < BLOB_UPLOAD_RETRIES => {
+                    retry_count += 1;
+                    // The next attempt asks the server for its offset and resumes from there
+                    let delay = Duration::from_secs(2u64.pow(retry_count));
+                    warn!(event_id=%event.event_id, error=%e, retry_count, "⏳ Blob upload failed — retrying in {:?}", delay);
+                    sleep(delay).await;
+                }
+                Err(e) if backlog => {
+                    warn!(event_id=%event.event_id, error=%e, "Backlog blob upload failed — left in the WAL");
+                    break;
+                }
+                Err(e) => {
+                    error!(event_id=%event.event_id, error=%e, "❌ Blob upload failed — buffering to WAL");
+                    metrics::counter!("blob_upload_failures_total").increment(1);
+                    if let Err(e) = backpressure::buffer_to_wal(event).await {
+                        error!(error=%e, "Failed to buffer blob to WAL");
+                    }
+                    break;
+                }
+            }
+        }
+    }
+}
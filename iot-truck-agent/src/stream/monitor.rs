@@ -0,0 +1,170 @@
+use crate::stream::types::NetworkQuality;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use tokio::time::{Duration, Instant};
+
+/// Outcomes older than this no longer count. A link that has gone quiet is
+/// given the benefit of the doubt again, so held-back bulk gets to probe it.
+const WINDOW: Duration = Duration::from_secs(300);
+/// Round trips of smaller batches are mostly latency, not transfer.
+const MIN_THROUGHPUT_BATCH_BYTES: usize = 8 * 1024;
+/// Reported until a batch large enough to measure has been acked in the window.
+const UNMEASURED_BANDWIDTH_KBPS: f32 = 1000.0;
+
+/// Link quality as the stream experiences it: latency from the MQTT
+/// keep-alive round trip, loss and throughput from how batches fare against
+/// the ack timeout.
+#[derive(Default)]
+pub struct NetworkMonitor {
+    link: Mutex<Link>,
+}
+
+#[derive(Default)]
+struct Link {
+    ping_sent: Option<Instant>,
+    latency_ms: f32,
+    outcomes: VecDeque<Outcome>,
+}
+
+enum Outcome {
+    Acked { at: Instant, bytes: usize, round_trip: Duration },
+    TimedOut { at: Instant },
+}
+
+impl Outcome {
+    fn at(&self) -> Instant {
+        match self {
+            Outcome::Acked { at, .. } | Outcome::TimedOut { at } => *at,
+        }
+    }
+}
+
+impl Link {
+    fn prune(&mut self, now: Instant) {
+        while self.outcomes.front().is_some_and(|o| now.duration_since(o.at()) > WINDOW) {
+            self.outcomes.pop_front();
+        }
+    }
+}
+
+impl NetworkMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The event loop has put a PINGREQ on the wire.
+    pub fn ping_sent(&self) {
+        self.link.lock().ping_sent = Some(Instant::now());
+    }
+
+    /// A PINGRESP arrived; one without a ping outstanding is ignored.
+    pub fn pong_received(&self) {
+        let mut link = self.link.lock();
+        if let Some(sent) = link.ping_sent.take() {
+            link.latency_ms = sent.elapsed().as_secs_f32() * 1000.0;
+        }
+    }
+
+    /// The connection dropped; a ping still outstanding will never be answered.
+    pub fn disconnected(&self) {
+        self.link.lock().ping_sent = None;
+    }
+
+    /// The server acked a batch of `bytes` on the wire `round_trip` after it was sent.
+    pub fn batch_acked(&self, bytes: usize, round_trip: Duration) {
+        self.push(Outcome::Acked { at: Instant::now(), bytes, round_trip });
+    }
+
+    /// A batch went unacked past the ack timeout.
+    pub fn batch_timed_out(&self) {
+        self.push(Outcome::TimedOut { at: Instant::now() });
+    }
+
+    fn push(&self, outcome: Outcome) {
+        let mut link = self.link.lock();
+        link.prune(outcome.at());
+        link.outcomes.push_back(outcome);
+    }
+
+    /// Loss is the share of batches that timed out; throughput is bytes over
+    /// round-trip time of the larger acked batches, so it errs low.
+    pub fn quality(&self) -> NetworkQuality {
+        let mut link = self.link.lock();
+        link.prune(Instant::now());
+
+        let (mut acked, mut lost, mut bytes, mut busy) = (0u32, 0u32, 0usize, Duration::ZERO);
+        for outcome in &link.outcomes {
+            match *outcome {
+                Outcome::Acked { bytes: b, round_trip, .. } => {
+                    acked += 1;
+                    if b >= MIN_THROUGHPUT_BATCH_BYTES {
+                        bytes += b;
+                        busy += round_trip;
+                    }
+                }
+                Outcome::TimedOut { .. } => lost += 1,
+            }
+        }
+
+        let packet_loss_percent = match acked + lost {
+            0 => 0.0,
+            total => lost as f32 * 100.0 / total as f32,
+        };
+        let bandwidth_kbps = if busy.is_zero() {
+            UNMEASURED_BANDWIDTH_KBPS
+        } else {
+            bytes as f32 * 8.0 / 1000.0 / busy.as_secs_f32()
+        };
+
+        NetworkQuality {
+            latency_ms: link.latency_ms,
+            packet_loss_percent,
+            bandwidth_kbps,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SamplingConfig;
+    use crate::stream::sampling::{LinkGrade, SamplingPolicy};
+
+    #[tokio::test(start_paused = true)]
+    async fn test_measured_link_drives_sampling_grade() {
+        let monitor = NetworkMonitor::new();
+        let policy = SamplingPolicy::new(&SamplingConfig::default());
+        assert_eq!(policy.grade(&monitor.quality()), LinkGrade::Good);
+
+        // The keep-alive round trip is latency, not the wall-clock time of the ping
+        monitor.ping_sent();
+        tokio::time::advance(Duration::from_millis(700)).await;
+        monitor.pong_received();
+        let quality = monitor.quality();
+        assert_eq!(quality.latency_ms.round(), 700.0);
+        assert_eq!(policy.grade(&quality), LinkGrade::Fair);
+
+        monitor.ping_sent();
+        tokio::time::advance(Duration::from_millis(100)).await;
+        monitor.pong_received();
+
+        // 64 KiB acked in 2 s is about 262 kbps; small batches do not count
+        monitor.batch_acked(64 * 1024, Duration::from_secs(2));
+        monitor.batch_acked(200, Duration::from_secs(2));
+        let quality = monitor.quality();
+        assert!((quality.bandwidth_kbps - 262.1).abs() < 0.1);
+        assert_eq!(policy.grade(&quality), LinkGrade::Fair);
+
+        // One batch in three lost to the ack timeout
+        monitor.batch_timed_out();
+        let quality = monitor.quality();
+        assert!((quality.packet_loss_percent - 33.3).abs() < 0.1);
+        assert_eq!(policy.grade(&quality), LinkGrade::Poor);
+
+        // Once the outcomes age out only the latency is left to go on
+        tokio::time::advance(WINDOW + Duration::from_secs(1)).await;
+        let quality = monitor.quality();
+        assert_eq!((quality.packet_loss_percent, quality.bandwidth_kbps), (0.0, UNMEASURED_BANDWIDTH_KBPS));
+        assert_eq!(policy.grade(&quality), LinkGrade::Good);
+    }
+}
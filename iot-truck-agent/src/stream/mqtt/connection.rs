@@ -1,55 +1,154 @@
-use rumqttc::{Event, EventLoop};
+use crate::ota::types::RemoteCommand;
+use crate::stream::auth::{DeviceIdentity, ReloadRequest};
+use crate::stream::monitor::NetworkMonitor;
+use crate::stream::mqtt::v5::{ReplyRoute, V5Client};
+use rumqttc::v5::mqttbytes::v5::Packet as V5Packet;
+use rumqttc::{Event, EventLoop, Outgoing, Packet};
+use std::collections::HashMap;
+use tokio::sync::mpsc;
 use tokio::time::{sleep, Duration};
 use tracing::{error, info, warn};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use truck_protocol::BatchAck;
+
+// A command not answered by then has hung or was lost in a restart; its route is dropped
+const REPLY_ROUTE_TTL: Duration = Duration::from_secs(6 * 3600);
+
+/// Reply routes of commands still being executed, by command id.
+pub type ReplyRoutes = Arc<parking_lot::Mutex<HashMap<String, ReplyRoute>>>;
 
 pub struct ConnectionMonitor {
-    eventloop: Arc<EventLoop>,
     is_connected: Arc<AtomicBool>,
-    latency_ms: tokio::sync::RwLock<f32>,
-    packet_loss: tokio::sync::RwLock<f32>,
+    ack_topic: String,
+    command_topic: String,
+    ack_tx: mpsc::Sender<BatchAck>,
+    command_tx: mpsc::Sender<RemoteCommand>,
+    reply_routes: ReplyRoutes,
+    identity: DeviceIdentity,
+    reload_rx: tokio::sync::Mutex<Option<mpsc::Receiver<ReloadRequest>>>,
+    link: NetworkMonitor,
 }
 
 impl ConnectionMonitor {
-    pub fn new(eventloop: Arc<EventLoop>, is_connected: Arc<AtomicBool>) -> Self {
+    pub fn new(
+        is_connected: Arc<AtomicBool>,
+        ack_topic: String,
+        command_topic: String,
+        ack_tx: mpsc::Sender<BatchAck>,
+        command_tx: mpsc::Sender<RemoteCommand>,
+        reply_routes: ReplyRoutes,
+        identity: DeviceIdentity,
+    ) -> Self {
+        let reload_rx = identity.take_reload_requests();
         Self {
-            eventloop,
             is_connected,
-            latency_ms: tokio::sync::RwLock::new(0.0),
-            packet_loss: tokio::sync::RwLock::new(0.0),
+            ack_topic,
+            command_topic,
+            ack_tx,
+            command_tx,
+            reply_routes,
+            identity,
+            reload_rx: tokio::sync::Mutex::new(reload_rx),
+            link: NetworkMonitor::new(),
         }
     }
 
-    pub async fn start(&self) {
-        let mut ping_interval = tokio::time::interval(Duration::from_secs(30));
-        let mut event_loop = self.eventloop.clone();
+    pub fn ack_topic(&self) -> &str {
+        &self.ack_topic
+    }
+
+    pub fn command_topic(&self) -> &str {
+        &self.command_topic
+    }
+
+    /// Where the stream reports batch outcomes for loss and throughput.
+    pub fn link(&self) -> &NetworkMonitor {
+        &self.link
+    }
+
+    /// Drives an MQTT 3.1.1 connection.
+    pub async fn start(&self, mut event_loop: EventLoop) {
+        let mut reload_rx = self.reload_rx.lock().await;
+        // Certificate rotation waiting to hear whether the broker took the new identity
+        let mut pending_reload: Option<ReloadRequest> = None;
 
         loop {
             tokio::select! {
-                _ = ping_interval.tick() => {
-                    self.send_ping().await;
+                Some(done) = next_reload(&mut reload_rx) => {
+                    if let Some(transport) = self.reload_transport(done, &mut pending_reload) {
+                        event_loop.mqtt_options.set_transport(transport);
+                        // Dropping the connection makes the next poll reconnect with them
+                        event_loop.network = None;
+                    }
                 }
                 result = event_loop.poll() => {
                     match result {
                         Ok(Event::Incoming(Packet::ConnAck(_))) => {
-                            self.is_connected.store(true, Ordering::Relaxed);
-                            info!("✅ MQTT connected");
-                            metrics::gauge!("mqtt_connected").set(1.0);
+                            self.on_connected(&mut pending_reload);
                         }
                         Ok(Event::Incoming(Packet::Disconnect)) => {
-                            self.is_connected.store(false, Ordering::Relaxed);
-                            warn!("MQTT disconnected");
-                            metrics::gauge!("mqtt_connected").set(0.0);
+                            self.on_disconnected();
+                        }
+                        Ok(Event::Incoming(Packet::Publish(publish))) => {
+                            self.on_publish(&publish.topic, &publish.payload, None).await;
+                        }
+                        // The keep-alive round trip is the latency measurement
+                        Ok(Event::Outgoing(Outgoing::PingReq)) => {
+                            self.link.ping_sent();
                         }
                         Ok(Event::Incoming(Packet::PingResp)) => {
-                            let latency = chrono::Utc::now().timestamp_millis() as f32 - self.last_ping_time().await;
-                            self.set_latency(latency).await;
+                            self.link.pong_received();
+                        }
+                        Err(e) => {
+                            error!(error=%e, "MQTT connection error");
+                            self.on_disconnected();
+                            tokio::time::sleep(Duration::from_secs(5)).await;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Drives an MQTT 5 connection; `client` gets each CONNACK to reset topic aliases.
+    pub async fn start_v5(&self, mut event_loop: rumqttc::v5::EventLoop, client: V5Client) {
+        let mut reload_rx = self.reload_rx.lock().await;
+        let mut pending_reload: Option<ReloadRequest> = None;
+
+        loop {
+            tokio::select! {
+                Some(done) = next_reload(&mut reload_rx) => {
+                    if let Some(transport) = self.reload_transport(done, &mut pending_reload) {
+                        event_loop.mqtt_options.set_transport(transport);
+                        event_loop.network = None;
+                    }
+                }
+                result = event_loop.poll() => {
+                    match result {
+                        Ok(rumqttc::v5::Event::Incoming(V5Packet::ConnAck(connack))) => {
+                            client.on_connack(connack.properties.and_then(|p| p.topic_alias_max));
+                            self.on_connected(&mut pending_reload);
+                        }
+                        Ok(rumqttc::v5::Event::Incoming(V5Packet::Disconnect(disconnect))) => {
+                            warn!(reason=?disconnect.reason_code, "MQTT 5 broker disconnected us");
+                            self.on_disconnected();
+                        }
+                        Ok(rumqttc::v5::Event::Incoming(V5Packet::Publish(publish))) => {
+                            let topic = String::from_utf8_lossy(&publish.topic);
+                            let reply = ReplyRoute::from_properties(publish.properties.as_ref());
+                            self.on_publish(&topic, &publish.payload, reply).await;
+                        }
+                        Ok(rumqttc::v5::Event::Outgoing(Outgoing::PingReq)) => {
+                            self.link.ping_sent();
+                        }
+                        Ok(rumqttc::v5::Event::Incoming(V5Packet::PingResp(_))) => {
+                            self.link.pong_received();
                         }
                         Err(e) => {
                             error!(error=%e, "MQTT connection error");
-                            self.is_connected.store(false, Ordering::Relaxed);
-                            metrics::gauge!("mqtt_connected").set(0.0);
+                            self.on_disconnected();
                             tokio::time::sleep(Duration::from_secs(5)).await;
                         }
                         _ => {}
@@ -59,31 +158,89 @@ impl ConnectionMonitor {
         }
     }
 
-    async fn send_ping(&self) {
-        // In production, send actual ping and measure response time
-        let _ = self.eventloop.poll();
-        self.set_last_ping_time(chrono::Utc::now().timestamp_millis() as f32).await;
+    fn on_connected(&self, pending_reload: &mut Option<ReloadRequest>) {
+        self.is_connected.store(true, Ordering::Relaxed);
+        info!("✅ MQTT connected");
+        metrics::gauge!("mqtt_connected").set(1.0);
+        if let Some(done) = pending_reload.take() {
+            let _ = done.send(true);
+        }
     }
 
-    async fn set_latency(&self, latency: f32) {
-        let mut l = self.latency_ms.write().await;
-        *l = latency;
+    fn on_disconnected(&self) {
+        self.link.disconnected();
+        if self.is_connected.swap(false, Ordering::Relaxed) {
+            warn!("MQTT disconnected");
+        }
+        metrics::gauge!("mqtt_connected").set(0.0);
     }
 
-    async fn last_ping_time(&self) -> f32 {
-        *self.latency_ms.read().await
+    /// Loads the rotated credentials; the caller swaps them into its event loop.
+    fn reload_transport(&self, done: ReloadRequest, pending_reload: &mut Option<ReloadRequest>) -> Option<rumqttc::Transport> {
+        match self.identity.transport() {
+            Ok(transport) => {
+                info!("🔐 Reconnecting MQTT with new device credentials");
+                self.on_disconnected();
+                *pending_reload = Some(done);
+                Some(transport)
+            }
+            Err(e) => {
+                error!(error=%e, "Cannot load new device credentials");
+                let _ = done.send(false);
+                None
+            }
+        }
     }
 
-    async fn set_last_ping_time(&self, time: f32) {
-        let mut l = self.latency_ms.write().await;
-        *l = time;
+    async fn on_publish(&self, topic: &str, payload: &[u8], reply: Option<ReplyRoute>) {
+        if topic == self.ack_topic {
+            self.forward_ack(payload).await;
+        } else if topic == self.command_topic {
+            self.forward_command(payload, reply).await;
+        }
     }
 
-    pub async fn get_quality(&self) -> crate::stream::types::NetworkQuality {
-        crate::stream::types::NetworkQuality {
-            latency_ms: *self.latency_ms.read().await,
-            packet_loss_percent: *self.packet_loss.read().await,
-            bandwidth_kbps: 1000.0, // Placeholder
+    async fn forward_ack(&self, payload: &[u8]) {
+        match BatchAck::decode(payload) {
+            Ok(ack) => {
+                if self.ack_tx.send(ack).await.is_err() {
+                    warn!("Ack receiver dropped — server ack discarded");
+                }
+            }
+            Err(e) => {
+                error!(error=%e, "Malformed server ack");
+                metrics::counter!("stream_errors_total").increment(1);
+            }
+        }
+    }
+
+    async fn forward_command(&self, payload: &[u8], reply: Option<ReplyRoute>) {
+        let command: RemoteCommand = match serde_json::from_slice(payload) {
+            Ok(command) => command,
+            Err(e) => {
+                error!(error=%e, "Malformed remote command");
+                return;
+            }
+        };
+        // MQTT 5 commands say where the response goes; keep it for when it is ready
+        if let Some(reply) = reply {
+            let mut routes = self.reply_routes.lock();
+            routes.retain(|_, route| route.received_at.elapsed() < REPLY_ROUTE_TTL);
+            routes.insert(command.command_id.clone(), reply);
+        }
+        if self.command_tx.send(command).await.is_err() {
+            warn!("Command receiver dropped — remote command discarded");
         }
     }
-}
\ No newline at end of file
+
+    pub async fn get_quality(&self) -> crate::stream::types::NetworkQuality {
+        self.link.quality()
+    }
+}
+
+async fn next_reload(rx: &mut Option<mpsc::Receiver<ReloadRequest>>) -> Option<ReloadRequest> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
@@ -1,115 +1,179 @@
-use crate::stream::types::{Batch, Ack, StreamEvent, QoSLevel};
-use crate::stream::error::Result;
-use rumqttc::{AsyncClient, MqttOptions, QoS, Event, EventLoop, Packet};
+use crate::stream::types::{Batch, QoSLevel};
+use crate::stream::error::{Result, StreamError};
+use crate::budget::{types::DataCategory, DataBudget};
+use crate::config::MqttConfig;
+use crate::ota::types::{CommandResponse, RemoteCommand};
+use crate::stream::auth::DeviceIdentity;
+use crate::stream::batcher::Lane;
+use crate::stream::mqtt::connection::{ConnectionMonitor, ReplyRoutes};
+use crate::stream::mqtt::v5::V5Client;
+use rumqttc::v5::mqttbytes::v5::PublishProperties;
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, QoS};
 use tokio::sync::mpsc;
 use tokio::time::{sleep, Duration};
-use tracing::{error, info, warn};
-use std::collections::HashMap;
+use tracing::{debug, error, info, warn};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use truck_protocol::{mqtt5, BatchAck, Encoding, MqttVersion, TopicLayout};
+
+pub mod connection;
+pub mod v5;
+
+// How long to wait for a CONNACK before trying the next protocol version
+const CONNACK_TIMEOUT: Duration = Duration::from_secs(15);
+// Longest wait between connection attempts while no broker answers
+const MAX_CONNECT_BACKOFF: Duration = Duration::from_secs(60);
+// Broker keeps our session (ack and command subscriptions) this long while offline
+const SESSION_EXPIRY_SEC: u32 = 7 * 24 * 3600;
+
+/// Client for whichever MQTT version the broker accepted.
+#[derive(Clone)]
+enum Link {
+    V311(AsyncClient),
+    V5(V5Client),
+}
 
 pub struct MqttStreamer {
-    client: AsyncClient,
-    eventloop: Arc<EventLoop>,
+    /// Set once a broker has answered; batches go over HTTP until then.
+    link: Arc<OnceLock<Link>>,
     is_connected: Arc<AtomicBool>,
     device_id: String,
-    pending_acks: Arc<tokio::sync::RwLock<HashMap<String, StreamEvent>>>,
-    connection_monitor: crate::stream::mqtt::connection::ConnectionMonitor,
+    topics: TopicLayout,
+    encoding: Encoding,
+    max_payload_bytes: usize,
+    bulk_expiry_sec: u32,
+    reply_routes: ReplyRoutes,
+    budget: DataBudget,
+    connection_monitor: Arc<ConnectionMonitor>,
 }
 
 impl MqttStreamer {
-    pub async fn new(broker_url: &str, client_id: &str, device_id: &str) -> Result<Self> {
-        let mut mqtt_options = MqttOptions::parse_url(broker_url)?;
-        mqtt_options.set_client_id(client_id);
-        mqtt_options.set_keep_alive(Duration::from_secs(30));
-        mqtt_options.set_max_packet_size(256 * 1024); // 256KB
-
-        let (client, eventloop) = AsyncClient::new(mqtt_options, 10);
-        let eventloop = Arc::new(eventloop);
+    /// Starts connecting with the configured MQTT version and returns at
+    /// once; a broker that is down or slow never holds up startup. In `auto`
+    /// mode MQTT 5 is tried first and 3.1.1 next, and the first CONNACK
+    /// decides the version. Until one arrives `send_batch` reports no transport.
+    pub async fn new(
+        config: &MqttConfig,
+        device_id: &str,
+        budget: DataBudget,
+        identity: DeviceIdentity,
+        ack_tx: mpsc::Sender<BatchAck>,
+        command_tx: mpsc::Sender<RemoteCommand>,
+    ) -> Result<Self> {
+        let max_payload_bytes = config.max_payload_kb * 1024;
         let is_connected = Arc::new(AtomicBool::new(false));
-        let pending_acks = Arc::new(tokio::sync::RwLock::new(HashMap::new()));
+        let topics = TopicLayout::new(&config.topic_prefix);
+        let reply_routes = ReplyRoutes::default();
 
-        // Start connection monitor
-        let connection_monitor = crate::stream::mqtt::connection::ConnectionMonitor::new(
-            eventloop.clone(),
+        // Server acks for our batches come back on {prefix}/{device_id}/ack
+        let connection_monitor = Arc::new(ConnectionMonitor::new(
             is_connected.clone(),
-        );
+            topics.ack(device_id),
+            topics.command(device_id),
+            ack_tx,
+            command_tx,
+            reply_routes.clone(),
+            identity.clone(),
+        ));
+
+        let link = Arc::new(OnceLock::new());
+        let connector = Connector {
+            config: config.clone(),
+            max_payload_bytes,
+            identity,
+            monitor: connection_monitor.clone(),
+            is_connected: is_connected.clone(),
+        };
+        let link_cell = link.clone();
         tokio::spawn(async move {
-            connection_monitor.start().await;
+            connector.run(&link_cell).await;
         });
 
-        info!(broker=%broker_url, client_id, "🔌 MQTT 5.0 client initialized");
         Ok(Self {
-            client,
-            eventloop,
+            link,
             is_connected,
             device_id: device_id.to_string(),
-            pending_acks,
+            topics,
+            encoding: config.encoding,
+            max_payload_bytes,
+            bulk_expiry_sec: config.bulk_expiry_sec,
+            reply_routes,
+            budget,
             connection_monitor,
         })
     }
 
-    pub async fn send_batch(&mut self, batch: Batch) -> Result<Ack> {
-        if !self.is_connected.load(Ordering::Relaxed) {
-            return Err(crate::stream::error::StreamError::NoTransport);
-        }
-
-        let topic = format!("{}/telemetry", self.device_id);
-        let payload = serde_json::to_vec(&batch)?;
-
-        // Use QoS based on batch priority
-        let qos = match batch.priority {
-            crate::stream::types::EventPriority::Critical => QoS::ExactlyOnce,
-            crate::stream::types::EventPriority::High => QoS::ExactlyOnce,
-            _ => QoS::AtLeastOnce,
+    /// Publishes a batch. The broker accepting it is not an ack: the events stay
+    /// in the WAL until a [`BatchAck`] for this batch arrives on the ack topic.
+    pub async fn send_batch(&self, batch: &Batch) -> Result<()> {
+        let Some(link) = self.link.get().filter(|_| self.is_connected()) else {
+            return Err(StreamError::NoTransport);
         };
 
-        // Store events for ACK tracking
-        {
-            let mut pending = self.pending_acks.write().await;
-            for event in &batch.events {
-                pending.insert(event.event_id.clone(), event.clone());
-            }
+        // CBOR payloads are self-describing, so the server needs no topic or property hint
+        let payload = crate::stream::wire::to_envelope(batch, &self.device_id).encode_as(self.encoding)?;
+        if payload.len() > self.max_payload_bytes {
+            return Err(StreamError::BatchTooLarge(payload.len()));
         }
 
-        let publish = self.client.publish(&topic, qos, false, payload);
-        let timeout = tokio::time::timeout(Duration::from_secs(30), publish).await??;
+        // Critical events get their own topic so the server never reads them behind bulk
+        let lane = Lane::of_batch(batch);
+        let (topic, qos) = match lane {
+            Lane::Critical => (self.topics.critical(&self.device_id), QoS::ExactlyOnce),
+            Lane::Priority => (self.topics.telemetry(&self.device_id), QoS::ExactlyOnce),
+            Lane::Bulk => (self.topics.telemetry(&self.device_id), QoS::AtLeastOnce),
+        };
 
-        // For QoS 2, wait for PUBCOMP
-        if qos == QoS::ExactlyOnce {
-            tokio::time::sleep(Duration::from_millis(100)).await;
+        // Delivery is confirmed by the server's BatchAck, not by PUBACK/PUBCOMP
+        let sent_bytes = topic.len() + payload.len();
+        match link {
+            Link::V311(client) => {
+                let publish = client.publish(&topic, qos, false, payload);
+                tokio::time::timeout(Duration::from_secs(30), publish).await??;
+            }
+            Link::V5(client) => {
+                let properties = PublishProperties {
+                    // Stale bulk telemetry is dropped by the broker; the WAL still holds it
+                    message_expiry_interval: (lane == Lane::Bulk && self.bulk_expiry_sec > 0)
+                        .then_some(self.bulk_expiry_sec),
+                    content_type: Some(self.encoding.content_type().to_string()),
+                    user_properties: mqtt5::user_properties(),
+                    response_topic: Some(self.topics.ack(&self.device_id)),
+                    correlation_data: Some(batch.batch_id.clone().into()),
+                    ..Default::default()
+                };
+                let publish = client.publish(&topic, qos, payload, properties);
+                tokio::time::timeout(Duration::from_secs(30), publish).await??;
+            }
         }
-
-        let ack = Ack {
-            batch_id: batch.batch_id.clone(),
-            received_at: chrono::Utc::now().timestamp_nanos() as u64,
-            event_ids: batch.events.iter().map(|e| e.event_id.clone()).collect(),
-            status: crate::stream::types::AckStatus::Success,
-            server_sequence: 0, // Will be filled by server
-        };
+        self.budget.record(DataCategory::Telemetry, sent_bytes);
 
         metrics::counter!("mqtt_batches_sent_total").increment(1);
         metrics::counter!("mqtt_events_sent_total").increment(batch.events.len() as u64);
         metrics::gauge!("mqtt_batch_size_bytes").set(batch.size_bytes as f64);
 
-        Ok(ack)
+        Ok(())
     }
 
-    pub async fn process_ack(&self, batch_id: &str) -> Result<()> {
-        let mut pending = self.pending_acks.write().await;
-        let events: Vec<_> = pending
-            .iter()
-            .filter(|(_, e)| e.metadata.sequence_number.to_string().contains(batch_id))
-            .map(|(k, v)| (k.clone(), v.clone()))
-            .collect();
-
-        for (event_id, _) in events {
-            pending.remove(&event_id);
-            // Notify WAL to delete this event
-            crate::stream::backpressure::notify_wal_ack(&event_id).await;
-        }
+    /// Answers a remote command on the response topic it arrived with. Only
+    /// MQTT 5 commands carry one; others are answered through the server API.
+    pub async fn send_command_response(&self, response: &CommandResponse) -> Result<()> {
+        let Some(reply) = self.reply_routes.lock().remove(&response.command_id) else {
+            debug!(command_id=%response.command_id, "Command has no response topic — not publishing result");
+            return Ok(());
+        };
+        let Some(Link::V5(client)) = self.link.get() else {
+            return Ok(());
+        };
 
-        Ok(())
+        let properties = PublishProperties {
+            content_type: Some(Encoding::Json.content_type().to_string()),
+            user_properties: mqtt5::user_properties(),
+            correlation_data: reply.correlation_data,
+            ..Default::default()
+        };
+        let payload = serde_json::to_vec(response)?;
+        client.publish(&reply.topic, QoS::AtLeastOnce, payload, properties).await
     }
 
     pub fn is_connected(&self) -> bool {
@@ -119,4 +183,201 @@ impl MqttStreamer {
     pub async fn get_connection_quality(&self) -> crate::stream::types::NetworkQuality {
         self.connection_monitor.get_quality().await
     }
-}
\ No newline at end of file
+
+    pub fn link_monitor(&self) -> &crate::stream::monitor::NetworkMonitor {
+        self.connection_monitor.link()
+    }
+}
+
+/// Connects in the background for [`MqttStreamer::new`].
+struct Connector {
+    config: MqttConfig,
+    max_payload_bytes: usize,
+    identity: DeviceIdentity,
+    monitor: Arc<ConnectionMonitor>,
+    is_connected: Arc<AtomicBool>,
+}
+
+impl Connector {
+    /// Keeps trying until a broker answers, then hands the connection to the monitor.
+    async fn run(self, link: &OnceLock<Link>) {
+        let mut backoff = Duration::from_secs(1);
+        let connected = loop {
+            match self.connect().await {
+                Ok(connected) => break connected,
+                Err(e) => {
+                    warn!(error=%e, retry_in=?backoff, "MQTT broker not reachable — sending over HTTP meanwhile");
+                    sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_CONNECT_BACKOFF);
+                }
+            }
+        };
+
+        let version = match connected {
+            Link::V311(_) => "3.1.1",
+            Link::V5(_) => "5.0",
+        };
+        info!(broker=%self.config.broker_url, client_id=%self.config.client_id, version, encoding=?self.config.encoding, tls=self.identity.is_enabled(), "🔌 MQTT client initialized");
+        metrics::gauge!("mqtt_protocol_version").set(if matches!(connected, Link::V5(_)) { 5.0 } else { 4.0 });
+        metrics::gauge!("mqtt_connected").set(1.0);
+        let _ = link.set(connected);
+    }
+
+    /// One attempt per allowed version, newest first; the connection whose
+    /// CONNACK arrives is subscribed and left running under the monitor.
+    async fn connect(&self) -> Result<Link> {
+        let ack_topic = self.monitor.ack_topic();
+        let command_topic = self.monitor.command_topic();
+
+        if self.config.version != MqttVersion::V311 {
+            let options = v5_options(&self.config, self.max_payload_bytes, &self.identity)?;
+            match V5Client::connect(options, self.config.topic_alias_max, CONNACK_TIMEOUT).await {
+                Ok((client, eventloop)) => {
+                    self.is_connected.store(true, Ordering::Relaxed);
+                    client.subscribe(ack_topic, QoS::AtLeastOnce).await?;
+                    client.subscribe(command_topic, QoS::AtLeastOnce).await?;
+                    let monitor = self.monitor.clone();
+                    let monitor_client = client.clone();
+                    tokio::spawn(async move {
+                        monitor.start_v5(eventloop, monitor_client).await;
+                    });
+                    return Ok(Link::V5(client));
+                }
+                Err(e) if self.config.version == MqttVersion::Auto => {
+                    warn!(error=%e, "Broker did not accept MQTT 5 — trying 3.1.1");
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let options = v311_options(&self.config, self.max_payload_bytes, &self.identity)?;
+        let (client, eventloop) = connect_v311(options, CONNACK_TIMEOUT).await?;
+        self.is_connected.store(true, Ordering::Relaxed);
+        client.subscribe(ack_topic, QoS::AtLeastOnce).await?;
+        client.subscribe(command_topic, QoS::AtLeastOnce).await?;
+        let monitor = self.monitor.clone();
+        tokio::spawn(async move {
+            monitor.start(eventloop).await;
+        });
+        Ok(Link::V311(client))
+    }
+}
+
+/// Waits for the broker's CONNACK on a 3.1.1 connection.
+async fn connect_v311(options: MqttOptions, timeout: Duration) -> Result<(AsyncClient, EventLoop)> {
+    let (client, mut eventloop) = AsyncClient::new(options, 10);
+    tokio::time::timeout(timeout, async {
+        loop {
+            match eventloop.poll().await {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => return Ok(()),
+                Ok(_) => {}
+                Err(e) => return Err(StreamError::ConnectFailed(e.to_string())),
+            }
+        }
+    })
+    .await
+    .map_err(|_| StreamError::Timeout)??;
+    Ok((client, eventloop))
+}
+
+fn v311_options(config: &MqttConfig, max_payload_bytes: usize, identity: &DeviceIdentity) -> Result<MqttOptions> {
+    let mut options = MqttOptions::parse_url(&config.broker_url)?;
+    options.set_client_id(&config.client_id);
+    options.set_keep_alive(Duration::from_secs(30));
+    // Headroom over our own limit for topic and MQTT headers
+    options.set_max_packet_size(max_payload_bytes + 4 * 1024);
+    // Keep the ack subscription (and acks queued while offline) across reconnects
+    options.set_clean_session(false);
+    if identity.is_enabled() {
+        options.set_transport(identity.transport()?);
+    }
+    Ok(options)
+}
+
+fn v5_options(config: &MqttConfig, max_payload_bytes: usize, identity: &DeviceIdentity) -> Result<rumqttc::v5::MqttOptions> {
+    let mut options = rumqttc::v5::MqttOptions::parse_url(&config.broker_url)
+        .map_err(|e| StreamError::ConnectFailed(e.to_string()))?;
+    options.set_client_id(config.client_id.clone());
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_max_packet_size(Some((max_payload_bytes + 4 * 1024) as u32));
+    // MQTT 5 only keeps a session across reconnects if it is given an expiry
+    options.set_clean_start(false);
+    options.set_session_expiry_interval(Some(SESSION_EXPIRY_SEC));
+    if identity.is_enabled() {
+        options.set_transport(identity.transport()?);
+    }
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{BudgetConfig, TlsConfig};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::time::Instant;
+
+    /// A broker that only speaks 3.1.1: MQTT 5 connections are dropped
+    /// unanswered, 3.1.1 ones accepted and held open.
+    async fn v311_only_broker() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut connect = [0u8; 512];
+                    let n = socket.read(&mut connect).await.unwrap_or(0);
+                    if n < 2 {
+                        return;
+                    }
+                    // Fixed header and remaining length, "\0\x04MQTT", then the protocol level
+                    let length_bytes = connect[1..n].iter().position(|b| b & 0x80 == 0).unwrap() + 1;
+                    let level_at = 1 + length_bytes + 6;
+                    if n <= level_at || connect[level_at] != 4 {
+                        return;
+                    }
+                    socket.write_all(&[0x20, 0x02, 0x00, 0x00]).await.unwrap();
+                    let mut rest = [0u8; 512];
+                    while socket.read(&mut rest).await.is_ok_and(|n| n > 0) {}
+                });
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn test_auto_falls_back_to_311_in_background() {
+        let port = v311_only_broker().await;
+        let dir = tempfile::tempdir().unwrap();
+        let config: MqttConfig = toml::from_str(&format!(
+            r#"
+            broker_url = "mqtt://127.0.0.1:{port}?client_id=truck-7"
+            client_id = "truck-7"
+            topic_prefix = "truck"
+            qos = 1
+            keep_alive = 30
+            "#
+        ))
+        .unwrap();
+        let budget = DataBudget::load(&BudgetConfig {
+            state_path: dir.path().join("budget.json").to_string_lossy().to_string(),
+            ..BudgetConfig::default()
+        });
+        let identity = DeviceIdentity::new(&TlsConfig::default(), "truck-7");
+        let (ack_tx, _ack_rx) = mpsc::channel(1);
+        let (command_tx, _command_rx) = mpsc::channel(1);
+
+        let streamer = MqttStreamer::new(&config, "truck-7", budget, identity, ack_tx, command_tx)
+            .await
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while streamer.link.get().is_none() {
+            assert!(Instant::now() < deadline, "no MQTT connection after falling back");
+            sleep(Duration::from_millis(50)).await;
+        }
+        assert!(matches!(streamer.link.get(), Some(Link::V311(_))));
+        assert!(streamer.is_connected());
+    }
+}
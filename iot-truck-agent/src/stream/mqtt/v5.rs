@@ -0,0 +1,100 @@
+use crate::stream::error::{Result, StreamError};
+use bytes::Bytes;
+use parking_lot::Mutex;
+use rumqttc::v5::mqttbytes::v5::{Packet, PublishProperties};
+use rumqttc::v5::mqttbytes::QoS;
+use rumqttc::v5::{AsyncClient, Event, EventLoop, MqttOptions};
+use std::sync::Arc;
+use tokio::time::{Duration, Instant};
+use truck_protocol::mqtt5::TopicAliases;
+
+/// MQTT 5 client with per-connection topic aliases.
+#[derive(Clone)]
+pub struct V5Client {
+    client: AsyncClient,
+    aliases: Arc<Mutex<TopicAliases>>,
+}
+
+/// Where to send the reply to a message, from its response-topic and
+/// correlation-data properties.
+#[derive(Debug, Clone)]
+pub struct ReplyRoute {
+    pub topic: String,
+    pub correlation_data: Option<Bytes>,
+    pub received_at: Instant,
+}
+
+impl V5Client {
+    /// Connects and waits for the broker's CONNACK, so a broker that only
+    /// speaks 3.1.1 is detected here rather than by every later publish.
+    pub async fn connect(options: MqttOptions, topic_alias_max: u16, timeout: Duration) -> Result<(Self, EventLoop)> {
+        let (client, mut eventloop) = AsyncClient::new(options, 10);
+        let aliases = Arc::new(Mutex::new(TopicAliases::new(topic_alias_max)));
+
+        let connack = tokio::time::timeout(timeout, async {
+            loop {
+                match eventloop.poll().await {
+                    Ok(Event::Incoming(Packet::ConnAck(connack))) => return Ok(connack),
+                    Ok(_) => {}
+                    Err(e) => return Err(StreamError::ConnectFailed(e.to_string())),
+                }
+            }
+        })
+        .await
+        .map_err(|_| StreamError::Timeout)??;
+
+        aliases.lock().reset(connack.properties.and_then(|p| p.topic_alias_max));
+        Ok((Self { client, aliases }, eventloop))
+    }
+
+    /// Called on every CONNACK: aliases do not survive a reconnect.
+    pub fn on_connack(&self, broker_alias_max: Option<u16>) {
+        self.aliases.lock().reset(broker_alias_max);
+    }
+
+    pub async fn subscribe(&self, topic: &str, qos: rumqttc::QoS) -> Result<()> {
+        self.client.subscribe(topic, to_v5(qos)).await?;
+        Ok(())
+    }
+
+    /// Publishes with `properties`, replacing the topic by its alias once the
+    /// broker has seen it.
+    pub async fn publish(
+        &self,
+        topic: &str,
+        qos: rumqttc::QoS,
+        payload: Vec<u8>,
+        mut properties: PublishProperties,
+    ) -> Result<()> {
+        let topic = match self.aliases.lock().resolve(topic) {
+            Some((alias, send_topic)) => {
+                properties.topic_alias = Some(alias);
+                if send_topic { topic.to_string() } else { String::new() }
+            }
+            None => topic.to_string(),
+        };
+        self.client
+            .publish_with_properties(topic, to_v5(qos), false, payload, properties)
+            .await?;
+        Ok(())
+    }
+}
+
+impl ReplyRoute {
+    pub fn from_properties(properties: Option<&PublishProperties>) -> Option<Self> {
+        let properties = properties?;
+        Some(Self {
+            topic: properties.response_topic.clone()?,
+            correlation_data: properties.correlation_data.clone(),
+            received_at: Instant::now(),
+        })
+    }
+}
+
+fn to_v5(qos: rumqttc::QoS) -> QoS {
+    match qos {
+        rumqttc::QoS::AtMostOnce => QoS::AtMostOnce,
+        rumqttc::QoS::AtLeastOnce => QoS::AtLeastOnce,
+        rumqttc::QoS::ExactlyOnce => QoS::ExactlyOnce,
+    }
+}
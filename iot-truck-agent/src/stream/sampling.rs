@@ -0,0 +1,231 @@
+use crate::config::{SamplingConfig, SensorIntervals};
+use crate::sensors::types::{SensorType, SensorValues};
+use crate::stream::batcher::Lane;
+use crate::stream::types::{Batch, EventPayload, NetworkQuality};
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use tracing::info;
+
+/// Link quality as far as sensor sampling is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkGrade {
+    Good = 0,
+    Fair = 1,
+    Poor = 2,
+}
+
+/// Thins bulk sensor readings to per-sensor publish intervals chosen from
+/// the current link quality.
+///
+/// Readings held back are never acked, so they stay in the WAL at full rate:
+/// the compactor only summarises acked samples, and the depot drain uploads
+/// them as backlog. Readings that do go out are tagged with the interval
+/// through [`Batch::sample_intervals`].
+pub struct SamplingPolicy {
+    config: SamplingConfig,
+    grade: Mutex<LinkGrade>,
+    /// Last interval window sent per sensor id, so a window split across
+    /// batches is only sent once.
+    last_window: Mutex<HashMap<String, u64>>,
+}
+
+impl SamplingPolicy {
+    pub fn new(config: &SamplingConfig) -> Self {
+        Self {
+            config: config.clone(),
+            grade: Mutex::new(LinkGrade::Good),
+            last_window: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn grade(&self, quality: &NetworkQuality) -> LinkGrade {
+        let c = &self.config;
+        if quality.bandwidth_kbps < c.poor_bandwidth_kbps
+            || quality.latency_ms > c.poor_latency_ms
+            || quality.packet_loss_percent > c.poor_loss_percent
+        {
+            LinkGrade::Poor
+        } else if quality.bandwidth_kbps < c.fair_bandwidth_kbps
+            || quality.latency_ms > c.fair_latency_ms
+            || quality.packet_loss_percent > c.fair_loss_percent
+        {
+            LinkGrade::Fair
+        } else {
+            LinkGrade::Good
+        }
+    }
+
+    /// Thins a bulk batch for `quality` and returns how many readings were
+    /// held back. GPS, OBD and TPMS keep the first reading of each interval;
+    /// IMU keeps the one with the strongest acceleration so harsh events
+    /// survive. Retransmitted batches were thinned already and pass as is.
+    pub fn apply(&self, batch: &mut Batch, quality: &NetworkQuality) -> usize {
+        if !self.config.enable || Lane::of_batch(batch) != Lane::Bulk || !batch.sample_intervals.is_empty() {
+            return 0;
+        }
+
+        let grade = self.grade(quality);
+        let previous = std::mem::replace(&mut *self.grade.lock(), grade);
+        if previous != grade {
+            info!(grade=?grade, latency_ms=quality.latency_ms, loss=quality.packet_loss_percent, bandwidth_kbps=quality.bandwidth_kbps, "📶 Link grade changed — adjusting sensor sampling");
+            metrics::gauge!("stream_sampling_grade").set(grade as u8 as f64);
+        }
+        let intervals = match grade {
+            LinkGrade::Good => return 0,
+            LinkGrade::Fair => &self.config.fair,
+            LinkGrade::Poor => &self.config.poor,
+        };
+
+        // Reading chosen to represent each (sensor, window), by index into the batch
+        let mut chosen: HashMap<(String, u64), (usize, f32)> = HashMap::new();
+        let mut last_window = self.last_window.lock();
+        for (index, event) in batch.events.iter().enumerate() {
+            let EventPayload::Sensor(reading) = &event.payload else {
+                continue;
+            };
+            let interval_ms = interval_for(intervals, &reading.sensor_type);
+            if interval_ms == 0 {
+                continue;
+            }
+            batch.sample_intervals.insert(reading.sensor_id.clone(), interval_ms);
+            let window = event.timestamp / (interval_ms as u64 * 1_000_000);
+            if last_window.get(&reading.sensor_id).is_some_and(|sent| window <= *sent) {
+                continue;
+            }
+            let strength = match &reading.values {
+                // Gravity-free once calibrated; before that 1 g is in every reading
+                SensorValues::Imu(imu) => match (imu.longitudinal_g, imu.lateral_g, imu.vertical_g) {
+                    (Some(lon), Some(lat), Some(vert)) => (lon.powi(2) + lat.powi(2) + vert.powi(2)).sqrt(),
+                    _ => (imu.accel_x.powi(2) + imu.accel_y.powi(2) + imu.accel_z.powi(2)).sqrt(),
+                },
+                _ => 0.0,
+            };
+            chosen
+                .entry((reading.sensor_id.clone(), window))
+                .and_modify(|best| {
+                    if strength > best.1 {
+                        *best = (index, strength);
+                    }
+                })
+                .or_insert((index, strength));
+        }
+
+        for (sensor_id, window) in chosen.keys() {
+            let sent = last_window.entry(sensor_id.clone()).or_insert(0);
+            *sent = (*sent).max(*window);
+        }
+        drop(last_window);
+
+        let keep: HashSet<usize> = chosen.values().map(|(index, _)| *index).collect();
+        let before = batch.events.len();
+        let mut index = 0;
+        batch.events.retain(|event| {
+            let thinned = matches!(&event.payload, EventPayload::Sensor(r) if batch.sample_intervals.contains_key(&r.sensor_id));
+            let kept = !thinned || keep.contains(&index);
+            index += 1;
+            kept
+        });
+
+        let held_back = before - batch.events.len();
+        metrics::counter!("stream_events_downsampled_total").increment(held_back as u64);
+        held_back
+    }
+}
+
+fn interval_for(intervals: &SensorIntervals, sensor_type: &SensorType) -> u32 {
+    match sensor_type {
+        SensorType::Gps | SensorType::Position => intervals.gps_ms,
+        SensorType::Obd => intervals.obd_ms,
+        SensorType::Imu => intervals.imu_ms,
+        SensorType::Tpms => intervals.tpms_ms,
+        SensorType::J1939 => intervals.j1939_ms,
+        // Sent only on change; thinning would lose the change
+        SensorType::Dtc => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::types::{GpsData, ImuData, SensorEvent};
+    use crate::stream::types::{CompressionType, EventPriority, StreamEvent};
+
+    fn reading(seq: u64, sensor_type: SensorType, values: SensorValues) -> StreamEvent {
+        let sensor_id = format!("{:?}-0", sensor_type).to_lowercase();
+        let mut event = StreamEvent::new_sensor(
+            SensorEvent {
+                sensor_id,
+                sensor_type,
+                timestamp: chrono::Utc::now(),
+                values,
+                raw_payload: None,
+            },
+            "TRK-001",
+            seq,
+        );
+        // 10 Hz
+        event.timestamp = seq * 100_000_000;
+        event
+    }
+
+    fn batch(events: Vec<StreamEvent>) -> Batch {
+        Batch {
+            batch_id: "batch-1".to_string(),
+            events,
+            created_at: 0,
+            size_bytes: 0,
+            compression_ratio: 1.0,
+            priority: EventPriority::Medium,
+            estimated_latency_ms: 0.0,
+            sensor_encoding: CompressionType::None,
+            sample_intervals: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_poor_link_thins_to_one_reading_per_interval() {
+        let policy = SamplingPolicy::new(&SamplingConfig::default());
+        let gps = |seq| reading(seq, SensorType::Gps, SensorValues::Gps(GpsData {
+            latitude: 37.0,
+            longitude: -122.0,
+            altitude: 0.0,
+            speed_kmh: 80.0,
+            heading: 90.0,
+            satellites: 9,
+            fix_quality: 1,
+            ..Default::default()
+        }));
+        let imu = |seq| reading(seq, SensorType::Imu, SensorValues::Imu(ImuData {
+            accel_x: if seq == 17 { -0.8 } else { 0.01 },
+            accel_y: 0.0,
+            accel_z: 1.0,
+            gyro_x: 0.0,
+            gyro_y: 0.0,
+            gyro_z: 0.0,
+            longitudinal_g: None,
+            lateral_g: None,
+            vertical_g: None,
+        }));
+        let events = (0..30).map(gps).chain((0..30).map(imu)).collect();
+
+        let good = NetworkQuality { latency_ms: 80.0, packet_loss_percent: 0.0, bandwidth_kbps: 2000.0 };
+        let mut full = batch(events);
+        assert_eq!(policy.apply(&mut full, &good), 0);
+        assert!(full.sample_intervals.is_empty());
+
+        // Three seconds at 10 Hz: one GPS fix and one IMU peak per second
+        let poor = NetworkQuality { latency_ms: 3000.0, packet_loss_percent: 0.0, bandwidth_kbps: 2000.0 };
+        let mut thinned = batch(full.events.clone());
+        assert_eq!(policy.apply(&mut thinned, &poor), 54);
+        let seqs: Vec<u64> = thinned.events.iter().map(|e| e.metadata.sequence_number).collect();
+        assert_eq!(seqs, vec![0, 10, 20, 0, 17, 20]);
+        assert_eq!(thinned.sample_intervals.get("gps-0"), Some(&1000));
+
+        // Retransmits are not thinned again; later batches do not repeat a sent second
+        let mut retransmit = thinned.clone();
+        assert_eq!(policy.apply(&mut retransmit, &poor), 0);
+        let mut late = batch(vec![gps(25), gps(31)]);
+        policy.apply(&mut late, &poor);
+        assert_eq!(late.events.len(), 1);
+    }
+}
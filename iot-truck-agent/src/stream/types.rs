@@ -170,6 +170,7 @@ pub enum EventType {
     Heartbeat,
     Checkpoint,
     CommandResponse,
+    DataLoss,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -186,6 +187,7 @@ pub enum EventPayload {
     Heartbeat(HeartbeatData),
     Checkpoint(crate::wal::types::CheckpointMarker),
     CommandResponse(CommandResponseData),
+    DataLoss(truck_protocol::events::DataLoss),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -203,6 +205,9 @@ pub struct HeartbeatData {
     pub disk_used_bytes: u64,
     pub last_ack_seq: u64,
     pub network_quality: NetworkQuality,
+    /// Filled in by the stream manager just before the heartbeat is sent.
+    #[serde(default)]
+    pub data_usage: Option<truck_protocol::events::DataUsage>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -262,6 +267,12 @@ pub struct Batch {
     pub compression_ratio: f32,
     pub priority: EventPriority,
     pub estimated_latency_ms: f32,
+    /// Wire encoding for the batch's sensor readings: `None` or `Delta`.
+    pub sensor_encoding: CompressionType,
+    /// Sensors the sampling policy thinned, by sensor id, with the interval
+    /// they were thinned to. Empty until the batch is first sent.
+    #[serde(default)]
+    pub sample_intervals: std::collections::HashMap<String, u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -302,6 +313,28 @@ impl StreamEvent {
         }
     }
 
+    /// Unacked WAL ranges evicted by retention; sent at `High` so the server
+    /// learns about the gap even while the link is degraded.
+    pub fn new_data_loss(loss: truck_protocol::events::DataLoss, device_id: &str, seq: u64) -> Self {
+        Self {
+            event_id: format!("loss-{}-{}", device_id, seq),
+            event_type: EventType::DataLoss,
+            payload: EventPayload::DataLoss(loss),
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64,
+            priority: EventPriority::High,
+            metadata: EventMetadata {
+                device_id: device_id.to_string(),
+                truck_id: device_id.to_string(),
+                sequence_number: seq,
+                retry_count: 0,
+                source_module: "wal".to_string(),
+                requires_ack: true,
+                qos: QoSLevel::AtLeastOnce,
+                encryption: None,
+            },
+        }
+    }
+
     // ... other constructors
 
     pub fn size_bytes(&self) -> usize {
@@ -314,6 +347,7 @@ impl StreamEvent {
             EventPayload::Heartbeat(_) => 128,
             EventPayload::Checkpoint(_) => 256,
             EventPayload::CommandResponse(_) => 512,
+            EventPayload::DataLoss(loss) => 64 + 32 * loss.ranges.len(),
         }
     }
 
@@ -0,0 +1,335 @@
+use crate::stream::types::{Batch, CompressionType, EventPayload, EventPriority, StreamEvent};
+use truck_protocol::events as wire;
+use truck_protocol::{Envelope, WireEvent};
+
+// Translation from in-process stream types to the shared wire protocol.
+// Anything that is not listed here stays on the truck.
+
+pub fn to_envelope(batch: &Batch, device_id: &str) -> Envelope {
+    let events = batch
+        .events
+        .iter()
+        .filter_map(to_wire_event)
+        .map(|mut event| {
+            // Tells the server it got a thinned series, not the full rate
+            if let wire::WirePayload::Sensor(reading) = &mut event.payload {
+                reading.sample_interval_ms = batch.sample_intervals.get(&reading.sensor_id).copied();
+            }
+            event
+        })
+        .collect();
+    let mut envelope = Envelope::new(device_id, &batch.batch_id, batch.created_at, events);
+    if batch.sensor_encoding == CompressionType::Delta {
+        envelope.pack_sensors();
+    }
+    envelope
+}
+
+pub fn to_wire_event(event: &StreamEvent) -> Option<WireEvent> {
+    let payload = to_wire_payload(&event.payload)?;
+    Some(WireEvent {
+        event_id: event.event_id.clone(),
+        kind: payload.kind(),
+        timestamp: event.timestamp,
+        priority: to_wire_priority(&event.priority),
+        sequence_number: event.metadata.sequence_number,
+        source_module: event.metadata.source_module.clone(),
+        payload,
+    })
+}
+
+pub fn to_wire_priority(priority: &EventPriority) -> wire::EventPriority {
+    match priority {
+        EventPriority::Critical => wire::EventPriority::Critical,
+        EventPriority::High => wire::EventPriority::High,
+        EventPriority::Medium => wire::EventPriority::Medium,
+        EventPriority::Low => wire::EventPriority::Low,
+    }
+}
+
+fn to_wire_payload(payload: &EventPayload) -> Option<wire::WirePayload> {
+    let payload = match payload {
+        EventPayload::Sensor(e) => wire::WirePayload::Sensor(sensor_reading(e)),
+        EventPayload::CameraMeta(m) => wire::WirePayload::CameraMeta(wire::CameraMeta {
+            camera_id: m.camera_id.to_string(),
+            width: m.width,
+            height: m.height,
+            format: m.format.clone(),
+            is_keyframe: m.is_keyframe,
+            blob_id: Some(m.blob_id.clone()),
+            trigger_event: m.trigger_event.clone(),
+        }),
+        EventPayload::CameraBlob { blob_id, data, compression_type } => {
+            // Blob bytes go over the HTTP upload path; only the reference is batched
+            wire::WirePayload::CameraBlob(wire::CameraBlobRef {
+                blob_id: blob_id.clone(),
+                size_bytes: data.len() as u64,
+                compression: format!("{:?}", compression_type).to_lowercase(),
+            })
+        }
+        EventPayload::Ml(e) => wire::WirePayload::Ml(ml_detection(e)),
+        EventPayload::Health(e) => wire::WirePayload::Health(health_report(e)),
+        EventPayload::Heartbeat(h) => wire::WirePayload::Heartbeat(wire::Heartbeat {
+            uptime_sec: h.uptime_sec,
+            memory_used_bytes: h.memory_used_bytes,
+            disk_used_bytes: h.disk_used_bytes,
+            last_ack_seq: h.last_ack_seq,
+            data_usage: h.data_usage.clone(),
+        }),
+        EventPayload::CommandResponse(r) => wire::WirePayload::CommandResponse(wire::CommandResponse {
+            command_id: r.command_id.clone(),
+            success: r.status == crate::stream::types::CommandStatus::Success,
+            message: r.message.clone(),
+            data: r.data.clone(),
+        }),
+        EventPayload::DataLoss(loss) => wire::WirePayload::DataLoss(loss.clone()),
+        // WAL checkpoints are local bookkeeping
+        EventPayload::Checkpoint(_) => return None,
+    };
+    Some(payload)
+}
+
+fn sensor_reading(event: &crate::sensors::types::SensorEvent) -> wire::SensorReading {
+    use crate::sensors::types::SensorValues;
+
+    let tire = |t: &crate::sensors::types::TireSensor| wire::TireSensor {
+        position: wire::TirePosition {
+            unit: t.position.unit,
+            axle: t.position.axle,
+            position: t.position.position,
+        },
+        pressure_psi: t.pressure_psi,
+        temperature_c: t.temperature_c,
+        battery_percent: t.battery_percent,
+        alert: t.alert,
+    };
+
+    let obd = |o: &crate::sensors::types::ObdData| wire::ObdData {
+        rpm: o.rpm,
+        speed_kmh: o.speed_kmh,
+        coolant_temp: o.coolant_temp,
+        fuel_level: o.fuel_level,
+        engine_load: o.engine_load,
+        throttle_pos: o.throttle_pos,
+        extra: o.extra.clone(),
+    };
+    let codes = |codes: &[crate::sensors::types::DiagnosticCode]| {
+        codes
+            .iter()
+            .map(|c| wire::DiagnosticCode {
+                code: c.code.clone(),
+                description: c.description.clone(),
+            })
+            .collect()
+    };
+
+    let values = match &event.values {
+        SensorValues::Gps(g) => wire::SensorValues::Gps(gps(g)),
+        SensorValues::Obd(o) => wire::SensorValues::Obd(obd(o)),
+        SensorValues::Imu(i) => wire::SensorValues::Imu(wire::ImuData {
+            accel_x: i.accel_x,
+            accel_y: i.accel_y,
+            accel_z: i.accel_z,
+            gyro_x: i.gyro_x,
+            gyro_y: i.gyro_y,
+            gyro_z: i.gyro_z,
+            longitudinal_g: i.longitudinal_g,
+            lateral_g: i.lateral_g,
+            vertical_g: i.vertical_g,
+        }),
+        SensorValues::Tpms(t) => wire::SensorValues::Tpms(wire::TpmsData {
+            tires: t.tires.iter().map(tire).collect(),
+        }),
+        SensorValues::J1939(j) => wire::SensorValues::J1939(wire::J1939Data {
+            engine_speed_rpm: j.engine_speed_rpm,
+            wheel_speed_kmh: j.wheel_speed_kmh,
+            fuel_rate_lph: j.fuel_rate_lph,
+            total_fuel_used_l: j.total_fuel_used_l,
+            odometer_km: j.odometer_km,
+            engine_hours: j.engine_hours,
+            coolant_temp_c: j.coolant_temp_c,
+            oil_temp_c: j.oil_temp_c,
+            amber_warning_lamp: j.amber_warning_lamp,
+            red_stop_lamp: j.red_stop_lamp,
+            active_faults: j
+                .active_faults
+                .iter()
+                .map(|f| wire::J1939Fault {
+                    source_address: f.source_address,
+                    spn: f.spn,
+                    fmi: f.fmi,
+                    occurrence_count: f.occurrence_count,
+                })
+                .collect(),
+        }),
+        SensorValues::Dtc(d) => wire::SensorValues::Dtc(wire::DtcData {
+            mil_on: d.mil_on,
+            stored: codes(&d.stored),
+            pending: codes(&d.pending),
+            permanent: codes(&d.permanent),
+            freeze_frame: d.freeze_frame.as_ref().map(|f| wire::FreezeFrame {
+                code: f.code.clone(),
+                values: obd(&f.values),
+            }),
+        }),
+        SensorValues::Position(p) => wire::SensorValues::Position(position(p)),
+    };
+
+    wire::SensorReading {
+        sensor_id: event.sensor_id.clone(),
+        values,
+        sample_interval_ms: None,
+    }
+}
+
+fn position(p: &crate::sensors::types::PositionData) -> wire::PositionData {
+    use crate::sensors::types::PositionSource;
+
+    wire::PositionData {
+        latitude: p.latitude,
+        longitude: p.longitude,
+        speed_kmh: p.speed_kmh,
+        heading: p.heading,
+        covariance_m2: p.covariance_m2,
+        speed_std_kmh: p.speed_std_kmh,
+        heading_std_deg: p.heading_std_deg,
+        source: match p.source {
+            PositionSource::Gnss => wire::PositionSource::Gnss,
+            PositionSource::Fused => wire::PositionSource::Fused,
+            PositionSource::DeadReckoned => wire::PositionSource::DeadReckoned,
+        },
+        gnss_age_ms: p.gnss_age_ms,
+    }
+}
+
+fn gps(g: &crate::sensors::types::GpsData) -> wire::GpsData {
+    use crate::sensors::types::{GnssFixType, GnssSystem};
+
+    wire::GpsData {
+        latitude: g.latitude,
+        longitude: g.longitude,
+        altitude: g.altitude,
+        speed_kmh: g.speed_kmh,
+        heading: g.heading,
+        satellites: g.satellites,
+        fix_quality: g.fix_quality,
+        fix_type: g.fix_type.map(|f| match f {
+            GnssFixType::NoFix => wire::GnssFixType::NoFix,
+            GnssFixType::DeadReckoning => wire::GnssFixType::DeadReckoning,
+            GnssFixType::Fix2d => wire::GnssFixType::Fix2d,
+            GnssFixType::Fix3d => wire::GnssFixType::Fix3d,
+        }),
+        hdop: g.hdop,
+        vdop: g.vdop,
+        pdop: g.pdop,
+        horizontal_accuracy_m: g.horizontal_accuracy_m,
+        vertical_accuracy_m: g.vertical_accuracy_m,
+        satellites_in_view: g
+            .satellites_in_view
+            .iter()
+            .map(|s| wire::SatelliteInView {
+                system: match s.system {
+                    GnssSystem::Gps => wire::GnssSystem::Gps,
+                    GnssSystem::Sbas => wire::GnssSystem::Sbas,
+                    GnssSystem::Galileo => wire::GnssSystem::Galileo,
+                    GnssSystem::Beidou => wire::GnssSystem::Beidou,
+                    GnssSystem::Qzss => wire::GnssSystem::Qzss,
+                    GnssSystem::Glonass => wire::GnssSystem::Glonass,
+                    GnssSystem::Navic => wire::GnssSystem::Navic,
+                },
+                prn: s.prn,
+                elevation_deg: s.elevation_deg,
+                azimuth_deg: s.azimuth_deg,
+                snr_dbhz: s.snr_dbhz,
+                used: s.used,
+            })
+            .collect(),
+    }
+}
+
+fn ml_detection(event: &crate::ml_edge::types::MLEvent) -> wire::MlDetection {
+    use crate::ml_edge::types::InferenceResult;
+
+    let result = match &event.result {
+        InferenceResult::Drowsiness(r) => wire::MlResult::Drowsiness {
+            is_drowsy: r.is_drowsy,
+            eye_closure_ratio: r.eye_closure_ratio,
+        },
+        InferenceResult::LaneDeparture(r) => wire::MlResult::LaneDeparture {
+            is_departing: r.is_departing,
+            deviation_pixels: r.deviation_pixels,
+        },
+        InferenceResult::CargoTamper(r) => wire::MlResult::CargoTamper {
+            is_tampered: r.is_tampered,
+            motion_score: r.motion_score,
+        },
+        InferenceResult::LicensePlate(r) => wire::MlResult::LicensePlate {
+            plate_text: r.plate_text.clone(),
+            bounding_box: r.bounding_box,
+        },
+        InferenceResult::Weather(r) => wire::MlResult::Weather {
+            weather_type: format!("{:?}", r.weather_type),
+            visibility_m: r.visibility_m,
+        },
+        InferenceResult::Unknown => wire::MlResult::Unknown,
+    };
+
+    wire::MlDetection {
+        model_name: event.model_name.clone(),
+        model_version: event.model_version.clone(),
+        result,
+        confidence: event.confidence,
+        calibrated_confidence: event.calibrated_confidence,
+        latency_ms: event.latency_ms,
+        hardware_used: format!("{:?}", event.hardware_used),
+        camera_id: event.metadata.camera_id.clone(),
+        driver_id: event.metadata.driver_id.clone(),
+        route_id: event.metadata.route_id.clone(),
+    }
+}
+
+fn health_report(event: &crate::health::types::HealthEvent) -> wire::HealthReport {
+    use crate::health::types::{AlertSeverity, HealthStatus};
+
+    let status = match event.status {
+        HealthStatus::Ok => wire::HealthState::Ok,
+        HealthStatus::Warning => wire::HealthState::Warning,
+        HealthStatus::Critical => wire::HealthState::Critical,
+        HealthStatus::Degraded => wire::HealthState::Degraded,
+        HealthStatus::ShutdownPending => wire::HealthState::ShutdownPending,
+    };
+
+    let alerts = event
+        .alerts
+        .iter()
+        .map(|a| wire::HealthAlert {
+            alert_id: a.alert_id.clone(),
+            alert_type: a.alert_type.clone(),
+            severity: match a.severity {
+                AlertSeverity::Info => wire::AlertSeverity::Info,
+                AlertSeverity::Warning => wire::AlertSeverity::Warning,
+                AlertSeverity::Critical => wire::AlertSeverity::Critical,
+            },
+            message: a.message.clone(),
+            triggered_at: a.triggered_at,
+            source: a.source.clone(),
+        })
+        .collect();
+
+    let r = &event.resources;
+    wire::HealthReport {
+        status,
+        cpu_percent: r.cpu_percent,
+        cpu_cores: r.cpu_cores,
+        memory_percent: r.memory_percent,
+        memory_used_mb: r.memory_used_mb,
+        memory_total_mb: r.memory_total_mb,
+        disk_percent: r.disk_percent,
+        disk_used_gb: r.disk_used_gb,
+        disk_total_gb: r.disk_total_gb,
+        temperature_c: r.temperature_c,
+        thermal_throttling: r.thermal_throttling,
+        uptime_sec: r.uptime_sec,
+        alerts,
+    }
+}
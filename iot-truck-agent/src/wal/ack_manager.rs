@@ -42,8 +42,27 @@ impl AckManager {
         Ok(self.acked_tree.contains_key(event_id)?)
     }
 
+    /// Whether the server has `entry`: flagged on the record, or acked under
+    /// its stream event id. The live ack path only writes the latter, so
+    /// anything deciding on ack state goes through here.
+    pub fn is_entry_acked(&self, entry: &WalEntry) -> Result<bool, sled::Error> {
+        if entry.metadata.acked {
+            return Ok(true);
+        }
+        let event_id = event_id(entry);
+        if self.pending_acks.read().unwrap().contains(&event_id) {
+            return Ok(true);
+        }
+        self.acked_tree.contains_key(event_id)
+    }
+
     pub async fn get_pending_acks(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
         let pending = self.pending_acks.read().unwrap();
         Ok(pending.iter().cloned().collect())
     }
+}
+
+// Stream events are keyed `evt-{device}-{seq}`; that is what the server acks
+pub fn event_id(entry: &WalEntry) -> String {
+    format!("evt-{}-{}", entry.metadata.device_id, entry.metadata.sequence_number)
 }
\ No newline at end of file
@@ -0,0 +1,397 @@
+use crate::wal::ack_manager::{event_id, AckManager};
+use crate::wal::reader::WalReader;
+use crate::wal::types::WalEntry;
+use chrono::{DateTime, Utc};
+use clap::{Args, Subcommand};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Offline inspection and repair of a truck's WAL.
+///
+/// sled holds an exclusive lock on the database, so stop the agent first.
+#[derive(Debug, Args)]
+pub struct WalArgs {
+    /// WAL directory (defaults to `storage.wal_path` from the agent config)
+    #[arg(long)]
+    pub wal_path: Option<String>,
+
+    /// Decrypt entries while reading (implied by `storage.enable_encryption`)
+    #[arg(long)]
+    pub encrypted: bool,
+
+    /// Keystore directory (defaults to `storage.wal_key_dir` from the agent config)
+    #[arg(long)]
+    pub key_dir: Option<String>,
+
+    #[command(subcommand)]
+    pub command: WalCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum WalCommand {
+    /// One line per entry
+    List,
+    /// Entry counts and sizes by type and priority
+    Stats,
+    /// Full entries for a sequence range (inclusive)
+    Dump {
+        #[arg(long, default_value_t = 0)]
+        from: u64,
+        #[arg(long)]
+        to: Option<u64>,
+    },
+    /// Entries matching a time window and/or event id
+    Filter {
+        /// RFC 3339, e.g. 2024-05-01T08:00:00Z
+        #[arg(long)]
+        since: Option<DateTime<Utc>>,
+        #[arg(long)]
+        until: Option<DateTime<Utc>>,
+        #[arg(long)]
+        event_id: Option<String>,
+    },
+    /// Entries still waiting for a server ack
+    Unacked,
+    /// Write entries as JSON lines
+    Export {
+        #[arg(long)]
+        output: PathBuf,
+        #[arg(long, default_value_t = 0)]
+        from: u64,
+    },
+    /// Decode every entry and report the first corrupt one
+    Verify {
+        /// Drop everything from the first corrupt entry onwards
+        #[arg(long)]
+        truncate: bool,
+    },
+    /// Generate a new encryption key for future entries; old keys are kept
+    RotateKey,
+}
+
+/// Result of decoding every entry in the WAL.
+#[derive(Debug, Default, PartialEq)]
+pub struct VerifyReport {
+    pub total: usize,
+    pub valid: usize,
+    pub corrupt: Vec<u64>,
+}
+
+impl VerifyReport {
+    /// First corrupt sequence number if it and everything after it is corrupt.
+    /// Corruption in the middle of the log is not a torn tail and is left alone.
+    pub fn corrupt_tail(&self, sequences: &[u64]) -> Option<u64> {
+        let first = *self.corrupt.first()?;
+        let tail_len = sequences.iter().filter(|&&s| s >= first).count();
+        (tail_len == self.corrupt.len()).then_some(first)
+    }
+}
+
+pub async fn run(
+    args: WalArgs,
+    storage: &crate::config::StorageConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let wal_path = args.wal_path.as_deref().unwrap_or(&storage.wal_path);
+    let key_dir = args.key_dir.as_deref().unwrap_or(&storage.wal_key_dir);
+
+    if let WalCommand::RotateKey = args.command {
+        let key_id = crate::wal::writer::encryption::KeyStore::open(key_dir)?.rotate()?;
+        println!("New WAL key {key_id} — takes effect on next agent start");
+        return Ok(());
+    }
+
+    let db = sled::open(wal_path)?;
+    let encrypted = args.encrypted || storage.enable_encryption;
+    let reader = WalReader::new(&db, encrypted.then_some(key_dir))?;
+    let acks = AckManager::new(db.clone())?;
+
+    match args.command {
+        WalCommand::List => {
+            for (seq, entry) in entries_from(&reader, 0)? {
+                match entry {
+                    Ok(entry) => println!("{}", summary_line(&entry, acks.is_entry_acked(&entry)?)),
+                    Err(e) => println!("{}", corrupt_line(seq, &e)),
+                }
+            }
+        }
+        WalCommand::Stats => print_stats(&reader, &acks)?,
+        WalCommand::Dump { from, to } => {
+            let to = to.unwrap_or(reader.last_sequence()?);
+            for seq in reader.sequences()?.into_iter().filter(|s| (from..=to).contains(s)) {
+                match reader.get_entry(seq) {
+                    Ok(Some(entry)) => println!("{}", serde_json::to_string_pretty(&entry)?),
+                    Ok(None) => {}
+                    Err(e) => println!("{}", corrupt_line(seq, &e)),
+                }
+            }
+        }
+        WalCommand::Filter { since, until, event_id } => {
+            let since = since.map(|t| t.timestamp_nanos() as u64).unwrap_or(0);
+            let until = until.map(|t| t.timestamp_nanos() as u64).unwrap_or(u64::MAX);
+            // A corrupt entry cannot be matched, so it is listed in place
+            for (seq, entry) in entries_from(&reader, 0)? {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        println!("{}", corrupt_line(seq, &e));
+                        continue;
+                    }
+                };
+                let in_window = (since..=until).contains(&entry.timestamp);
+                let id_matches = event_id.as_deref().is_none_or(|id| matches_event_id(&entry, id));
+                if in_window && id_matches {
+                    println!("{}", summary_line(&entry, acks.is_entry_acked(&entry)?));
+                }
+            }
+        }
+        WalCommand::Unacked => {
+            let mut count = 0;
+            for (seq, entry) in entries_from(&reader, 0)? {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        println!("{}", corrupt_line(seq, &e));
+                        continue;
+                    }
+                };
+                if !entry.metadata.requires_ack || acks.is_entry_acked(&entry)? {
+                    continue;
+                }
+                println!("{}", summary_line(&entry, false));
+                count += 1;
+            }
+            println!("{count} unacked entries");
+        }
+        WalCommand::Export { output, from } => {
+            let mut file = std::io::BufWriter::new(std::fs::File::create(&output)?);
+            let (mut exported, mut corrupt) = (0, 0);
+            for (seq, entry) in entries_from(&reader, from)? {
+                match entry {
+                    Ok(entry) => {
+                        serde_json::to_writer(&mut file, &entry)?;
+                        file.write_all(b"\n")?;
+                        exported += 1;
+                    }
+                    Err(e) => {
+                        println!("{}", corrupt_line(seq, &e));
+                        corrupt += 1;
+                    }
+                }
+            }
+            file.flush()?;
+            println!("Exported {} entries to {} ({} corrupt skipped)", exported, output.display(), corrupt);
+        }
+        WalCommand::Verify { truncate } => {
+            let sequences = reader.sequences()?;
+            let report = verify(&reader, &sequences);
+            println!(
+                "{} entries, {} valid, {} corrupt",
+                report.total,
+                report.valid,
+                report.corrupt.len()
+            );
+            for seq in &report.corrupt {
+                println!("  corrupt: #{seq}");
+            }
+
+            match (report.corrupt_tail(&sequences), truncate) {
+                (Some(seq), true) => {
+                    let removed = reader.truncate_from(seq)?;
+                    println!("Truncated {removed} entries from #{seq}");
+                }
+                (Some(seq), false) => println!("Corrupt tail starts at #{seq} — rerun with --truncate to drop it"),
+                (None, _) if !report.corrupt.is_empty() => {
+                    println!("Corruption is not confined to the tail — not truncating");
+                }
+                (None, _) => {}
+            }
+        }
+        WalCommand::RotateKey => unreachable!("handled before opening the WAL"),
+    }
+
+    Ok(())
+}
+
+pub fn verify(reader: &WalReader, sequences: &[u64]) -> VerifyReport {
+    let mut report = VerifyReport {
+        total: sequences.len(),
+        ..Default::default()
+    };
+    for &seq in sequences {
+        match reader.get_entry(seq) {
+            Ok(Some(_)) => report.valid += 1,
+            _ => report.corrupt.push(seq),
+        }
+    }
+    report
+}
+
+/// Entries from `from` on, decoded one at a time so a damaged record is
+/// reported in its place instead of ending the walk.
+pub fn entries_from(
+    reader: &WalReader,
+    from: u64,
+) -> crate::wal::error::Result<impl Iterator<Item = (u64, crate::wal::error::Result<WalEntry>)> + '_> {
+    let sequences = reader.sequences()?;
+    Ok(sequences
+        .into_iter()
+        .filter(move |&seq| seq >= from)
+        .filter_map(|seq| reader.get_entry(seq).transpose().map(|entry| (seq, entry))))
+}
+
+fn print_stats(reader: &WalReader, acks: &AckManager) -> Result<(), Box<dyn std::error::Error>> {
+    let mut by_type: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    let mut by_priority: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    let (mut total, mut acked, mut corrupt) = (0, 0, 0);
+
+    for (_, entry) in entries_from(reader, 0)? {
+        total += 1;
+        let Ok(entry) = entry else {
+            corrupt += 1;
+            continue;
+        };
+        let t = by_type.entry(format!("{:?}", entry.entry_type)).or_default();
+        t.0 += 1;
+        t.1 += entry.size_bytes;
+        let p = by_priority.entry(format!("{:?}", entry.priority)).or_default();
+        p.0 += 1;
+        p.1 += entry.size_bytes;
+        if acks.is_entry_acked(&entry)? {
+            acked += 1;
+        }
+    }
+
+    println!(
+        "entries: {} (acked: {}, corrupt: {}), last seq: {}",
+        total,
+        acked,
+        corrupt,
+        reader.last_sequence()?
+    );
+    println!("\nby type:");
+    for (name, (count, bytes)) in &by_type {
+        println!("  {name:<12} {count:>8} {bytes:>12} B");
+    }
+    println!("\nby priority:");
+    for (name, (count, bytes)) in &by_priority {
+        println!("  {name:<12} {count:>8} {bytes:>12} B");
+    }
+    Ok(())
+}
+
+fn summary_line(entry: &WalEntry, acked: bool) -> String {
+    let ts = DateTime::<Utc>::from_timestamp(
+        (entry.timestamp / 1_000_000_000) as i64,
+        (entry.timestamp % 1_000_000_000) as u32,
+    )
+    .map(|t| t.to_rfc3339())
+    .unwrap_or_else(|| entry.timestamp.to_string());
+
+    format!(
+        "#{:<10} {} {:<10} {:<8} {:>8} B {} {}",
+        entry.metadata.sequence_number,
+        ts,
+        format!("{:?}", entry.entry_type),
+        format!("{:?}", entry.priority),
+        entry.size_bytes,
+        if acked { "acked  " } else { "pending" },
+        entry.entry_id,
+    )
+}
+
+fn corrupt_line(seq: u64, e: &crate::wal::error::WalError) -> String {
+    format!("#{seq}: <corrupt: {e}>")
+}
+
+fn matches_event_id(entry: &WalEntry, id: &str) -> bool {
+    entry.entry_id == id || event_id(entry) == id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wal::types::*;
+
+    fn heartbeat(seq: u64) -> WalEntry {
+        WalEntry {
+            entry_id: format!("wal-TRK-001-{seq}"),
+            entry_type: EntryType::Heartbeat,
+            payload: EntryPayload::Heartbeat(HeartbeatData {
+                uptime_sec: seq,
+                memory_used_bytes: 0,
+                disk_used_bytes: 0,
+            }),
+            timestamp: seq * 1_000_000_000,
+            priority: EntryPriority::Low,
+            size_bytes: 64,
+            compression: CompressionInfo {
+                algorithm: "none".to_string(),
+                level: 0,
+                original_size: 64,
+                compressed_size: 64,
+            },
+            encryption: None,
+            metadata: EntryMetadata {
+                device_id: "TRK-001".to_string(),
+                truck_id: "TRK-001".to_string(),
+                sequence_number: seq,
+                source_module: "test".to_string(),
+                requires_ack: true,
+                acked: false,
+                retention_policy: RetentionPolicy::TimeBased { max_age_hours: 72 },
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_listing_walks_past_corrupt_records_and_reads_live_acks() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = sled::open(dir.path()).unwrap();
+        let tree = db.open_tree("main").unwrap();
+        for seq in 1..=3u64 {
+            tree.insert(seq.to_be_bytes(), crate::wal::record::encode(seq, &heartbeat(seq)).unwrap()).unwrap();
+        }
+        tree.insert(2u64.to_be_bytes(), &b"\x01\x02"[..]).unwrap();
+
+        // Acked the way the stream does it, leaving the record's flag alone
+        let acks = AckManager::new(db.clone()).unwrap();
+        acks.mark_acked("evt-TRK-001-3").await.unwrap();
+
+        let reader = WalReader::new(&db, None).unwrap();
+        let listed: Vec<_> = entries_from(&reader, 0).unwrap().collect();
+        assert_eq!(listed.iter().map(|(seq, e)| (*seq, e.is_ok())).collect::<Vec<_>>(), [(1, true), (2, false), (3, true)]);
+
+        let acked: Vec<_> = listed
+            .iter()
+            .filter_map(|(_, e)| e.as_ref().ok())
+            .map(|e| (e.metadata.acked, acks.is_entry_acked(e).unwrap()))
+            .collect();
+        assert_eq!(acked, [(false, false), (false, true)]);
+    }
+
+    #[test]
+    fn test_verify_truncates_only_corrupt_tail() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = sled::open(dir.path()).unwrap();
+        let tree = db.open_tree("main").unwrap();
+        for seq in 1..=3u64 {
+            tree.insert(seq.to_be_bytes(), crate::wal::record::encode(seq, &heartbeat(seq)).unwrap()).unwrap();
+        }
+        // Torn write after power loss
+        tree.insert(4u64.to_be_bytes(), &b"\x01\x02"[..]).unwrap();
+
+        let reader = WalReader::new(&db, None).unwrap();
+        let sequences = reader.sequences().unwrap();
+        let report = verify(&reader, &sequences);
+        assert_eq!(report, VerifyReport { total: 4, valid: 3, corrupt: vec![4] });
+        assert_eq!(report.corrupt_tail(&sequences), Some(4));
+
+        assert_eq!(reader.truncate_from(4).unwrap(), 1);
+        assert_eq!(reader.last_sequence().unwrap(), 3);
+
+        // A corrupt entry followed by good ones is not a tail
+        let mid = VerifyReport { total: 3, valid: 2, corrupt: vec![2] };
+        assert_eq!(mid.corrupt_tail(&[1, 2, 3]), None);
+    }
+}
@@ -1,22 +1,43 @@
+use crate::wal::ack_manager::AckManager;
+use crate::wal::compactor::strategy::{summarize, CompactionStrategy, SummaryGroup};
 use crate::wal::types::WalEntry;
+use crate::wal::writer::encryption::DataEncryptor;
 use crate::wal::error::Result;
 use sled::{Db, Tree};
+use std::sync::Arc;
 use tokio::time::{sleep, Duration};
-use tracing::{info, warn};
+use tracing::{error, info};
+
+pub mod strategy;
 
 pub struct Compactor {
     db: Db,
     tree: Tree,
+    acks: Arc<AckManager>,
     interval_sec: u64,
+    strategy: CompactionStrategy,
+    encryptor: Option<DataEncryptor>,
 }
 
 impl Compactor {
-    pub fn new(db: Db, tree: Tree, interval_sec: u64) -> Self {
-        Self {
+    pub fn new(
+        db: Db,
+        tree: Tree,
+        acks: Arc<AckManager>,
+        interval_sec: u64,
+        strategy: CompactionStrategy,
+        encryption_key_dir: Option<&str>,
+    ) -> Result<Self> {
+        // Samples are opened only where the planner needs them; summaries are sealed again
+        let encryptor = encryption_key_dir.map(DataEncryptor::new).transpose()?;
+        Ok(Self {
             db,
             tree,
+            acks,
             interval_sec,
-        }
+            strategy,
+            encryptor,
+        })
     }
 
     pub async fn start(&self) -> Result<()> {
@@ -42,22 +63,34 @@ impl Compactor {
 
     async fn compact(&self) -> Result<crate::wal::types::CompactionStats> {
         let start = std::time::Instant::now();
+        let now = chrono::Utc::now().timestamp_nanos() as u64;
         let mut entries_compacted = 0;
         let mut bytes_saved = 0;
         let mut batch = sled::Batch::default();
+        let mut planner = self.strategy.planner(now);
+        let mut windows = 0;
 
-        let mut iter = self.tree.iter();
         let mut current_size = 0;
 
-        while let Some(Ok((key, value))) = iter.next() {
+        for item in self.tree.iter() {
+            let (key, value) = item?;
             let seq = u64::from_be_bytes(key.as_ref().try_into().unwrap());
-            let entry: WalEntry = deserialize_wal_entry(&value)?;
+            let entry: WalEntry = crate::wal::record::decode(seq, &value)?;
+            let acked = self.acks.is_entry_acked(&entry)?;
 
-            // Skip if already acked and old
-            if entry.metadata.acked && entry.should_retain(chrono::Utc::now().timestamp_nanos() as u64, 80.0) {
+            // Drop if already acked and old
+            if acked && !entry.should_retain(now, 80.0) {
                 batch.remove(key);
                 entries_compacted += 1;
                 bytes_saved += entry.size_bytes as u64;
+            } else {
+                let entry = if planner.needs_payload(&entry) { self.open(entry)? } else { entry };
+                for group in planner.push(seq, entry, acked) {
+                    let (replaced, saved) = self.summarize(&group)?;
+                    windows += 1;
+                    entries_compacted += replaced;
+                    bytes_saved += saved;
+                }
             }
 
             current_size += 1;
@@ -68,8 +101,17 @@ impl Compactor {
             }
         }
 
-        if !batch.is_empty() {
-            self.tree.apply_batch(batch)?;
+        self.tree.apply_batch(batch)?;
+
+        for group in planner.finish() {
+            let (replaced, saved) = self.summarize(&group)?;
+            windows += 1;
+            entries_compacted += replaced;
+            bytes_saved += saved;
+        }
+        if windows > 0 {
+            info!(windows, "📉 Summarised aged sensor data");
+            metrics::counter!("wal_sensor_windows_summarized_total").increment(windows);
         }
 
         self.db.flush()?;
@@ -80,8 +122,91 @@ impl Compactor {
             compaction_time_ms: start.elapsed().as_millis() as u64,
         })
     }
+
+    /// Replaces one window of acked raw samples with a summary entry, stored
+    /// under the window's first sequence number. Returns the entries removed
+    /// and bytes saved.
+    fn summarize(&self, group: &SummaryGroup) -> Result<(u64, u64)> {
+        let Some(summary) = summarize(&group.raw) else {
+            return Ok((0, 0));
+        };
+        let raw_bytes: u64 = group.raw.iter().map(|(_, e)| e.size_bytes as u64).sum();
+        let bytes_saved = raw_bytes.saturating_sub(summary.size_bytes as u64);
+
+        let summary = match &self.encryptor {
+            Some(encryptor) => encryptor.encrypt_entry(summary)?,
+            None => summary,
+        };
+
+        // One atomic batch per window so a crash never loses samples without their summary
+        let first = group.raw[0].0;
+        let mut batch = sled::Batch::default();
+        batch.insert(&first.to_be_bytes(), crate::wal::record::encode(first, &summary)?);
+        for (seq, _) in &group.raw[1..] {
+            batch.remove(&seq.to_be_bytes());
+        }
+        self.tree.apply_batch(batch)?;
+        Ok((group.raw.len() as u64 - 1, bytes_saved))
+    }
+
+    fn open(&self, entry: WalEntry) -> Result<WalEntry> {
+        match (&self.encryptor, entry.encryption.is_some()) {
+            (Some(encryptor), true) => encryptor.decrypt_entry(entry),
+            _ => Ok(entry),
+        }
+    }
 }
 
-fn deserialize_wal_entry( &[u8]) -> Result<WalEntry> {
-    bincode::deserialize(data).map_err(|e| crate::wal::error::WalError::SerializeError(Box::new(e)))
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::types::{ImuData, SensorEvent, SensorType, SensorValues};
+    use crate::wal::types::EntryPayload;
+
+    fn imu(seq: u64) -> WalEntry {
+        let event = SensorEvent {
+            sensor_id: "imu-0".to_string(),
+            sensor_type: SensorType::Imu,
+            timestamp: chrono::DateTime::from_timestamp(seq as i64 - 1, 0).unwrap(),
+            values: SensorValues::Imu(ImuData {
+                accel_x: seq as f32,
+                accel_y: 0.0,
+                accel_z: 1.0,
+                gyro_x: 0.0,
+                gyro_y: 0.0,
+                gyro_z: 0.0,
+                longitudinal_g: None,
+                lateral_g: None,
+                vertical_g: None,
+            }),
+            raw_payload: None,
+        };
+        WalEntry::new_sensor(event, "TRK-001", seq)
+    }
+
+    #[tokio::test]
+    async fn test_only_windows_acked_by_the_stream_are_summarised() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = sled::open(dir.path()).unwrap();
+        let tree = db.open_tree("main").unwrap();
+        // Two minutes of old 1 Hz samples
+        for seq in 1..=120u64 {
+            tree.insert(seq.to_be_bytes(), crate::wal::record::encode(seq, &imu(seq)).unwrap()).unwrap();
+        }
+
+        // Only the first minute reached the server; the records keep `acked: false`
+        let acks = Arc::new(AckManager::new(db.clone()).unwrap());
+        for seq in 1..=60 {
+            acks.mark_acked(&format!("evt-TRK-001-{seq}")).await.unwrap();
+        }
+
+        let strategy = CompactionStrategy { min_age_sec: 3600, window_sec: 60, preserve_around_sec: 10 };
+        let compactor = Compactor::new(db.clone(), tree.clone(), acks, 300, strategy, None).unwrap();
+        compactor.compact().await.unwrap();
+
+        let left: Vec<u64> = tree.iter().keys().map(|k| u64::from_be_bytes(k.unwrap().as_ref().try_into().unwrap())).collect();
+        assert_eq!(left, std::iter::once(1).chain(61..=120).collect::<Vec<_>>());
+        let summary: WalEntry = crate::wal::record::decode(1, &tree.get(1u64.to_be_bytes()).unwrap().unwrap()).unwrap();
+        assert!(matches!(summary.payload, EntryPayload::SensorSummary(_)));
+    }
+}
@@ -0,0 +1,347 @@
+use crate::sensors::types::{SensorType, SensorValues};
+use crate::wal::types::{
+    EntryPayload, EntryPriority, EntryType, FieldSummary, SensorSummary, WalEntry,
+};
+use std::collections::BTreeMap;
+
+/// How aged sensor data is folded into per-window summaries.
+#[derive(Debug, Clone)]
+pub struct CompactionStrategy {
+    /// Samples younger than this are left raw.
+    pub min_age_sec: u64,
+    pub window_sec: u64,
+    /// Raw samples this close to an alert or ML event are never summarised.
+    pub preserve_around_sec: u64,
+}
+
+/// Raw samples of one sensor in one window, to be replaced by a summary.
+#[derive(Debug, Clone)]
+pub struct SummaryGroup {
+    pub sensor_id: String,
+    /// Samples in sequence order; the summary is stored under the first.
+    pub raw: Vec<(u64, WalEntry)>,
+}
+
+impl SummaryGroup {
+    pub fn seqs(&self) -> Vec<u64> {
+        self.raw.iter().map(|(seq, _)| *seq).collect()
+    }
+}
+
+impl CompactionStrategy {
+    pub fn planner(&self, now: u64) -> WindowPlanner {
+        WindowPlanner {
+            cutoff: now.saturating_sub(self.min_age_sec * 1_000_000_000),
+            preserve: self.preserve_around_sec * 1_000_000_000,
+            window: self.window_sec.max(1) * 1_000_000_000,
+            horizon: 0,
+            anchors: Vec::new(),
+            open: BTreeMap::new(),
+        }
+    }
+
+    /// Plans a whole slice at once; see [`WindowPlanner`].
+    pub fn plan(&self, entries: &[(u64, WalEntry)], now: u64, acked: impl Fn(&WalEntry) -> bool) -> Vec<SummaryGroup> {
+        let mut planner = self.planner(now);
+        let mut plan: Vec<SummaryGroup> = entries
+            .iter()
+            .flat_map(|(seq, entry)| planner.push(*seq, entry.clone(), acked(entry)))
+            .collect();
+        plan.extend(planner.finish());
+        plan.sort_by_key(|g| g.raw[0].0);
+        plan
+    }
+}
+
+/// Groups summarisable entries by sensor and window while the WAL is scanned
+/// in sequence order, holding only the windows that may still change. Sequence
+/// order is taken to follow sample time; an anchor written late only protects
+/// samples whose window is still open.
+///
+/// Only acked samples are summarised: the server already has the raw data,
+/// and unacked samples must reach it as they were recorded. While a truck is
+/// offline nothing is acked, so nothing is summarised. Single-sample groups
+/// are dropped — nothing to gain.
+pub struct WindowPlanner {
+    cutoff: u64,
+    preserve: u64,
+    window: u64,
+    /// Latest sample time seen so far.
+    horizon: u64,
+    anchors: Vec<u64>,
+    open: BTreeMap<(String, u64), Vec<(u64, WalEntry)>>,
+}
+
+impl WindowPlanner {
+    /// Whether [`push`](Self::push) needs `entry`'s payload in plaintext.
+    /// Anything younger can neither be summarised nor protect a sample that can.
+    pub fn needs_payload(&self, entry: &WalEntry) -> bool {
+        matches!(entry.entry_type, EntryType::Sensor | EntryType::Health)
+            && entry.timestamp <= self.cutoff.saturating_add(self.preserve)
+    }
+
+    /// Feeds the next entry in sequence order, with whether the server has
+    /// acked it, and returns the windows that can no longer change.
+    pub fn push(&mut self, seq: u64, entry: WalEntry, acked: bool) -> Vec<SummaryGroup> {
+        let ts = sample_time(&entry);
+        self.horizon = self.horizon.max(ts);
+        if is_anchor(&entry) {
+            self.anchors.push(ts);
+        }
+
+        if let EntryPayload::Sensor(event) = &entry.payload {
+            if acked && is_high_rate(&event.sensor_type) && ts <= self.cutoff {
+                let key = (event.sensor_id.clone(), ts / self.window);
+                self.open.entry(key).or_default().push((seq, entry));
+            }
+        }
+
+        // A window is final once later samples can neither fall into it nor
+        // sit close enough to an anchor that would preserve it
+        let (horizon, window, preserve) = (self.horizon, self.window, self.preserve);
+        let done: Vec<_> = self
+            .open
+            .keys()
+            .filter(|(_, w)| (w + 1) * window + preserve < horizon)
+            .cloned()
+            .collect();
+        let groups = done
+            .into_iter()
+            .filter_map(|key| {
+                let raw = self.open.remove(&key)?;
+                self.close(key.0, raw)
+            })
+            .collect();
+
+        let oldest = self.open.keys().map(|(_, w)| w * window).min().unwrap_or(horizon);
+        self.anchors.retain(|a| a + preserve >= oldest);
+        groups
+    }
+
+    /// Closes every window still open at the end of the scan.
+    pub fn finish(mut self) -> Vec<SummaryGroup> {
+        let open = std::mem::take(&mut self.open);
+        open.into_iter()
+            .filter_map(|((sensor_id, _), raw)| self.close(sensor_id, raw))
+            .collect()
+    }
+
+    fn close(&self, sensor_id: String, raw: Vec<(u64, WalEntry)>) -> Option<SummaryGroup> {
+        let raw: Vec<_> = raw
+            .into_iter()
+            .filter(|(_, e)| {
+                let ts = sample_time(e);
+                !self.anchors.iter().any(|a| a.abs_diff(ts) <= self.preserve)
+            })
+            .collect();
+        (raw.len() > 1).then_some(SummaryGroup { sensor_id, raw })
+    }
+}
+
+/// Summary entry replacing `raw` (all from one sensor, in sequence order).
+/// Identity and metadata are taken from the first sample.
+pub fn summarize(raw: &[(u64, WalEntry)]) -> Option<WalEntry> {
+    let mut samples = raw.iter().filter_map(|(seq, e)| match &e.payload {
+        EntryPayload::Sensor(event) => Some((*seq, sample_time(e), event)),
+        _ => None,
+    });
+    let (first_seq, window_start, first) = samples.next()?;
+
+    let mut fields: Vec<FieldSummary> = field_values(&first.values)
+        .into_iter()
+        .map(|(name, v)| FieldSummary { name: name.to_string(), min: v, max: v, mean: v, last: v })
+        .collect();
+    let (mut last_seq, mut window_end, mut count) = (first_seq, window_start, 1u64);
+
+    for (seq, ts, event) in samples {
+        for (field, (_, v)) in fields.iter_mut().zip(field_values(&event.values)) {
+            field.min = field.min.min(v);
+            field.max = field.max.max(v);
+            field.mean += v; // running sum until the end
+            field.last = v;
+        }
+        last_seq = seq;
+        window_end = ts;
+        count += 1;
+    }
+    for field in &mut fields {
+        field.mean /= count as f64;
+    }
+
+    let template = &raw[0].1;
+    let summary = SensorSummary {
+        sensor_id: first.sensor_id.clone(),
+        sensor_type: first.sensor_type.clone(),
+        window_start,
+        window_end,
+        first_seq,
+        last_seq,
+        event_count: count,
+        fields,
+    };
+    let size = bincode::serialized_size(&summary).unwrap_or(0) as usize;
+
+    let mut metadata = template.metadata.clone();
+    metadata.source_module = "compactor".to_string();
+    Some(WalEntry {
+        entry_id: format!("{}-summary", template.entry_id),
+        entry_type: EntryType::SensorSummary,
+        payload: EntryPayload::SensorSummary(summary),
+        timestamp: template.timestamp,
+        priority: EntryPriority::Medium,
+        size_bytes: size,
+        compression: crate::wal::types::CompressionInfo {
+            algorithm: "summary".to_string(),
+            level: 0,
+            original_size: raw.iter().map(|(_, e)| e.size_bytes).sum(),
+            compressed_size: size,
+        },
+        encryption: None,
+        metadata,
+    })
+}
+
+/// Entries whose surroundings are kept at full resolution for investigation.
+fn is_anchor(entry: &WalEntry) -> bool {
+    // Decided on the clear header where possible; `Ml` payloads stay sealed
+    if entry.priority == EntryPriority::Critical || matches!(entry.entry_type, EntryType::Ml) {
+        return true;
+    }
+    match &entry.payload {
+        EntryPayload::Health(h) => !h.alerts.is_empty(),
+        EntryPayload::Sensor(e) => match &e.values {
+            SensorValues::Tpms(t) => t.tires.iter().any(|tire| tire.alert),
+            SensorValues::J1939(j) => j.red_stop_lamp,
+            SensorValues::Dtc(d) => d.mil_on,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+// IMU and OBD dominate volume; GPS and TPMS are cheap and worth keeping raw
+fn is_high_rate(sensor_type: &SensorType) -> bool {
+    matches!(sensor_type, SensorType::Imu | SensorType::Obd)
+}
+
+fn sample_time(entry: &WalEntry) -> u64 {
+    match &entry.payload {
+        EntryPayload::Sensor(e) => e.timestamp.timestamp_nanos() as u64,
+        _ => entry.timestamp,
+    }
+}
+
+fn field_values(values: &SensorValues) -> Vec<(&'static str, f64)> {
+    match values {
+        SensorValues::Imu(d) => {
+            let mut values = vec![
+                ("accel_x", d.accel_x as f64),
+                ("accel_y", d.accel_y as f64),
+                ("accel_z", d.accel_z as f64),
+                ("gyro_x", d.gyro_x as f64),
+                ("gyro_y", d.gyro_y as f64),
+                ("gyro_z", d.gyro_z as f64),
+            ];
+            // Last, so a window spanning the calibration still lines up
+            let vehicle = [("longitudinal_g", d.longitudinal_g), ("lateral_g", d.lateral_g), ("vertical_g", d.vertical_g)];
+            values.extend(vehicle.into_iter().filter_map(|(name, v)| Some((name, v? as f64))));
+            values
+        }
+        SensorValues::Obd(d) => vec![
+            ("rpm", d.rpm as f64),
+            ("speed_kmh", d.speed_kmh as f64),
+            ("coolant_temp", d.coolant_temp as f64),
+            ("fuel_level", d.fuel_level as f64),
+            ("engine_load", d.engine_load as f64),
+            ("throttle_pos", d.throttle_pos as f64),
+        ],
+        SensorValues::Gps(d) => vec![
+            ("latitude", d.latitude),
+            ("longitude", d.longitude),
+            ("speed_kmh", d.speed_kmh as f64),
+            ("heading", d.heading as f64),
+        ],
+        SensorValues::Position(d) => vec![
+            ("latitude", d.latitude),
+            ("longitude", d.longitude),
+            ("speed_kmh", d.speed_kmh as f64),
+            ("heading", d.heading as f64),
+        ],
+        SensorValues::Tpms(_) | SensorValues::J1939(_) | SensorValues::Dtc(_) => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::types::{ImuData, SensorEvent};
+    use crate::wal::types::*;
+
+    const SEC: u64 = 1_000_000_000;
+
+    fn imu(seq: u64, ts_sec: u64, accel_x: f32) -> (u64, WalEntry) {
+        let event = SensorEvent {
+            sensor_id: "imu-0".to_string(),
+            sensor_type: SensorType::Imu,
+            timestamp: chrono::DateTime::from_timestamp(ts_sec as i64, 0).unwrap(),
+            values: SensorValues::Imu(ImuData {
+                accel_x,
+                accel_y: 0.0,
+                accel_z: 1.0,
+                gyro_x: 0.0,
+                gyro_y: 0.0,
+                gyro_z: 0.0,
+                longitudinal_g: None,
+                lateral_g: None,
+                vertical_g: None,
+            }),
+            raw_payload: None,
+        };
+        (seq, WalEntry::new_sensor(event, "TRK-001", seq))
+    }
+
+    fn critical(seq: u64, ts_sec: u64) -> (u64, WalEntry) {
+        let (_, mut entry) = imu(seq, 0, 0.0);
+        entry.payload = EntryPayload::Command(CommandData {
+            command_id: "panic-button".to_string(),
+            command_type: "alert".to_string(),
+            parameters: serde_json::Value::Null,
+            issued_at: ts_sec * SEC,
+        });
+        entry.entry_type = EntryType::Command;
+        entry.priority = EntryPriority::Critical;
+        entry.timestamp = ts_sec * SEC;
+        (seq, entry)
+    }
+
+    #[test]
+    fn test_windows_summarised_around_preserved_alert() {
+        let strategy = CompactionStrategy { min_age_sec: 3600, window_sec: 60, preserve_around_sec: 10 };
+        // Two minutes at 1 Hz, with an alert at t=90
+        let mut entries: Vec<(u64, WalEntry)> = (0..120).map(|t| imu(t + 1, t, t as f32)).collect();
+        entries.push(critical(121, 90));
+
+        let plan = strategy.plan(&entries, 10 * 3600 * SEC, |_| true);
+        assert_eq!(plan.len(), 2);
+        assert_eq!(plan[0].seqs(), (1..=60).collect::<Vec<_>>());
+        // t=80..=100 stay raw around the alert
+        assert_eq!(plan[1].seqs(), (61..=80).chain(102..=120).collect::<Vec<_>>());
+
+        let raw: Vec<_> = entries[..60].to_vec();
+        let summary = summarize(&raw).unwrap();
+        let EntryPayload::SensorSummary(s) = &summary.payload else { panic!() };
+        assert_eq!((s.first_seq, s.last_seq, s.event_count), (1, 60, 60));
+        assert_eq!(
+            s.fields[0],
+            FieldSummary { name: "accel_x".to_string(), min: 0.0, max: 59.0, mean: 29.5, last: 59.0 }
+        );
+
+        // Nothing is young enough to be left alone yet
+        assert!(strategy.plan(&entries, 120 * SEC, |_| true).is_empty());
+
+        // Samples the server has not acked yet are left raw
+        let plan = strategy.plan(&entries, 10 * 3600 * SEC, |e| e.metadata.sequence_number > 60);
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan[0].seqs()[0], 61);
+    }
+}
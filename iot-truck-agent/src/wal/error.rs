@@ -17,6 +17,18 @@ pub enum WalError {
     #[error("Corrupt WAL entry at seq {0}")]
     CorruptEntry(u64),
 
+    #[error("Corrupt WAL record at seq {seq}: {reason}")]
+    CorruptRecord {
+        seq: u64,
+        reason: crate::wal::record::RecordError,
+    },
+
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+
+    #[error("Unknown encryption key: {0}")]
+    UnknownKey(String),
+
     #[error("Checkpoint conflict: {0}")]
     CheckpointConflict(String),
 
@@ -0,0 +1,23 @@
+//! `#[serde(with = "crate::wal::json_string")]` for `serde_json::Value`
+//! fields in WAL payloads. Records are bincode, which cannot read a `Value`
+//! back, so there the value is stored as its JSON text; human-readable
+//! formats still see the value itself.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub fn serialize<S: Serializer>(value: &serde_json::Value, serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        return value.serialize(serializer);
+    }
+    serde_json::to_string(value)
+        .map_err(serde::ser::Error::custom)?
+        .serialize(serializer)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<serde_json::Value, D::Error> {
+    if deserializer.is_human_readable() {
+        return serde_json::Value::deserialize(deserializer);
+    }
+    let text = String::deserialize(deserializer)?;
+    serde_json::from_str(&text).map_err(serde::de::Error::custom)
+}
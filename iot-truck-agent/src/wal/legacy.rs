@@ -0,0 +1,224 @@
+//! Payload layouts of older record versions, decoded and converted to the
+//! current types. Bincode carries no field names, so every change to a
+//! struct written to the WAL bumps `record::RECORD_VERSION` and keeps the
+//! previous layout here.
+
+use crate::camera::types::CameraFrameMeta;
+use crate::health::types::HealthEvent;
+use crate::ml_edge::types::MLEvent;
+use crate::sensors::types as current;
+use crate::wal::types::{
+    CheckpointData, CommandData, CompressionInfo, EncryptionInfo, EntryMetadata, EntryPayload, EntryPriority,
+    EntryType, HeartbeatData, WalEntry,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+/// Version 1, and the unframed version 0 before it: no compactor summaries,
+/// four named tires, OBD without catalogue PIDs, GPS without fix quality
+/// details, IMU in chip axes only.
+pub mod v1 {
+    use super::*;
+
+    pub fn decode_entry(bytes: &[u8]) -> bincode::Result<WalEntry> {
+        bincode::deserialize::<Entry>(bytes).map(Into::into)
+    }
+
+    /// Plaintext of a payload sealed under version 1.
+    pub fn decode_payload(bytes: &[u8]) -> bincode::Result<EntryPayload> {
+        bincode::deserialize::<Payload>(bytes).map(Into::into)
+    }
+
+    #[derive(Deserialize)]
+    struct Entry {
+        entry_id: String,
+        entry_type: EntryType,
+        payload: Payload,
+        timestamp: u64,
+        priority: EntryPriority,
+        size_bytes: usize,
+        compression: CompressionInfo,
+        encryption: Option<EncryptionInfo>,
+        metadata: EntryMetadata,
+    }
+
+    #[derive(Deserialize)]
+    enum Payload {
+        Sensor(SensorEvent),
+        CameraMeta(CameraFrameMeta),
+        CameraBlob {
+            blob_id: String,
+            data: Vec<u8>,
+            format: String,
+        },
+        Ml(MLEvent),
+        Health(HealthEvent),
+        Heartbeat(HeartbeatData),
+        Checkpoint(CheckpointData),
+        Command(CommandData),
+        Encrypted {
+            ciphertext: Vec<u8>,
+            nonce: Vec<u8>,
+        },
+    }
+
+    #[derive(Deserialize)]
+    struct SensorEvent {
+        sensor_id: String,
+        sensor_type: current::SensorType,
+        timestamp: DateTime<Utc>,
+        values: SensorValues,
+        raw_payload: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    enum SensorValues {
+        Gps(GpsData),
+        Obd(ObdData),
+        Imu(ImuData),
+        Tpms(TpmsData),
+    }
+
+    #[derive(Deserialize)]
+    struct GpsData {
+        latitude: f64,
+        longitude: f64,
+        altitude: f32,
+        speed_kmh: f32,
+        heading: f32,
+        satellites: u8,
+        fix_quality: u8,
+    }
+
+    #[derive(Deserialize)]
+    struct ObdData {
+        rpm: u16,
+        speed_kmh: u8,
+        coolant_temp: i8,
+        fuel_level: u8,
+        engine_load: u8,
+        throttle_pos: u8,
+    }
+
+    #[derive(Deserialize)]
+    struct ImuData {
+        accel_x: f32,
+        accel_y: f32,
+        accel_z: f32,
+        gyro_x: f32,
+        gyro_y: f32,
+        gyro_z: f32,
+    }
+
+    #[derive(Deserialize)]
+    struct TpmsData {
+        front_left: TireSensor,
+        front_right: TireSensor,
+        rear_left: TireSensor,
+        rear_right: TireSensor,
+    }
+
+    #[derive(Deserialize)]
+    struct TireSensor {
+        pressure_psi: f32,
+        temperature_c: f32,
+        battery_percent: u8,
+        alert: bool,
+    }
+
+    impl From<Entry> for WalEntry {
+        fn from(e: Entry) -> Self {
+            WalEntry {
+                entry_id: e.entry_id,
+                entry_type: e.entry_type,
+                payload: e.payload.into(),
+                timestamp: e.timestamp,
+                priority: e.priority,
+                size_bytes: e.size_bytes,
+                compression: e.compression,
+                encryption: e.encryption,
+                metadata: e.metadata,
+            }
+        }
+    }
+
+    impl From<Payload> for EntryPayload {
+        fn from(p: Payload) -> Self {
+            match p {
+                Payload::Sensor(event) => EntryPayload::Sensor(event.into()),
+                Payload::CameraMeta(meta) => EntryPayload::CameraMeta(meta),
+                Payload::CameraBlob { blob_id, data, format } => EntryPayload::CameraBlob { blob_id, data, format },
+                Payload::Ml(event) => EntryPayload::Ml(event),
+                Payload::Health(event) => EntryPayload::Health(event),
+                Payload::Heartbeat(data) => EntryPayload::Heartbeat(data),
+                Payload::Checkpoint(data) => EntryPayload::Checkpoint(data),
+                Payload::Command(data) => EntryPayload::Command(data),
+                Payload::Encrypted { ciphertext, nonce } => EntryPayload::Encrypted { ciphertext, nonce },
+            }
+        }
+    }
+
+    impl From<SensorEvent> for current::SensorEvent {
+        fn from(e: SensorEvent) -> Self {
+            current::SensorEvent {
+                sensor_id: e.sensor_id,
+                sensor_type: e.sensor_type,
+                timestamp: e.timestamp,
+                values: e.values.into(),
+                raw_payload: e.raw_payload,
+            }
+        }
+    }
+
+    impl From<SensorValues> for current::SensorValues {
+        fn from(v: SensorValues) -> Self {
+            match v {
+                SensorValues::Gps(g) => current::SensorValues::Gps(current::GpsData {
+                    latitude: g.latitude,
+                    longitude: g.longitude,
+                    altitude: g.altitude,
+                    speed_kmh: g.speed_kmh,
+                    heading: g.heading,
+                    satellites: g.satellites,
+                    fix_quality: g.fix_quality,
+                    ..Default::default()
+                }),
+                SensorValues::Obd(o) => current::SensorValues::Obd(current::ObdData {
+                    rpm: o.rpm,
+                    speed_kmh: o.speed_kmh,
+                    coolant_temp: o.coolant_temp,
+                    fuel_level: o.fuel_level,
+                    engine_load: o.engine_load,
+                    throttle_pos: o.throttle_pos,
+                    ..Default::default()
+                }),
+                SensorValues::Imu(i) => current::SensorValues::Imu(current::ImuData {
+                    accel_x: i.accel_x,
+                    accel_y: i.accel_y,
+                    accel_z: i.accel_z,
+                    gyro_x: i.gyro_x,
+                    gyro_y: i.gyro_y,
+                    gyro_z: i.gyro_z,
+                    longitudinal_g: None,
+                    lateral_g: None,
+                    vertical_g: None,
+                }),
+                SensorValues::Tpms(t) => {
+                    let wheels = [(0, 0, t.front_left), (0, 1, t.front_right), (1, 0, t.rear_left), (1, 1, t.rear_right)];
+                    current::SensorValues::Tpms(current::TpmsData {
+                        tires: wheels
+                            .into_iter()
+                            .map(|(axle, position, tire)| current::TireSensor {
+                                position: current::TirePosition { unit: 0, axle, position },
+                                pressure_psi: tire.pressure_psi,
+                                temperature_c: tire.temperature_c,
+                                battery_percent: Some(tire.battery_percent),
+                                alert: tire.alert,
+                            })
+                            .collect(),
+                    })
+                }
+            }
+        }
+    }
+}
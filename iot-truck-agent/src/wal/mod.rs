@@ -13,21 +13,20 @@ pub struct WalWriter {
     max_size_bytes: u64,
     buffer: crate::wal::writer::buffer::WriteBuffer,
     encryptor: Option<crate::wal::writer::encryption::DataEncryptor>,
+    retention_trigger: Option<mpsc::Sender<crate::wal::retention::RetentionRequest>>,
 }
 
 impl WalWriter {
-    pub fn new(wal_path: &str, max_size_bytes: u64, enable_encryption: bool) -> Result<Self> {
+    pub fn new(wal_path: &str, max_size_bytes: u64, encryption_key_dir: Option<&str>) -> Result<Self> {
         let db = sled::open(wal_path)?;
         let tree = db.open_tree("main")?;
 
         let buffer = crate::wal::writer::buffer::WriteBuffer::new(1024 * 1024); // 1MB buffer
-        let encryptor = if enable_encryption {
-            Some(crate::wal::writer::encryption::DataEncryptor::new()?)
-        } else {
-            None
-        };
+        let encryptor = encryption_key_dir
+            .map(crate::wal::writer::encryption::DataEncryptor::new)
+            .transpose()?;
 
-        info!(path=%wal_path, "📂 WAL database opened with encryption: {}", enable_encryption);
+        info!(path=%wal_path, "📂 WAL database opened with encryption: {}", encryptor.is_some());
 
         Ok(Self {
             db: Arc::new(db),
@@ -36,6 +35,7 @@ impl WalWriter {
             max_size_bytes,
             buffer,
             encryptor,
+            retention_trigger: None,
         })
     }
 
@@ -45,9 +45,15 @@ impl WalWriter {
         }
 
         // Check disk space
-        if self.tree.len() as u64 > self.max_size_bytes * 9 / 10 {
+        if self.db.size_on_disk()? > self.max_size_bytes * 9 / 10 {
             warn!("WAL approaching max size — triggering retention");
-            // In production, trigger retention policy
+            if let Some(trigger) = &self.retention_trigger {
+                // Full channel means a run is already queued
+                let _ = trigger.try_send(crate::wal::retention::RetentionRequest {
+                    free_fraction: 0.2,
+                    reason: "wal_max_size".to_string(),
+                });
+            }
         }
 
         // Compress if needed
@@ -73,13 +79,15 @@ impl WalWriter {
         Ok(seq)
     }
 
+    pub fn set_retention_trigger(&mut self, trigger: mpsc::Sender<crate::wal::retention::RetentionRequest>) {
+        self.retention_trigger = Some(trigger);
+    }
+
     async fn flush_buffer(&self) -> Result<()> {
         let entries = self.buffer.flush().await?;
         for (seq, entry) in entries {
-            let serialized = bincode::serialize(&entry)
-                .map_err(|e| crate::wal::error::WalError::SerializeError(Box::new(e)))?;
-
-            self.tree.insert(seq.to_be_bytes(), serialized)?;
+            let record = crate::wal::record::encode(seq, &entry)?;
+            self.tree.insert(seq.to_be_bytes(), record)?;
         }
         self.db.flush()?;
         Ok(())
@@ -132,10 +140,10 @@ impl WalWriter {
 use crate::config::Config;
 use crate::health::types::ResourceUsage;
 use crate::wal::ack_manager::AckManager;
-use crate::wal::compactor::Compactor;
+use crate::wal::compactor::{strategy::CompactionStrategy, Compactor};
 use crate::wal::health_integration::HealthIntegration;
 use crate::wal::reader::WalReader;
-use crate::wal::retention::RetentionManager;
+use crate::wal::retention::{RetentionManager, RetentionRequest, RetentionStats};
 use crate::wal::types::WalEntry;
 use crate::wal::writer::WalWriter;
 use tokio::sync::mpsc;
@@ -146,7 +154,10 @@ pub mod cli;
 pub mod compactor;
 pub mod error;
 pub mod health_integration;
+pub mod json_string;
+pub mod legacy;
 pub mod reader;
+pub mod record;
 pub mod retention;
 pub mod types;
 pub mod writer;
@@ -155,6 +166,7 @@ pub mod writer;
 metrics::describe_counter!("wal_entries_written_total", "Total WAL entries written");
 metrics::describe_counter!("wal_dropped_entries_total", "Entries dropped due to errors");
 metrics::describe_counter!("wal_write_errors_total", "WAL write errors");
+metrics::describe_counter!("wal_unreadable_entries_total", "Entries skipped by range reads because they did not decode");
 metrics::describe_gauge!("wal_size_bytes", "Current WAL size in bytes");
 metrics::describe_gauge!("wal_buffer_size_bytes", "Current WAL buffer size in bytes");
 metrics::describe_counter!("checkpoints_total", "Total checkpoints created");
@@ -167,10 +179,14 @@ metrics::describe_counter!("wal_entries_replayed_total", "Entries replayed on st
 metrics::describe_counter!("wal_compactions_total", "Total compactions");
 metrics::describe_counter!("wal_entries_compacted_total", "Entries compacted");
 metrics::describe_counter!("wal_bytes_saved_total", "Bytes saved by compaction");
+metrics::describe_counter!("wal_sensor_windows_summarized_total", "Sensor windows replaced by a summary");
 metrics::describe_counter!("wal_retention_runs_total", "Total retention runs");
 metrics::describe_counter!("wal_entries_deleted_total", "Entries deleted by retention");
 metrics::describe_counter!("wal_bytes_deleted_total", "Bytes deleted by retention");
+metrics::describe_counter!("wal_entries_downsampled_total", "Sensor entries thinned out by retention");
 metrics::describe_counter!("wal_events_acked_total", "Events acknowledged");
+metrics::describe_counter!("wal_records_quarantined_total", "Corrupt records quarantined at startup");
+metrics::describe_counter!("wal_keys_unusable_total", "Damaged non-current WAL keys skipped at load");
 metrics::describe_gauge!("wal_throttled", "WAL writes throttled due to health");
 
 pub struct WalManager {
@@ -178,8 +194,11 @@ pub struct WalManager {
     reader: WalReader,
     compactor: Compactor,
     retention_manager: RetentionManager,
-    ack_manager: AckManager,
+    ack_manager: Arc<AckManager>,
     health_integration: HealthIntegration,
+    recovery_report: crate::wal::reader::recovery::RecoveryReport,
+    retention_trigger: mpsc::Sender<RetentionRequest>,
+    retention_reports: std::sync::Mutex<Option<mpsc::Receiver<RetentionStats>>>,
     tx: mpsc::Sender<WalEntry>,
     device_id: String,
 }
@@ -193,23 +212,45 @@ impl WalManager {
 
         let wal_path = &config.storage.wal_path;
         let max_size_bytes = config.storage.max_wal_size_mb * 1024 * 1024;
-        let enable_encryption = false; // Set from config
+        let enable_encryption = config.storage.enable_encryption;
+        let key_dir = enable_encryption.then_some(config.storage.wal_key_dir.as_str());
+
+        // Retention runs hourly, or on demand from the writer and disk pressure manager
+        let (retention_trigger, mut retention_rx) = mpsc::channel::<RetentionRequest>(4);
+        let (reports_tx, reports_rx) = mpsc::channel::<RetentionStats>(16);
 
         // Create WAL writer
-        let writer = WalWriter::new(wal_path, max_size_bytes, enable_encryption)?;
+        let mut writer = WalWriter::new(wal_path, max_size_bytes, key_dir)?;
+        writer.set_retention_trigger(retention_trigger.clone());
 
-        // Create WAL reader
+        // Quarantine torn or corrupt records before anything reads them
         let db_reader = sled::open(wal_path)?;
-        let reader = WalReader::new(&db_reader, enable_encryption)?;
+        let recovery_encryptor = key_dir.map(crate::wal::writer::encryption::DataEncryptor::new).transpose()?;
+        let recovery_report = crate::wal::reader::recovery::recover(&db_reader, recovery_encryptor.as_ref())?;
+
+        // Create WAL reader
+        let reader = WalReader::new(&db_reader, key_dir)?;
+
+        // Acks arrive by event id; retention and compaction read them from here
+        let db_ack = sled::open(wal_path)?;
+        let ack_manager = Arc::new(AckManager::new(db_ack)?);
 
         // Create compactor
+        let strategy = CompactionStrategy {
+            min_age_sec: config.storage.compact_after_hours * 3600,
+            window_sec: config.storage.compaction_window_sec,
+            preserve_around_sec: config.storage.preserve_around_event_sec,
+        };
         let db_compact = sled::open(wal_path)?;
         let tree_compact = db_compact.open_tree("main")?;
         let compactor = Compactor::new(
             db_compact,
             tree_compact,
+            ack_manager.clone(),
             config.storage.checkpoint_interval_sec,
-        );
+            strategy.clone(),
+            key_dir,
+        )?;
 
         // Create retention manager
         let db_retention = sled::open(wal_path)?;
@@ -217,26 +258,24 @@ impl WalManager {
         let retention_manager = RetentionManager::new(
             db_retention,
             tree_retention,
+            ack_manager.clone(),
             crate::wal::retention::RetentionConfig {
                 max_age_hours: 72,
                 max_size_percent: 90.0,
                 min_priority_to_retain: crate::wal::types::EntryPriority::Medium,
+                wal_path: wal_path.to_string(),
             },
         );
 
-        // Create ACK manager
-        let db_ack = sled::open(wal_path)?;
-        let ack_manager = AckManager::new(db_ack)?;
-
         // Create health integration
         let health_integration = HealthIntegration::new(resource_usage.clone());
 
-        // Start WAL writer task
+        // Start compaction task
+        let db_writer = sled::open(wal_path)?;
+        let tree_writer = db_writer.open_tree("main")?;
+        let background_compactor = Compactor::new(db_writer, tree_writer, ack_manager.clone(), 300, strategy, key_dir)?;
         tokio::spawn(async move {
-            let db_writer = sled::open(wal_path).unwrap();
-            let tree_writer = db_writer.open_tree("main").unwrap();
-            let compactor = Compactor::new(db_writer, tree_writer, 300);
-            if let Err(e) = compactor.start().await {
+            if let Err(e) = background_compactor.start().await {
                 error!(error=%e, "Compactor crashed");
             }
         });
@@ -244,11 +283,24 @@ impl WalManager {
         // Start retention task
         let retention_manager_clone = retention_manager.clone();
         tokio::spawn(async move {
+            let mut hourly = tokio::time::interval(tokio::time::Duration::from_secs(3600));
             loop {
-                if let Err(e) = retention_manager_clone.enforce_retention().await {
-                    error!(error=%e, "Retention failed");
+                let result = tokio::select! {
+                    _ = hourly.tick() => retention_manager_clone.enforce_retention().await,
+                    Some(request) = retention_rx.recv() => {
+                        warn!(reason=%request.reason, fraction=request.free_fraction, "💾 Retention requested under pressure");
+                        retention_manager_clone.relieve_pressure(&request).await
+                    }
+                };
+                match result {
+                    Ok(stats) if !stats.lost.is_empty() => {
+                        if reports_tx.try_send(stats).is_err() {
+                            warn!("Retention report dropped — nobody listening");
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!(error=%e, "Retention failed"),
                 }
-                tokio::time::sleep(tokio::time::Duration::from_secs(3600)).await; // Run hourly
             }
         });
 
@@ -276,24 +328,26 @@ impl WalManager {
             retention_manager,
             ack_manager,
             health_integration,
+            recovery_report,
+            retention_trigger,
+            retention_reports: std::sync::Mutex::new(Some(reports_rx)),
             tx,
             device_id: config.device_id.clone(),
         })
     }
 
+    /// `seq` is the sequence number the event is streamed under, so the ack
+    /// for `evt-{device}-{seq}` finds this entry.
     pub async fn write_sensor(
         &self,
         event: crate::sensors::types::SensorEvent,
+        seq: u64,
     ) -> Result<(), Box<dyn std::error::Error>> {
         if self.health_integration.should_throttle_writes().await {
             return Err("WAL writes throttled due to system health".into());
         }
 
-        let entry = WalEntry::new_sensor(
-            event,
-            &self.device_id,
-            chrono::Utc::now().timestamp_nanos() as u64,
-        );
+        let entry = WalEntry::new_sensor(event, &self.device_id, seq);
         if self.tx.send(entry).await.is_err() {
             return Err("WAL channel closed".into());
         }
@@ -306,7 +360,49 @@ impl WalManager {
         self.ack_manager.mark_acked(event_id).await
     }
 
+    /// Handle for asking retention to free space now.
+    pub fn retention_trigger(&self) -> mpsc::Sender<RetentionRequest> {
+        self.retention_trigger.clone()
+    }
+
+    /// Reports of retention runs that evicted unacked data. Can be taken once.
+    pub fn take_retention_reports(&self) -> Option<mpsc::Receiver<RetentionStats>> {
+        self.retention_reports.lock().unwrap().take()
+    }
+
+    /// Alerts describing records dropped by startup recovery, if any.
+    pub fn recovery_alerts(&self) -> Vec<crate::health::types::AlertInfo> {
+        self.recovery_report.to_alerts()
+    }
+
     pub async fn is_acked(&self, event_id: &str) -> Result<bool, Box<dyn std::error::Error>> {
         self.ack_manager.is_acked(event_id).await
     }
+
+    /// Highest sequence number written so far.
+    pub fn last_sequence(&self) -> Result<u64, Box<dyn std::error::Error>> {
+        Ok(self.reader.last_sequence()?)
+    }
+
+    /// Unacked entries from `start_seq` up to `end_seq`, reading at most
+    /// `limit` entries, each with the event id it is streamed and acked under
+    /// (`evt-{device}-{seq}`). Also returns where the next read starts.
+    pub async fn backlog(
+        &self,
+        start_seq: u64,
+        end_seq: u64,
+        limit: usize,
+    ) -> Result<(Vec<(String, WalEntry)>, u64), Box<dyn std::error::Error>> {
+        let (entries, last_seq) = self.reader.read_range(start_seq, end_seq, limit.max(1))?;
+        let next = last_seq.map_or(end_seq.saturating_add(1), |seq| seq + 1);
+
+        let mut backlog = Vec::new();
+        for (_, entry) in entries {
+            if self.ack_manager.is_entry_acked(&entry)? {
+                continue;
+            }
+            backlog.push((crate::wal::ack_manager::event_id(&entry), entry));
+        }
+        Ok((backlog, next))
+    }
 }
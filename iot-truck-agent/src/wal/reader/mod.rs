@@ -3,19 +3,20 @@ use crate::wal::error::Result;
 use sled::{Db, Tree};
 use tracing::{info, warn};
 
+pub mod recovery;
+
 pub struct WalReader {
     tree: Tree,
     encryptor: Option<crate::wal::writer::encryption::DataEncryptor>,
 }
 
 impl WalReader {
-    pub fn new(db: &Db, enable_encryption: bool) -> Result<Self> {
+    /// `encryption_key_dir` is the keystore holding every key entries may be sealed with.
+    pub fn new(db: &Db, encryption_key_dir: Option<&str>) -> Result<Self> {
         let tree = db.open_tree("main")?;
-        let encryptor = if enable_encryption {
-            Some(crate::wal::writer::encryption::DataEncryptor::new()?)
-        } else {
-            None
-        };
+        let encryptor = encryption_key_dir
+            .map(crate::wal::writer::encryption::DataEncryptor::new)
+            .transpose()?;
 
         Ok(Self {
             tree,
@@ -29,7 +30,7 @@ impl WalReader {
 
         while let Some(Ok((key, value))) = iter.next() {
             let seq = u64::from_be_bytes(key.as_ref().try_into().unwrap());
-            let mut entry: WalEntry = deserialize_wal_entry(&value)?;
+            let mut entry: WalEntry = crate::wal::record::decode(seq, &value)?;
 
             // Decrypt if needed
             if let Some(encryptor) = &self.encryptor {
@@ -46,10 +47,38 @@ impl WalReader {
         Ok(entries)
     }
 
+    /// Up to `limit` entries with sequence numbers in `start_seq..=end_seq`,
+    /// with their sequence numbers, and the last sequence number looked at.
+    /// Entries that do not decode or decrypt are skipped and counted, so a
+    /// reader walking the range is never stuck on one.
+    pub fn read_range(&self, start_seq: u64, end_seq: u64, limit: usize) -> Result<(Vec<(u64, WalEntry)>, Option<u64>)> {
+        let mut entries = Vec::new();
+        let mut last_seq = None;
+        for item in self.tree.range(start_seq.to_be_bytes()..=end_seq.to_be_bytes()).take(limit) {
+            let (key, value) = item?;
+            let seq = u64::from_be_bytes(key.as_ref().try_into().unwrap());
+            last_seq = Some(seq);
+            let decoded = crate::wal::record::decode(seq, &value).and_then(|entry: WalEntry| {
+                match &self.encryptor {
+                    Some(encryptor) if entry.encryption.is_some() => encryptor.decrypt_entry(entry),
+                    _ => Ok(entry),
+                }
+            });
+            match decoded {
+                Ok(entry) => entries.push((seq, entry)),
+                Err(e) => {
+                    warn!(seq, error=%e, "Skipping unreadable WAL entry");
+                    metrics::counter!("wal_unreadable_entries_total").increment(1);
+                }
+            }
+        }
+        Ok((entries, last_seq))
+    }
+
     pub fn get_entry(&self, seq: u64) -> Result<Option<WalEntry>> {
         let key = seq.to_be_bytes();
         if let Some(value) = self.tree.get(key)? {
-            let mut entry: WalEntry = deserialize_wal_entry(&value)?;
+            let mut entry: WalEntry = crate::wal::record::decode(seq, &value)?;
             
             if let Some(encryptor) = &self.encryptor {
                 if entry.encryption.is_some() {
@@ -74,14 +103,36 @@ impl WalReader {
         }
     }
 
+    /// Every sequence number present, in order, without decoding the entries.
+    pub fn sequences(&self) -> Result<Vec<u64>> {
+        let mut seqs = Vec::new();
+        for item in self.tree.iter().keys() {
+            let key = item?;
+            let seq = u64::from_be_bytes(key.as_ref().try_into().unwrap());
+            seqs.push(seq);
+        }
+        Ok(seqs)
+    }
+
+    /// Removes every entry at or after `seq`. Used to drop a corrupt tail.
+    pub fn truncate_from(&self, seq: u64) -> Result<usize> {
+        let mut removed = 0;
+        for item in self.tree.range(seq.to_be_bytes()..).keys() {
+            self.tree.remove(item?)?;
+            removed += 1;
+        }
+        self.tree.flush()?;
+        warn!(seq, removed, "✂️  WAL truncated");
+        Ok(removed)
+    }
+
     pub fn mark_acked(&self, seq: u64) -> Result<()> {
-        if let Some(mut entry) = self.get_entry(seq)? {
+        let key = seq.to_be_bytes();
+        if let Some(value) = self.tree.get(key)? {
+            // Metadata is stored in the clear, so the payload stays sealed
+            let mut entry = crate::wal::record::decode(seq, &value)?;
             entry.metadata.acked = true;
-            
-            let serialized = bincode::serialize(&entry)
-                .map_err(|e| crate::wal::error::WalError::SerializeError(Box::new(e)))?;
-            let key = seq.to_be_bytes();
-            self.tree.insert(key, serialized)?;
+            self.tree.insert(key, crate::wal::record::encode(seq, &entry)?)?;
             
             info!(seq, "✅ Entry marked as acknowledged");
             Ok(())
@@ -91,13 +142,3 @@ impl WalReader {
         }
     }
 }
-
-fn deserialize_wal_entry( &[u8]) -> Result<WalEntry> {
-    if data.starts_with(b"zstd:") {
-        let compressed = base64::decode(&data[5..])?;
-        let decompressed = zstd::decode_all(&compressed[..])?;
-        bincode::deserialize(&decompressed).map_err(|e| crate::wal::error::WalError::SerializeError(Box::new(e)))
-    } else {
-        bincode::deserialize(data).map_err(|e| crate::wal::error::WalError::SerializeError(Box::new(e)))
-    }
-}
\ No newline at end of file
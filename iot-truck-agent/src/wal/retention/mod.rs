@@ -1,95 +1,290 @@
+use crate::wal::ack_manager::AckManager;
+use crate::wal::retention::policy::{Candidate, LostRange};
 use crate::wal::types::{WalEntry, EntryPriority};
 use crate::wal::error::Result;
+use serde::{Deserialize, Serialize};
 use sled::{Db, Tree};
+use std::sync::Arc;
 use tracing::{info, warn};
 
+pub mod policy;
+
+#[derive(Clone)]
 pub struct RetentionManager {
     db: Db,
     tree: Tree,
+    acks: Arc<AckManager>,
     config: RetentionConfig,
 }
 
+#[derive(Debug, Clone)]
 pub struct RetentionConfig {
     pub max_age_hours: u32,
     pub max_size_percent: f32,
     pub min_priority_to_retain: EntryPriority,
+    /// Disk usage is measured on the filesystem holding this path.
+    pub wal_path: String,
+}
+
+/// Ask the retention task to free space now instead of at the next hourly run.
+#[derive(Debug, Clone)]
+pub struct RetentionRequest {
+    /// Share of the WAL's current bytes to free, 0.0-1.0.
+    pub free_fraction: f32,
+    pub reason: String,
 }
 
 impl RetentionManager {
-    pub fn new(db: Db, tree: Tree, config: RetentionConfig) -> Self {
+    /// `acks` is the manager the stream acks through; it, not the record's
+    /// flag, says what the server has.
+    pub fn new(db: Db, tree: Tree, acks: Arc<AckManager>, config: RetentionConfig) -> Self {
         Self {
             db,
             tree,
+            acks,
             config,
         }
     }
 
+    /// Periodic age-based cleanup. Protected entries (see
+    /// [`Candidate::is_protected`]) are kept however old.
     pub async fn enforce_retention(&self) -> Result<RetentionStats> {
         let start = std::time::Instant::now();
-        let mut entries_deleted = 0;
-        let mut bytes_deleted = 0;
-        let mut batch = sled::Batch::default();
-
         let current_time = chrono::Utc::now().timestamp_nanos() as u64;
-        let disk_usage_percent = get_disk_usage_percent(); // Implement this
-
-        let mut iter = self.tree.iter();
-        let mut current_size = 0;
+        let disk_usage_percent = match disk_usage_percent(&self.config.wal_path) {
+            Ok(percent) => percent,
+            Err(e) => {
+                // Age limits still apply; only the size-based rule is skipped
+                warn!(error = %e, path = %self.config.wal_path, "Failed to read WAL disk usage");
+                0.0
+            }
+        };
+        metrics::gauge!("wal_disk_usage_percent").set(disk_usage_percent as f64);
 
-        while let Some(Ok((key, value))) = iter.next() {
+        let mut candidates = Vec::new();
+        let mut expired = Vec::new();
+        for item in self.tree.iter() {
+            let (key, value) = item?;
             let seq = u64::from_be_bytes(key.as_ref().try_into().unwrap());
-            let entry: WalEntry = deserialize_wal_entry(&value)?;
+            let entry: WalEntry = crate::wal::record::decode(seq, &value)?;
+            let candidate = Candidate::new(seq, &entry, self.acks.is_entry_acked(&entry)?);
 
             let should_delete = !entry.should_retain(current_time, disk_usage_percent) ||
                                (disk_usage_percent > self.config.max_size_percent &&
                                 entry.priority > self.config.min_priority_to_retain);
 
-            if should_delete {
-                batch.remove(key);
-                entries_deleted += 1;
-                bytes_deleted += entry.size_bytes as u64;
-            }
-
-            current_size += 1;
-            if current_size > 1000 {
-                self.tree.apply_batch(batch)?;
-                batch = sled::Batch::default();
-                current_size = 0;
-            }
+            expired.push(should_delete && !candidate.is_protected());
+            candidates.push(candidate);
         }
 
-        if !batch.is_empty() {
-            self.tree.apply_batch(batch)?;
-        }
-
-        self.db.flush()?;
+        let evict: Vec<u64> = candidates
+            .iter()
+            .zip(&expired)
+            .filter(|(_, e)| **e)
+            .map(|(c, _)| c.seq)
+            .collect();
+        let bytes_deleted = candidates
+            .iter()
+            .zip(&expired)
+            .filter(|(_, e)| **e)
+            .map(|(c, _)| c.size_bytes)
+            .sum();
+        self.remove(&evict)?;
 
         let stats = RetentionStats {
-            entries_deleted,
+            reason: "age".to_string(),
+            entries_deleted: evict.len() as u64,
             bytes_deleted,
+            downsampled: 0,
+            lost: policy::lost_ranges(&candidates, &expired),
             retention_time_ms: start.elapsed().as_millis() as u64,
         };
+        stats.record();
+        Ok(stats)
+    }
 
-        info!(entries_deleted=stats.entries_deleted, bytes_deleted=stats.bytes_deleted, "🗑️  Retention policy enforced");
-        metrics::counter!("wal_retention_runs_total").increment(1);
-        metrics::counter!("wal_entries_deleted_total").increment(stats.entries_deleted);
-        metrics::counter!("wal_bytes_deleted_total").increment(stats.bytes_deleted);
+    /// Frees space under disk pressure following [`policy::plan_eviction`].
+    pub async fn relieve_pressure(&self, request: &RetentionRequest) -> Result<RetentionStats> {
+        let start = std::time::Instant::now();
+
+        let mut candidates = Vec::new();
+        for item in self.tree.iter() {
+            let (key, value) = item?;
+            let seq = u64::from_be_bytes(key.as_ref().try_into().unwrap());
+            let entry: WalEntry = crate::wal::record::decode(seq, &value)?;
+            candidates.push(Candidate::new(seq, &entry, self.acks.is_entry_acked(&entry)?));
+        }
 
+        let total: u64 = candidates.iter().map(|c| c.size_bytes).sum();
+        let bytes_to_free = (total as f64 * request.free_fraction.clamp(0.0, 1.0) as f64) as u64;
+        let plan = policy::plan_eviction(&candidates, bytes_to_free);
+        self.remove(&plan.evict)?;
+
+        if plan.bytes_freed < bytes_to_free {
+            warn!(
+                wanted = bytes_to_free,
+                freed = plan.bytes_freed,
+                "⚠️  WAL retention could not free enough — only protected entries remain"
+            );
+        }
+
+        let stats = RetentionStats {
+            reason: request.reason.clone(),
+            entries_deleted: plan.evict.len() as u64,
+            bytes_deleted: plan.bytes_freed,
+            downsampled: plan.downsampled,
+            lost: plan.lost,
+            retention_time_ms: start.elapsed().as_millis() as u64,
+        };
+        stats.record();
         Ok(stats)
     }
+
+    fn remove(&self, seqs: &[u64]) -> Result<()> {
+        for chunk in seqs.chunks(1000) {
+            let mut batch = sled::Batch::default();
+            for seq in chunk {
+                batch.remove(&seq.to_be_bytes());
+            }
+            self.tree.apply_batch(batch)?;
+        }
+        self.db.flush()?;
+        Ok(())
+    }
 }
 
+/// Outcome of one retention run, reported upstream so the server knows
+/// which sequence ranges it will never receive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RetentionStats {
+    pub reason: String,
     pub entries_deleted: u64,
     pub bytes_deleted: u64,
+    pub downsampled: u64,
+    /// Unacked data that was deleted; acked entries are not listed.
+    pub lost: Vec<LostRange>,
     pub retention_time_ms: u64,
 }
 
-fn get_disk_usage_percent() -> f32 {
-    // Implement disk usage monitoring
-    50.0
+impl RetentionStats {
+    fn record(&self) {
+        info!(
+            reason = %self.reason,
+            entries_deleted = self.entries_deleted,
+            bytes_deleted = self.bytes_deleted,
+            downsampled = self.downsampled,
+            lost_ranges = self.lost.len(),
+            "🗑️  Retention policy enforced"
+        );
+        metrics::counter!("wal_retention_runs_total").increment(1);
+        metrics::counter!("wal_entries_deleted_total").increment(self.entries_deleted);
+        metrics::counter!("wal_bytes_deleted_total").increment(self.bytes_deleted);
+        metrics::counter!("wal_entries_downsampled_total").increment(self.downsampled);
+    }
+
+    /// Structured report of the unacked ranges for the server, if any were lost.
+    pub fn to_data_loss(&self) -> Option<truck_protocol::events::DataLoss> {
+        use truck_protocol::events as wire;
+
+        if self.lost.is_empty() {
+            return None;
+        }
+        let ranges = self
+            .lost
+            .iter()
+            .map(|r| wire::LostRange {
+                first_seq: r.first_seq,
+                last_seq: r.last_seq,
+                priority: match r.priority {
+                    EntryPriority::Critical => wire::EventPriority::Critical,
+                    EntryPriority::High => wire::EventPriority::High,
+                    EntryPriority::Medium => wire::EventPriority::Medium,
+                    EntryPriority::Low => wire::EventPriority::Low,
+                },
+                entries: r.entries,
+            })
+            .collect();
+        Some(wire::DataLoss { reason: self.reason.clone(), ranges })
+    }
+}
+
+/// Used share of the filesystem holding `path`, 0-100.
+fn disk_usage_percent(path: &str) -> nix::Result<f32> {
+    let stat = nix::sys::statvfs::statvfs(path)?;
+    // Like `df`: blocks reserved for root are left out of the total
+    let used = (stat.blocks() - stat.blocks_free()) as f64;
+    let usable = used + stat.blocks_available() as f64;
+    if usable == 0.0 {
+        return Ok(0.0);
+    }
+    Ok((used / usable * 100.0) as f32)
 }
 
-fn deserialize_wal_entry( &[u8]) -> Result<WalEntry> {
-    bincode::deserialize(data).map_err(|e| crate::wal::error::WalError::SerializeError(Box::new(e)))
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wal::types::*;
+
+    fn entry(seq: u64, priority: EntryPriority) -> WalEntry {
+        WalEntry {
+            entry_id: format!("wal-TRK-001-{seq}"),
+            entry_type: EntryType::Heartbeat,
+            payload: EntryPayload::Heartbeat(HeartbeatData {
+                uptime_sec: seq,
+                memory_used_bytes: 0,
+                disk_used_bytes: 0,
+            }),
+            timestamp: chrono::Utc::now().timestamp_nanos() as u64,
+            priority,
+            size_bytes: 100,
+            compression: CompressionInfo {
+                algorithm: "none".to_string(),
+                level: 0,
+                original_size: 100,
+                compressed_size: 100,
+            },
+            encryption: None,
+            metadata: EntryMetadata {
+                device_id: "TRK-001".to_string(),
+                truck_id: "TRK-001".to_string(),
+                sequence_number: seq,
+                source_module: "test".to_string(),
+                requires_ack: true,
+                acked: false,
+                retention_policy: RetentionPolicy::TimeBased { max_age_hours: 72 },
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_entries_acked_by_the_stream_are_evicted_first_and_not_reported_lost() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = sled::open(dir.path()).unwrap();
+        let tree = db.open_tree("main").unwrap();
+        let priorities = [EntryPriority::High, EntryPriority::High, EntryPriority::Low, EntryPriority::Low];
+        for (seq, priority) in (1u64..).zip(priorities) {
+            tree.insert(seq.to_be_bytes(), crate::wal::record::encode(seq, &entry(seq, priority)).unwrap()).unwrap();
+        }
+
+        // The stream acks by event id; the records keep `acked: false`
+        let acks = Arc::new(AckManager::new(db.clone()).unwrap());
+        acks.mark_acked("evt-TRK-001-1").await.unwrap();
+        acks.mark_acked("evt-TRK-001-3").await.unwrap();
+
+        let config = RetentionConfig {
+            max_age_hours: 72,
+            max_size_percent: 90.0,
+            min_priority_to_retain: EntryPriority::Medium,
+            wal_path: dir.path().to_string_lossy().to_string(),
+        };
+        let retention = RetentionManager::new(db.clone(), tree.clone(), acks, config);
+        let request = RetentionRequest { free_fraction: 0.5, reason: "test".to_string() };
+        let stats = retention.relieve_pressure(&request).await.unwrap();
+
+        // The delivered High and Low go; the undelivered High stays protected
+        let left: Vec<u64> = tree.iter().keys().map(|k| u64::from_be_bytes(k.unwrap().as_ref().try_into().unwrap())).collect();
+        assert_eq!(left, [2, 4]);
+        assert_eq!(stats.entries_deleted, 2);
+        assert!(stats.lost.is_empty());
+    }
+}
@@ -0,0 +1,189 @@
+use crate::wal::types::{EntryPriority, EntryType, WalEntry};
+use serde::{Deserialize, Serialize};
+
+/// What retention needs to know about an entry, without its payload.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub seq: u64,
+    pub priority: EntryPriority,
+    pub is_sensor: bool,
+    pub acked: bool,
+    pub size_bytes: u64,
+}
+
+impl Candidate {
+    /// `acked` comes from [`AckManager::is_entry_acked`](crate::wal::ack_manager::AckManager::is_entry_acked).
+    pub fn new(seq: u64, entry: &WalEntry, acked: bool) -> Self {
+        Self {
+            seq,
+            priority: entry.priority.clone(),
+            is_sensor: matches!(entry.entry_type, EntryType::Sensor),
+            acked,
+            size_bytes: entry.size_bytes as u64,
+        }
+    }
+
+    /// Safety events are never evicted, whatever the pressure, and `High`
+    /// entries only once the server has them.
+    pub fn is_protected(&self) -> bool {
+        match self.priority {
+            EntryPriority::Critical => true,
+            EntryPriority::High => !self.acked,
+            EntryPriority::Medium | EntryPriority::Low => false,
+        }
+    }
+}
+
+/// Contiguous run of unacked entries of one priority that were evicted
+/// before the server received them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LostRange {
+    pub first_seq: u64,
+    pub last_seq: u64,
+    pub priority: EntryPriority,
+    pub entries: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct EvictionPlan {
+    pub evict: Vec<u64>,
+    pub bytes_freed: u64,
+    /// Sensor entries removed by thinning rather than wholesale deletion.
+    pub downsampled: u64,
+    pub lost: Vec<LostRange>,
+}
+
+/// Picks entries to evict until `bytes_to_free` is reached. `candidates` must
+/// be in sequence order (oldest first).
+///
+/// Order: acked entries below `Critical`, then unacked `Low` and `Medium`.
+/// Within an unacked tier, every other sensor reading is dropped first so the
+/// remaining track keeps its shape, then what is left goes oldest-first.
+/// Protected entries are never selected.
+pub fn plan_eviction(candidates: &[Candidate], bytes_to_free: u64) -> EvictionPlan {
+    let mut selected = vec![false; candidates.len()];
+    let mut plan = EvictionPlan::default();
+
+    let take = |i: usize, plan: &mut EvictionPlan, selected: &mut Vec<bool>| -> bool {
+        if plan.bytes_freed >= bytes_to_free {
+            return false;
+        }
+        if !selected[i] {
+            selected[i] = true;
+            plan.bytes_freed += candidates[i].size_bytes;
+        }
+        true
+    };
+
+    // 1. Already on the server
+    for i in (0..candidates.len()).filter(|&i| candidates[i].acked && !candidates[i].is_protected()) {
+        if !take(i, &mut plan, &mut selected) {
+            break;
+        }
+    }
+
+    for tier in [EntryPriority::Low, EntryPriority::Medium] {
+        let unacked: Vec<usize> = (0..candidates.len())
+            .filter(|&i| !candidates[i].acked && candidates[i].priority == tier)
+            .collect();
+
+        // 2. Thin sensor data: keep every other reading
+        let sensors = unacked.iter().filter(|&&i| candidates[i].is_sensor);
+        for &i in sensors.skip(1).step_by(2) {
+            if !take(i, &mut plan, &mut selected) {
+                break;
+            }
+            plan.downsampled += 1;
+        }
+
+        // 3. Everything else in this tier, oldest-first
+        for &i in &unacked {
+            if !take(i, &mut plan, &mut selected) {
+                break;
+            }
+        }
+    }
+
+    plan.evict = (0..candidates.len())
+        .filter(|&i| selected[i])
+        .map(|i| candidates[i].seq)
+        .collect();
+    plan.lost = lost_ranges(candidates, &selected);
+    plan
+}
+
+/// Groups evicted unacked entries into ranges of neighbouring same-priority entries.
+pub fn lost_ranges(candidates: &[Candidate], evicted: &[bool]) -> Vec<LostRange> {
+    let mut ranges: Vec<LostRange> = Vec::new();
+    let mut prev: Option<usize> = None;
+
+    for (i, c) in candidates.iter().enumerate() {
+        if !evicted[i] || c.acked {
+            continue;
+        }
+        let extends = matches!(
+            (prev, ranges.last()),
+            (Some(p), Some(r)) if p + 1 == i && r.priority == c.priority
+        );
+        if extends {
+            let r = ranges.last_mut().unwrap();
+            r.last_seq = c.seq;
+            r.entries += 1;
+        } else {
+            ranges.push(LostRange {
+                first_seq: c.seq,
+                last_seq: c.seq,
+                priority: c.priority.clone(),
+                entries: 1,
+            });
+        }
+        prev = Some(i);
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn c(seq: u64, priority: EntryPriority, is_sensor: bool, acked: bool) -> Candidate {
+        Candidate { seq, priority, is_sensor, acked, size_bytes: 100 }
+    }
+
+    #[test]
+    fn test_eviction_order() {
+        use EntryPriority::*;
+        let candidates = vec![
+            c(1, Critical, false, false),
+            c(2, Medium, true, false),
+            c(3, Medium, true, false),
+            c(4, Medium, true, false),
+            c(5, Medium, true, false),
+            c(6, Low, false, false),
+            c(7, High, false, true),
+            c(8, Critical, false, false),
+            c(9, Critical, false, true),
+            c(10, High, false, false),
+        ];
+
+        // Acked first, then Low, then thinned sensors
+        let plan = plan_eviction(&candidates, 400);
+        assert_eq!(plan.evict, vec![3, 5, 6, 7]);
+        assert_eq!(plan.downsampled, 2);
+        assert_eq!(
+            plan.lost,
+            vec![
+                LostRange { first_seq: 3, last_seq: 3, priority: Medium, entries: 1 },
+                LostRange { first_seq: 5, last_seq: 5, priority: Medium, entries: 1 },
+                LostRange { first_seq: 6, last_seq: 6, priority: Low, entries: 1 },
+            ]
+        );
+
+        // Unbounded pressure still never touches Critical or unacked High entries
+        let plan = plan_eviction(&candidates, u64::MAX);
+        assert_eq!(plan.evict, vec![2, 3, 4, 5, 6, 7]);
+        assert_eq!(plan.lost[0], LostRange { first_seq: 2, last_seq: 5, priority: Medium, entries: 4 });
+        assert_eq!(candidates.iter().filter(|c| c.is_protected()).count(), 4);
+        assert!(candidates.iter().filter(|c| c.is_protected()).all(|c| !plan.evict.contains(&c.seq)));
+    }
+}
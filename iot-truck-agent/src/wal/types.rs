@@ -77,7 +77,7 @@ impl WalEntry {
         }
     }
 }
-use crate::sensors::types::SensorEvent;
+use crate::sensors::types::{SensorEvent, SensorType};
 use crate::camera::types::CameraFrameMeta;
 use crate::ml_edge::types::MLEvent;
 use crate::health::types::HealthEvent;
@@ -115,6 +115,7 @@ pub enum EntryType {
     Heartbeat,
     Checkpoint,
     Command,
+    SensorSummary,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -131,6 +132,14 @@ pub enum EntryPayload {
     Heartbeat(HeartbeatData),
     Checkpoint(CheckpointData),
     Command(CommandData),
+    /// Aged, already acked high-rate sensor samples folded into one window by
+    /// the compactor; kept for local investigation and never uploaded.
+    SensorSummary(SensorSummary),
+    /// Sealed form of any other payload; see `EncryptionInfo` for the key.
+    Encrypted {
+        ciphertext: Vec<u8>,
+        nonce: Vec<u8>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -153,10 +162,32 @@ pub struct CheckpointData {
 pub struct CommandData {
     pub command_id: String,
     pub command_type: String,
+    #[serde(with = "crate::wal::json_string")]
     pub parameters: serde_json::Value,
     pub issued_at: u64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SensorSummary {
+    pub sensor_id: String,
+    pub sensor_type: SensorType,
+    pub window_start: u64, // nanos, first sample
+    pub window_end: u64,   // nanos, last sample
+    pub first_seq: u64,
+    pub last_seq: u64,
+    pub event_count: u64,
+    pub fields: Vec<FieldSummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FieldSummary {
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub last: f64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompactionStats {
     pub entries_compacted: u64,
@@ -176,7 +207,6 @@ pub struct CompressionInfo {
 pub struct EncryptionInfo {
     pub algorithm: String,
     pub key_id: String,
-    pub nonce: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1,58 +1,284 @@
-use crate::wal::types::WalEntry;
+use crate::wal::error::{Result, WalError};
+use crate::wal::types::{EncryptionInfo, EntryPayload, WalEntry};
 use aes_gcm::{
-    aead::{Aead, KeyInit, OsRng},
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
     Aes256Gcm, Key, Nonce,
 };
-use base64;
-use ring::rand;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+const ALGORITHM: &str = "AES-256-GCM";
+const KEY_LEN: usize = 32;
+const CURRENT_FILE: &str = "current";
+
+/// On-disk keystore: one `{key_id}.key` file of raw key bytes per key, plus a
+/// `current` file naming the key new entries are sealed with.
+///
+/// Keys are never deleted on rotation, so entries written under an older key
+/// stay readable until retention removes them.
+pub struct KeyStore {
+    dir: PathBuf,
+}
+
+impl KeyStore {
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    pub fn current_key_id(&self) -> Result<Option<String>> {
+        match fs::read_to_string(self.dir.join(CURRENT_FILE)) {
+            Ok(id) => Ok(Some(id.trim().to_string())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Every usable key. A damaged key other than the current one is skipped
+    /// and reported, so only the entries sealed with it become unreadable.
+    pub fn load_all(&self) -> Result<HashMap<String, Vec<u8>>> {
+        let current = self.current_key_id()?;
+        let mut keys = HashMap::new();
+        for file in fs::read_dir(&self.dir)? {
+            let path = file?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("key") {
+                continue;
+            }
+            let key_id = path.file_stem().unwrap().to_string_lossy().to_string();
+            let problem = match fs::read(&path) {
+                Ok(bytes) if bytes.len() == KEY_LEN => {
+                    keys.insert(key_id, bytes);
+                    continue;
+                }
+                Ok(bytes) => format!("{} bytes instead of {}", bytes.len(), KEY_LEN),
+                Err(e) => e.to_string(),
+            };
+            if current.as_ref() == Some(&key_id) {
+                return Err(WalError::Encryption(format!("current key {} is unusable: {}", key_id, problem)));
+            }
+            warn!(key_id=%key_id, problem=%problem, "⚠️  Skipping unusable WAL key — entries sealed with it cannot be read");
+            metrics::counter!("wal_keys_unusable_total").increment(1);
+        }
+        Ok(keys)
+    }
+
+    /// Generates a new key and makes it current. Returns its id.
+    pub fn rotate(&self) -> Result<String> {
+        let key_id = format!("key-{}", chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f"));
+        let key = Aes256Gcm::generate_key(&mut OsRng);
+
+        // The key is on disk before `current` can name it, and `current` is
+        // replaced by rename, so a power cut leaves the old key or the new one
+        write_private(&self.dir.join(format!("{}.key", key_id)), key.as_slice())?;
+        sync_dir(&self.dir)?;
+        let tmp = self.dir.join(format!("{}.tmp", CURRENT_FILE));
+        write_private(&tmp, key_id.as_bytes())?;
+        fs::rename(&tmp, self.dir.join(CURRENT_FILE))?;
+        sync_dir(&self.dir)?;
+
+        info!(key_id=%key_id, "🔑 WAL encryption key rotated");
+        Ok(key_id)
+    }
+}
+
+/// Creates `path` readable by the agent only and syncs it.
+fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Makes new names and renames in `dir` durable.
+fn sync_dir(dir: &Path) -> Result<()> {
+    fs::File::open(dir)?.sync_all()?;
+    Ok(())
+}
 
 pub struct DataEncryptor {
-    key: Vec<u8>,
+    keys: HashMap<String, Vec<u8>>,
     key_id: String,
 }
 
 impl DataEncryptor {
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        // In production, get key from secure storage or KMS
-        let key = rand::SystemRandom::new();
-        let mut key_bytes = vec![0u8; 32];
-        key.fill(&mut key_bytes)?;
-        
-        let key_id = "key-1".to_string(); // In production, use actual key ID
-        
-        Ok(Self {
-            key: key_bytes,
-            key_id,
-        })
-    }
-
-    pub fn encrypt_entry(&self, mut entry: WalEntry) -> Result<WalEntry, Box<dyn std::error::Error>> {
-        let json = serde_json::to_vec(&entry.payload)?;
-        
-        let key = Key::<Aes256Gcm>::from_slice(&self.key);
-        let cipher = Aes256Gcm::new(key);
-        
+    /// Loads every key from `key_dir`, creating the first one on a fresh device.
+    pub fn new(key_dir: &str) -> Result<Self> {
+        let store = KeyStore::open(key_dir)?;
+        let key_id = match store.current_key_id()? {
+            Some(id) => id,
+            None => store.rotate()?,
+        };
+
+        let keys = store.load_all()?;
+        if !keys.contains_key(&key_id) {
+            return Err(WalError::UnknownKey(key_id));
+        }
+
+        info!(key_id=%key_id, keys=keys.len(), "🔐 WAL encryption keys loaded");
+        Ok(Self { keys, key_id })
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    pub fn encrypt_entry(&self, mut entry: WalEntry) -> Result<WalEntry> {
+        let plaintext = bincode::serialize(&entry.payload)?;
+
+        let cipher = self.cipher(&self.key_id)?;
         let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
-        let ciphertext = cipher.encrypt(&nonce, json.as_ref())?;
-        
-        // Update entry with encrypted payload
-        entry.payload = crate::wal::types::EntryPayload::Heartbeat(crate::wal::types::HeartbeatData {
-            uptime_sec: 0,
-            memory_used_bytes: 0,
-            disk_used_bytes: 0,
-        }); // Placeholder - need to create encrypted payload type
-        
-        entry.encryption = Some(crate::wal::types::EncryptionInfo {
-            algorithm: "AES-256-GCM".to_string(),
+        // Bind the ciphertext to its entry so it cannot be swapped into another one
+        let ciphertext = cipher
+            .encrypt(&nonce, Payload { msg: &plaintext, aad: entry.entry_id.as_bytes() })
+            .map_err(|_| WalError::Encryption(format!("failed to seal {}", entry.entry_id)))?;
+
+        entry.payload = EntryPayload::Encrypted {
+            ciphertext,
+            nonce: nonce.to_vec(),
+        };
+        entry.encryption = Some(EncryptionInfo {
+            algorithm: ALGORITHM.to_string(),
             key_id: self.key_id.clone(),
-            nonce: base64::encode(nonce.as_slice()),
         });
-        
+
         Ok(entry)
     }
 
-    pub fn decrypt_entry(&self, entry: WalEntry) -> Result<WalEntry, Box<dyn std::error::Error>> {
-        // Implement decryption
+    pub fn decrypt_entry(&self, entry: WalEntry) -> Result<WalEntry> {
+        self.decrypt_entry_with(entry, |plaintext| Ok(bincode::deserialize(plaintext)?))
+    }
+
+    /// Opens a payload sealed in an older record layout with that layout's
+    /// decoder.
+    pub fn decrypt_entry_with(
+        &self,
+        mut entry: WalEntry,
+        decode: impl FnOnce(&[u8]) -> Result<EntryPayload>,
+    ) -> Result<WalEntry> {
+        let Some(info) = entry.encryption.take() else {
+            return Ok(entry);
+        };
+        let EntryPayload::Encrypted { ciphertext, nonce } = &entry.payload else {
+            return Err(WalError::Encryption(format!("{} has encryption info but a plain payload", entry.entry_id)));
+        };
+        if info.algorithm != ALGORITHM {
+            return Err(WalError::Encryption(format!("unsupported algorithm {}", info.algorithm)));
+        }
+        if nonce.len() != 12 {
+            return Err(WalError::Encryption(format!("{} has a malformed nonce", entry.entry_id)));
+        }
+
+        let cipher = self.cipher(&info.key_id)?;
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: entry.entry_id.as_bytes() })
+            .map_err(|_| WalError::Encryption(format!("authentication failed for {}", entry.entry_id)))?;
+
+        entry.payload = decode(&plaintext)?;
         Ok(entry)
     }
-}
\ No newline at end of file
+
+    fn cipher(&self, key_id: &str) -> Result<Aes256Gcm> {
+        let key = self
+            .keys
+            .get(key_id)
+            .ok_or_else(|| WalError::UnknownKey(key_id.to_string()))?;
+        Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wal::types::*;
+
+    fn command_entry() -> WalEntry {
+        WalEntry {
+            entry_id: "wal-TRK-001-1".to_string(),
+            entry_type: EntryType::Command,
+            payload: EntryPayload::Command(CommandData {
+                command_id: "cmd-1".to_string(),
+                command_type: "reboot".to_string(),
+                parameters: serde_json::json!({ "delay_sec": 30 }),
+                issued_at: 1,
+            }),
+            timestamp: 1,
+            priority: EntryPriority::High,
+            size_bytes: 64,
+            compression: CompressionInfo {
+                algorithm: "none".to_string(),
+                level: 0,
+                original_size: 64,
+                compressed_size: 64,
+            },
+            encryption: None,
+            metadata: EntryMetadata {
+                device_id: "TRK-001".to_string(),
+                truck_id: "TRK-001".to_string(),
+                sequence_number: 1,
+                source_module: "test".to_string(),
+                requires_ack: true,
+                acked: false,
+                retention_policy: RetentionPolicy::TimeBased { max_age_hours: 72 },
+            },
+        }
+    }
+
+    #[test]
+    fn test_old_entries_readable_after_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_dir = dir.path().to_str().unwrap();
+
+        let sealed = DataEncryptor::new(key_dir).unwrap().encrypt_entry(command_entry()).unwrap();
+        assert!(matches!(sealed.payload, EntryPayload::Encrypted { .. }));
+
+        let old_key = sealed.encryption.as_ref().unwrap().key_id.clone();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        KeyStore::open(key_dir).unwrap().rotate().unwrap();
+
+        // Fresh process after reboot + rotation
+        let encryptor = DataEncryptor::new(key_dir).unwrap();
+        assert_ne!(encryptor.key_id(), old_key);
+
+        let opened = encryptor.decrypt_entry(sealed.clone()).unwrap();
+        assert!(matches!(
+            opened.payload,
+            EntryPayload::Command(ref c) if c.command_id == "cmd-1" && c.parameters["delay_sec"] == 30
+        ));
+        assert!(opened.encryption.is_none());
+
+        // Ciphertext moved onto another entry fails authentication
+        let mut swapped = sealed;
+        swapped.entry_id = "wal-TRK-001-2".to_string();
+        assert!(encryptor.decrypt_entry(swapped).is_err());
+    }
+
+    #[test]
+    fn test_damaged_old_key_is_skipped_but_damaged_current_key_is_not() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = KeyStore::open(dir.path()).unwrap();
+        let current = store.rotate().unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.path().join(format!("{current}.key"))).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // Torn by a power cut during an earlier rotation
+        fs::write(dir.path().join("key-old.key"), [0u8; 7]).unwrap();
+        let keys = store.load_all().unwrap();
+        assert_eq!(keys.keys().collect::<Vec<_>>(), [&current]);
+        assert!(DataEncryptor::new(dir.path().to_str().unwrap()).is_ok());
+
+        fs::write(dir.path().join(format!("{current}.key")), []).unwrap();
+        assert!(store.load_all().is_err());
+    }
+}
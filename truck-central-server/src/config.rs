@@ -39,6 +39,22 @@ pub struct MessageQueueSettings {
     pub mqtt_broker: String,
     pub mqtt_username: Option<String>,
     pub mqtt_password: Option<String>,
+    #[serde(default = "default_mqtt_topic_prefix")]
+    pub mqtt_topic_prefix: String,
+    /// `auto` tries MQTT 5 and falls back to 3.1.1; `v5` or `v3` pin the version.
+    #[serde(default)]
+    pub mqtt_version: truck_protocol::MqttVersion,
+    /// MQTT 5 topic aliases for ack topics; 0 always sends full topics.
+    #[serde(default = "default_mqtt_topic_alias_max")]
+    pub mqtt_topic_alias_max: u16,
+}
+
+fn default_mqtt_topic_prefix() -> String {
+    truck_protocol::topics::DEFAULT_PREFIX.to_string()
+}
+
+fn default_mqtt_topic_alias_max() -> u16 {
+    64
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +35,7 @@ pub enum AlertType {
     SensorFailure,
     HarshBraking,
     RapidAcceleration,
+    HarshCornering,
     SeatbeltNotFastened,
     DoorOpenWhileMoving,
     OverSpeeding,
@@ -42,6 +43,8 @@ pub enum AlertType {
     UpdateFailed,
     RollbackTriggered,
     ConfigError,
+    /// The agent evicted undelivered WAL entries; `context` lists the ranges.
+    DataLoss,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -74,6 +74,9 @@ pub struct RemoteCommand {
     pub issued_at: DateTime<Utc>,
     pub deadline: Option<DateTime<Utc>>,
     pub requires_ack: bool,
+    /// Authenticated operator who issued the command; the truck audits it.
+    #[serde(default)]
+    pub issued_by: Option<String>,
     pub status: CommandStatus,
     pub result: Option<serde_json::Value>,
     pub error: Option<String>,
@@ -92,6 +95,9 @@ pub enum CommandType {
     RunHealthCheck,
     CaptureSnapshot,
     FlushWAL,
+    RotateCertificate,
+    /// Clears OBD-II trouble codes; the truck audits who asked and what was cleared.
+    ClearDtcs,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -14,6 +14,9 @@ pub struct TelemetryData {
     pub sensors: SensorData,
     pub cameras: Option<CameraData>,
     pub scenario: Option<String>,
+    /// Interval the agent thinned this sensor to on a poor link; `None` is full rate.
+    #[serde(default)]
+    pub sample_interval_ms: Option<u32>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -23,9 +26,19 @@ pub struct SensorData {
     pub obd: ObdData,
     pub imu: ImuData,
     pub tpms: TpmsData,
+    /// Heavy-duty engine data; `None` for trucks without a J1939 reader.
+    #[serde(default)]
+    pub j1939: Option<J1939Data>,
+    /// OBD-II trouble codes; set on the readings that report a change.
+    #[serde(default)]
+    pub dtc: Option<DtcData>,
+    /// Latest output of the agent's position filter; `None` for agents
+    /// without it.
+    #[serde(default)]
+    pub position: Option<PositionData>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GpsData {
     pub latitude: f64,
     pub longitude: f64,
@@ -34,6 +47,23 @@ pub struct GpsData {
     pub heading: f32,
     pub satellites: u8,
     pub fix_quality: u8,
+    /// Fix quality for judging a position in a dispute; empty from agents
+    /// whose receiver only sends GGA and RMC.
+    #[serde(default)]
+    pub fix_type: Option<GnssFixType>,
+    #[serde(default)]
+    pub hdop: Option<f32>,
+    #[serde(default)]
+    pub vdop: Option<f32>,
+    #[serde(default)]
+    pub pdop: Option<f32>,
+    #[serde(default)]
+    pub horizontal_accuracy_m: Option<f32>,
+    #[serde(default)]
+    pub vertical_accuracy_m: Option<f32>,
+    /// Only on the readings that carry the sky view, every few seconds.
+    #[serde(default)]
+    pub satellites_in_view: Vec<SatelliteInView>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +74,9 @@ pub struct ObdData {
     pub fuel_level: u8,
     pub engine_load: u8,
     pub throttle_pos: u8,
+    /// Catalogue PIDs the agent reports beyond the fields above, by name.
+    #[serde(default)]
+    pub extra: std::collections::BTreeMap<String, f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,23 +87,22 @@ pub struct ImuData {
     pub gyro_x: f32,
     pub gyro_y: f32,
     pub gyro_z: f32,
+    /// Gravity removed, along the truck's axes; `None` until the agent has
+    /// calibrated the IMU mounting.
+    #[serde(default)]
+    pub longitudinal_g: Option<f32>,
+    #[serde(default)]
+    pub lateral_g: Option<f32>,
+    #[serde(default)]
+    pub vertical_g: Option<f32>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TpmsData {
-    pub front_left: TireSensor,
-    pub front_right: TireSensor,
-    pub rear_left: TireSensor,
-    pub rear_right: TireSensor,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TireSensor {
-    pub pressure_psi: f32,
-    pub temperature_c: f32,
-    pub battery_percent: u8,
-    pub alert: bool,
-}
+// Shared with the wire format so documents stored with the old four-wheel
+// layout still load.
+pub use truck_protocol::events::{
+    DiagnosticCode, DtcData, FreezeFrame, GnssFixType, GnssSystem, J1939Data, J1939Fault, PositionData,
+    PositionSource, SatelliteInView, TirePosition, TireSensor, TpmsData,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CameraData {
@@ -116,6 +148,5 @@ pub struct TelemetrySummary {
     pub last_accel_x: f32,
     pub last_accel_y: f32,
     pub last_accel_z: f32,
-    pub tire_pressures: [f32; 4],
-    pub tire_temperatures: [f32; 4],
+    pub tires: Vec<TireSensor>,
 }
\ No newline at end of file
@@ -0,0 +1,296 @@
+use crate::server::ingestion::idempotency::{Claim, IdempotencyStore};
+use crate::server::ingestion::translate::Translated;
+use crate::server::ingestion::IngestionEvent;
+use crate::server::storage::StorageManager;
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+use truck_protocol::{AckStatus, BatchAck, Envelope, RejectedEvent};
+
+// How many recent batch acks to remember for duplicate detection
+const RECENT_BATCHES: usize = 4096;
+
+/// Where committed events are written.
+pub trait EventStore: Send + Sync + 'static {
+    fn store_ingested(&self, event: &IngestionEvent) -> impl Future<Output = Result<(), Box<dyn std::error::Error>>> + Send;
+}
+
+impl EventStore for StorageManager {
+    fn store_ingested(&self, event: &IngestionEvent) -> impl Future<Output = Result<(), Box<dyn std::error::Error>>> + Send {
+        StorageManager::store_ingested(self, event)
+    }
+}
+
+/// Persists translated batches and produces the ack sent back to the agent.
+///
+/// An event is only listed as accepted once every record derived from it has
+/// been written to storage; processing sees it after that. Events already in
+/// storage, per the [`IdempotencyStore`], are accepted without writing again.
+pub struct BatchCommitter<S = StorageManager> {
+    storage: Arc<S>,
+    idempotency: IdempotencyStore,
+    server_sequence: Arc<AtomicU64>,
+    recent: Arc<Mutex<RecentAcks>>,
+}
+
+#[derive(Default)]
+struct RecentAcks {
+    order: VecDeque<(String, String)>,
+    acks: HashMap<(String, String), BatchAck>,
+}
+
+impl RecentAcks {
+    fn get(&self, key: &(String, String)) -> Option<&BatchAck> {
+        self.acks.get(key)
+    }
+
+    fn insert(&mut self, key: (String, String), ack: BatchAck) {
+        if self.acks.insert(key.clone(), ack).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > RECENT_BATCHES {
+            if let Some(oldest) = self.order.pop_front() {
+                self.acks.remove(&oldest);
+            }
+        }
+    }
+}
+
+impl<S> Clone for BatchCommitter<S> {
+    fn clone(&self) -> Self {
+        Self {
+            storage: self.storage.clone(),
+            idempotency: self.idempotency.clone(),
+            server_sequence: self.server_sequence.clone(),
+            recent: self.recent.clone(),
+        }
+    }
+}
+
+impl<S: EventStore> BatchCommitter<S> {
+    pub fn new(storage: Arc<S>, idempotency: IdempotencyStore) -> Self {
+        Self {
+            storage,
+            idempotency,
+            server_sequence: Arc::new(AtomicU64::new(0)),
+            recent: Arc::new(Mutex::new(RecentAcks::default())),
+        }
+    }
+
+    pub async fn commit(
+        &self,
+        envelope: &Envelope,
+        translated: Vec<Translated>,
+        tx: &broadcast::Sender<IngestionEvent>,
+    ) -> BatchAck {
+        let key = (envelope.device_id.clone(), envelope.batch_id.clone());
+
+        // A redelivered batch that was fully stored is acked again without rewriting it
+        let previous = self.recent.lock().get(&key).cloned();
+        if let Some(previous) = previous {
+            if matches!(previous.status, AckStatus::Success | AckStatus::Duplicate) {
+                let mut ack = previous;
+                ack.status = AckStatus::Duplicate;
+                info!(device_id=%envelope.device_id, batch_id=%envelope.batch_id, "🔁 Duplicate batch — re-acking");
+                return ack;
+            }
+        }
+
+        let device_id = &envelope.device_id;
+        let mut accepted = Vec::new();
+        let mut rejected = Vec::new();
+        let mut duplicates = 0;
+
+        for item in translated {
+            match self.idempotency.claim(device_id, &item.event_id, item.sequence_number).await {
+                Ok(Claim::New) => {}
+                Ok(Claim::AlreadyStored) => {
+                    duplicates += 1;
+                    accepted.push(item.event_id);
+                    continue;
+                }
+                Ok(Claim::InFlight) => {
+                    rejected.push(RejectedEvent {
+                        event_id: item.event_id,
+                        reason: "already being stored".to_string(),
+                        retryable: true,
+                    });
+                    continue;
+                }
+                Err(e) => {
+                    // Storing without a claim could write the event twice
+                    warn!(event_id=%item.event_id, error=%e, "Idempotency store unavailable");
+                    rejected.push(RejectedEvent {
+                        event_id: item.event_id,
+                        reason: format!("idempotency store unavailable: {}", e),
+                        retryable: true,
+                    });
+                    continue;
+                }
+            }
+
+            let mut failure = None;
+            for event in &item.events {
+                if let Err(e) = self.storage.store_ingested(event).await {
+                    failure = Some(e.to_string());
+                    break;
+                }
+            }
+
+            match failure {
+                None => {
+                    if let Err(e) = self.idempotency.complete(device_id, &item.event_id, item.sequence_number).await {
+                        // The pending claim expires and a replay would store it again
+                        warn!(event_id=%item.event_id, error=%e, "Failed to mark event stored");
+                    }
+                    for event in item.events {
+                        if let Err(e) = tx.send(event) {
+                            warn!("No processing subscriber for ingested event: {}", e);
+                        }
+                    }
+                    accepted.push(item.event_id);
+                }
+                Some(reason) => {
+                    warn!(event_id=%item.event_id, reason=%reason, "Failed to persist event");
+                    if let Err(e) = self.idempotency.release(device_id, &item.event_id, item.sequence_number).await {
+                        warn!(event_id=%item.event_id, error=%e, "Failed to release idempotency claim");
+                    }
+                    rejected.push(RejectedEvent {
+                        event_id: item.event_id,
+                        reason,
+                        retryable: true,
+                    });
+                }
+            }
+        }
+
+        let all_duplicates = duplicates > 0 && duplicates == accepted.len() && rejected.is_empty();
+        let mut ack = BatchAck::new(
+            &envelope.device_id,
+            &envelope.batch_id,
+            accepted,
+            rejected,
+            self.server_sequence.fetch_add(1, Ordering::Relaxed) + 1,
+            chrono::Utc::now().timestamp_nanos() as u64,
+        );
+
+        if all_duplicates {
+            info!(device_id=%envelope.device_id, batch_id=%envelope.batch_id, events=duplicates, "🔁 Replayed events already stored — acking as duplicate");
+            ack.status = AckStatus::Duplicate;
+        }
+
+        self.recent.lock().insert(key, ack.clone());
+        ack
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::ingestion::translate::EnvelopeTranslator;
+    use std::sync::atomic::AtomicUsize;
+    use truck_protocol::events::{EventPriority, GpsData, SensorReading, SensorValues, WirePayload};
+    use truck_protocol::WireEvent;
+
+    #[derive(Default)]
+    struct CountingStore {
+        writes: AtomicUsize,
+        /// The next this many writes fail.
+        failures: AtomicUsize,
+    }
+
+    impl EventStore for CountingStore {
+        fn store_ingested(&self, _event: &IngestionEvent) -> impl Future<Output = Result<(), Box<dyn std::error::Error>>> + Send {
+            let failed = self
+                .failures
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+                .is_ok();
+            if !failed {
+                self.writes.fetch_add(1, Ordering::Relaxed);
+            }
+            async move {
+                if failed {
+                    return Err("database unavailable".into());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn envelope(seqs: std::ops::RangeInclusive<u64>) -> Envelope {
+        let events = seqs
+            .map(|seq| {
+                let payload = WirePayload::Sensor(SensorReading {
+                    sensor_id: "gps-0".to_string(),
+                    values: SensorValues::Gps(GpsData {
+                        latitude: 37.7749,
+                        longitude: -122.4194,
+                        fix_quality: 1,
+                        ..Default::default()
+                    }),
+                    sample_interval_ms: None,
+                });
+                WireEvent {
+                    event_id: format!("evt-TRK-0001-{}", seq),
+                    kind: payload.kind(),
+                    timestamp: 1_700_000_000_000_000_000 + seq,
+                    priority: EventPriority::Medium,
+                    sequence_number: seq,
+                    source_module: "sensor".to_string(),
+                    payload,
+                }
+            })
+            .collect();
+        Envelope::new("TRK-0001", "batch-1", 0, events)
+    }
+
+    #[tokio::test]
+    async fn test_redelivered_batch_is_acked_as_duplicate_without_rewriting() {
+        let (tx, _rx) = broadcast::channel(16);
+        let storage = Arc::new(CountingStore::default());
+        let idempotency = IdempotencyStore::in_memory();
+        let envelope = envelope(1..=2);
+
+        let committer = BatchCommitter::new(storage.clone(), idempotency.clone());
+        let first = committer.commit(&envelope, EnvelopeTranslator::new().translate(&envelope), &tx).await;
+        assert_eq!(first.status, AckStatus::Success);
+        assert_eq!(storage.writes.load(Ordering::Relaxed), 2);
+
+        // Redelivered by the broker: answered from the recent acks
+        let again = committer.commit(&envelope, EnvelopeTranslator::new().translate(&envelope), &tx).await;
+        assert_eq!(again.status, AckStatus::Duplicate);
+        assert_eq!(again.accepted, first.accepted);
+
+        // Replayed from the agent's WAL after a server restart: answered from the idempotency store
+        let restarted = BatchCommitter::new(storage.clone(), idempotency);
+        let replay = restarted.commit(&envelope, EnvelopeTranslator::new().translate(&envelope), &tx).await;
+        assert_eq!(replay.status, AckStatus::Duplicate);
+        assert_eq!(replay.accepted, vec!["evt-TRK-0001-1", "evt-TRK-0001-2"]);
+        assert_eq!(storage.writes.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_is_rejected_for_retry_and_stored_on_resend() {
+        let (tx, _rx) = broadcast::channel(16);
+        let storage = Arc::new(CountingStore::default());
+        let committer = BatchCommitter::new(storage.clone(), IdempotencyStore::in_memory());
+
+        storage.failures.store(1, Ordering::Relaxed);
+        let batch = envelope(1..=2);
+        let ack = committer.commit(&batch, EnvelopeTranslator::new().translate(&batch), &tx).await;
+        assert_eq!(ack.status, AckStatus::Partial);
+        assert_eq!(ack.accepted, vec!["evt-TRK-0001-2"]);
+        assert_eq!(ack.retry_ids().collect::<Vec<_>>(), vec!["evt-TRK-0001-1"]);
+
+        // The agent resends only the rejected event; its claim was released
+        let resend = envelope(1..=1);
+        let ack = committer.commit(&resend, EnvelopeTranslator::new().translate(&resend), &tx).await;
+        assert_eq!(ack.status, AckStatus::Success);
+        assert_eq!(ack.accepted, vec!["evt-TRK-0001-1"]);
+        assert_eq!(storage.writes.load(Ordering::Relaxed), 2);
+    }
+}
@@ -1,23 +1,47 @@
 use axum::{
     Router,
-    routing::post,
+    routing::{get, post},
     Json,
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
 };
-use tracing::{info, error};
+use tracing::{info, error, warn};
 use crate::server::ingestion::IngestionEvent;
+use crate::server::ingestion::commit::BatchCommitter;
+use crate::server::ingestion::translate::{truck_uuid, EnvelopeTranslator};
+use crate::server::ingestion::upload::{ChunkOutcome, UploadAssembler, UploadError};
+use crate::server::storage::StorageManager;
 use tokio::sync::broadcast;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use truck_protocol::{upload, Encoding, Envelope};
+
+#[derive(Clone)]
+struct BatchState {
+    tx: broadcast::Sender<IngestionEvent>,
+    translator: Arc<tokio::sync::Mutex<EnvelopeTranslator>>,
+    committer: BatchCommitter,
+}
+
+#[derive(Clone)]
+struct BlobState {
+    assembler: UploadAssembler,
+    storage: Arc<StorageManager>,
+}
 
 #[derive(Clone)]
 pub struct HttpIngestionHandler {
     port: u16,
+    committer: BatchCommitter,
+    storage: Arc<StorageManager>,
 }
 
 impl HttpIngestionHandler {
-    pub fn new(port: u16) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new(port: u16, committer: BatchCommitter, storage: Arc<StorageManager>) -> Result<Self, Box<dyn std::error::Error>> {
         Ok(Self {
             port,
+            committer,
+            storage,
         })
     }
     
@@ -25,12 +49,31 @@ impl HttpIngestionHandler {
         info!("🚀 Starting HTTP ingestion on port {}", self.port);
         
         let tx_clone = tx.clone();
+        let batch_state = BatchState {
+            tx: tx.clone(),
+            translator: Arc::new(tokio::sync::Mutex::new(EnvelopeTranslator::new())),
+            committer: self.committer.clone(),
+        };
+        let batch_routes = Router::new()
+            .route("/ingest/batch/:device_id", post(handle_batch))
+            .with_state(batch_state);
+
+        let blob_state = BlobState {
+            assembler: UploadAssembler::new(),
+            storage: self.storage.clone(),
+        };
+        let blob_routes = Router::new()
+            .route("/ingest/blob/:device_id/:event_id", get(handle_blob_status).put(handle_blob_chunk))
+            .with_state(blob_state);
+
         let app = Router::new()
             .route("/ingest/telemetry", post(handle_telemetry))
             .route("/ingest/alert", post(handle_alert))
             .route("/ingest/ml", post(handle_ml_event))
             .route("/ingest/health", post(handle_health_status))
-            .with_state(tx_clone);
+            .with_state(tx_clone)
+            .merge(batch_routes)
+            .merge(blob_routes);
         
         let addr = SocketAddr::from(([0, 0, 0, 0], self.port));
         axum::Server::bind(&addr)
@@ -41,6 +84,107 @@ impl HttpIngestionHandler {
     }
 }
 
+// Agent batches in the shared wire format (HTTP fallback for MQTT).
+// The response body is the same ack the MQTT path publishes, in the
+// encoding the batch was sent in.
+async fn handle_batch(
+    axum::extract::State(state): axum::extract::State<BatchState>,
+    axum::extract::Path(device_id): axum::extract::Path<String>,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> Result<Response, StatusCode> {
+    let declared = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|ct| Encoding::from_content_type(ct).ok_or(StatusCode::UNSUPPORTED_MEDIA_TYPE))
+        .transpose()?;
+    let encoding = Encoding::detect(&body);
+    if declared.is_some_and(|d| d != encoding) {
+        warn!(device_id=%device_id, declared=?declared, detected=?encoding, "Content-Type does not match batch body");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let envelope = Envelope::decode_from(&device_id, &body).map_err(|e| {
+        warn!(device_id=%device_id, error=%e, "Rejected batch envelope");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let translated = state.translator.lock().await.translate(&envelope);
+    let ack = state.committer.commit(&envelope, translated, &state.tx).await;
+    let body = ack.encode_as(encoding).map_err(|e| {
+        error!(device_id=%device_id, error=%e, "Failed to encode ack");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(([(header::CONTENT_TYPE, encoding.content_type())], body).into_response())
+}
+
+// Resumable camera blob uploads; see truck_protocol::upload for the exchange.
+async fn handle_blob_status(
+    axum::extract::State(state): axum::extract::State<BlobState>,
+    axum::extract::Path((device_id, event_id)): axum::extract::Path<(String, String)>,
+) -> Result<Json<upload::UploadStatus>, StatusCode> {
+    state.assembler.status(&device_id, &event_id).await
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn handle_blob_chunk(
+    axum::extract::State(state): axum::extract::State<BlobState>,
+    axum::extract::Path((device_id, event_id)): axum::extract::Path<(String, String)>,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> Result<Response, StatusCode> {
+    let header_u64 = |name: &str| {
+        headers.get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok())
+            .ok_or(StatusCode::BAD_REQUEST)
+    };
+    let offset = header_u64(upload::HEADER_UPLOAD_OFFSET)?;
+    let total = header_u64(upload::HEADER_UPLOAD_LENGTH)?;
+    let format = headers.get(upload::HEADER_BLOB_FORMAT)
+        .and_then(|v| v.to_str().ok())
+        .filter(|f| !f.is_empty() && f.chars().all(|c| c.is_ascii_alphanumeric()))
+        .unwrap_or("bin");
+
+    let outcome = state.assembler.append(&device_id, &event_id, offset, total, format, &body).await;
+    let (data, format) = match outcome {
+        Ok(ChunkOutcome::Partial(status)) | Ok(ChunkOutcome::AlreadyStored(status)) => {
+            return Ok(Json(status).into_response());
+        }
+        Ok(ChunkOutcome::Assembled { data, format }) => (data, format),
+        Err(UploadError::OffsetMismatch(status)) => {
+            warn!(device_id=%device_id, event_id=%event_id, sent=offset, expected=status.offset, "Blob chunk out of order");
+            return Ok((StatusCode::CONFLICT, Json(status)).into_response());
+        }
+        Err(e) => {
+            warn!(device_id=%device_id, event_id=%event_id, error=%e, "Rejected blob chunk");
+            return Err(match e {
+                UploadError::TooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+                // The agent keeps the blob and retries later
+                UploadError::TooManyUploads(_) | UploadError::Busy => StatusCode::SERVICE_UNAVAILABLE,
+                _ => StatusCode::BAD_REQUEST,
+            });
+        }
+    };
+
+    let blob_store = state.storage.get_blob_store().await;
+    let stored = blob_store.lock().await
+        .store_blob(truck_uuid(&device_id), &event_id, &data, &format).await;
+    match stored {
+        Ok(key) => {
+            info!(device_id=%device_id, event_id=%event_id, bytes=data.len(), key=%key, "📦 Stored uploaded blob");
+            let status = state.assembler.finish(&device_id, &event_id, total, key).await;
+            Ok(Json(status).into_response())
+        }
+        Err(e) => {
+            // The assembled data is dropped; the agent sees 404 and resends from 0
+            error!(device_id=%device_id, event_id=%event_id, error=%e, "Failed to store uploaded blob");
+            Err(StatusCode::SERVICE_UNAVAILABLE)
+        }
+    }
+}
+
 async fn handle_telemetry(
     axum::extract::State(tx): axum::extract::State<broadcast::Sender<IngestionEvent>>,
     Json(payload): Json<crate::models::telemetry::TelemetryData>,
@@ -0,0 +1,135 @@
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+
+// Long enough to cover a week offline followed by a full WAL replay
+const STORED_TTL_SECS: usize = 8 * 24 * 3600;
+// A claim left by a crashed writer expires and the event can be stored again
+const PENDING_TTL_SECS: usize = 60;
+
+const PENDING: &str = "pending";
+const STORED: &str = "stored";
+
+/// Outcome of claiming an event for storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Claim {
+    /// Not seen before; the caller must store it, then `complete` or `release`.
+    New,
+    /// Already in storage; ack it without writing again.
+    AlreadyStored,
+    /// Another delivery of the same event is being stored right now.
+    InFlight,
+}
+
+/// Records which agent events are already in storage, keyed by device,
+/// `event_id` and `sequence_number`, so a replayed event is written once no
+/// matter which ingestion path or server instance receives it.
+///
+/// Every ingestion path claims through one multiplexed Redis connection, so
+/// a claim neither waits on the storage cache nor opens a connection.
+#[derive(Clone)]
+pub struct IdempotencyStore {
+    backend: Backend,
+}
+
+#[derive(Clone)]
+enum Backend {
+    Redis(ConnectionManager),
+    #[cfg(test)]
+    Memory(std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, String>>>),
+}
+
+impl IdempotencyStore {
+    pub async fn connect(client: redis::Client) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            backend: Backend::Redis(ConnectionManager::new(client).await?),
+        })
+    }
+
+    /// Without Redis, and without expiry; for tests.
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Self {
+            backend: Backend::Memory(Default::default()),
+        }
+    }
+
+    pub async fn claim(&self, device_id: &str, event_id: &str, sequence_number: u64) -> Result<Claim, Box<dyn std::error::Error>> {
+        let key = key(device_id, event_id, sequence_number);
+        if self.set_if_absent(&key, PENDING, PENDING_TTL_SECS).await? {
+            return Ok(Claim::New);
+        }
+        Ok(match self.get(&key).await?.as_deref() {
+            Some(STORED) => Claim::AlreadyStored,
+            Some(_) => Claim::InFlight,
+            // Pending claim expired between the two calls; retrying will win it
+            None => Claim::InFlight,
+        })
+    }
+
+    pub async fn complete(&self, device_id: &str, event_id: &str, sequence_number: u64) -> Result<(), Box<dyn std::error::Error>> {
+        let key = key(device_id, event_id, sequence_number);
+        match &self.backend {
+            Backend::Redis(conn) => {
+                let _: () = conn.clone().set_ex(key, STORED, STORED_TTL_SECS).await?;
+            }
+            #[cfg(test)]
+            Backend::Memory(map) => {
+                map.lock().unwrap().insert(key, STORED.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Gives up a claim after storing failed, so a retry can store the event.
+    pub async fn release(&self, device_id: &str, event_id: &str, sequence_number: u64) -> Result<(), Box<dyn std::error::Error>> {
+        let key = key(device_id, event_id, sequence_number);
+        match &self.backend {
+            Backend::Redis(conn) => {
+                let _: () = conn.clone().del(key).await?;
+            }
+            #[cfg(test)]
+            Backend::Memory(map) => {
+                map.lock().unwrap().remove(&key);
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets `key` only if it does not exist yet; returns whether it was set.
+    async fn set_if_absent(&self, key: &str, value: &str, ttl_secs: usize) -> Result<bool, Box<dyn std::error::Error>> {
+        match &self.backend {
+            Backend::Redis(conn) => {
+                let set: Option<String> = redis::cmd("SET")
+                    .arg(key)
+                    .arg(value)
+                    .arg("NX")
+                    .arg("EX")
+                    .arg(ttl_secs)
+                    .query_async(&mut conn.clone())
+                    .await?;
+                Ok(set.is_some())
+            }
+            #[cfg(test)]
+            Backend::Memory(map) => {
+                let mut map = map.lock().unwrap();
+                if map.contains_key(key) {
+                    return Ok(false);
+                }
+                map.insert(key.to_string(), value.to_string());
+                Ok(true)
+            }
+        }
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        match &self.backend {
+            Backend::Redis(conn) => Ok(conn.clone().get(key).await?),
+            #[cfg(test)]
+            Backend::Memory(map) => Ok(map.lock().unwrap().get(key).cloned()),
+        }
+    }
+}
+
+fn key(device_id: &str, event_id: &str, sequence_number: u64) -> String {
+    format!("ingested:{}:{}:{}", device_id, event_id, sequence_number)
+}
@@ -3,19 +3,31 @@ use crate::models::telemetry::TelemetryData;
 use crate::models::alert::Alert;
 use crate::models::ml::MlEvent;
 use crate::models::health::HealthStatus;
+use crate::server::ingestion::commit::BatchCommitter;
+use crate::server::ingestion::idempotency::IdempotencyStore;
+use crate::server::storage::StorageManager;
+use std::sync::Arc;
 use tokio::sync::broadcast;
 use tracing::{info, error};
 
+pub mod commit;
+pub mod idempotency;
 pub mod mqtt;
 pub mod http;
 pub mod websocket;
 pub mod kafka;
+pub mod translate;
+pub mod upload;
 
+#[derive(Clone)]
 pub struct IngestionManager {
     config: ServerConfig,
     tx: broadcast::Sender<IngestionEvent>,
+    committer: BatchCommitter,
+    storage: Arc<StorageManager>,
 }
 
+#[derive(Clone)]
 pub enum IngestionEvent {
     Telemetry(TelemetryData),
     Alert(Alert),
@@ -24,12 +36,16 @@ pub enum IngestionEvent {
 }
 
 impl IngestionManager {
-    pub async fn new(config: ServerConfig) -> Result<Self, Box<dyn std::error::Error>> {
+    pub async fn new(config: ServerConfig, storage: Arc<StorageManager>) -> Result<Self, Box<dyn std::error::Error>> {
         let (tx, _) = broadcast::channel(1000);
+        let idempotency = IdempotencyStore::connect(storage.get_redis_client().await).await?;
+        let committer = BatchCommitter::new(storage.clone(), idempotency);
         
         Ok(Self {
             config,
             tx,
+            committer,
+            storage,
         })
     }
     
@@ -42,6 +58,10 @@ impl IngestionManager {
                 &self.config.message_queue.mqtt_broker,
                 self.config.message_queue.mqtt_username.clone(),
                 self.config.message_queue.mqtt_password.clone(),
+                &self.config.message_queue.mqtt_topic_prefix,
+                self.config.message_queue.mqtt_version,
+                self.config.message_queue.mqtt_topic_alias_max,
+                self.committer.clone(),
             )?;
             
             let tx = self.tx.clone();
@@ -68,7 +88,7 @@ impl IngestionManager {
         }
         
         // Start HTTP ingestion
-        let http_handler = http::HttpIngestionHandler::new(self.config.server.http_port)?;
+        let http_handler = http::HttpIngestionHandler::new(self.config.server.http_port, self.committer.clone(), self.storage.clone())?;
         let tx = self.tx.clone();
         tokio::spawn(async move {
             if let Err(e) = http_handler.start(tx).await {
@@ -77,7 +97,7 @@ impl IngestionManager {
         });
         
         // Start WebSocket ingestion
-        let websocket_handler = websocket::WebSocketIngestionHandler::new(self.config.server.websocket_port)?;
+        let websocket_handler = websocket::WebSocketIngestionHandler::new(self.config.server.websocket_port, self.committer.clone())?;
         let tx = self.tx.clone();
         tokio::spawn(async move {
             if let Err(e) = websocket_handler.start(tx).await {
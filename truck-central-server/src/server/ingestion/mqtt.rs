@@ -1,109 +1,307 @@
 use rumqttc::{AsyncClient, MqttOptions, QoS, Event, EventLoop};
+use rumqttc::v5::mqttbytes::v5::{Packet as V5Packet, PublishProperties};
 use tokio::time::{sleep, Duration};
-use tracing::{info, error};
+use tracing::{info, error, warn};
 use crate::server::ingestion::IngestionEvent;
+use crate::server::ingestion::commit::BatchCommitter;
+use crate::server::ingestion::translate::EnvelopeTranslator;
 use tokio::sync::broadcast;
-use std::collections::HashMap;
+use truck_protocol::mqtt5::{self, TopicAliases};
+use truck_protocol::{BatchAck, Channel, Encoding, Envelope, MqttVersion, TopicLayout};
+
+// How long to wait for a CONNACK before deciding the broker does not do MQTT 5
+const V5_PROBE_TIMEOUT: Duration = Duration::from_secs(15);
 
 pub struct MqttIngestionHandler {
     broker: String,
     username: Option<String>,
     password: Option<String>,
+    topics: TopicLayout,
+    version: MqttVersion,
+    topic_alias_max: u16,
+    committer: BatchCommitter,
 }
 
 impl MqttIngestionHandler {
-    pub fn new(broker: &str, username: Option<String>, password: Option<String>) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new(
+        broker: &str,
+        username: Option<String>,
+        password: Option<String>,
+        topic_prefix: &str,
+        version: MqttVersion,
+        topic_alias_max: u16,
+        committer: BatchCommitter,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         Ok(Self {
             broker: broker.to_string(),
             username,
             password,
+            topics: TopicLayout::new(topic_prefix),
+            version,
+            topic_alias_max,
+            committer,
         })
     }
-    
+
+    /// Subscribes to agent batches with the configured MQTT version. In `auto`
+    /// mode MQTT 5 is tried first and 3.1.1 used if the broker refuses it.
     pub async fn start(&self, tx: broadcast::Sender<IngestionEvent>) -> Result<(), Box<dyn std::error::Error>> {
         info!("🚀 Starting MQTT ingestion from {}", self.broker);
-        
+        let client_id = format!("truck-central-server-{}", uuid::Uuid::new_v4());
+
+        if self.version != MqttVersion::V311 {
+            match self.connect_v5(&client_id).await {
+                Ok((client, eventloop, aliases)) => {
+                    info!("📡 MQTT 5 ingestion connected");
+                    self.subscribe_v5(&client).await?;
+                    let ingest = Ingest::new(self.topics.clone(), self.committer.clone(), tx);
+                    tokio::spawn(run_v5(client, eventloop, aliases, ingest));
+                    return Ok(());
+                }
+                Err(e) if self.version == MqttVersion::Auto => {
+                    warn!(error=%e, "Broker did not accept MQTT 5 — falling back to 3.1.1");
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
         let mut mqtt_options = MqttOptions::parse_url(&self.broker)?;
-        mqtt_options.set_client_id(format!("truck-central-server-{}", uuid::Uuid::new_v4()));
+        mqtt_options.set_client_id(client_id);
         mqtt_options.set_keep_alive(Duration::from_secs(30));
-        
+
         if let Some(username) = &self.username {
             mqtt_options.set_credentials(username, self.password.as_deref().unwrap_or(""));
         }
-        
+
         let (client, eventloop) = AsyncClient::new(mqtt_options, 10);
-        
-        // Subscribe to topics
-        client.subscribe("truck/+/telemetry", QoS::AtLeastOnce).await?;
-        client.subscribe("truck/+/alert", QoS::AtLeastOnce).await?;
-        client.subscribe("truck/+/ml", QoS::AtLeastOnce).await?;
-        client.subscribe("truck/+/health", QoS::AtLeastOnce).await?;
-        
-        // Start event loop
-        tokio::spawn(async move {
-            let mut event_loop = event_loop;
+
+        // Subscribe to agent batches
+        let filter = self.topics.subscription(Channel::Telemetry);
+        client.subscribe(&filter, QoS::AtLeastOnce).await?;
+        info!(filter=%filter, "📥 Subscribed to agent telemetry");
+
+        // Critical events arrive one per envelope on their own topic, QoS 2 end to end
+        let critical_filter = self.topics.subscription(Channel::Critical);
+        client.subscribe(&critical_filter, QoS::ExactlyOnce).await?;
+        info!(filter=%critical_filter, "📥 Subscribed to agent critical events");
+
+        let ingest = Ingest::new(self.topics.clone(), self.committer.clone(), tx);
+        tokio::spawn(run_v311(client, eventloop, ingest));
+
+        Ok(())
+    }
+
+    async fn connect_v5(
+        &self,
+        client_id: &str,
+    ) -> Result<(rumqttc::v5::AsyncClient, rumqttc::v5::EventLoop, TopicAliases), Box<dyn std::error::Error>> {
+        let mut mqtt_options = rumqttc::v5::MqttOptions::parse_url(&self.broker)?;
+        mqtt_options.set_client_id(client_id.to_string());
+        mqtt_options.set_keep_alive(Duration::from_secs(30));
+        if let Some(username) = &self.username {
+            mqtt_options.set_credentials(username, self.password.as_deref().unwrap_or(""));
+        }
+
+        let (client, mut eventloop) = rumqttc::v5::AsyncClient::new(mqtt_options, 10);
+        let connack = tokio::time::timeout(V5_PROBE_TIMEOUT, async {
             loop {
-                match event_loop.poll().await {
-                    Ok(Event::Incoming(rumqttc::Packet::Publish(p))) => {
-                        let topic = p.topic;
-                        let payload = p.payload;
-                        
-                        // Parse topic to get truck ID
-                        let parts: Vec<&str> = topic.split('/').collect();
-                        if parts.len() >= 3 {
-                            let truck_id_str = parts[1];
-                            let event_type = parts[2];
-                            
-                            match event_type {
-                                "telemetry" => {
-                                    if let Ok(telemetry) = serde_json::from_slice::<crate::models::telemetry::TelemetryData>(&payload) {
-                                        if let Err(e) = tx.send(IngestionEvent::Telemetry(telemetry)) {
-                                            error!("Failed to send telemetry event: {}", e);
-                                        }
-                                    }
-                                }
-                                "alert" => {
-                                    if let Ok(alert) = serde_json::from_slice::<crate::models::alert::Alert>(&payload) {
-                                        if let Err(e) = tx.send(IngestionEvent::Alert(alert)) {
-                                            error!("Failed to send alert event: {}", e);
-                                        }
-                                    }
-                                }
-                                "ml" => {
-                                    if let Ok(ml_event) = serde_json::from_slice::<crate::models::ml::MlEvent>(&payload) {
-                                        if let Err(e) = tx.send(IngestionEvent::MlEvent(ml_event)) {
-                                            error!("Failed to send ML event: {}", e);
-                                        }
-                                    }
-                                }
-                                "health" => {
-                                    if let Ok(health_status) = serde_json::from_slice::<crate::models::health::HealthStatus>(&payload) {
-                                        if let Err(e) = tx.send(IngestionEvent::HealthStatus(health_status)) {
-                                            error!("Failed to send health status: {}", e);
-                                        }
-                                    }
-                                }
-                                _ => {
-                                    error!("Unknown event type: {}", event_type);
-                                }
-                            }
+                match eventloop.poll().await {
+                    Ok(rumqttc::v5::Event::Incoming(V5Packet::ConnAck(connack))) => return Ok(connack),
+                    Ok(_) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        })
+        .await??;
+
+        let mut aliases = TopicAliases::new(self.topic_alias_max);
+        aliases.reset(connack.properties.and_then(|p| p.topic_alias_max));
+        Ok((client, eventloop, aliases))
+    }
+
+    async fn subscribe_v5(&self, client: &rumqttc::v5::AsyncClient) -> Result<(), Box<dyn std::error::Error>> {
+        use rumqttc::v5::mqttbytes::QoS as V5QoS;
+
+        let filter = self.topics.subscription(Channel::Telemetry);
+        client.subscribe(&filter, V5QoS::AtLeastOnce).await?;
+        info!(filter=%filter, "📥 Subscribed to agent telemetry");
+
+        let critical_filter = self.topics.subscription(Channel::Critical);
+        client.subscribe(&critical_filter, V5QoS::ExactlyOnce).await?;
+        info!(filter=%critical_filter, "📥 Subscribed to agent critical events");
+        Ok(())
+    }
+}
+
+/// Decodes, persists and acks one agent publish; shared by both MQTT versions.
+struct Ingest {
+    topics: TopicLayout,
+    committer: BatchCommitter,
+    translator: EnvelopeTranslator,
+    tx: broadcast::Sender<IngestionEvent>,
+}
+
+impl Ingest {
+    fn new(topics: TopicLayout, committer: BatchCommitter, tx: broadcast::Sender<IngestionEvent>) -> Self {
+        Self {
+            topics,
+            committer,
+            translator: EnvelopeTranslator::new(),
+            tx,
+        }
+    }
+
+    /// Returns the envelope and its ack, or `None` if the publish is not a batch we can read.
+    async fn batch(&mut self, topic: &str, payload: &[u8]) -> Option<(Envelope, BatchAck)> {
+        let device_id = match self.topics.parse(topic) {
+            Ok((device_id, Channel::Telemetry | Channel::Critical)) => device_id,
+            Ok(_) => return None,
+            Err(e) => {
+                warn!(error=%e, "Ignoring publish on unexpected topic");
+                return None;
+            }
+        };
+
+        let envelope = match Envelope::decode_from(device_id, payload) {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                error!(device_id, error=%e, "❌ Failed to decode batch envelope");
+                return None;
+            }
+        };
+
+        // Persist first, then tell the agent what it may drop from its WAL
+        let translated = self.translator.translate(&envelope);
+        let ack = self.committer.commit(&envelope, translated, &self.tx).await;
+
+        tracing::debug!(
+            device_id=%envelope.device_id,
+            batch_id=%envelope.batch_id,
+            accepted=ack.accepted.len(),
+            rejected=ack.rejected.len(),
+            "📥 Batch ingested and acked"
+        );
+        Some((envelope, ack))
+    }
+}
+
+async fn run_v311(client: AsyncClient, mut event_loop: EventLoop, mut ingest: Ingest) {
+    loop {
+        match event_loop.poll().await {
+            Ok(Event::Incoming(rumqttc::Packet::Publish(p))) => {
+                let Some((envelope, ack)) = ingest.batch(&p.topic, &p.payload).await else {
+                    continue;
+                };
+
+                let ack_topic = ingest.topics.ack(&envelope.device_id);
+                match ack.encode_as(Encoding::detect(&p.payload)) {
+                    Ok(payload) => {
+                        // try_publish: this task also drives the event loop, so awaiting
+                        // a full request queue here would deadlock
+                        if let Err(e) = client.try_publish(&ack_topic, QoS::AtLeastOnce, false, payload) {
+                            error!(device_id=%envelope.device_id, batch_id=%envelope.batch_id, "Failed to publish ack: {}", e);
                         }
                     }
-                    Ok(Event::Incoming(rumqttc::Packet::ConnAck(_))) => {
-                        info!("✅ MQTT connected");
-                    }
-                    Ok(Event::Incoming(rumqttc::Packet::Disconnect)) => {
-                        error!("MQTT disconnected");
+                    Err(e) => error!("Failed to encode ack: {}", e),
+                }
+            }
+            Ok(Event::Incoming(rumqttc::Packet::ConnAck(_))) => {
+                info!("✅ MQTT connected");
+            }
+            Ok(Event::Incoming(rumqttc::Packet::Disconnect)) => {
+                error!("MQTT disconnected");
+            }
+            Err(e) => {
+                error!("MQTT error: {}", e);
+                sleep(Duration::from_secs(5)).await;
+            }
+            _ => {}
+        }
+    }
+}
+
+async fn run_v5(
+    client: rumqttc::v5::AsyncClient,
+    mut event_loop: rumqttc::v5::EventLoop,
+    mut aliases: TopicAliases,
+    mut ingest: Ingest,
+) {
+    loop {
+        match event_loop.poll().await {
+            Ok(rumqttc::v5::Event::Incoming(V5Packet::Publish(p))) => {
+                let topic = String::from_utf8_lossy(&p.topic).to_string();
+                let properties = p.properties.unwrap_or_default();
+
+                if let Some(version) = mqtt5::schema_version(&properties.user_properties) {
+                    if !truck_protocol::is_supported(version) {
+                        error!(topic, version, "❌ Unsupported schema version — not ingesting");
+                        continue;
                     }
+                }
+
+                let Some((envelope, ack)) = ingest.batch(&topic, &p.payload).await else {
+                    continue;
+                };
+
+                // Answer in the agent's encoding and with the correlation it asked for.
+                // The ack always goes to the device's own ack topic: a client-chosen
+                // response topic would let one device publish acks into another's
+                let encoding = properties
+                    .content_type
+                    .as_deref()
+                    .and_then(Encoding::from_content_type)
+                    .unwrap_or_else(|| Encoding::detect(&p.payload));
+                let ack_topic = ingest.topics.ack(&envelope.device_id);
+                if properties.response_topic.as_ref().is_some_and(|t| *t != ack_topic) {
+                    warn!(device_id=%envelope.device_id, "Ignoring response topic other than the device's ack topic");
+                }
+                let payload = match ack.encode_as(encoding) {
+                    Ok(payload) => payload,
                     Err(e) => {
-                        error!("MQTT error: {}", e);
-                        sleep(Duration::from_secs(5)).await;
+                        error!("Failed to encode ack: {}", e);
+                        continue;
                     }
-                    _ => {}
+                };
+
+                let mut ack_properties = PublishProperties {
+                    content_type: Some(encoding.content_type().to_string()),
+                    user_properties: mqtt5::user_properties(),
+                    correlation_data: properties.correlation_data,
+                    ..Default::default()
+                };
+                let publish_topic = match aliases.resolve(&ack_topic) {
+                    Some((alias, send_topic)) => {
+                        ack_properties.topic_alias = Some(alias);
+                        if send_topic { ack_topic } else { String::new() }
+                    }
+                    None => ack_topic,
+                };
+
+                // try_publish: this task also drives the event loop, so awaiting
+                // a full request queue here would deadlock
+                if let Err(e) = client.try_publish_with_properties(
+                    publish_topic,
+                    rumqttc::v5::mqttbytes::QoS::AtLeastOnce,
+                    false,
+                    payload,
+                    ack_properties,
+                ) {
+                    error!(device_id=%envelope.device_id, batch_id=%envelope.batch_id, "Failed to publish ack: {}", e);
                 }
             }
-        });
-        
-        Ok(())
+            Ok(rumqttc::v5::Event::Incoming(V5Packet::ConnAck(connack))) => {
+                // The broker forgets topic aliases on reconnect
+                aliases.reset(connack.properties.and_then(|p| p.topic_alias_max));
+                info!("✅ MQTT connected");
+            }
+            Ok(rumqttc::v5::Event::Incoming(V5Packet::Disconnect(disconnect))) => {
+                error!(reason=?disconnect.reason_code, "MQTT disconnected");
+            }
+            Err(e) => {
+                error!("MQTT error: {}", e);
+                sleep(Duration::from_secs(5)).await;
+            }
+            _ => {}
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,664 @@
+use crate::models::alert::{Alert, AlertSeverity, AlertStatus, AlertType};
+use crate::models::health::{self, HealthEventMetadata, HealthStatus, HealthStatusType, ResourceUsage};
+use crate::models::ml::{HardwareType, MlEvent, MlEventMetadata, MlResult, WeatherType};
+use crate::models::telemetry::{self, SensorData, TelemetryData};
+use crate::server::ingestion::IngestionEvent;
+use chrono::{DateTime, TimeZone, Utc};
+use geo::Point;
+use std::collections::HashMap;
+use truck_protocol::events as wire;
+use truck_protocol::{Envelope, WireEvent};
+use uuid::Uuid;
+
+/// Translates agent envelopes into the server's storage models.
+///
+/// Agents stream each sensor separately, while `TelemetryData` is a full
+/// snapshot, so the translator keeps the last reading of every sensor per
+/// truck and emits a merged snapshot whenever a new reading arrives.
+pub struct EnvelopeTranslator {
+    last_sensors: HashMap<String, LastReadings>,
+}
+
+struct LastReadings {
+    sensors: SensorData,
+    gps_at: u64, // event timestamps, nanos
+    position_at: u64,
+}
+
+/// Events produced from one wire event, keyed by the originating event id.
+pub struct Translated {
+    pub event_id: String,
+    pub sequence_number: u64,
+    pub events: Vec<IngestionEvent>,
+}
+
+impl EnvelopeTranslator {
+    pub fn new() -> Self {
+        Self {
+            last_sensors: HashMap::new(),
+        }
+    }
+
+    pub fn translate(&mut self, envelope: &Envelope) -> Vec<Translated> {
+        envelope
+            .events
+            .iter()
+            .map(|event| Translated {
+                event_id: event.event_id.clone(),
+                sequence_number: event.sequence_number,
+                events: self.translate_event(&envelope.device_id, event),
+            })
+            .collect()
+    }
+
+    fn translate_event(&mut self, device_id: &str, event: &WireEvent) -> Vec<IngestionEvent> {
+        let truck_id = truck_uuid(device_id);
+        let timestamp = from_nanos(event.timestamp);
+        let record_id = record_uuid(&truck_id, &event.event_id);
+
+        match &event.payload {
+            wire::WirePayload::Sensor(reading) => {
+                let last = self.last_sensors.entry(device_id.to_string()).or_insert_with(|| LastReadings {
+                    sensors: empty_sensor_data(),
+                    gps_at: 0,
+                    position_at: 0,
+                });
+                apply_reading(&mut last.sensors, &reading.values);
+                match reading.values {
+                    // Retransmitted readings arrive late; the times only move forward
+                    wire::SensorValues::Gps(_) => last.gps_at = last.gps_at.max(event.timestamp),
+                    wire::SensorValues::Position(_) => last.position_at = last.position_at.max(event.timestamp),
+                    _ => {}
+                }
+                let sensors = &last.sensors;
+
+                // The fused position keeps going where the receiver has no fix,
+                // but a later fix wins over a stale one
+                let fused = sensors.position.as_ref().filter(|_| last.position_at > last.gps_at);
+                let (location, speed_kmh, heading) = match fused {
+                    Some(p) => (Point::new(p.longitude, p.latitude), p.speed_kmh, p.heading),
+                    None => (
+                        Point::new(sensors.gps.longitude, sensors.gps.latitude),
+                        sensors.gps.speed_kmh,
+                        sensors.gps.heading,
+                    ),
+                };
+                vec![IngestionEvent::Telemetry(TelemetryData {
+                    id: record_id,
+                    truck_id,
+                    timestamp,
+                    location,
+                    speed_kmh,
+                    heading,
+                    sensors: sensors.clone(),
+                    cameras: None,
+                    scenario: None,
+                    sample_interval_ms: reading.sample_interval_ms,
+                    created_at: Utc::now(),
+                })]
+            }
+            wire::WirePayload::Ml(detection) => {
+                let mut out = Vec::new();
+                if let Some(alert) = ml_alert(&truck_id, &event.event_id, timestamp, detection) {
+                    out.push(IngestionEvent::Alert(alert));
+                }
+                out.push(IngestionEvent::MlEvent(ml_event(
+                    record_id, truck_id, device_id, &event.event_id, timestamp, detection,
+                )));
+                out
+            }
+            wire::WirePayload::Health(report) => {
+                let mut out: Vec<IngestionEvent> = report
+                    .alerts
+                    .iter()
+                    .filter(|a| a.severity == wire::AlertSeverity::Critical)
+                    .map(|a| IngestionEvent::Alert(health_alert(&truck_id, a)))
+                    .collect();
+                out.push(IngestionEvent::HealthStatus(health_status(
+                    record_id, truck_id, device_id, timestamp, report,
+                )));
+                out
+            }
+            wire::WirePayload::DataLoss(loss) => {
+                vec![IngestionEvent::Alert(data_loss_alert(&truck_id, &event.event_id, timestamp, loss))]
+            }
+            // Camera references, heartbeats and command responses are accepted
+            // but have no storage model yet.
+            wire::WirePayload::CameraMeta(_)
+            | wire::WirePayload::CameraBlob(_)
+            | wire::WirePayload::Heartbeat(_)
+            | wire::WirePayload::CommandResponse(_) => Vec::new(),
+        }
+    }
+}
+
+impl Default for EnvelopeTranslator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Stable server-side id for an agent device id.
+pub fn truck_uuid(device_id: &str) -> Uuid {
+    Uuid::parse_str(device_id)
+        .unwrap_or_else(|_| Uuid::new_v5(&Uuid::NAMESPACE_OID, device_id.as_bytes()))
+}
+
+// Deterministic so a redelivered event maps onto the same stored record
+fn record_uuid(truck_id: &Uuid, event_id: &str) -> Uuid {
+    Uuid::new_v5(truck_id, event_id.as_bytes())
+}
+
+fn from_nanos(nanos: u64) -> DateTime<Utc> {
+    Utc.timestamp_nanos(nanos as i64)
+}
+
+fn empty_sensor_data() -> SensorData {
+    SensorData {
+        gps: telemetry::GpsData::default(),
+        obd: telemetry::ObdData {
+            rpm: 0,
+            speed_kmh: 0,
+            coolant_temp: 0,
+            fuel_level: 0,
+            engine_load: 0,
+            throttle_pos: 0,
+            extra: Default::default(),
+        },
+        imu: telemetry::ImuData {
+            accel_x: 0.0,
+            accel_y: 0.0,
+            accel_z: 0.0,
+            gyro_x: 0.0,
+            gyro_y: 0.0,
+            gyro_z: 0.0,
+            longitudinal_g: None,
+            lateral_g: None,
+            vertical_g: None,
+        },
+        tpms: telemetry::TpmsData { tires: Vec::new() },
+        j1939: None,
+        dtc: None,
+        position: None,
+    }
+}
+
+fn apply_reading(sensors: &mut SensorData, values: &wire::SensorValues) {
+    match values {
+        wire::SensorValues::Gps(g) => {
+            sensors.gps = telemetry::GpsData {
+                latitude: g.latitude,
+                longitude: g.longitude,
+                altitude: g.altitude,
+                speed_kmh: g.speed_kmh,
+                heading: g.heading,
+                satellites: g.satellites,
+                fix_quality: g.fix_quality,
+                fix_type: g.fix_type,
+                hdop: g.hdop,
+                vdop: g.vdop,
+                pdop: g.pdop,
+                horizontal_accuracy_m: g.horizontal_accuracy_m,
+                vertical_accuracy_m: g.vertical_accuracy_m,
+                satellites_in_view: g.satellites_in_view.clone(),
+            }
+        }
+        wire::SensorValues::Obd(o) => {
+            sensors.obd = telemetry::ObdData {
+                rpm: o.rpm,
+                speed_kmh: o.speed_kmh,
+                coolant_temp: o.coolant_temp,
+                fuel_level: o.fuel_level,
+                engine_load: o.engine_load,
+                throttle_pos: o.throttle_pos,
+                extra: o.extra.clone(),
+            }
+        }
+        wire::SensorValues::Imu(i) => {
+            sensors.imu = telemetry::ImuData {
+                accel_x: i.accel_x,
+                accel_y: i.accel_y,
+                accel_z: i.accel_z,
+                gyro_x: i.gyro_x,
+                gyro_y: i.gyro_y,
+                gyro_z: i.gyro_z,
+                longitudinal_g: i.longitudinal_g,
+                lateral_g: i.lateral_g,
+                vertical_g: i.vertical_g,
+            }
+        }
+        wire::SensorValues::Tpms(t) => {
+            sensors.tpms = t.clone()
+        }
+        wire::SensorValues::J1939(j) => {
+            sensors.j1939 = Some(j.clone())
+        }
+        wire::SensorValues::Dtc(d) => {
+            sensors.dtc = Some(d.clone())
+        }
+        wire::SensorValues::Position(p) => {
+            sensors.position = Some(p.clone())
+        }
+    }
+}
+
+fn ml_event(
+    id: Uuid,
+    truck_id: Uuid,
+    device_id: &str,
+    event_id: &str,
+    timestamp: DateTime<Utc>,
+    d: &wire::MlDetection,
+) -> MlEvent {
+    let result = match &d.result {
+        wire::MlResult::Drowsiness { is_drowsy, eye_closure_ratio } => MlResult::Drowsiness {
+            is_drowsy: *is_drowsy,
+            eye_closure_ratio: *eye_closure_ratio,
+        },
+        wire::MlResult::LaneDeparture { is_departing, deviation_pixels } => MlResult::LaneDeparture {
+            is_departing: *is_departing,
+            deviation_pixels: *deviation_pixels,
+        },
+        wire::MlResult::CargoTamper { is_tampered, motion_score } => MlResult::CargoTamper {
+            is_tampered: *is_tampered,
+            motion_score: *motion_score,
+        },
+        wire::MlResult::LicensePlate { plate_text, bounding_box } => MlResult::LicensePlate {
+            plate_text: plate_text.clone(),
+            bounding_box: *bounding_box,
+        },
+        wire::MlResult::Weather { weather_type, visibility_m } => MlResult::Weather {
+            weather_type: match weather_type.as_str() {
+                "Rain" => WeatherType::Rain,
+                "Fog" => WeatherType::Fog,
+                "Snow" => WeatherType::Snow,
+                "Night" => WeatherType::Night,
+                _ => WeatherType::Clear,
+            },
+            visibility_m: *visibility_m,
+        },
+        wire::MlResult::Unknown => MlResult::Unknown,
+    };
+
+    let hardware_used = match d.hardware_used.as_str() {
+        "Cuda" => HardwareType::Cuda,
+        "OpenVino" => HardwareType::OpenVino,
+        "Fallback" => HardwareType::Fallback,
+        _ => HardwareType::Cpu,
+    };
+
+    MlEvent {
+        id,
+        event_id: event_id.to_string(),
+        truck_id,
+        model_name: d.model_name.clone(),
+        model_version: d.model_version.clone(),
+        timestamp,
+        result,
+        confidence: d.confidence,
+        calibrated_confidence: d.calibrated_confidence,
+        latency_ms: d.latency_ms,
+        hardware_used,
+        metadata: MlEventMetadata {
+            device_id: device_id.to_string(),
+            truck_id: truck_id.to_string(),
+            route_id: d.route_id.clone(),
+            driver_id: d.driver_id.clone(),
+            camera_id: d.camera_id.clone(),
+            frame_timestamp: timestamp,
+            sensor_context: None,
+            cpu_usage_percent: 0.0,
+            gpu_usage_percent: 0.0,
+            memory_used_bytes: 0,
+            temperature_c: 0.0,
+            model_checksum: String::new(),
+            retry_count: 0,
+            fallback_reason: None,
+        },
+        created_at: Utc::now(),
+    }
+}
+
+fn ml_alert(
+    truck_id: &Uuid,
+    event_id: &str,
+    timestamp: DateTime<Utc>,
+    d: &wire::MlDetection,
+) -> Option<Alert> {
+    if !d.result.is_alert() {
+        return None;
+    }
+
+    let (alert_type, severity, message) = match &d.result {
+        wire::MlResult::Drowsiness { .. } => (
+            AlertType::DrowsyDriver,
+            AlertSeverity::Critical,
+            "Driver drowsiness detected",
+        ),
+        wire::MlResult::LaneDeparture { .. } => (
+            AlertType::LaneDeparture,
+            AlertSeverity::Warning,
+            "Lane departure detected",
+        ),
+        wire::MlResult::CargoTamper { .. } => (
+            AlertType::CargoTamper,
+            AlertSeverity::Critical,
+            "Cargo tampering detected",
+        ),
+        _ => return None,
+    };
+
+    let now = Utc::now();
+    Some(Alert {
+        id: Uuid::new_v5(truck_id, format!("alert-{}", event_id).as_bytes()),
+        alert_id: format!("alert-{}", event_id),
+        truck_id: *truck_id,
+        alert_type,
+        severity,
+        message: message.to_string(),
+        triggered_at: timestamp,
+        acknowledged_at: None,
+        resolved_at: None,
+        source: format!("ml:{}", d.model_name),
+        context: serde_json::json!({
+            "event_id": event_id,
+            "confidence": d.confidence,
+            "camera_id": d.camera_id,
+        }),
+        actions: Vec::new(),
+        status: AlertStatus::Triggered,
+        created_at: now,
+        updated_at: now,
+    })
+}
+
+fn health_alert(truck_id: &Uuid, a: &wire::HealthAlert) -> Alert {
+    let kind = a.alert_type.to_lowercase();
+    let alert_type = if kind.contains("temp") || kind.contains("thermal") {
+        AlertType::HighTemperature
+    } else if kind.contains("disk") {
+        AlertType::LowDiskSpace
+    } else if kind.contains("cpu") {
+        AlertType::HighCpuUsage
+    } else if kind.contains("network") || kind.contains("mqtt") {
+        AlertType::NetworkFailure
+    } else {
+        AlertType::SensorFailure
+    };
+
+    let now = Utc::now();
+    Alert {
+        id: Uuid::new_v5(truck_id, a.alert_id.as_bytes()),
+        alert_id: a.alert_id.clone(),
+        truck_id: *truck_id,
+        alert_type,
+        severity: AlertSeverity::Critical,
+        message: a.message.clone(),
+        triggered_at: from_nanos(a.triggered_at),
+        acknowledged_at: None,
+        resolved_at: None,
+        source: a.source.clone(),
+        context: serde_json::Value::Null,
+        actions: Vec::new(),
+        status: AlertStatus::Triggered,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+fn data_loss_alert(truck_id: &Uuid, event_id: &str, timestamp: DateTime<Utc>, loss: &wire::DataLoss) -> Alert {
+    let entries: u64 = loss.ranges.iter().map(|r| r.entries).sum();
+    let now = Utc::now();
+    Alert {
+        id: Uuid::new_v5(truck_id, event_id.as_bytes()),
+        alert_id: event_id.to_string(),
+        truck_id: *truck_id,
+        alert_type: AlertType::DataLoss,
+        severity: AlertSeverity::Warning,
+        message: format!(
+            "Agent WAL retention ({}) evicted {} undelivered events in {} ranges",
+            loss.reason,
+            entries,
+            loss.ranges.len()
+        ),
+        triggered_at: timestamp,
+        acknowledged_at: None,
+        resolved_at: None,
+        source: "wal".to_string(),
+        context: serde_json::to_value(&loss.ranges).unwrap_or(serde_json::Value::Null),
+        actions: Vec::new(),
+        status: AlertStatus::Triggered,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+fn health_status(
+    id: Uuid,
+    truck_id: Uuid,
+    device_id: &str,
+    timestamp: DateTime<Utc>,
+    r: &wire::HealthReport,
+) -> HealthStatus {
+    let status = match r.status {
+        wire::HealthState::Ok => HealthStatusType::Ok,
+        wire::HealthState::Warning => HealthStatusType::Warning,
+        wire::HealthState::Critical => HealthStatusType::Critical,
+        wire::HealthState::Degraded => HealthStatusType::Degraded,
+        wire::HealthState::ShutdownPending => HealthStatusType::ShutdownPending,
+    };
+
+    let alerts = r
+        .alerts
+        .iter()
+        .map(|a| health::HealthAlert {
+            alert_id: a.alert_id.clone(),
+            alert_type: a.alert_type.clone(),
+            severity: match a.severity {
+                wire::AlertSeverity::Info => health::AlertSeverity::Info,
+                wire::AlertSeverity::Warning => health::AlertSeverity::Warning,
+                wire::AlertSeverity::Critical => health::AlertSeverity::Critical,
+            },
+            message: a.message.clone(),
+            triggered_at: from_nanos(a.triggered_at),
+            source: a.source.clone(),
+            recommended_action: String::new(),
+        })
+        .collect();
+
+    HealthStatus {
+        id,
+        truck_id,
+        timestamp,
+        status,
+        resources: ResourceUsage {
+            cpu_percent: r.cpu_percent,
+            cpu_cores: r.cpu_cores,
+            memory_percent: r.memory_percent,
+            memory_used_mb: r.memory_used_mb,
+            memory_total_mb: r.memory_total_mb,
+            memory_available_mb: r.memory_total_mb.saturating_sub(r.memory_used_mb),
+            swap_percent: 0.0,
+            disk_percent: r.disk_percent,
+            disk_used_gb: r.disk_used_gb,
+            disk_total_gb: r.disk_total_gb,
+            disk_available_gb: r.disk_total_gb.saturating_sub(r.disk_used_gb),
+            temperature_c: r.temperature_c,
+            thermal_throttling: r.thermal_throttling,
+            uptime_sec: r.uptime_sec,
+            load_average: (0.0, 0.0, 0.0),
+        },
+        tasks: Vec::new(),
+        alerts,
+        actions_taken: Vec::new(),
+        metadata: HealthEventMetadata {
+            device_id: device_id.to_string(),
+            version: String::new(),
+            hostname: String::new(),
+            ip_address: String::new(),
+            mac_address: String::new(),
+            location: None,
+            hardware_model: String::new(),
+        },
+        created_at: Utc::now(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use truck_protocol::events::{
+        EventPriority, GpsData, ObdData, PositionData, PositionSource, SensorReading, SensorValues, WirePayload,
+    };
+
+    fn sensor_event(seq: u64, values: SensorValues) -> WireEvent {
+        let payload = WirePayload::Sensor(SensorReading {
+            sensor_id: "sensor-0".to_string(),
+            values,
+            sample_interval_ms: None,
+        });
+        WireEvent {
+            event_id: format!("evt-TRK-0001-{}", seq),
+            kind: payload.kind(),
+            timestamp: 1_700_000_000_000_000_000 + seq,
+            priority: EventPriority::Medium,
+            sequence_number: seq,
+            source_module: "sensor".to_string(),
+            payload,
+        }
+    }
+
+    #[test]
+    fn test_sensor_readings_merge_into_snapshot() {
+        let gps = sensor_event(1, SensorValues::Gps(GpsData {
+            latitude: 37.7749,
+            longitude: -122.4194,
+            altitude: 10.0,
+            speed_kmh: 72.0,
+            heading: 180.0,
+            satellites: 8,
+            fix_quality: 1,
+            hdop: Some(0.9),
+            horizontal_accuracy_m: Some(2.5),
+            ..Default::default()
+        }));
+        let obd = sensor_event(2, SensorValues::Obd(ObdData {
+            rpm: 1800,
+            speed_kmh: 72,
+            coolant_temp: 88,
+            fuel_level: 64,
+            engine_load: 40,
+            throttle_pos: 22,
+            extra: Default::default(),
+        }));
+        // In a tunnel, a little further on
+        let position = sensor_event(3, SensorValues::Position(PositionData {
+            latitude: 37.7740,
+            longitude: -122.4194,
+            speed_kmh: 70.0,
+            heading: 180.0,
+            covariance_m2: [40.0, 60.0, 5.0],
+            speed_std_kmh: 1.0,
+            heading_std_deg: 2.0,
+            source: PositionSource::DeadReckoned,
+            gnss_age_ms: 12_000,
+        }));
+
+        // Out of the tunnel, the receiver has a fix again
+        let fix = sensor_event(4, SensorValues::Gps(GpsData {
+            latitude: 37.7700,
+            longitude: -122.4194,
+            speed_kmh: 68.0,
+            satellites: 7,
+            fix_quality: 1,
+            ..Default::default()
+        }));
+
+        // Encode as the agent would, decode as the MQTT handler does
+        let bytes = Envelope::new("TRK-0001", "batch-1", 0, vec![gps.clone(), obd, position.clone(), fix]).encode().unwrap();
+        let envelope = Envelope::decode_from("TRK-0001", &bytes).unwrap();
+
+        let mut translator = EnvelopeTranslator::new();
+        let translated = translator.translate(&envelope);
+        assert_eq!(translated.len(), 4);
+
+        match &translated[1].events[..] {
+            [IngestionEvent::Telemetry(t)] => {
+                assert_eq!(t.truck_id, truck_uuid("TRK-0001"));
+                assert_eq!(t.sensors.obd.rpm, 1800);
+                assert_eq!(t.sensors.gps.satellites, 8);
+                assert_eq!((t.sensors.gps.hdop, t.sensors.gps.horizontal_accuracy_m), (Some(0.9), Some(2.5)));
+                assert_eq!(t.speed_kmh, 72.0);
+            }
+            _ => panic!("expected a single telemetry event"),
+        }
+        match &translated[2].events[..] {
+            [IngestionEvent::Telemetry(t)] => {
+                assert_eq!((t.location.y(), t.speed_kmh), (37.7740, 70.0));
+                assert_eq!(t.sensors.gps.latitude, 37.7749);
+                assert_eq!(t.sensors.position.as_ref().unwrap().horizontal_std_m(), 10.0);
+            }
+            _ => panic!("expected a single telemetry event"),
+        }
+        match &translated[3].events[..] {
+            [IngestionEvent::Telemetry(t)] => {
+                assert_eq!((t.location.y(), t.speed_kmh), (37.7700, 68.0));
+                assert!(t.sensors.position.is_some());
+            }
+            _ => panic!("expected a single telemetry event"),
+        }
+
+        // A retransmitted batch replays readings older than the fix
+        let envelope = Envelope::new("TRK-0001", "batch-2", 0, vec![gps, position]);
+        let translated = translator.translate(&envelope);
+        match &translated[1].events[..] {
+            [IngestionEvent::Telemetry(t)] => assert_eq!(t.location.y(), t.sensors.gps.latitude),
+            _ => panic!("expected a single telemetry event"),
+        }
+    }
+
+    #[test]
+    fn test_positive_detection_raises_alert() {
+        let payload = WirePayload::Ml(wire::MlDetection {
+            model_name: "drowsiness".to_string(),
+            model_version: "1.0.0".to_string(),
+            result: wire::MlResult::Drowsiness { is_drowsy: true, eye_closure_ratio: 0.8 },
+            confidence: 0.95,
+            calibrated_confidence: 0.92,
+            latency_ms: 12.0,
+            hardware_used: "Cpu".to_string(),
+            camera_id: "driver".to_string(),
+            driver_id: "drv-1".to_string(),
+            route_id: "route-1".to_string(),
+        });
+        let event = WireEvent {
+            event_id: "evt-TRK-0001-9".to_string(),
+            kind: payload.kind(),
+            timestamp: 1_700_000_000_000_000_000,
+            priority: EventPriority::Critical,
+            sequence_number: 9,
+            source_module: "ml".to_string(),
+            payload,
+        };
+
+        let envelope = Envelope::new("TRK-0001", "batch-2", 0, vec![event]);
+        let translated = EnvelopeTranslator::new().translate(&envelope);
+
+        match &translated[0].events[..] {
+            [IngestionEvent::Alert(a), IngestionEvent::MlEvent(m)] => {
+                assert_eq!(a.alert_type, AlertType::DrowsyDriver);
+                assert_eq!(m.event_id, "evt-TRK-0001-9");
+            }
+            _ => panic!("expected alert followed by ML event"),
+        }
+    }
+
+    #[test]
+    fn test_truck_uuid_is_stable() {
+        assert_eq!(truck_uuid("TRK-0001"), truck_uuid("TRK-0001"));
+        assert_ne!(truck_uuid("TRK-0001"), truck_uuid("TRK-0002"));
+
+        let id = Uuid::new_v4();
+        assert_eq!(truck_uuid(&id.to_string()), id);
+    }
+}
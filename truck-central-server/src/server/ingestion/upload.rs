@@ -0,0 +1,271 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::sync::Mutex;
+use truck_protocol::UploadStatus;
+
+// Largest blob accepted; a 10 s 1080p H.264 clip is well under this
+const MAX_BLOB_BYTES: u64 = 256 * 1024 * 1024;
+// Partial uploads untouched for this long are dropped; the agent starts over
+const PARTIAL_TTL: Duration = Duration::from_secs(24 * 3600);
+// How many completed uploads to remember for idempotent replies
+const RECENT_COMPLETED: usize = 4096;
+// Uploads one device may have in progress at once
+const MAX_PARTIALS_PER_DEVICE: usize = 8;
+// Bytes held across all partial uploads; `total` is only the client's claim,
+// so memory is spent as chunks arrive and capped here
+const MAX_HELD_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+#[derive(Error, Debug)]
+pub enum UploadError {
+    #[error("chunk does not start at offset {}", .0.offset)]
+    OffsetMismatch(UploadStatus),
+
+    #[error("upload length changed from {expected} to {actual}")]
+    LengthMismatch { expected: u64, actual: u64 },
+
+    #[error("blob of {0} bytes exceeds the upload limit")]
+    TooLarge(u64),
+
+    #[error("{0} already has the maximum number of uploads in progress")]
+    TooManyUploads(String),
+
+    #[error("upload buffers are full")]
+    Busy,
+}
+
+pub enum ChunkOutcome {
+    /// More chunks expected.
+    Partial(UploadStatus),
+    /// Last chunk arrived; the caller stores the blob and calls [`UploadAssembler::finish`].
+    Assembled { data: Vec<u8>, format: String },
+    /// Already stored earlier; nothing to do.
+    AlreadyStored(UploadStatus),
+}
+
+struct Partial {
+    total: u64,
+    format: String,
+    data: Vec<u8>,
+    touched: Instant,
+}
+
+#[derive(Default)]
+struct Uploads {
+    partial: HashMap<(String, String), Partial>,
+    completed: HashMap<(String, String), UploadStatus>,
+    completed_order: VecDeque<(String, String)>,
+    /// Sum of `data.len()` over `partial`.
+    held_bytes: u64,
+}
+
+/// Assembles chunked blob uploads in memory, keyed by device and event id.
+///
+/// Partials are lost on restart; the agent then sees a 404 on its status
+/// request and uploads again from offset 0.
+#[derive(Clone, Default)]
+pub struct UploadAssembler {
+    uploads: Arc<Mutex<Uploads>>,
+}
+
+impl UploadAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn status(&self, device_id: &str, event_id: &str) -> Option<UploadStatus> {
+        let uploads = self.uploads.lock().await;
+        let key = (device_id.to_string(), event_id.to_string());
+        if let Some(done) = uploads.completed.get(&key) {
+            return Some(done.clone());
+        }
+        uploads.partial.get(&key).map(|p| UploadStatus {
+            event_id: event_id.to_string(),
+            total_size: p.total,
+            offset: p.data.len() as u64,
+            stored_key: None,
+        })
+    }
+
+    pub async fn append(
+        &self,
+        device_id: &str,
+        event_id: &str,
+        offset: u64,
+        total: u64,
+        format: &str,
+        chunk: &[u8],
+    ) -> Result<ChunkOutcome, UploadError> {
+        if total > MAX_BLOB_BYTES {
+            return Err(UploadError::TooLarge(total));
+        }
+
+        let mut guard = self.uploads.lock().await;
+        let uploads = &mut *guard;
+        let held_bytes = &mut uploads.held_bytes;
+        uploads.partial.retain(|_, p| {
+            let live = p.touched.elapsed() < PARTIAL_TTL;
+            if !live {
+                *held_bytes -= p.data.len() as u64;
+            }
+            live
+        });
+
+        let key = (device_id.to_string(), event_id.to_string());
+        if let Some(done) = uploads.completed.get(&key) {
+            return Ok(ChunkOutcome::AlreadyStored(done.clone()));
+        }
+
+        if !uploads.partial.contains_key(&key) {
+            let in_progress = uploads.partial.keys().filter(|(device, _)| device == device_id).count();
+            if in_progress >= MAX_PARTIALS_PER_DEVICE {
+                return Err(UploadError::TooManyUploads(device_id.to_string()));
+            }
+        }
+        if uploads.held_bytes + chunk.len() as u64 > MAX_HELD_BYTES {
+            return Err(UploadError::Busy);
+        }
+
+        // Validate against what is held before holding anything, so a rejected
+        // first chunk leaves no upload behind
+        let received = match uploads.partial.get(&key) {
+            Some(partial) if partial.total != total => {
+                return Err(UploadError::LengthMismatch { expected: partial.total, actual: total });
+            }
+            Some(partial) => partial.data.len() as u64,
+            None => 0,
+        };
+        if offset != received || received + chunk.len() as u64 > total {
+            return Err(UploadError::OffsetMismatch(UploadStatus {
+                event_id: event_id.to_string(),
+                total_size: total,
+                offset: received,
+                stored_key: None,
+            }));
+        }
+
+        let partial = uploads.partial.entry(key.clone()).or_insert_with(|| Partial {
+            total,
+            format: format.to_string(),
+            data: Vec::new(),
+            touched: Instant::now(),
+        });
+        partial.data.extend_from_slice(chunk);
+        partial.touched = Instant::now();
+        uploads.held_bytes += chunk.len() as u64;
+
+        if (partial.data.len() as u64) < total {
+            return Ok(ChunkOutcome::Partial(UploadStatus {
+                event_id: event_id.to_string(),
+                total_size: total,
+                offset: partial.data.len() as u64,
+                stored_key: None,
+            }));
+        }
+
+        let partial = uploads.partial.remove(&key).unwrap();
+        uploads.held_bytes -= partial.data.len() as u64;
+        Ok(ChunkOutcome::Assembled { data: partial.data, format: partial.format })
+    }
+
+    /// Records a stored blob so repeated chunks and status requests answer complete.
+    pub async fn finish(&self, device_id: &str, event_id: &str, total: u64, stored_key: String) -> UploadStatus {
+        let status = UploadStatus {
+            event_id: event_id.to_string(),
+            total_size: total,
+            offset: total,
+            stored_key: Some(stored_key),
+        };
+
+        let mut uploads = self.uploads.lock().await;
+        let key = (device_id.to_string(), event_id.to_string());
+        if uploads.completed.insert(key.clone(), status.clone()).is_none() {
+            uploads.completed_order.push_back(key);
+        }
+        while uploads.completed_order.len() > RECENT_COMPLETED {
+            if let Some(oldest) = uploads.completed_order.pop_front() {
+                uploads.completed.remove(&oldest);
+            }
+        }
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_resume_after_lost_chunk() {
+        let assembler = UploadAssembler::new();
+        let blob: Vec<u8> = (0..10u8).collect();
+
+        assert!(matches!(
+            assembler.append("TRK-1", "evt-1", 0, 10, "jpeg", &blob[..4]).await,
+            Ok(ChunkOutcome::Partial(ref s)) if s.offset == 4
+        ));
+
+        // The agent reconnects believing the second chunk never arrived and skips ahead
+        match assembler.append("TRK-1", "evt-1", 8, 10, "jpeg", &blob[8..]).await {
+            Err(UploadError::OffsetMismatch(status)) => assert_eq!(status.offset, 4),
+            _ => panic!("expected offset mismatch"),
+        }
+        assert_eq!(assembler.status("TRK-1", "evt-1").await.unwrap().offset, 4);
+
+        let data = match assembler.append("TRK-1", "evt-1", 4, 10, "jpeg", &blob[4..]).await {
+            Ok(ChunkOutcome::Assembled { data, .. }) => data,
+            _ => panic!("expected assembled blob"),
+        };
+        assert_eq!(data, blob);
+
+        assembler.finish("TRK-1", "evt-1", 10, "trucks/x/blobs/evt-1.jpeg".to_string()).await;
+        // A full resend after an agent restart is recognised, not stored again
+        assert!(matches!(
+            assembler.append("TRK-1", "evt-1", 0, 10, "jpeg", &blob).await,
+            Ok(ChunkOutcome::AlreadyStored(ref s)) if s.is_complete()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_partial_uploads_are_capped_per_device() {
+        let assembler = UploadAssembler::new();
+        for i in 0..MAX_PARTIALS_PER_DEVICE {
+            let event_id = format!("evt-{}", i);
+            // A huge claimed total costs nothing until the bytes arrive
+            let outcome = assembler.append("TRK-1", &event_id, 0, MAX_BLOB_BYTES, "h264", &[0; 4]).await;
+            assert!(matches!(outcome, Ok(ChunkOutcome::Partial(_))));
+        }
+        assert_eq!(assembler.uploads.lock().await.held_bytes, 4 * MAX_PARTIALS_PER_DEVICE as u64);
+
+        assert!(matches!(
+            assembler.append("TRK-1", "evt-new", 0, 10, "jpeg", &[0; 4]).await,
+            Err(UploadError::TooManyUploads(_))
+        ));
+        // Uploads already under way continue, and other devices are unaffected
+        assert!(assembler.append("TRK-1", "evt-0", 4, MAX_BLOB_BYTES, "h264", &[0; 4]).await.is_ok());
+        assert!(assembler.append("TRK-2", "evt-0", 0, 10, "jpeg", &[0; 4]).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rejected_first_chunk_holds_no_upload() {
+        let assembler = UploadAssembler::new();
+        // A first chunk at a non-zero offset, and one longer than the claimed total
+        assert!(matches!(
+            assembler.append("TRK-1", "evt-1", 4, 10, "jpeg", &[0; 4]).await,
+            Err(UploadError::OffsetMismatch(ref s)) if s.offset == 0
+        ));
+        assert!(matches!(
+            assembler.append("TRK-1", "evt-2", 0, 2, "jpeg", &[0; 4]).await,
+            Err(UploadError::OffsetMismatch(_))
+        ));
+        assert!(assembler.status("TRK-1", "evt-1").await.is_none());
+        assert!(assembler.uploads.lock().await.partial.is_empty());
+
+        // Rejected chunks do not count against the per-device cap either
+        for _ in 0..MAX_PARTIALS_PER_DEVICE {
+            assert!(assembler.append("TRK-1", "evt-bad", 1, 10, "jpeg", &[0; 4]).await.is_err());
+        }
+        assert!(assembler.append("TRK-1", "evt-1", 0, 10, "jpeg", &[0; 4]).await.is_ok());
+    }
+}
@@ -1,19 +1,24 @@
 use warp::Filter;
 use tokio_tungstenite::tungstenite::Message;
-use tracing::{info, error};
+use tracing::{info, error, warn};
 use crate::server::ingestion::IngestionEvent;
+use crate::server::ingestion::commit::BatchCommitter;
+use crate::server::ingestion::translate::EnvelopeTranslator;
 use tokio::sync::broadcast;
 use std::net::SocketAddr;
+use truck_protocol::{Encoding, Envelope};
 
 #[derive(Clone)]
 pub struct WebSocketIngestionHandler {
     port: u16,
+    committer: BatchCommitter,
 }
 
 impl WebSocketIngestionHandler {
-    pub fn new(port: u16) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new(port: u16, committer: BatchCommitter) -> Result<Self, Box<dyn std::error::Error>> {
         Ok(Self {
             port,
+            committer,
         })
     }
     
@@ -21,11 +26,13 @@ impl WebSocketIngestionHandler {
         info!("🚀 Starting WebSocket ingestion on port {}", self.port);
         
         let tx_clone = tx.clone();
+        let committer = self.committer.clone();
         let ws_route = warp::path("ingest")
             .and(warp::ws())
             .map(move |ws: warp::ws::Ws| {
                 let tx = tx_clone.clone();
-                ws.on_upgrade(move |websocket| handle_websocket_connection(websocket, tx))
+                let committer = committer.clone();
+                ws.on_upgrade(move |websocket| handle_websocket_connection(websocket, tx, committer))
             });
         
         let addr = SocketAddr::from(([0, 0, 0, 0], self.port));
@@ -40,8 +47,10 @@ impl WebSocketIngestionHandler {
 async fn handle_websocket_connection(
     websocket: warp::ws::WebSocket,
     tx: broadcast::Sender<IngestionEvent>,
+    committer: BatchCommitter,
 ) {
     let (mut ws_tx, mut ws_rx) = websocket.split();
+    let mut translator = EnvelopeTranslator::new();
     
     // Send welcome message
     if let Err(e) = ws_tx.send(Message::text("Connected to Truck Central Server")).await {
@@ -53,7 +62,40 @@ async fn handle_websocket_connection(
     while let Some(result) = ws_rx.next().await {
         match result {
             Ok(msg) => {
-                if msg.is_text() {
+                // Agent batches: binary frames are JSON or CBOR, told apart by the
+                // header; a text frame is a JSON envelope only if it is shaped like one
+                let is_envelope = msg.is_binary()
+                    || msg
+                        .to_text()
+                        .ok()
+                        .and_then(|t| serde_json::from_str::<serde_json::Value>(t).ok())
+                        .map_or(false, |v| v.get("protocol_version").is_some());
+                if is_envelope {
+                    let bytes = msg.into_data();
+                    let encoding = Encoding::detect(&bytes);
+                    let envelope = match Envelope::decode(&bytes) {
+                        Ok(envelope) => envelope,
+                        Err(e) => {
+                            warn!(error=%e, "Rejected batch envelope");
+                            continue;
+                        }
+                    };
+
+                    let translated = translator.translate(&envelope);
+                    let ack = committer.commit(&envelope, translated, &tx).await;
+                    let reply = match ack.encode_as(encoding) {
+                        Ok(payload) if encoding == Encoding::Cbor => Message::binary(payload),
+                        Ok(payload) => Message::text(String::from_utf8_lossy(&payload).into_owned()),
+                        Err(e) => {
+                            error!("Failed to encode ack: {}", e);
+                            continue;
+                        }
+                    };
+                    if let Err(e) = ws_tx.send(reply).await {
+                        error!("Failed to send ack: {}", e);
+                        break;
+                    }
+                } else if msg.is_text() {
                     let text = msg.to_text().unwrap_or_default();
                     
                     // Try to parse as JSON and determine event type
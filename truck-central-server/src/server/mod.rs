@@ -4,6 +4,7 @@ use crate::server::storage::StorageManager;
 use crate::server::processing::ProcessingManager;
 use crate::server::realtime::RealtimeManager;
 use crate::server::api;
+use std::sync::Arc;
 use tokio::sync::broadcast;
 use tracing::{info, error};
 
@@ -25,8 +26,8 @@ pub struct CentralServer {
 
 impl CentralServer {
     pub async fn new(config: ServerConfig) -> Result<Self, Box<dyn std::error::Error>> {
-        let ingestion_manager = IngestionManager::new(config.clone()).await?;
         let storage_manager = StorageManager::new(config.clone()).await?;
+        let ingestion_manager = IngestionManager::new(config.clone(), Arc::new(storage_manager.clone())).await?;
         let processing_manager = ProcessingManager::new(config.clone()).await?;
         let realtime_manager = RealtimeManager::new(config.clone()).await?;
         
@@ -5,6 +5,10 @@ use crate::models::alert::{Alert, AlertType, AlertSeverity, AlertStatus, AlertAc
 use uuid::Uuid;
 use chrono::Utc;
 
+// Gravity-free acceleration along the truck (g); same limits as the agent
+const HARSH_BRAKING_G: f32 = 0.4;
+const RAPID_ACCELERATION_G: f32 = 0.3;
+
 pub struct AlertProcessor;
 
 impl AlertProcessor {
@@ -15,11 +19,12 @@ impl AlertProcessor {
     pub async fn process_telemetry(&self, telemetry: &TelemetryData) -> Result<Vec<Alert>, Box<dyn std::error::Error>> {
         let mut alerts = Vec::new();
         
+        // Raw axes include gravity and may be the chip's; without the
+        // calibrated ones there is nothing to judge
+        let longitudinal_g = telemetry.sensors.imu.longitudinal_g.unwrap_or(0.0);
+
         // Check for harsh braking
-        let g_force = (telemetry.sensors.imu.accel_x.powi(2) + 
-                      telemetry.sensors.imu.accel_y.powi(2) + 
-                      telemetry.sensors.imu.accel_z.powi(2)).sqrt();
-        if g_force > 0.8 {
+        if longitudinal_g <= -HARSH_BRAKING_G {
             alerts.push(Alert {
                 id: Uuid::new_v4(),
                 alert_id: format!("alert-{}-harsh_braking-{}", telemetry.truck_id, Utc::now().timestamp_nanos()),
@@ -32,7 +37,7 @@ impl AlertProcessor {
                 resolved_at: None,
                 source: "telemetry_processor".to_string(),
                 context: serde_json::json!({
-                    "g_force": g_force,
+                    "g_force": -longitudinal_g,
                     "speed_kmh": telemetry.speed_kmh,
                     "location": {
                         "lat": telemetry.location.y(),
@@ -60,7 +65,7 @@ impl AlertProcessor {
         }
         
         // Check for rapid acceleration
-        if telemetry.sensors.imu.accel_x > 0.6 {
+        if longitudinal_g >= RAPID_ACCELERATION_G {
             alerts.push(Alert {
                 id: Uuid::new_v4(),
                 alert_id: format!("alert-{}-rapid_acceleration-{}", telemetry.truck_id, Utc::now().timestamp_nanos()),
@@ -73,7 +78,7 @@ impl AlertProcessor {
                 resolved_at: None,
                 source: "telemetry_processor".to_string(),
                 context: serde_json::json!({
-                    "acceleration": telemetry.sensors.imu.accel_x,
+                    "acceleration": longitudinal_g,
                     "speed_kmh": telemetry.speed_kmh,
                     "location": {
                         "lat": telemetry.location.y(),
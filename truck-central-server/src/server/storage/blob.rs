@@ -27,12 +27,24 @@ impl BlobStore {
             bucket: self.bucket.clone(),
             key: key.clone(),
             body: Some(frame_data.to_vec().into()),
-            content_type: Some(match format {
-                "jpg" | "jpeg" => "image/jpeg",
-                "png" => "image/png",
-                "h264" => "video/h264",
-                _ => "application/octet-stream",
-            }.to_string()),
+            content_type: Some(content_type(format).to_string()),
+            ..Default::default()
+        };
+        
+        self.client.put_object(request).await?;
+        Ok(key)
+    }
+    
+    // Keyed by event id so a blob re-uploaded after an agent restart
+    // overwrites the same object instead of creating a second one.
+    pub async fn store_blob(&mut self, truck_id: Uuid, event_id: &str, data: &[u8], format: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let key = format!("trucks/{}/blobs/{}.{}", truck_id, event_id, format);
+        
+        let request = PutObjectRequest {
+            bucket: self.bucket.clone(),
+            key: key.clone(),
+            body: Some(data.to_vec().into()),
+            content_type: Some(content_type(format).to_string()),
             ..Default::default()
         };
         
@@ -57,4 +69,13 @@ impl BlobStore {
         // For now, return a dummy URL
         Ok(format!("https://{}/{}?expires={}", self.bucket, key, expires_in))
     }
-}
\ No newline at end of file
+}
+
+fn content_type(format: &str) -> &'static str {
+    match format {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "h264" => "video/h264",
+        _ => "application/octet-stream",
+    }
+}
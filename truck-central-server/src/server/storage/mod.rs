@@ -17,6 +17,7 @@ pub mod document;
 pub mod blob;
 pub mod cache;
 
+#[derive(Clone)]
 pub struct StorageManager {
     config: ServerConfig,
     mongo_client: MongoClient,
@@ -74,6 +75,16 @@ impl StorageManager {
         })
     }
     
+    /// Persists one ingested event in the stores that back its model.
+    pub async fn store_ingested(&self, event: &crate::server::ingestion::IngestionEvent) -> Result<(), Box<dyn std::error::Error>> {
+        match event {
+            crate::server::ingestion::IngestionEvent::Telemetry(t) => self.store_telemetry(t).await,
+            crate::server::ingestion::IngestionEvent::Alert(a) => self.store_alert(a).await,
+            crate::server::ingestion::IngestionEvent::MlEvent(m) => self.store_ml_event(m).await,
+            crate::server::ingestion::IngestionEvent::HealthStatus(h) => self.store_health_status(h).await,
+        }
+    }
+
     pub async fn store_telemetry(&self, telemetry: &TelemetryData) -> Result<(), Box<dyn std::error::Error>> {
         // Store in document DB
         {
@@ -172,4 +183,8 @@ impl StorageManager {
     pub async fn get_cache_store(&self) -> Arc<Mutex<cache::CacheStore>> {
         self.cache_store.clone()
     }
+    
+    pub async fn get_redis_client(&self) -> RedisClient {
+        self.redis_client.clone()
+    }
 }
\ No newline at end of file
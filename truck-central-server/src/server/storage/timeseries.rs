@@ -21,9 +21,14 @@ impl TimeseriesStore {
     }
     
     pub async fn store_telemetry(&mut self, telemetry: &TelemetryData) -> Result<(), Box<dyn std::error::Error>> {
-        let point = DataPoint::builder("telemetry")
+        let mut point = DataPoint::builder("telemetry")
             .tag("truck_id", telemetry.truck_id.to_string())
             .tag("scenario", telemetry.scenario.as_deref().unwrap_or("unknown"))
+            // Lets queries prefer the full-rate backfill over thinned points
+            .tag("resolution", match telemetry.sample_interval_ms {
+                Some(interval_ms) => format!("{}ms", interval_ms),
+                None => "full".to_string(),
+            })
             .field("speed_kmh", telemetry.speed_kmh)
             .field("heading", telemetry.heading)
             .field("gps_latitude", telemetry.location.y())
@@ -36,16 +41,85 @@ impl TimeseriesStore {
             .field("imu_accel_x", telemetry.sensors.imu.accel_x)
             .field("imu_accel_y", telemetry.sensors.imu.accel_y)
             .field("imu_accel_z", telemetry.sensors.imu.accel_z)
-            .field("tpms_front_left_pressure", telemetry.sensors.tpms.front_left.pressure_psi)
-            .field("tpms_front_left_temperature", telemetry.sensors.tpms.front_left.temperature_c)
-            .field("tpms_front_right_pressure", telemetry.sensors.tpms.front_right.pressure_psi)
-            .field("tpms_front_right_temperature", telemetry.sensors.tpms.front_right.temperature_c)
-            .field("tpms_rear_left_pressure", telemetry.sensors.tpms.rear_left.pressure_psi)
-            .field("tpms_rear_left_temperature", telemetry.sensors.tpms.rear_left.temperature_c)
-            .field("tpms_rear_right_pressure", telemetry.sensors.tpms.rear_right.pressure_psi)
-            .field("tpms_rear_right_temperature", telemetry.sensors.tpms.rear_right.temperature_c)
-            .timestamp(telemetry.timestamp.timestamp_nanos())
-            .build()?;
+            .timestamp(telemetry.timestamp.timestamp_nanos());
+        let gps = &telemetry.sensors.gps;
+        let accuracy = [
+            ("gps_hdop", gps.hdop),
+            ("gps_vdop", gps.vdop),
+            ("gps_pdop", gps.pdop),
+            ("gps_horizontal_accuracy_m", gps.horizontal_accuracy_m),
+            ("gps_vertical_accuracy_m", gps.vertical_accuracy_m),
+        ];
+        for (name, value) in accuracy {
+            if let Some(value) = value {
+                point = point.field(name, value as f64);
+            }
+        }
+        if let Some(fix_type) = gps.fix_type {
+            point = point.field("gps_fix_type", format!("{:?}", fix_type));
+        }
+        let snrs: Vec<f64> = gps.satellites_in_view.iter().filter_map(|s| s.snr_dbhz).map(f64::from).collect();
+        if !snrs.is_empty() {
+            point = point
+                .field("gps_satellites_tracked", snrs.len() as i64)
+                .field("gps_snr_mean_dbhz", snrs.iter().sum::<f64>() / snrs.len() as f64);
+        }
+        let imu = &telemetry.sensors.imu;
+        let vehicle_axes = [
+            ("imu_longitudinal_g", imu.longitudinal_g),
+            ("imu_lateral_g", imu.lateral_g),
+            ("imu_vertical_g", imu.vertical_g),
+        ];
+        for (name, value) in vehicle_axes {
+            if let Some(value) = value {
+                point = point.field(name, value as f64);
+            }
+        }
+        if let Some(position) = &telemetry.sensors.position {
+            point = point
+                .field("position_latitude", position.latitude)
+                .field("position_longitude", position.longitude)
+                .field("position_std_m", position.horizontal_std_m() as f64)
+                .field("position_source", format!("{:?}", position.source))
+                .field("position_gnss_age_ms", position.gnss_age_ms as i64);
+        }
+        for (name, value) in &telemetry.sensors.obd.extra {
+            point = point.field(format!("obd_{}", name), *value);
+        }
+        // One field pair per tire, e.g. tpms_u1_a0_p3_pressure for the first
+        // trailer's front axle, right outer
+        for tire in &telemetry.sensors.tpms.tires {
+            let label = tire.position.label();
+            point = point
+                .field(format!("tpms_{}_pressure", label), tire.pressure_psi)
+                .field(format!("tpms_{}_temperature", label), tire.temperature_c);
+        }
+        if let Some(j1939) = &telemetry.sensors.j1939 {
+            let fields = [
+                ("j1939_engine_speed_rpm", j1939.engine_speed_rpm.map(f64::from)),
+                ("j1939_wheel_speed_kmh", j1939.wheel_speed_kmh.map(f64::from)),
+                ("j1939_fuel_rate_lph", j1939.fuel_rate_lph.map(f64::from)),
+                ("j1939_total_fuel_used_l", j1939.total_fuel_used_l),
+                ("j1939_odometer_km", j1939.odometer_km),
+                ("j1939_engine_hours", j1939.engine_hours),
+                ("j1939_coolant_temp_c", j1939.coolant_temp_c.map(f64::from)),
+                ("j1939_oil_temp_c", j1939.oil_temp_c.map(f64::from)),
+            ];
+            for (name, value) in fields {
+                if let Some(value) = value {
+                    point = point.field(name, value);
+                }
+            }
+            point = point.field("j1939_active_faults", j1939.active_faults.len() as i64);
+        }
+        if let Some(dtc) = &telemetry.sensors.dtc {
+            point = point
+                .field("dtc_mil_on", dtc.mil_on)
+                .field("dtc_stored", dtc.stored.len() as i64)
+                .field("dtc_pending", dtc.pending.len() as i64)
+                .field("dtc_permanent", dtc.permanent.len() as i64);
+        }
+        let point = point.build()?;
         
         self.client.write(&self.org, &self.bucket, stream::iter(vec![point])).await?;
         Ok(())
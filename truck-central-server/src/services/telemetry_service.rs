@@ -78,18 +78,7 @@ impl TelemetryService {
             last_accel_x: sensors.imu.accel_x,
             last_accel_y: sensors.imu.accel_y,
             last_accel_z: sensors.imu.accel_z,
-            tire_pressures: [
-                sensors.tpms.front_left.pressure_psi,
-                sensors.tpms.front_right.pressure_psi,
-                sensors.tpms.rear_left.pressure_psi,
-                sensors.tpms.rear_right.pressure_psi,
-            ],
-            tire_temperatures: [
-                sensors.tpms.front_left.temperature_c,
-                sensors.tpms.front_right.temperature_c,
-                sensors.tpms.rear_left.temperature_c,
-                sensors.tpms.rear_right.temperature_c,
-            ],
+            tires: sensors.tpms.tires.clone(),
         };
 
         metrics::counter!("telemetry_summaries_retrieved_total").increment(1);
@@ -189,20 +178,6 @@ impl TelemetryService {
         
         let telemetry = store.get_latest_telemetry(truck_id).await?;
         
-        let tire_pressures = [
-            telemetry.sensors.tpms.front_left.pressure_psi,
-            telemetry.sensors.tpms.front_right.pressure_psi,
-            telemetry.sensors.tpms.rear_left.pressure_psi,
-            telemetry.sensors.tpms.rear_right.pressure_psi,
-        ];
-        
-        let tire_temperatures = [
-            telemetry.sensors.tpms.front_left.temperature_c,
-            telemetry.sensors.tpms.front_right.temperature_c,
-            telemetry.sensors.tpms.rear_left.temperature_c,
-            telemetry.sensors.tpms.rear_right.temperature_c,
-        ];
-        
         Ok(TelemetrySummary {
             truck_id: telemetry.truck_id,
             last_timestamp: telemetry.timestamp,
@@ -215,8 +190,7 @@ impl TelemetryService {
             last_accel_x: telemetry.sensors.imu.accel_x,
             last_accel_y: telemetry.sensors.imu.accel_y,
             last_accel_z: telemetry.sensors.imu.accel_z,
-            tire_pressures,
-            tire_temperatures,
+            tires: telemetry.sensors.tpms.tires,
         })
     }
 
@@ -0,0 +1,112 @@
+//! Bytes per sample of the sensor block codec against the plain JSON
+//! envelope, with zstd on top and as CBOR.
+//!
+//!     cargo bench --bench sensor_codec
+
+use std::time::Instant;
+use truck_protocol::events::{GpsData, ImuData, ObdData, SensorReading, SensorValues};
+use truck_protocol::{Encoding, Envelope, EventKind, EventPriority, WireEvent, WirePayload};
+
+const BATCH: u64 = 500;
+
+fn event(seq: u64, sensor_id: &str, values: SensorValues) -> WireEvent {
+    WireEvent {
+        event_id: format!("evt-TRK-001-{}", seq),
+        kind: EventKind::Sensor,
+        // 100 Hz with a little clock jitter
+        timestamp: 1_700_000_000_000_000_000 + seq * 10_000_000 + (seq * 7919 % 50_000),
+        priority: EventPriority::Medium,
+        sequence_number: seq,
+        source_module: "sensor".to_string(),
+        payload: WirePayload::Sensor(SensorReading {
+            sensor_id: sensor_id.to_string(),
+            values,
+            sample_interval_ms: None,
+        }),
+    }
+}
+
+fn imu(seq: u64) -> WireEvent {
+    let t = seq as f32 * 0.01;
+    event(seq, "imu-0", SensorValues::Imu(ImuData {
+        accel_x: (t * 1.3).sin() * 0.15,
+        accel_y: (t * 0.7).cos() * 0.05,
+        accel_z: 1.0 + (t * 9.0).sin() * 0.02,
+        gyro_x: (t * 2.0).sin() * 1.5,
+        gyro_y: 0.0,
+        gyro_z: (t * 0.1).cos() * 3.0,
+        longitudinal_g: Some((t * 1.3).sin() * 0.15),
+        lateral_g: Some((t * 0.7).cos() * 0.05),
+        vertical_g: Some((t * 9.0).sin() * 0.02),
+    }))
+}
+
+fn gps(seq: u64) -> WireEvent {
+    let t = seq as f64;
+    event(seq, "gps-0", SensorValues::Gps(GpsData {
+        latitude: 37.7749 + t * 0.00001,
+        longitude: -122.4194 + t * 0.000013,
+        altitude: 12.0 + (seq % 5) as f32 * 0.1,
+        speed_kmh: 61.5 + (seq % 10) as f32 * 0.5,
+        heading: 90.0,
+        satellites: 9,
+        fix_quality: 1,
+        ..Default::default()
+    }))
+}
+
+fn obd(seq: u64) -> WireEvent {
+    event(seq, "obd-0", SensorValues::Obd(ObdData {
+        rpm: 1500 + (seq % 40) as u16 * 5,
+        speed_kmh: 62,
+        coolant_temp: 88,
+        fuel_level: 71 - (seq / 400) as u8,
+        engine_load: 40 + (seq % 7) as u8,
+        throttle_pos: 22,
+        extra: Default::default(),
+    }))
+}
+
+fn report(name: &str, make: fn(u64) -> WireEvent) {
+    let events: Vec<WireEvent> = (1..=BATCH).map(make).collect();
+    let plain = Envelope::new("TRK-001", "batch-1", 0, events);
+    let mut packed = plain.clone();
+    packed.pack_sensors();
+
+    let plain_json = plain.encode().unwrap();
+    let packed_json = packed.encode().unwrap();
+    let plain_zstd = zstd::encode_all(&plain_json[..], 3).unwrap();
+    let packed_zstd = zstd::encode_all(&packed_json[..], 3).unwrap();
+    let plain_cbor = plain.encode_as(Encoding::Cbor).unwrap();
+    let packed_cbor = packed.encode_as(Encoding::Cbor).unwrap();
+
+    let start = Instant::now();
+    for _ in 0..20 {
+        Envelope::decode(&packed_json).unwrap();
+    }
+    let decode_us = start.elapsed().as_micros() as f64 / 20.0 / BATCH as f64;
+
+    let per = |bytes: &[u8]| bytes.len() as f64 / BATCH as f64;
+    println!(
+        "{:<4} {:>10.1} {:>10.1} {:>10.1} {:>10.1} {:>10.1} {:>10.1} {:>12.2}",
+        name,
+        per(&plain_json),
+        per(&plain_zstd),
+        per(&plain_cbor),
+        per(&packed_json),
+        per(&packed_zstd),
+        per(&packed_cbor),
+        decode_us,
+    );
+}
+
+fn main() {
+    println!("bytes per sample, {} samples per batch", BATCH);
+    println!(
+        "{:<4} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>12}",
+        "", "json", "json+zstd", "cbor", "delta", "delta+zstd", "delta+cbor", "decode us"
+    );
+    report("imu", imu);
+    report("gps", gps);
+    report("obd", obd);
+}
@@ -0,0 +1,112 @@
+use crate::encoding::Encoding;
+use crate::error::{ProtocolError, Result};
+use serde::{Deserialize, Serialize};
+
+/// Server -> agent acknowledgement for one [`Envelope`](crate::Envelope).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BatchAck {
+    pub protocol_version: u16,
+    pub device_id: String,
+    pub batch_id: String,
+    pub status: AckStatus,
+    pub accepted: Vec<String>,
+    pub rejected: Vec<RejectedEvent>,
+    pub server_sequence: u64,
+    pub received_at: u64, // nanos since epoch
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AckStatus {
+    Success,
+    Partial,
+    Duplicate,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RejectedEvent {
+    pub event_id: String,
+    pub reason: String,
+    /// Whether the agent should resend this event (transient server-side failure).
+    pub retryable: bool,
+}
+
+impl BatchAck {
+    /// Builds an ack, deriving the status from the accepted/rejected split.
+    pub fn new(
+        device_id: &str,
+        batch_id: &str,
+        accepted: Vec<String>,
+        rejected: Vec<RejectedEvent>,
+        server_sequence: u64,
+        received_at: u64,
+    ) -> Self {
+        let status = match (accepted.is_empty(), rejected.is_empty()) {
+            (_, true) => AckStatus::Success,
+            (false, false) => AckStatus::Partial,
+            (true, false) => AckStatus::Failed,
+        };
+
+        Self {
+            protocol_version: crate::PROTOCOL_VERSION,
+            device_id: device_id.to_string(),
+            batch_id: batch_id.to_string(),
+            status,
+            accepted,
+            rejected,
+            server_sequence,
+            received_at,
+        }
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        self.encode_as(Encoding::Json)
+    }
+
+    /// Servers answer in the encoding the batch arrived in.
+    pub fn encode_as(&self, encoding: Encoding) -> Result<Vec<u8>> {
+        encoding.to_vec(self)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let ack: BatchAck = Encoding::from_slice(bytes)?;
+        if !crate::is_supported(ack.protocol_version) {
+            return Err(ProtocolError::UnsupportedVersion(ack.protocol_version));
+        }
+        Ok(ack)
+    }
+
+    /// Event ids the agent should put back on the wire.
+    pub fn retry_ids(&self) -> impl Iterator<Item = &str> {
+        self.rejected
+            .iter()
+            .filter(|r| r.retryable)
+            .map(|r| r.event_id.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ack_status_derivation() {
+        let ok = BatchAck::new("TRK-001", "b1", vec!["e1".into()], vec![], 1, 0);
+        assert_eq!(ok.status, AckStatus::Success);
+
+        let rejected = RejectedEvent {
+            event_id: "e2".into(),
+            reason: "storage unavailable".into(),
+            retryable: true,
+        };
+        let partial = BatchAck::new("TRK-001", "b1", vec!["e1".into()], vec![rejected.clone()], 2, 0);
+        assert_eq!(partial.status, AckStatus::Partial);
+        assert_eq!(partial.retry_ids().collect::<Vec<_>>(), vec!["e2"]);
+
+        let failed = BatchAck::new("TRK-001", "b1", vec![], vec![rejected], 3, 0);
+        assert_eq!(failed.status, AckStatus::Failed);
+
+        let decoded = BatchAck::decode(&partial.encode().unwrap()).unwrap();
+        assert_eq!(decoded, partial);
+    }
+}
@@ -0,0 +1,258 @@
+// Bit-level primitives for the sensor block codec. Everything is written
+// MSB-first into one stream; nothing is byte-aligned except the whole block.
+
+use crate::error::{ProtocolError, Result};
+
+#[derive(Default)]
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    current: u8,
+    used: u32,
+}
+
+impl BitWriter {
+    pub fn write_bit(&mut self, bit: bool) {
+        self.current = (self.current << 1) | bit as u8;
+        self.used += 1;
+        if self.used == 8 {
+            self.bytes.push(self.current);
+            self.current = 0;
+            self.used = 0;
+        }
+    }
+
+    /// Low `n` bits of `value`, most significant first.
+    pub fn write_bits(&mut self, value: u64, n: u32) {
+        for i in (0..n).rev() {
+            self.write_bit((value >> i) & 1 == 1);
+        }
+    }
+
+    /// LEB128 on the bit stream.
+    pub fn write_varint(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.write_bits(byte as u64, 8);
+                return;
+            }
+            self.write_bits((byte | 0x80) as u64, 8);
+        }
+    }
+
+    /// One `0` bit for zero, otherwise `1` followed by a zigzag varint.
+    pub fn write_signed(&mut self, value: i64) {
+        if value == 0 {
+            self.write_bit(false);
+        } else {
+            self.write_bit(true);
+            self.write_varint(zigzag(value));
+        }
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        if self.used > 0 {
+            self.bytes.push(self.current << (8 - self.used));
+        }
+        self.bytes
+    }
+}
+
+pub struct BitReader<'a> {
+    bytes: &'a [u8],
+    pos: usize, // in bits
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    /// Bits left to read; every encoded value takes at least one.
+    pub fn remaining_bits(&self) -> usize {
+        (self.bytes.len() * 8).saturating_sub(self.pos)
+    }
+
+    pub fn read_bit(&mut self) -> Result<bool> {
+        let byte = self
+            .bytes
+            .get(self.pos / 8)
+            .ok_or_else(|| ProtocolError::SensorBlock("unexpected end of block".to_string()))?;
+        let bit = (byte >> (7 - self.pos % 8)) & 1 == 1;
+        self.pos += 1;
+        Ok(bit)
+    }
+
+    pub fn read_bits(&mut self, n: u32) -> Result<u64> {
+        let mut value = 0u64;
+        for _ in 0..n {
+            value = (value << 1) | self.read_bit()? as u64;
+        }
+        Ok(value)
+    }
+
+    pub fn read_varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_bits(8)?;
+            value |= (byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(ProtocolError::SensorBlock("varint overflow".to_string()))
+    }
+
+    pub fn read_signed(&mut self) -> Result<i64> {
+        if self.read_bit()? {
+            Ok(unzigzag(self.read_varint()?))
+        } else {
+            Ok(0)
+        }
+    }
+}
+
+/// Rejects a `count` the rest of the input could not hold, before anything
+/// is allocated for it.
+pub fn check_count(r: &BitReader, count: usize) -> Result<()> {
+    if count > r.remaining_bits() {
+        return Err(ProtocolError::SensorBlock(format!("count {} exceeds block size", count)));
+    }
+    Ok(())
+}
+
+fn zigzag(v: i64) -> u64 {
+    ((v << 1) ^ (v >> 63)) as u64
+}
+
+fn unzigzag(v: u64) -> i64 {
+    ((v >> 1) as i64) ^ -((v & 1) as i64)
+}
+
+/// Delta-of-delta column: first value, first delta, then changes in the delta.
+/// Regular timestamps and consecutive sequence numbers cost one bit each.
+pub fn write_delta_of_delta(w: &mut BitWriter, values: &[u64]) {
+    let mut prev = 0u64;
+    let mut prev_delta = 0i64;
+    for (i, &v) in values.iter().enumerate() {
+        match i {
+            0 => w.write_varint(v),
+            _ => {
+                let delta = v.wrapping_sub(prev) as i64;
+                w.write_signed(delta.wrapping_sub(prev_delta));
+                prev_delta = delta;
+            }
+        }
+        prev = v;
+    }
+}
+
+pub fn read_delta_of_delta(r: &mut BitReader, count: usize) -> Result<Vec<u64>> {
+    check_count(r, count)?;
+    let mut values: Vec<u64> = Vec::with_capacity(count);
+    let mut prev_delta = 0i64;
+    for i in 0..count {
+        let v = match i {
+            0 => r.read_varint()?,
+            _ => {
+                let delta = prev_delta.wrapping_add(r.read_signed()?);
+                prev_delta = delta;
+                values[i - 1].wrapping_add(delta as u64)
+            }
+        };
+        values.push(v);
+    }
+    Ok(values)
+}
+
+/// Plain delta column for small integers (OBD readings, satellite counts).
+pub fn write_deltas(w: &mut BitWriter, values: &[i64]) {
+    let mut prev = 0i64;
+    for &v in values {
+        w.write_signed(v.wrapping_sub(prev));
+        prev = v;
+    }
+}
+
+pub fn read_deltas(r: &mut BitReader, count: usize) -> Result<Vec<i64>> {
+    check_count(r, count)?;
+    let mut prev = 0i64;
+    (0..count)
+        .map(|_| {
+            prev = prev.wrapping_add(r.read_signed()?);
+            Ok(prev)
+        })
+        .collect()
+}
+
+/// Gorilla XOR column: each value is XORed with the previous one and only the
+/// meaningful bits are stored, reusing the previous leading/trailing-zero
+/// window when the new bits fit inside it.
+pub fn write_xor_floats(w: &mut BitWriter, values: &[f64]) {
+    let mut prev = 0u64;
+    let mut window: Option<(u32, u32)> = None; // (leading, trailing)
+
+    for (i, v) in values.iter().enumerate() {
+        let bits = v.to_bits();
+        if i == 0 {
+            w.write_bits(bits, 64);
+            prev = bits;
+            continue;
+        }
+
+        let xor = bits ^ prev;
+        prev = bits;
+        if xor == 0 {
+            w.write_bit(false);
+            continue;
+        }
+        w.write_bit(true);
+
+        let leading = xor.leading_zeros().min(31);
+        let trailing = xor.trailing_zeros();
+        match window {
+            Some((l, t)) if leading >= l && trailing >= t => {
+                w.write_bit(false);
+                w.write_bits(xor >> t, 64 - l - t);
+            }
+            _ => {
+                let significant = 64 - leading - trailing;
+                w.write_bit(true);
+                w.write_bits(leading as u64, 5);
+                w.write_bits((significant - 1) as u64, 6);
+                w.write_bits(xor >> trailing, significant);
+                window = Some((leading, trailing));
+            }
+        }
+    }
+}
+
+pub fn read_xor_floats(r: &mut BitReader, count: usize) -> Result<Vec<f64>> {
+    check_count(r, count)?;
+    let mut values = Vec::with_capacity(count);
+    let mut prev = 0u64;
+    let mut window: Option<(u32, u32)> = None;
+
+    for i in 0..count {
+        if i == 0 {
+            prev = r.read_bits(64)?;
+        } else if r.read_bit()? {
+            let (leading, trailing) = if r.read_bit()? {
+                let leading = r.read_bits(5)? as u32;
+                let significant = r.read_bits(6)? as u32 + 1;
+                if leading + significant > 64 {
+                    return Err(ProtocolError::SensorBlock("invalid XOR window".to_string()));
+                }
+                let w = (leading, 64 - leading - significant);
+                window = Some(w);
+                w
+            } else {
+                window.ok_or_else(|| ProtocolError::SensorBlock("XOR window reused before set".to_string()))?
+            };
+            prev ^= r.read_bits(64 - leading - trailing)? << trailing;
+        }
+        values.push(f64::from_bits(prev));
+    }
+    Ok(values)
+}
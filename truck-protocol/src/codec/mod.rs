@@ -0,0 +1,563 @@
+//! Columnar codec for runs of sensor readings.
+//!
+//! A [`SensorBlock`] replaces consecutive readings of one sensor in an
+//! envelope. Timestamps and sequence numbers are delta-of-delta coded, GPS and
+//! IMU floats are Gorilla XOR coded and OBD integers are zigzag varint deltas.
+//! Values round-trip exactly; `f32` fields are widened to `f64` losslessly.
+
+mod bits;
+
+use crate::error::{ProtocolError, Result};
+use crate::events::{
+    EventKind, EventPriority, GnssFixType, GpsData, ImuData, ObdData, SensorReading, SensorValues, WireEvent,
+    WirePayload,
+};
+use bits::{BitReader, BitWriter};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Layout version of [`SensorBlock::data`].
+pub const CODEC_VERSION: u8 = 1;
+
+/// Readings of one sensor sharing priority and source module, column-encoded.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SensorBlock {
+    pub sensor_id: String,
+    pub priority: EventPriority,
+    pub source_module: String,
+    /// Event ids are `{id_prefix}{sequence_number}`.
+    pub id_prefix: String,
+    /// [`SensorReading::sample_interval_ms`] shared by every reading.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_interval_ms: Option<u32>,
+    pub count: u32,
+    #[serde(with = "base64_bytes")]
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Layout {
+    Gps = 0,
+    Obd = 1,
+    Imu = 2,
+    /// GPS with fix type, DOPs and accuracy; absent values are NaN.
+    GpsAccuracy = 3,
+    /// IMU with the gravity-free vehicle axes; absent values are NaN.
+    ImuVehicle = 4,
+}
+
+impl Layout {
+    fn of(values: &SensorValues) -> Option<Self> {
+        match values {
+            // The sky view is a list; readings carrying it stay plain events
+            SensorValues::Gps(g) if !g.satellites_in_view.is_empty() => None,
+            SensorValues::Gps(g) if g.has_accuracy() => Some(Layout::GpsAccuracy),
+            SensorValues::Gps(_) => Some(Layout::Gps),
+            // The columns only hold the fixed fields
+            SensorValues::Obd(o) if o.extra.is_empty() => Some(Layout::Obd),
+            SensorValues::Obd(_) => None,
+            SensorValues::Imu(m) if m.is_vehicle_frame() => Some(Layout::ImuVehicle),
+            SensorValues::Imu(_) => Some(Layout::Imu),
+            // Tire sensors report every few seconds; not worth a layout
+            SensorValues::Tpms(_) => None,
+            // Published about once a second, mostly optional fields
+            SensorValues::J1939(_) => None,
+            // Only sent when the codes change
+            SensorValues::Dtc(_) => None,
+            // Once a second, beside the GPS fixes it mostly repeats
+            SensorValues::Position(_) => None,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Result<Self> {
+        match tag {
+            0 => Ok(Layout::Gps),
+            1 => Ok(Layout::Obd),
+            2 => Ok(Layout::Imu),
+            3 => Ok(Layout::GpsAccuracy),
+            4 => Ok(Layout::ImuVehicle),
+            other => Err(ProtocolError::SensorBlock(format!("unknown layout {}", other))),
+        }
+    }
+}
+
+/// Key under which an event can share a block, or `None` if it must stay a
+/// plain event (not a sensor reading, TPMS, or an id not ending in its seq).
+type BlockKey = (String, Layout, EventPriority, String, String, Option<u32>);
+
+fn block_key(event: &WireEvent) -> Option<BlockKey> {
+    let WirePayload::Sensor(reading) = &event.payload else {
+        return None;
+    };
+    let layout = Layout::of(&reading.values)?;
+    let prefix = event.event_id.strip_suffix(&event.sequence_number.to_string())?;
+    Some((
+        reading.sensor_id.clone(),
+        layout,
+        event.priority,
+        event.source_module.clone(),
+        prefix.to_string(),
+        reading.sample_interval_ms,
+    ))
+}
+
+/// Moves runs of packable readings out of `events` into blocks. Readings
+/// without a partner stay where they were. Order within a block is preserved.
+pub fn pack(events: &mut Vec<WireEvent>) -> Vec<SensorBlock> {
+    let keys: Vec<_> = events.iter().map(block_key).collect();
+    let mut sizes: HashMap<&BlockKey, usize> = HashMap::new();
+    for key in keys.iter().flatten() {
+        *sizes.entry(key).or_default() += 1;
+    }
+
+    let mut slots: HashMap<&BlockKey, usize> = HashMap::new();
+    let mut groups: Vec<(BlockKey, Vec<WireEvent>)> = Vec::new();
+    let mut rest = Vec::with_capacity(events.len());
+
+    for (event, key) in events.drain(..).zip(&keys) {
+        match key {
+            Some(key) if sizes[key] > 1 => {
+                let slot = *slots.entry(key).or_insert_with(|| {
+                    groups.push((key.clone(), Vec::new()));
+                    groups.len() - 1
+                });
+                groups[slot].1.push(event);
+            }
+            _ => rest.push(event),
+        }
+    }
+    *events = rest;
+
+    groups
+        .into_iter()
+        .map(|((sensor_id, layout, priority, source_module, id_prefix, sample_interval_ms), members)| SensorBlock {
+            sensor_id,
+            priority,
+            source_module,
+            id_prefix,
+            sample_interval_ms,
+            count: members.len() as u32,
+            data: encode_columns(layout, &members),
+        })
+        .collect()
+}
+
+impl SensorBlock {
+    /// Expands the block back into the events it was built from.
+    pub fn unpack(&self) -> Result<Vec<WireEvent>> {
+        let (&version, rest) = self
+            .data
+            .split_first()
+            .ok_or_else(|| ProtocolError::SensorBlock("empty block".to_string()))?;
+        if version != CODEC_VERSION {
+            return Err(ProtocolError::SensorBlock(format!("unsupported codec version {}", version)));
+        }
+        let (&tag, columns) = rest
+            .split_first()
+            .ok_or_else(|| ProtocolError::SensorBlock("missing layout".to_string()))?;
+        let layout = Layout::from_tag(tag)?;
+
+        let n = self.count as usize;
+        let mut r = BitReader::new(columns);
+        bits::check_count(&r, n)?;
+        let timestamps = bits::read_delta_of_delta(&mut r, n)?;
+        let sequences = bits::read_delta_of_delta(&mut r, n)?;
+
+        let values: Vec<SensorValues> = match layout {
+            Layout::Gps | Layout::GpsAccuracy => {
+                let f = read_float_columns(&mut r, n, 5)?;
+                let i = read_int_columns(&mut r, n, 2)?;
+                let mut gps: Vec<GpsData> = (0..n)
+                    .map(|k| GpsData {
+                        latitude: f[0][k],
+                        longitude: f[1][k],
+                        altitude: f[2][k] as f32,
+                        speed_kmh: f[3][k] as f32,
+                        heading: f[4][k] as f32,
+                        satellites: i[0][k] as u8,
+                        fix_quality: i[1][k] as u8,
+                        ..Default::default()
+                    })
+                    .collect();
+                if layout == Layout::GpsAccuracy {
+                    let f = read_float_columns(&mut r, n, 5)?;
+                    let fix = read_int_columns(&mut r, n, 1)?;
+                    let opt = |v: f64| (!v.is_nan()).then_some(v as f32);
+                    for (k, g) in gps.iter_mut().enumerate() {
+                        g.hdop = opt(f[0][k]);
+                        g.vdop = opt(f[1][k]);
+                        g.pdop = opt(f[2][k]);
+                        g.horizontal_accuracy_m = opt(f[3][k]);
+                        g.vertical_accuracy_m = opt(f[4][k]);
+                        g.fix_type = fix_type_from_code(fix[0][k]);
+                    }
+                }
+                gps.into_iter().map(SensorValues::Gps).collect()
+            }
+            Layout::Obd => {
+                let i = read_int_columns(&mut r, n, 6)?;
+                (0..n)
+                    .map(|k| {
+                        SensorValues::Obd(ObdData {
+                            rpm: i[0][k] as u16,
+                            speed_kmh: i[1][k] as u8,
+                            coolant_temp: i[2][k] as i8,
+                            fuel_level: i[3][k] as u8,
+                            engine_load: i[4][k] as u8,
+                            throttle_pos: i[5][k] as u8,
+                            extra: Default::default(),
+                        })
+                    })
+                    .collect()
+            }
+            Layout::Imu | Layout::ImuVehicle => {
+                let f = read_float_columns(&mut r, n, 6)?;
+                let mut imu: Vec<ImuData> = (0..n)
+                    .map(|k| ImuData {
+                        accel_x: f[0][k] as f32,
+                        accel_y: f[1][k] as f32,
+                        accel_z: f[2][k] as f32,
+                        gyro_x: f[3][k] as f32,
+                        gyro_y: f[4][k] as f32,
+                        gyro_z: f[5][k] as f32,
+                        longitudinal_g: None,
+                        lateral_g: None,
+                        vertical_g: None,
+                    })
+                    .collect();
+                if layout == Layout::ImuVehicle {
+                    let f = read_float_columns(&mut r, n, 3)?;
+                    let opt = |v: f64| (!v.is_nan()).then_some(v as f32);
+                    for (k, m) in imu.iter_mut().enumerate() {
+                        m.longitudinal_g = opt(f[0][k]);
+                        m.lateral_g = opt(f[1][k]);
+                        m.vertical_g = opt(f[2][k]);
+                    }
+                }
+                imu.into_iter().map(SensorValues::Imu).collect()
+            }
+        };
+
+        Ok(values
+            .into_iter()
+            .zip(timestamps.into_iter().zip(sequences))
+            .map(|(values, (timestamp, sequence_number))| WireEvent {
+                event_id: format!("{}{}", self.id_prefix, sequence_number),
+                kind: EventKind::Sensor,
+                timestamp,
+                priority: self.priority,
+                sequence_number,
+                source_module: self.source_module.clone(),
+                payload: WirePayload::Sensor(SensorReading {
+                    sensor_id: self.sensor_id.clone(),
+                    values,
+                    sample_interval_ms: self.sample_interval_ms,
+                }),
+            })
+            .collect())
+    }
+}
+
+fn encode_columns(layout: Layout, events: &[WireEvent]) -> Vec<u8> {
+    let readings: Vec<&SensorValues> = events
+        .iter()
+        .filter_map(|e| match &e.payload {
+            WirePayload::Sensor(r) => Some(&r.values),
+            _ => None,
+        })
+        .collect();
+
+    let mut w = BitWriter::default();
+    let timestamps: Vec<u64> = events.iter().map(|e| e.timestamp).collect();
+    let sequences: Vec<u64> = events.iter().map(|e| e.sequence_number).collect();
+    bits::write_delta_of_delta(&mut w, &timestamps);
+    bits::write_delta_of_delta(&mut w, &sequences);
+
+    match layout {
+        Layout::Gps | Layout::GpsAccuracy => {
+            let gps: Vec<&GpsData> = readings
+                .iter()
+                .filter_map(|v| match v {
+                    SensorValues::Gps(g) => Some(g),
+                    _ => None,
+                })
+                .collect();
+            write_float_column(&mut w, &gps, |g| g.latitude);
+            write_float_column(&mut w, &gps, |g| g.longitude);
+            write_float_column(&mut w, &gps, |g| g.altitude as f64);
+            write_float_column(&mut w, &gps, |g| g.speed_kmh as f64);
+            write_float_column(&mut w, &gps, |g| g.heading as f64);
+            write_int_column(&mut w, &gps, |g| g.satellites as i64);
+            write_int_column(&mut w, &gps, |g| g.fix_quality as i64);
+            if layout == Layout::GpsAccuracy {
+                let nan = |v: Option<f32>| v.map_or(f64::NAN, |v| v as f64);
+                write_float_column(&mut w, &gps, |g| nan(g.hdop));
+                write_float_column(&mut w, &gps, |g| nan(g.vdop));
+                write_float_column(&mut w, &gps, |g| nan(g.pdop));
+                write_float_column(&mut w, &gps, |g| nan(g.horizontal_accuracy_m));
+                write_float_column(&mut w, &gps, |g| nan(g.vertical_accuracy_m));
+                write_int_column(&mut w, &gps, |g| fix_type_code(g.fix_type));
+            }
+        }
+        Layout::Obd => {
+            let obd: Vec<&ObdData> = readings
+                .iter()
+                .filter_map(|v| match v {
+                    SensorValues::Obd(o) => Some(o),
+                    _ => None,
+                })
+                .collect();
+            write_int_column(&mut w, &obd, |o| o.rpm as i64);
+            write_int_column(&mut w, &obd, |o| o.speed_kmh as i64);
+            write_int_column(&mut w, &obd, |o| o.coolant_temp as i64);
+            write_int_column(&mut w, &obd, |o| o.fuel_level as i64);
+            write_int_column(&mut w, &obd, |o| o.engine_load as i64);
+            write_int_column(&mut w, &obd, |o| o.throttle_pos as i64);
+        }
+        Layout::Imu | Layout::ImuVehicle => {
+            let imu: Vec<&ImuData> = readings
+                .iter()
+                .filter_map(|v| match v {
+                    SensorValues::Imu(m) => Some(m),
+                    _ => None,
+                })
+                .collect();
+            write_float_column(&mut w, &imu, |m| m.accel_x as f64);
+            write_float_column(&mut w, &imu, |m| m.accel_y as f64);
+            write_float_column(&mut w, &imu, |m| m.accel_z as f64);
+            write_float_column(&mut w, &imu, |m| m.gyro_x as f64);
+            write_float_column(&mut w, &imu, |m| m.gyro_y as f64);
+            write_float_column(&mut w, &imu, |m| m.gyro_z as f64);
+            if layout == Layout::ImuVehicle {
+                let nan = |v: Option<f32>| v.map_or(f64::NAN, |v| v as f64);
+                write_float_column(&mut w, &imu, |m| nan(m.longitudinal_g));
+                write_float_column(&mut w, &imu, |m| nan(m.lateral_g));
+                write_float_column(&mut w, &imu, |m| nan(m.vertical_g));
+            }
+        }
+    }
+
+    let mut data = vec![CODEC_VERSION, layout as u8];
+    data.extend(w.finish());
+    data
+}
+
+fn fix_type_code(fix_type: Option<GnssFixType>) -> i64 {
+    match fix_type {
+        None => 0,
+        Some(GnssFixType::NoFix) => 1,
+        Some(GnssFixType::DeadReckoning) => 2,
+        Some(GnssFixType::Fix2d) => 3,
+        Some(GnssFixType::Fix3d) => 4,
+    }
+}
+
+fn fix_type_from_code(code: i64) -> Option<GnssFixType> {
+    match code {
+        1 => Some(GnssFixType::NoFix),
+        2 => Some(GnssFixType::DeadReckoning),
+        3 => Some(GnssFixType::Fix2d),
+        4 => Some(GnssFixType::Fix3d),
+        _ => None,
+    }
+}
+
+fn write_float_column<T>(w: &mut BitWriter, rows: &[&T], field: impl Fn(&T) -> f64) {
+    let column: Vec<f64> = rows.iter().map(|r| field(r)).collect();
+    bits::write_xor_floats(w, &column);
+}
+
+fn write_int_column<T>(w: &mut BitWriter, rows: &[&T], field: impl Fn(&T) -> i64) {
+    let column: Vec<i64> = rows.iter().map(|r| field(r)).collect();
+    bits::write_deltas(w, &column);
+}
+
+fn read_float_columns(r: &mut BitReader, n: usize, columns: usize) -> Result<Vec<Vec<f64>>> {
+    (0..columns).map(|_| bits::read_xor_floats(r, n)).collect()
+}
+
+fn read_int_columns(r: &mut BitReader, n: usize, columns: usize) -> Result<Vec<Vec<i64>>> {
+    (0..columns).map(|_| bits::read_deltas(r, n)).collect()
+}
+
+// Blocks are binary: base64 inside JSON envelopes, raw bytes in CBOR
+mod base64_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.serialize_str(&base64::encode(bytes))
+        } else {
+            s.serialize_bytes(bytes)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        if d.is_human_readable() {
+            let encoded = String::deserialize(d)?;
+            base64::decode(encoded).map_err(serde::de::Error::custom)
+        } else {
+            d.deserialize_byte_buf(BytesVisitor)
+        }
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> serde::de::Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("a byte string")
+        }
+
+        fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+            Ok(v.to_vec())
+        }
+
+        fn visit_byte_buf<E: serde::de::Error>(self, v: Vec<u8>) -> Result<Vec<u8>, E> {
+            Ok(v)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(seq: u64, values: SensorValues) -> WireEvent {
+        WireEvent {
+            event_id: format!("evt-TRK-001-{}", seq),
+            kind: EventKind::Sensor,
+            timestamp: 1_700_000_000_000_000_000 + seq * 10_000_000 + (seq % 3) * 17,
+            priority: EventPriority::Medium,
+            sequence_number: seq,
+            source_module: "sensor".to_string(),
+            payload: WirePayload::Sensor(SensorReading {
+                sensor_id: match values {
+                    SensorValues::Imu(_) => "imu-0".to_string(),
+                    SensorValues::Gps(_) => "gps-0".to_string(),
+                    _ => "obd-0".to_string(),
+                },
+                values,
+                sample_interval_ms: None,
+            }),
+        }
+    }
+
+    fn imu(seq: u64) -> SensorValues {
+        let t = seq as f32 * 0.01;
+        SensorValues::Imu(ImuData {
+            accel_x: (t * 3.0).sin() * 0.2,
+            accel_y: -0.01,
+            accel_z: 1.0 + (t * 7.0).cos() * 0.05,
+            gyro_x: f32::NAN,
+            gyro_y: -0.0,
+            gyro_z: f32::MAX,
+            // Calibrated halfway through
+            longitudinal_g: (seq > 100).then_some((t * 3.0).sin() * 0.2),
+            lateral_g: (seq > 100).then_some(0.0),
+            vertical_g: (seq > 100 && seq.is_multiple_of(3)).then_some(-0.02),
+        })
+    }
+
+    fn obd(seq: u64) -> SensorValues {
+        SensorValues::Obd(ObdData {
+            rpm: 800 + (seq as u16 * 37) % 2000,
+            speed_kmh: (seq % 120) as u8,
+            coolant_temp: (seq % 120) as i8 - 40,
+            fuel_level: 100 - (seq / 10) as u8,
+            engine_load: 255,
+            throttle_pos: 0,
+            extra: Default::default(),
+        })
+    }
+
+    fn gps(seq: u64) -> SensorValues {
+        SensorValues::Gps(GpsData {
+            latitude: 37.7749 + seq as f64 * 0.00001,
+            longitude: -122.4194,
+            altitude: 12.0,
+            speed_kmh: 61.5,
+            heading: 90.0,
+            satellites: 9,
+            fix_quality: 1,
+            fix_type: Some(GnssFixType::Fix3d),
+            hdop: Some(0.9),
+            vdop: Some(1.4),
+            pdop: (seq < 805).then_some(1.7),
+            horizontal_accuracy_m: Some(2.5 + seq as f32 * 0.01),
+            vertical_accuracy_m: None,
+            satellites_in_view: Vec::new(),
+        })
+    }
+
+    fn bits_of(events: &[WireEvent]) -> Vec<String> {
+        // NaN never equals itself; compare through Debug, which also tells -0.0 apart
+        events.iter().map(|e| format!("{:?}", e)).collect()
+    }
+
+    #[test]
+    fn test_pack_unpack_roundtrip() {
+        let mut events: Vec<WireEvent> = (1..=200)
+            .map(|seq| if seq % 4 == 0 { event(seq, obd(seq)) } else { event(seq, imu(seq)) })
+            .collect();
+        // Id that doesn't follow the seq convention stays a plain event
+        let mut odd = event(500, imu(500));
+        odd.event_id = "custom".to_string();
+        events.push(odd.clone());
+        // Downsampled readings keep their tag and do not share a block with full-rate ones
+        for seq in [600, 700] {
+            let mut sparse = event(seq, imu(seq));
+            if let WirePayload::Sensor(reading) = &mut sparse.payload {
+                reading.sample_interval_ms = Some(1000);
+            }
+            events.push(sparse);
+        }
+        events.extend((800..810).map(|seq| event(seq, gps(seq))));
+        // The sky view does not fit the columns
+        let mut sky = event(810, gps(810));
+        if let WirePayload::Sensor(SensorReading { values: SensorValues::Gps(g), .. }) = &mut sky.payload {
+            g.satellites_in_view.push(crate::events::SatelliteInView {
+                system: crate::events::GnssSystem::Galileo,
+                prn: 11,
+                elevation_deg: Some(42),
+                azimuth_deg: Some(270),
+                snr_dbhz: Some(38),
+                used: true,
+            });
+        }
+        events.push(sky.clone());
+        let original = events.clone();
+
+        let blocks = pack(&mut events);
+        assert_eq!(blocks.len(), 5);
+        assert_eq!(bits_of(&events), bits_of(&[odd, sky]));
+
+        let mut restored: Vec<WireEvent> = blocks.iter().flat_map(|b| b.unpack().unwrap()).collect();
+        restored.extend(events);
+        restored.sort_by_key(|e| e.sequence_number);
+        assert_eq!(bits_of(&restored), bits_of(&original));
+    }
+
+    #[test]
+    fn test_truncated_block_is_rejected() {
+        let mut events: Vec<WireEvent> = (1..=50).map(|seq| event(seq, imu(seq))).collect();
+        let block = pack(&mut events).remove(0);
+        for len in 0..block.data.len() {
+            let mut cut = block.clone();
+            cut.data.truncate(len);
+            assert!(cut.unpack().is_err(), "truncation to {} bytes decoded", len);
+        }
+    }
+
+    #[test]
+    fn test_count_beyond_block_is_rejected() {
+        let mut events: Vec<WireEvent> = (1..=50).map(|seq| event(seq, gps(seq))).collect();
+        let mut block = pack(&mut events).remove(0);
+        // A hostile count must fail before it sizes any column
+        block.count = u32::MAX;
+        assert!(block.unpack().is_err());
+        block.count = 51;
+        assert!(block.unpack().is_err());
+    }
+}
@@ -0,0 +1,83 @@
+use crate::error::{ProtocolError, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// CBOR self-describe tag (RFC 8949 §3.4.6). Every CBOR payload starts with
+/// it, so receivers can tell the encoding from the bytes alone — MQTT 3.1.1
+/// has no content-type property to carry it.
+const CBOR_MAGIC: [u8; 3] = [0xd9, 0xd9, 0xf7];
+
+/// Serialization of envelopes and acks on the wire.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    #[default]
+    Json,
+    /// Roughly half the size of JSON for typical batches; meant for metered links.
+    Cbor,
+}
+
+impl Encoding {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Encoding::Json => "application/json",
+            Encoding::Cbor => "application/cbor",
+        }
+    }
+
+    /// Parses an HTTP `Content-Type`, ignoring parameters such as `charset`.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        match content_type.split(';').next()?.trim() {
+            "application/json" => Some(Encoding::Json),
+            "application/cbor" => Some(Encoding::Cbor),
+            _ => None,
+        }
+    }
+
+    /// Encoding of a payload produced by [`Encoding::to_vec`].
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&CBOR_MAGIC) {
+            Encoding::Cbor
+        } else {
+            Encoding::Json
+        }
+    }
+
+    pub fn to_vec<T: Serialize>(self, value: &T) -> Result<Vec<u8>> {
+        match self {
+            Encoding::Json => Ok(serde_json::to_vec(value)?),
+            Encoding::Cbor => {
+                let mut buf = CBOR_MAGIC.to_vec();
+                ciborium::ser::into_writer(value, &mut buf)
+                    .map_err(|e| ProtocolError::Cbor(e.to_string()))?;
+                Ok(buf)
+            }
+        }
+    }
+
+    /// Decodes `bytes` in whichever encoding they declare.
+    pub fn from_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+        match Self::detect(bytes) {
+            Encoding::Json => Ok(serde_json::from_slice(bytes)?),
+            Encoding::Cbor => ciborium::de::from_reader(&bytes[CBOR_MAGIC.len()..])
+                .map_err(|e| ProtocolError::Cbor(e.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_and_content_type() {
+        let cbor = Encoding::Cbor.to_vec(&vec![1u8, 2, 3]).unwrap();
+        assert_eq!(Encoding::detect(&cbor), Encoding::Cbor);
+        assert_eq!(Encoding::detect(b"{\"a\":1}"), Encoding::Json);
+        assert_eq!(Encoding::from_slice::<Vec<u8>>(&cbor).unwrap(), vec![1, 2, 3]);
+
+        assert_eq!(Encoding::from_content_type("application/cbor"), Some(Encoding::Cbor));
+        assert_eq!(Encoding::from_content_type("application/json; charset=utf-8"), Some(Encoding::Json));
+        assert_eq!(Encoding::from_content_type("text/plain"), None);
+    }
+}
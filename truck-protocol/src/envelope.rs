@@ -0,0 +1,214 @@
+use crate::codec::{self, SensorBlock};
+use crate::encoding::Encoding;
+use crate::error::{ProtocolError, Result};
+use crate::events::{EventPriority, WireEvent};
+use serde::{Deserialize, Serialize};
+
+/// Versioned batch envelope published on the telemetry channel.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Envelope {
+    pub protocol_version: u16,
+    pub device_id: String,
+    pub batch_id: String,
+    pub created_at: u64, // nanos since epoch
+    pub priority: EventPriority,
+    pub events: Vec<WireEvent>,
+    /// Column-encoded sensor readings; see [`Envelope::pack_sensors`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sensor_blocks: Vec<SensorBlock>,
+}
+
+impl Envelope {
+    pub fn new(device_id: &str, batch_id: &str, created_at: u64, events: Vec<WireEvent>) -> Self {
+        let priority = events
+            .iter()
+            .map(|e| e.priority)
+            .min()
+            .unwrap_or(EventPriority::Low);
+
+        Self {
+            protocol_version: crate::PROTOCOL_VERSION,
+            device_id: device_id.to_string(),
+            batch_id: batch_id.to_string(),
+            created_at,
+            priority,
+            events,
+            sensor_blocks: Vec::new(),
+        }
+    }
+
+    /// Moves runs of GPS, OBD and IMU readings into [`SensorBlock`]s.
+    /// [`Envelope::decode`] expands them again, so receivers only see events.
+    pub fn pack_sensors(&mut self) {
+        let blocks = codec::pack(&mut self.events);
+        self.sensor_blocks.extend(blocks);
+    }
+
+    /// Expands sensor blocks back into `events`, ordered by sequence number.
+    pub fn unpack_sensors(&mut self) -> Result<()> {
+        if self.sensor_blocks.is_empty() {
+            return Ok(());
+        }
+        for block in std::mem::take(&mut self.sensor_blocks) {
+            self.events.extend(block.unpack()?);
+        }
+        self.events.sort_by_key(|e| e.sequence_number);
+        Ok(())
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        self.encode_as(Encoding::Json)
+    }
+
+    pub fn encode_as(&self, encoding: Encoding) -> Result<Vec<u8>> {
+        encoding.to_vec(self)
+    }
+
+    /// Decodes a JSON or CBOR envelope, rejecting protocol versions this build
+    /// cannot read.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        // Peek at the version first so a newer peer gets a precise error
+        // instead of a generic field mismatch.
+        #[derive(Deserialize)]
+        struct VersionProbe {
+            protocol_version: u16,
+        }
+
+        let probe: VersionProbe = Encoding::from_slice(bytes)?;
+        if !crate::is_supported(probe.protocol_version) {
+            return Err(ProtocolError::UnsupportedVersion(probe.protocol_version));
+        }
+
+        let mut envelope: Self = Encoding::from_slice(bytes)?;
+        envelope.unpack_sensors()?;
+        Ok(envelope)
+    }
+
+    /// Decodes an envelope received on `topic_device_id`'s topic and checks they agree.
+    pub fn decode_from(topic_device_id: &str, bytes: &[u8]) -> Result<Self> {
+        let envelope = Self::decode(bytes)?;
+        if envelope.device_id != topic_device_id {
+            return Err(ProtocolError::DeviceMismatch {
+                envelope: envelope.device_id,
+                topic: topic_device_id.to_string(),
+            });
+        }
+        Ok(envelope)
+    }
+
+    pub fn event_ids(&self) -> Vec<String> {
+        self.events.iter().map(|e| e.event_id.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{EventKind, GpsData, MlDetection, MlResult, SensorReading, SensorValues, WirePayload};
+
+    fn sample_envelope() -> Envelope {
+        let gps = WireEvent {
+            event_id: "evt-TRK-001-1".to_string(),
+            kind: EventKind::Sensor,
+            timestamp: 1_700_000_000_000_000_000,
+            priority: EventPriority::Medium,
+            sequence_number: 1,
+            source_module: "sensor".to_string(),
+            payload: WirePayload::Sensor(SensorReading {
+                sensor_id: "gps-0".to_string(),
+                values: SensorValues::Gps(GpsData {
+                    latitude: 37.7749,
+                    longitude: -122.4194,
+                    altitude: 12.0,
+                    speed_kmh: 61.5,
+                    heading: 90.0,
+                    satellites: 9,
+                    fix_quality: 1,
+                    ..Default::default()
+                }),
+                sample_interval_ms: None,
+            }),
+        };
+        let drowsy = WireEvent {
+            event_id: "evt-TRK-001-2".to_string(),
+            kind: EventKind::Ml,
+            timestamp: 1_700_000_000_500_000_000,
+            priority: EventPriority::Critical,
+            sequence_number: 2,
+            source_module: "ml".to_string(),
+            payload: WirePayload::Ml(MlDetection {
+                model_name: "drowsiness".to_string(),
+                model_version: "1.2.0".to_string(),
+                result: MlResult::Drowsiness { is_drowsy: true, eye_closure_ratio: 0.81 },
+                confidence: 0.93,
+                calibrated_confidence: 0.9,
+                latency_ms: 18.0,
+                hardware_used: "Cpu".to_string(),
+                camera_id: "driver".to_string(),
+                driver_id: "drv-42".to_string(),
+                route_id: "route-7".to_string(),
+            }),
+        };
+        Envelope::new("TRK-001", "batch-1", 1_700_000_001_000_000_000, vec![gps, drowsy])
+    }
+
+    #[test]
+    fn test_envelope_roundtrip() {
+        let envelope = sample_envelope();
+        assert_eq!(envelope.priority, EventPriority::Critical);
+
+        let bytes = envelope.encode().unwrap();
+        let decoded = Envelope::decode_from("TRK-001", &bytes).unwrap();
+        assert_eq!(decoded, envelope);
+        assert_eq!(decoded.event_ids(), vec!["evt-TRK-001-1", "evt-TRK-001-2"]);
+    }
+
+    #[test]
+    fn test_cbor_roundtrip_is_smaller() {
+        let mut envelope = sample_envelope();
+        envelope.pack_sensors();
+
+        let json = envelope.encode().unwrap();
+        let cbor = envelope.encode_as(Encoding::Cbor).unwrap();
+        assert!(cbor.len() < json.len(), "cbor {} >= json {}", cbor.len(), json.len());
+        assert_eq!(Envelope::decode_from("TRK-001", &cbor).unwrap(), sample_envelope());
+    }
+
+    #[test]
+    fn test_packed_sensors_decode_to_events() {
+        let mut envelope = sample_envelope();
+        let mut second = envelope.events[0].clone();
+        second.event_id = "evt-TRK-001-3".to_string();
+        second.sequence_number = 3;
+        envelope.events.push(second);
+        let plain = envelope.clone();
+
+        envelope.pack_sensors();
+        assert_eq!(envelope.sensor_blocks.len(), 1);
+        assert_eq!(envelope.events.len(), 1);
+
+        let decoded = Envelope::decode(&envelope.encode().unwrap()).unwrap();
+        assert_eq!(decoded, plain);
+    }
+
+    #[test]
+    fn test_rejects_unsupported_version() {
+        let mut envelope = sample_envelope();
+        envelope.protocol_version = crate::PROTOCOL_VERSION + 1;
+        let bytes = envelope.encode().unwrap();
+
+        match Envelope::decode(&bytes) {
+            Err(ProtocolError::UnsupportedVersion(v)) => assert_eq!(v, crate::PROTOCOL_VERSION + 1),
+            other => panic!("expected version error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_rejects_device_mismatch() {
+        let bytes = sample_envelope().encode().unwrap();
+        assert!(matches!(
+            Envelope::decode_from("TRK-999", &bytes),
+            Err(ProtocolError::DeviceMismatch { .. })
+        ));
+    }
+}
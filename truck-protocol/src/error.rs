@@ -0,0 +1,24 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ProtocolError {
+    #[error("Unsupported protocol version: {0}")]
+    UnsupportedVersion(u16),
+
+    #[error("Decode error: {0}")]
+    DecodeError(#[from] serde_json::Error),
+
+    #[error("Invalid topic: {0}")]
+    InvalidTopic(String),
+
+    #[error("CBOR error: {0}")]
+    Cbor(String),
+
+    #[error("Sensor block decode error: {0}")]
+    SensorBlock(String),
+
+    #[error("Device mismatch: envelope from {envelope} on topic for {topic}")]
+    DeviceMismatch { envelope: String, topic: String },
+}
+
+pub type Result<T> = std::result::Result<T, ProtocolError>;
@@ -0,0 +1,562 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A single event inside an [`Envelope`](crate::Envelope).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WireEvent {
+    pub event_id: String,   // unique per device, used for dedup and acks
+    pub kind: EventKind,
+    pub timestamp: u64,     // nanos since epoch
+    pub priority: EventPriority,
+    pub sequence_number: u64,
+    pub source_module: String,
+    pub payload: WirePayload,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EventPriority {
+    Critical = 0,
+    High = 1,
+    Medium = 2,
+    Low = 3,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    Sensor,
+    CameraMeta,
+    CameraBlob,
+    Ml,
+    Health,
+    Heartbeat,
+    CommandResponse,
+    DataLoss,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", content = "data")]
+pub enum WirePayload {
+    Sensor(SensorReading),
+    CameraMeta(CameraMeta),
+    CameraBlob(CameraBlobRef),
+    Ml(MlDetection),
+    Health(HealthReport),
+    Heartbeat(Heartbeat),
+    CommandResponse(CommandResponse),
+    DataLoss(DataLoss),
+}
+
+impl WirePayload {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            WirePayload::Sensor(_) => EventKind::Sensor,
+            WirePayload::CameraMeta(_) => EventKind::CameraMeta,
+            WirePayload::CameraBlob(_) => EventKind::CameraBlob,
+            WirePayload::Ml(_) => EventKind::Ml,
+            WirePayload::Health(_) => EventKind::Health,
+            WirePayload::Heartbeat(_) => EventKind::Heartbeat,
+            WirePayload::CommandResponse(_) => EventKind::CommandResponse,
+            WirePayload::DataLoss(_) => EventKind::DataLoss,
+        }
+    }
+}
+
+// --- Sensors ---
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SensorReading {
+    pub sensor_id: String,
+    pub values: SensorValues,
+    /// Set when the agent downsampled this sensor because of a poor link: at
+    /// most one reading per interval was sent. The full-rate readings stay in
+    /// the agent's WAL and arrive later without this tag.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_interval_ms: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum SensorValues {
+    Gps(GpsData),
+    Obd(ObdData),
+    Imu(ImuData),
+    Tpms(TpmsData),
+    J1939(J1939Data),
+    Dtc(DtcData),
+    Position(PositionData),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct GpsData {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: f32,
+    pub speed_kmh: f32,
+    pub heading: f32,
+    pub satellites: u8,
+    pub fix_quality: u8,
+    /// From GSA or UBX; receivers that only send GGA and RMC leave the
+    /// accuracy fields empty.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fix_type: Option<GnssFixType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hdop: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vdop: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pdop: Option<f32>,
+    /// Estimated 1-sigma position error in metres (GST, or UBX hAcc/vAcc).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub horizontal_accuracy_m: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vertical_accuracy_m: Option<f32>,
+    /// Sky view, attached every few seconds rather than to every fix.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub satellites_in_view: Vec<SatelliteInView>,
+}
+
+impl GpsData {
+    pub fn has_accuracy(&self) -> bool {
+        self.fix_type.is_some()
+            || self.hdop.is_some()
+            || self.vdop.is_some()
+            || self.pdop.is_some()
+            || self.horizontal_accuracy_m.is_some()
+            || self.vertical_accuracy_m.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum GnssFixType {
+    NoFix,
+    DeadReckoning,
+    Fix2d,
+    Fix3d,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GnssSystem {
+    Gps,
+    Sbas,
+    Galileo,
+    Beidou,
+    Qzss,
+    Glonass,
+    Navic,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SatelliteInView {
+    pub system: GnssSystem,
+    /// PRN or SV id within the system.
+    pub prn: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elevation_deg: Option<i8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub azimuth_deg: Option<u16>,
+    /// Carrier-to-noise density in dB-Hz; `None` while not tracked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snr_dbhz: Option<u8>,
+    /// Used in the navigation solution.
+    pub used: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ObdData {
+    pub rpm: u16,
+    pub speed_kmh: u8,
+    pub coolant_temp: i8,
+    pub fuel_level: u8,
+    pub engine_load: u8,
+    pub throttle_pos: u8,
+    /// PIDs from the agent's catalogue beyond the fields above, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra: BTreeMap<String, f64>,
+}
+
+/// Acceleration (g) and angular rate (deg/s). Once the agent knows how the
+/// IMU is mounted, the axes are the truck's: x forward, y left, z up.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ImuData {
+    pub accel_x: f32,
+    pub accel_y: f32,
+    pub accel_z: f32,
+    pub gyro_x: f32,
+    pub gyro_y: f32,
+    pub gyro_z: f32,
+    /// Acceleration with gravity removed, along the truck's axes (g). Absent
+    /// until the mounting is calibrated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub longitudinal_g: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lateral_g: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vertical_g: Option<f32>,
+}
+
+impl ImuData {
+    pub fn is_vehicle_frame(&self) -> bool {
+        self.longitudinal_g.is_some() || self.lateral_g.is_some() || self.vertical_g.is_some()
+    }
+}
+
+/// Every tire the TPMS has heard from recently, tractor first, each unit
+/// front axle to rear.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(from = "TpmsRepr")]
+pub struct TpmsData {
+    pub tires: Vec<TireSensor>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TireSensor {
+    pub position: TirePosition,
+    pub pressure_psi: f32,
+    pub temperature_c: f32,
+    /// Not every TPMS reports it (J1939 does not).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub battery_percent: Option<u8>,
+    pub alert: bool,
+}
+
+/// Where a tire sits. Axles count from the front of each unit and positions
+/// from the left-most tire, as in the J1939 tire location byte, so a dual
+/// axle has positions 0–3.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TirePosition {
+    /// 0 is the tractor, 1 the first trailer.
+    pub unit: u8,
+    pub axle: u8,
+    pub position: u8,
+}
+
+impl TirePosition {
+    /// Compact key for field names, e.g. `u1_a0_p3`.
+    pub fn label(&self) -> String {
+        format!("u{}_a{}_p{}", self.unit, self.axle, self.position)
+    }
+}
+
+/// Protocol 1 and 2 readings carried four named wheels.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TpmsRepr {
+    Tires {
+        tires: Vec<TireSensor>,
+    },
+    FourWheels {
+        front_left: LegacyTire,
+        front_right: LegacyTire,
+        rear_left: LegacyTire,
+        rear_right: LegacyTire,
+    },
+}
+
+#[derive(Deserialize)]
+struct LegacyTire {
+    pressure_psi: f32,
+    temperature_c: f32,
+    battery_percent: u8,
+    alert: bool,
+}
+
+impl From<TpmsRepr> for TpmsData {
+    fn from(repr: TpmsRepr) -> Self {
+        match repr {
+            TpmsRepr::Tires { tires } => TpmsData { tires },
+            TpmsRepr::FourWheels { front_left, front_right, rear_left, rear_right } => {
+                let wheels = [(0, 0, front_left), (0, 1, front_right), (1, 0, rear_left), (1, 1, rear_right)];
+                TpmsData {
+                    tires: wheels
+                        .into_iter()
+                        .map(|(axle, position, t)| TireSensor {
+                            position: TirePosition { unit: 0, axle, position },
+                            pressure_psi: t.pressure_psi,
+                            temperature_c: t.temperature_c,
+                            battery_percent: Some(t.battery_percent),
+                            alert: t.alert,
+                        })
+                        .collect(),
+                }
+            }
+        }
+    }
+}
+
+/// Heavy-duty vehicle network (SAE J1939). Values stay `None` until an ECU
+/// has reported them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct J1939Data {
+    /// SPN 190
+    pub engine_speed_rpm: Option<f32>,
+    /// SPN 84
+    pub wheel_speed_kmh: Option<f32>,
+    /// SPN 183
+    pub fuel_rate_lph: Option<f32>,
+    /// SPN 250
+    pub total_fuel_used_l: Option<f64>,
+    /// SPN 917, or SPN 245 where the high-resolution distance is not sent
+    pub odometer_km: Option<f64>,
+    /// SPN 247
+    pub engine_hours: Option<f64>,
+    /// SPN 110
+    pub coolant_temp_c: Option<f32>,
+    /// SPN 175
+    pub oil_temp_c: Option<f32>,
+    /// Any ECU's DM1 lamp status.
+    pub amber_warning_lamp: bool,
+    pub red_stop_lamp: bool,
+    /// DM1 active faults of every ECU.
+    pub active_faults: Vec<J1939Fault>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct J1939Fault {
+    pub source_address: u8,
+    pub spn: u32,
+    pub fmi: u8,
+    pub occurrence_count: u8,
+}
+
+/// OBD-II diagnostic trouble codes, sent when they change.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DtcData {
+    /// Malfunction indicator lamp (mode 01 PID 01).
+    pub mil_on: bool,
+    /// Mode 03: confirmed codes.
+    pub stored: Vec<DiagnosticCode>,
+    /// Mode 07: seen in the current or last drive cycle, not yet confirmed.
+    pub pending: Vec<DiagnosticCode>,
+    /// Mode 0A: survive a clear until the ECU has verified the repair.
+    pub permanent: Vec<DiagnosticCode>,
+    /// Mode 02 snapshot of the moment a stored code was set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub freeze_frame: Option<FreezeFrame>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DiagnosticCode {
+    /// SAE J2012 form, e.g. `P0301`.
+    pub code: String,
+    /// `None` for codes, mostly manufacturer-specific, the agent has no text for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FreezeFrame {
+    /// Code that caused the snapshot.
+    pub code: String,
+    pub values: ObdData,
+}
+
+/// Output of the agent's position filter, which combines GNSS fixes with
+/// IMU and wheel speed and keeps going through tunnels and urban canyons.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PositionData {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub speed_kmh: f32,
+    /// Degrees clockwise from true north.
+    pub heading: f32,
+    /// Horizontal position covariance in m²: east variance, north variance
+    /// and their covariance.
+    pub covariance_m2: [f32; 3],
+    pub speed_std_kmh: f32,
+    pub heading_std_deg: f32,
+    pub source: PositionSource,
+    /// Time since the filter last accepted a GNSS fix.
+    pub gnss_age_ms: u32,
+}
+
+impl PositionData {
+    /// Root of the summed east and north variances (DRMS), in metres.
+    pub fn horizontal_std_m(&self) -> f32 {
+        (self.covariance_m2[0] + self.covariance_m2[1]).max(0.0).sqrt()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PositionSource {
+    /// GNSS alone; the truck has no IMU or wheel speed to fuse.
+    Gnss,
+    /// GNSS corrected recently, propagated with IMU and wheel speed between
+    /// fixes.
+    Fused,
+    /// No usable GNSS fix for a while; the covariance grows until one
+    /// returns.
+    DeadReckoned,
+}
+
+// --- Camera ---
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CameraMeta {
+    pub camera_id: String,
+    pub width: u32,
+    pub height: u32,
+    pub format: String,
+    pub is_keyframe: bool,
+    pub blob_id: Option<String>,
+    pub trigger_event: Option<String>,
+}
+
+/// Camera blobs travel out of band; the envelope only carries a reference.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CameraBlobRef {
+    pub blob_id: String,
+    pub size_bytes: u64,
+    pub compression: String,
+}
+
+// --- ML ---
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MlDetection {
+    pub model_name: String,
+    pub model_version: String,
+    pub result: MlResult,
+    pub confidence: f32,
+    pub calibrated_confidence: f32,
+    pub latency_ms: f32,
+    pub hardware_used: String,
+    pub camera_id: String,
+    pub driver_id: String,
+    pub route_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum MlResult {
+    Drowsiness { is_drowsy: bool, eye_closure_ratio: f32 },
+    LaneDeparture { is_departing: bool, deviation_pixels: i32 },
+    CargoTamper { is_tampered: bool, motion_score: f32 },
+    LicensePlate { plate_text: String, bounding_box: (f32, f32, f32, f32) },
+    Weather { weather_type: String, visibility_m: f32 },
+    Unknown,
+}
+
+impl MlResult {
+    /// Whether the detection describes a condition the fleet should be alerted to.
+    pub fn is_alert(&self) -> bool {
+        match self {
+            MlResult::Drowsiness { is_drowsy, .. } => *is_drowsy,
+            MlResult::LaneDeparture { is_departing, .. } => *is_departing,
+            MlResult::CargoTamper { is_tampered, .. } => *is_tampered,
+            _ => false,
+        }
+    }
+}
+
+// --- Health ---
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HealthReport {
+    pub status: HealthState,
+    pub cpu_percent: f32,
+    pub cpu_cores: usize,
+    pub memory_percent: f32,
+    pub memory_used_mb: u64,
+    pub memory_total_mb: u64,
+    pub disk_percent: f32,
+    pub disk_used_gb: u64,
+    pub disk_total_gb: u64,
+    pub temperature_c: f32,
+    pub thermal_throttling: bool,
+    pub uptime_sec: u64,
+    pub alerts: Vec<HealthAlert>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum HealthState {
+    Ok,
+    Warning,
+    Critical,
+    Degraded,
+    ShutdownPending,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HealthAlert {
+    pub alert_id: String,
+    pub alert_type: String,
+    pub severity: AlertSeverity,
+    pub message: String,
+    pub triggered_at: u64,
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AlertSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+// --- Heartbeat / commands ---
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Heartbeat {
+    pub uptime_sec: u64,
+    pub memory_used_bytes: u64,
+    pub disk_used_bytes: u64,
+    pub last_ack_seq: u64,
+    /// Cellular usage in the current billing cycle; absent from older agents.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_usage: Option<DataUsage>,
+}
+
+/// Bytes sent per traffic category since `cycle_start` (unix seconds).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DataUsage {
+    pub cycle_start: i64,
+    pub budget_bytes: u64,
+    pub telemetry_bytes: u64,
+    pub camera_bytes: u64,
+    pub ota_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CommandResponse {
+    pub command_id: String,
+    pub success: bool,
+    pub message: String,
+    pub data: Option<serde_json::Value>,
+}
+
+// --- WAL retention ---
+/// Unacked events the agent deleted from its WAL before they were delivered;
+/// the server will never receive these sequence numbers.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DataLoss {
+    pub reason: String,
+    pub ranges: Vec<LostRange>,
+}
+
+/// Contiguous run of WAL sequence numbers of one priority.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LostRange {
+    pub first_seq: u64,
+    pub last_seq: u64,
+    pub priority: EventPriority,
+    pub entries: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_four_wheel_tpms_from_older_agents() {
+        let tire = r#"{"pressure_psi":105.0,"temperature_c":30.0,"battery_percent":80,"alert":false}"#;
+        let legacy = format!(
+            r#"{{"Tpms":{{"front_left":{0},"front_right":{0},"rear_left":{0},"rear_right":{1}}}}}"#,
+            tire,
+            tire.replace("false", "true")
+        );
+        let SensorValues::Tpms(tpms) = serde_json::from_str(&legacy).unwrap() else {
+            panic!("not a TPMS reading");
+        };
+        let positions: Vec<_> = tpms.tires.iter().map(|t| t.position.label()).collect();
+        assert_eq!(positions, ["u0_a0_p0", "u0_a0_p1", "u0_a1_p0", "u0_a1_p1"]);
+        assert!(tpms.tires[3].alert);
+        assert_eq!(tpms.tires[0].battery_percent, Some(80));
+
+        let current = SensorValues::Tpms(tpms);
+        let json = serde_json::to_string(&current).unwrap();
+        assert_eq!(serde_json::from_str::<SensorValues>(&json).unwrap(), current);
+    }
+}
@@ -0,0 +1,50 @@
+//! Wire protocol shared by the truck agent, the central server and the simulator.
+//!
+//! Everything that crosses the network between a truck and the fleet backend is
+//! defined here: the batch envelope, the event payloads it carries, the MQTT
+//! topic layout and the acknowledgement schema. Envelopes and acks travel as
+//! JSON or CBOR (see [`Encoding`]); receivers accept either. Bump [`PROTOCOL_VERSION`] on any
+//! breaking change to these types.
+
+pub mod ack;
+pub mod codec;
+pub mod encoding;
+pub mod envelope;
+pub mod error;
+pub mod events;
+pub mod mqtt5;
+pub mod topics;
+pub mod upload;
+
+pub use ack::{AckStatus, BatchAck, RejectedEvent};
+pub use codec::SensorBlock;
+pub use encoding::Encoding;
+pub use envelope::Envelope;
+pub use error::{ProtocolError, Result};
+pub use events::{EventKind, EventPriority, WireEvent, WirePayload};
+pub use mqtt5::MqttVersion;
+pub use topics::{Channel, TopicLayout};
+pub use upload::UploadStatus;
+
+/// Current wire protocol version.
+///
+/// 2: envelopes may carry `sensor_blocks`, which version 1 readers would drop.
+/// 3: TPMS readings list tires by position instead of four named wheels;
+///    older readings are still decoded.
+/// 4: sensor readings may be `SensorValues::J1939`.
+/// 5: sensor readings may be `SensorValues::Dtc`.
+/// 6: GPS readings may carry fix type, DOPs, accuracy and the sky view, and
+///    sensor blocks the GPS-with-accuracy layout.
+/// 7: sensor readings may be `SensorValues::Position`, the fused position.
+/// 8: IMU readings may carry gravity-free vehicle-axis acceleration, and
+///    sensor blocks the IMU-with-vehicle-axes layout.
+/// 9: events may be `WirePayload::DataLoss`, WAL ranges evicted before upload.
+pub const PROTOCOL_VERSION: u16 = 9;
+
+/// Oldest protocol version this build can still decode.
+pub const MIN_SUPPORTED_VERSION: u16 = 1;
+
+/// Returns true if a peer speaking `version` can be decoded by this build.
+pub fn is_supported(version: u16) -> bool {
+    (MIN_SUPPORTED_VERSION..=PROTOCOL_VERSION).contains(&version)
+}
@@ -0,0 +1,109 @@
+//! MQTT 5 conventions shared by the agent and the server.
+//!
+//! Nothing here depends on an MQTT client: both ends map these onto their
+//! client's publish properties. On MQTT 3.1.1 links none of it is sent and
+//! receivers fall back to sniffing the payload.
+
+use crate::PROTOCOL_VERSION;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// User property carrying the [`PROTOCOL_VERSION`] of the payload.
+pub const SCHEMA_VERSION_PROPERTY: &str = "schema-version";
+
+/// Which MQTT protocol version to speak.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MqttVersion {
+    /// MQTT 5, falling back to 3.1.1 if the broker refuses it.
+    #[default]
+    Auto,
+    V5,
+    #[serde(rename = "v3")]
+    V311,
+}
+
+/// User properties stamped on every envelope and ack.
+pub fn user_properties() -> Vec<(String, String)> {
+    vec![(SCHEMA_VERSION_PROPERTY.to_string(), PROTOCOL_VERSION.to_string())]
+}
+
+/// Schema version from a message's user properties, if present and numeric.
+pub fn schema_version(user_properties: &[(String, String)]) -> Option<u16> {
+    user_properties
+        .iter()
+        .find(|(key, _)| key == SCHEMA_VERSION_PROPERTY)
+        .and_then(|(_, value)| value.parse().ok())
+}
+
+/// Outgoing topic alias table for one connection.
+///
+/// The first publish on a topic sends the topic together with a new alias;
+/// later ones send only the alias. Topics beyond the broker's
+/// `topic_alias_maximum` go out in full.
+#[derive(Debug, Default)]
+pub struct TopicAliases {
+    /// Our own cap.
+    limit: u16,
+    /// Cap for the current connection.
+    max: u16,
+    aliases: HashMap<String, u16>,
+}
+
+impl TopicAliases {
+    /// No aliases are handed out until [`Self::reset`] reports the broker's maximum.
+    pub fn new(limit: u16) -> Self {
+        Self {
+            limit,
+            max: 0,
+            aliases: HashMap::new(),
+        }
+    }
+
+    /// Starts a new connection: the broker forgets aliases on reconnect and
+    /// announces how many it accepts in CONNACK (absent means none).
+    pub fn reset(&mut self, broker_max: Option<u16>) {
+        self.aliases.clear();
+        self.max = self.limit.min(broker_max.unwrap_or(0));
+    }
+
+    /// Alias for `topic` and whether the topic itself must still be sent.
+    /// `None` when no alias is available.
+    pub fn resolve(&mut self, topic: &str) -> Option<(u16, bool)> {
+        if let Some(alias) = self.aliases.get(topic) {
+            return Some((*alias, false));
+        }
+        let next = self.aliases.len() as u16 + 1;
+        if next > self.max {
+            return None;
+        }
+        self.aliases.insert(topic.to_string(), next);
+        Some((next, true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_aliases() {
+        let mut aliases = TopicAliases::new(2);
+        assert_eq!(aliases.resolve("truck/a/telemetry"), None);
+
+        aliases.reset(Some(10));
+        assert_eq!(aliases.resolve("truck/a/telemetry"), Some((1, true)));
+        assert_eq!(aliases.resolve("truck/a/telemetry"), Some((1, false)));
+        assert_eq!(aliases.resolve("truck/a/critical"), Some((2, true)));
+        assert_eq!(aliases.resolve("truck/b/telemetry"), None);
+
+        aliases.reset(Some(1));
+        assert_eq!(aliases.resolve("truck/a/critical"), Some((1, true)));
+        assert_eq!(aliases.resolve("truck/a/telemetry"), None);
+
+        aliases.reset(None);
+        assert_eq!(aliases.resolve("truck/a/critical"), None);
+
+        assert_eq!(schema_version(&user_properties()), Some(PROTOCOL_VERSION));
+    }
+}
@@ -0,0 +1,138 @@
+use crate::error::{ProtocolError, Result};
+use serde::{Deserialize, Serialize};
+
+/// Default topic root used by the agent, the server and the simulator.
+pub const DEFAULT_PREFIX: &str = "truck";
+
+/// Logical channel carried under a device's topic subtree.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Channel {
+    /// Agent -> server: batched event envelopes.
+    Telemetry,
+    /// Agent -> server: single critical events (safety alerts), published with
+    /// QoS 2 so they never queue behind bulk telemetry.
+    Critical,
+    /// Server -> agent: per-batch acknowledgements.
+    Ack,
+    /// Server -> agent: remote commands (OTA, config, diagnostics).
+    Command,
+}
+
+impl Channel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Channel::Telemetry => "telemetry",
+            Channel::Critical => "critical",
+            Channel::Ack => "ack",
+            Channel::Command => "command",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "telemetry" => Some(Channel::Telemetry),
+            "critical" => Some(Channel::Critical),
+            "ack" => Some(Channel::Ack),
+            "command" => Some(Channel::Command),
+            _ => None,
+        }
+    }
+}
+
+/// Topic layout: `{prefix}/{device_id}/{channel}`.
+///
+/// The prefix may itself contain slashes (e.g. `fleet/truck`), device ids may not.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TopicLayout {
+    prefix: String,
+}
+
+impl Default for TopicLayout {
+    fn default() -> Self {
+        Self::new(DEFAULT_PREFIX)
+    }
+}
+
+impl TopicLayout {
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.trim_matches('/').to_string(),
+        }
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    pub fn topic(&self, device_id: &str, channel: Channel) -> String {
+        format!("{}/{}/{}", self.prefix, device_id, channel.as_str())
+    }
+
+    pub fn telemetry(&self, device_id: &str) -> String {
+        self.topic(device_id, Channel::Telemetry)
+    }
+
+    pub fn critical(&self, device_id: &str) -> String {
+        self.topic(device_id, Channel::Critical)
+    }
+
+    pub fn ack(&self, device_id: &str) -> String {
+        self.topic(device_id, Channel::Ack)
+    }
+
+    pub fn command(&self, device_id: &str) -> String {
+        self.topic(device_id, Channel::Command)
+    }
+
+    /// Wildcard filter matching `channel` for every device.
+    pub fn subscription(&self, channel: Channel) -> String {
+        format!("{}/+/{}", self.prefix, channel.as_str())
+    }
+
+    /// Splits a concrete topic into `(device_id, channel)`.
+    pub fn parse<'a>(&self, topic: &'a str) -> Result<(&'a str, Channel)> {
+        let rest = topic
+            .strip_prefix(self.prefix.as_str())
+            .and_then(|r| r.strip_prefix('/'))
+            .ok_or_else(|| ProtocolError::InvalidTopic(topic.to_string()))?;
+
+        let (device_id, channel) = rest
+            .split_once('/')
+            .ok_or_else(|| ProtocolError::InvalidTopic(topic.to_string()))?;
+
+        if device_id.is_empty() || channel.contains('/') {
+            return Err(ProtocolError::InvalidTopic(topic.to_string()));
+        }
+
+        let channel =
+            Channel::parse(channel).ok_or_else(|| ProtocolError::InvalidTopic(topic.to_string()))?;
+        Ok((device_id, channel))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_roundtrip() {
+        let layout = TopicLayout::new("fleet/truck/");
+        let topic = layout.telemetry("TRK-7A3B9C");
+        assert_eq!(topic, "fleet/truck/TRK-7A3B9C/telemetry");
+
+        let (device_id, channel) = layout.parse(&topic).unwrap();
+        assert_eq!(device_id, "TRK-7A3B9C");
+        assert_eq!(channel, Channel::Telemetry);
+        assert_eq!(layout.subscription(Channel::Ack), "fleet/truck/+/ack");
+        assert_eq!(layout.parse(&layout.critical("TRK-1")).unwrap(), ("TRK-1", Channel::Critical));
+    }
+
+    #[test]
+    fn test_parse_rejects_foreign_topics() {
+        let layout = TopicLayout::default();
+        assert!(layout.parse("TRK-1/telemetry").is_err());
+        assert!(layout.parse("truck/TRK-1/unknown").is_err());
+        assert!(layout.parse("truck//telemetry").is_err());
+        assert!(layout.parse("truck/TRK-1/telemetry/extra").is_err());
+    }
+}
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+// Resumable blob upload over HTTP.
+//
+//   GET  /ingest/blob/{device_id}/{event_id}   -> UploadStatus (404: nothing received yet)
+//   PUT  /ingest/blob/{device_id}/{event_id}   body = one chunk, headers below
+//        200 -> UploadStatus after the chunk
+//        409 -> UploadStatus; the chunk did not start at `offset`, resend from there
+//
+// Uploads are keyed by event id, so resending a chunk or a whole blob after a
+// reconnect or a restart never stores it twice.
+
+/// Byte offset of the chunk in the request body.
+pub const HEADER_UPLOAD_OFFSET: &str = "upload-offset";
+/// Total size of the blob, sent with every chunk.
+pub const HEADER_UPLOAD_LENGTH: &str = "upload-length";
+/// Blob format, e.g. `jpeg` or `h264`.
+pub const HEADER_BLOB_FORMAT: &str = "blob-format";
+
+pub fn blob_path(device_id: &str, event_id: &str) -> String {
+    format!("/ingest/blob/{}/{}", device_id, event_id)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UploadStatus {
+    pub event_id: String,
+    pub total_size: u64,
+    /// Bytes received so far; the next chunk must start here.
+    pub offset: u64,
+    /// Object key once the blob has been assembled and stored.
+    pub stored_key: Option<String>,
+}
+
+impl UploadStatus {
+    pub fn is_complete(&self) -> bool {
+        self.stored_key.is_some()
+    }
+}
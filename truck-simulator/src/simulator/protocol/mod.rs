@@ -1,3 +1,8 @@
+pub mod http;
+pub mod mqtt;
+pub mod websocket;
+pub mod wire;
+
 use crate::config::ScenarioConfig;
 use crate::simulator::truck::TruckSimulator;
 use crate::simulator::types::TruckState;
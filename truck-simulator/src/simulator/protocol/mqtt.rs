@@ -1,3 +1,4 @@
+use crate::simulator::protocol::wire::{state_to_envelope, TruckSequence};
 use crate::simulator::types::TruckState;
 use tokio::sync::broadcast;
 use tracing::{info, error};
@@ -5,6 +6,8 @@ use rumqttd::Broker;
 use rumqttd::Config;
 use std::net::SocketAddr;
 use std::collections::HashMap;
+use rumqttc::{AsyncClient, MqttOptions, QoS};
+use truck_protocol::TopicLayout;
 
 #[derive(Clone)]
 pub struct MqttHandler {
@@ -35,19 +38,34 @@ impl MqttHandler {
             }
         });
         
-        // Start publishing task
+        // Publish through the embedded broker using the agent wire protocol
+        let mut mqtt_options = MqttOptions::new("truck-simulator", "127.0.0.1", self.port);
+        mqtt_options.set_keep_alive(tokio::time::Duration::from_secs(30));
+        let (client, mut eventloop) = AsyncClient::new(mqtt_options, 100);
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = eventloop.poll().await {
+                    error!("Simulator MQTT client error: {}", e);
+                    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+                }
+            }
+        });
+
+        let topics = TopicLayout::default();
+        let mut sequences: HashMap<String, TruckSequence> = HashMap::new();
+
         while let Ok(state) = rx.recv().await {
-            // Publish to MQTT topics
-            let client_id = format!("simulator-{}", state.truck_id);
-            let topic = format!("truck/{}/telemetry", state.truck_id);
-            
-            // Convert state to JSON
-            let payload = serde_json::to_vec(&state)?;
-            
-            // In production, use rumqttc to publish
-            // For now, just log
+            let sequence = sequences.entry(state.truck_id.clone()).or_default();
+
+            let topic = topics.telemetry(&state.truck_id);
+            let payload = state_to_envelope(&state, sequence).encode()?;
+
+            if let Err(e) = client.publish(&topic, QoS::AtLeastOnce, false, payload).await {
+                error!(truck_id=%state.truck_id, "Failed to publish batch: {}", e);
+                continue;
+            }
             info!(truck_id=%state.truck_id, "📤 Published to MQTT topic: {}", topic);
-            
+
             // Add delay to simulate network
             tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
         }
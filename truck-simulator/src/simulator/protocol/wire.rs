@@ -0,0 +1,290 @@
+use crate::simulator::types::{self, TruckState};
+use truck_protocol::events as wire;
+use truck_protocol::{Envelope, WireEvent};
+
+// Simulated trucks speak the same wire protocol as the real agent, so the
+// central server can ingest either without special-casing.
+
+/// Batch and event numbering for one truck, kept across batches so event ids
+/// stay unique however many ML events or health alerts a batch carries.
+#[derive(Debug, Default)]
+pub struct TruckSequence {
+    batch: u64,
+    event: u64,
+}
+
+pub fn state_to_envelope(state: &TruckState, sequence: &mut TruckSequence) -> Envelope {
+    let timestamp = state.timestamp * 1_000_000; // simulator keeps millis
+    sequence.batch += 1;
+    let batch_seq = sequence.batch;
+    let mut events = Vec::new();
+
+    let mut push = |priority: wire::EventPriority, source: &str, payload: wire::WirePayload| {
+        sequence.event += 1;
+        events.push(WireEvent {
+            event_id: format!("evt-{}-{}", state.truck_id, sequence.event),
+            kind: payload.kind(),
+            timestamp,
+            priority,
+            sequence_number: sequence.event,
+            source_module: source.to_string(),
+            payload,
+        });
+    };
+
+    let s = &state.sensors;
+    for values in [
+        wire::SensorValues::Gps(wire::GpsData {
+            latitude: s.gps.latitude,
+            longitude: s.gps.longitude,
+            altitude: s.gps.altitude,
+            speed_kmh: s.gps.speed_kmh,
+            heading: s.gps.heading,
+            satellites: s.gps.satellites,
+            fix_quality: s.gps.fix_quality,
+            ..Default::default()
+        }),
+        wire::SensorValues::Obd(wire::ObdData {
+            rpm: s.obd.rpm,
+            speed_kmh: s.obd.speed_kmh,
+            coolant_temp: s.obd.coolant_temp,
+            fuel_level: s.obd.fuel_level,
+            engine_load: s.obd.engine_load,
+            throttle_pos: s.obd.throttle_pos,
+            extra: Default::default(),
+        }),
+        wire::SensorValues::Imu(wire::ImuData {
+            accel_x: s.imu.accel_x,
+            accel_y: s.imu.accel_y,
+            accel_z: s.imu.accel_z,
+            gyro_x: s.imu.gyro_x,
+            gyro_y: s.imu.gyro_y,
+            gyro_z: s.imu.gyro_z,
+            // The simulated IMU sits level, along the truck
+            longitudinal_g: Some(s.imu.accel_x),
+            lateral_g: Some(s.imu.accel_y),
+            vertical_g: Some(s.imu.accel_z - 1.0),
+        }),
+        wire::SensorValues::Tpms(wire::TpmsData {
+            tires: s.tpms.tires.iter().map(tire).collect(),
+        }),
+    ] {
+        push(
+            wire::EventPriority::Medium,
+            "sensor",
+            wire::WirePayload::Sensor(wire::SensorReading {
+                sensor_id: format!("sim-{}", state.truck_id),
+                values,
+                sample_interval_ms: None,
+            }),
+        );
+    }
+
+    for ml in &state.ml_events {
+        let result = ml_result(&ml.result);
+        let priority = if result.is_alert() {
+            wire::EventPriority::Critical
+        } else {
+            wire::EventPriority::High
+        };
+        push(
+            priority,
+            "ml",
+            wire::WirePayload::Ml(wire::MlDetection {
+                model_name: ml.model_name.clone(),
+                model_version: "sim".to_string(),
+                result,
+                confidence: ml.confidence,
+                calibrated_confidence: ml.confidence,
+                latency_ms: ml.latency_ms,
+                hardware_used: "Cpu".to_string(),
+                camera_id: String::new(),
+                driver_id: String::new(),
+                route_id: state.scenario.clone(),
+            }),
+        );
+    }
+
+    let h = &state.health_status;
+    push(
+        wire::EventPriority::Low,
+        "health",
+        wire::WirePayload::Health(wire::HealthReport {
+            status: if h.alerts.iter().any(|a| a.severity == types::AlertSeverity::Critical) {
+                wire::HealthState::Critical
+            } else if h.alerts.is_empty() {
+                wire::HealthState::Ok
+            } else {
+                wire::HealthState::Warning
+            },
+            cpu_percent: h.cpu_percent,
+            cpu_cores: 4,
+            memory_percent: h.memory_percent,
+            memory_used_mb: 0,
+            memory_total_mb: 0,
+            disk_percent: h.disk_percent,
+            disk_used_gb: 0,
+            disk_total_gb: 0,
+            temperature_c: h.temperature_c,
+            thermal_throttling: false,
+            uptime_sec: 0,
+            alerts: h
+                .alerts
+                .iter()
+                .enumerate()
+                .map(|(i, a)| wire::HealthAlert {
+                    alert_id: format!("sim-{}-{}-{}", state.truck_id, batch_seq, i),
+                    alert_type: a.alert_type.clone(),
+                    severity: match a.severity {
+                        types::AlertSeverity::Info => wire::AlertSeverity::Info,
+                        types::AlertSeverity::Warning => wire::AlertSeverity::Warning,
+                        types::AlertSeverity::Critical => wire::AlertSeverity::Critical,
+                    },
+                    message: a.message.clone(),
+                    triggered_at: a.timestamp * 1_000_000,
+                    source: "simulator".to_string(),
+                })
+                .collect(),
+        }),
+    );
+
+    Envelope::new(
+        &state.truck_id,
+        &format!("batch-{}-{}", state.truck_id, batch_seq),
+        timestamp,
+        events,
+    )
+}
+
+fn tire(t: &types::TireSensor) -> wire::TireSensor {
+    wire::TireSensor {
+        position: wire::TirePosition {
+            unit: t.unit,
+            axle: t.axle,
+            position: t.position,
+        },
+        pressure_psi: t.pressure_psi,
+        temperature_c: t.temperature_c,
+        battery_percent: Some(t.battery_percent),
+        alert: t.alert,
+    }
+}
+
+fn ml_result(result: &types::MlResult) -> wire::MlResult {
+    match result {
+        types::MlResult::Drowsiness { is_drowsy, eye_closure_ratio } => wire::MlResult::Drowsiness {
+            is_drowsy: *is_drowsy,
+            eye_closure_ratio: *eye_closure_ratio,
+        },
+        types::MlResult::LaneDeparture { is_departing, deviation_pixels } => wire::MlResult::LaneDeparture {
+            is_departing: *is_departing,
+            deviation_pixels: *deviation_pixels,
+        },
+        types::MlResult::CargoTamper { is_tampered, motion_score } => wire::MlResult::CargoTamper {
+            is_tampered: *is_tampered,
+            motion_score: *motion_score,
+        },
+        types::MlResult::LicensePlate { plate_text, bounding_box } => wire::MlResult::LicensePlate {
+            plate_text: plate_text.clone(),
+            bounding_box: *bounding_box,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn state(ml_events: usize) -> TruckState {
+        TruckState {
+            truck_id: "TRK-0001".to_string(),
+            timestamp: 1_700_000_000_000,
+            location: (37.7749, -122.4194),
+            speed_kmh: 72.0,
+            heading: 180.0,
+            scenario: "drowsy_driver".to_string(),
+            sensors: types::SensorData {
+                gps: types::GpsData {
+                    latitude: 37.7749,
+                    longitude: -122.4194,
+                    altitude: 10.0,
+                    speed_kmh: 72.0,
+                    heading: 180.0,
+                    satellites: 9,
+                    fix_quality: 1,
+                },
+                obd: types::ObdData {
+                    rpm: 1800,
+                    speed_kmh: 72,
+                    coolant_temp: 88,
+                    fuel_level: 64,
+                    engine_load: 40,
+                    throttle_pos: 22,
+                },
+                imu: types::ImuData {
+                    accel_x: -0.1,
+                    accel_y: 0.0,
+                    accel_z: 1.0,
+                    gyro_x: 0.0,
+                    gyro_y: 0.0,
+                    gyro_z: 0.5,
+                },
+                tpms: types::TpmsData { tires: Vec::new() },
+            },
+            cameras: types::CameraData {
+                front_camera: None,
+                driver_camera: None,
+                cargo_camera: None,
+            },
+            ml_events: (0..ml_events)
+                .map(|i| types::MlEvent {
+                    event_id: format!("ml-{}", i),
+                    model_name: "drowsiness".to_string(),
+                    timestamp: 1_700_000_000_000,
+                    result: types::MlResult::Drowsiness { is_drowsy: true, eye_closure_ratio: 0.8 },
+                    confidence: 0.9,
+                    latency_ms: 12.0,
+                })
+                .collect(),
+            health_status: types::HealthStatus {
+                cpu_percent: 20.0,
+                memory_percent: 30.0,
+                disk_percent: 40.0,
+                temperature_c: 50.0,
+                network_quality: types::NetworkQuality {
+                    latency_ms: 50.0,
+                    packet_loss_percent: 0.0,
+                    bandwidth_kbps: 1000.0,
+                },
+                alerts: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_event_ids_stay_unique_across_busy_batches() {
+        let mut sequence = TruckSequence::default();
+        let mut ids = HashSet::new();
+        let mut last_seq = 0;
+        // More events than the old fixed stride of 16 per batch
+        for ml_events in [20, 0, 3] {
+            let bytes = state_to_envelope(&state(ml_events), &mut sequence).encode().unwrap();
+            // Decoded the way the server's MQTT handler does
+            let envelope = Envelope::decode_from("TRK-0001", &bytes).unwrap();
+            assert_eq!(envelope.events.len(), 4 + ml_events + 1);
+            for event in &envelope.events {
+                assert!(ids.insert(event.event_id.clone()), "duplicate {}", event.event_id);
+                assert!(event.sequence_number > last_seq);
+                last_seq = event.sequence_number;
+            }
+            match &envelope.events[2].payload {
+                wire::WirePayload::Sensor(wire::SensorReading {
+                    values: wire::SensorValues::Imu(imu),
+                    ..
+                }) => assert_eq!(imu.longitudinal_g, Some(-0.1)),
+                other => panic!("expected the IMU reading, got {:?}", other),
+            }
+        }
+    }
+}
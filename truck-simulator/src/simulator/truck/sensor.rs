@@ -1,4 +1,4 @@
-use crate::simulator::types::{SensorData, GpsData, ObdData, ImuData, TpmsData};
+use crate::simulator::types::{SensorData, GpsData, ObdData, ImuData, TpmsData, TireSensor};
 use rand::Rng;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -72,33 +72,24 @@ impl SensorGenerator {
             gyro_z: self.rng.gen_range(-1.0..1.0),
         };
 
-        // TPMS data - tire pressure monitoring
-        let tpms = TpmsData {
-            front_left: TireSensor {
-                pressure_psi: 32.0 + self.rng.gen_range(-2.0..2.0),
-                temperature_c: 25.0 + self.rng.gen_range(-5.0..15.0),
-                battery_percent: 90 + self.rng.gen_range(-10..10) as u8,
-                alert: false,
-            },
-            front_right: TireSensor {
-                pressure_psi: 32.0 + self.rng.gen_range(-2.0..2.0),
-                temperature_c: 25.0 + self.rng.gen_range(-5.0..15.0),
-                battery_percent: 90 + self.rng.gen_range(-10..10) as u8,
-                alert: false,
-            },
-            rear_left: TireSensor {
-                pressure_psi: 32.0 + self.rng.gen_range(-2.0..2.0),
-                temperature_c: 25.0 + self.rng.gen_range(-5.0..15.0),
-                battery_percent: 90 + self.rng.gen_range(-10..10) as u8,
-                alert: false,
-            },
-            rear_right: TireSensor {
-                pressure_psi: 32.0 + self.rng.gen_range(-2.0..2.0),
-                temperature_c: 25.0 + self.rng.gen_range(-5.0..15.0),
-                battery_percent: 90 + self.rng.gen_range(-10..10) as u8,
-                alert: false,
-            },
-        };
+        // TPMS data - tire pressure monitoring on a five-axle tractor-trailer:
+        // single steer tires, dual drive and trailer tires
+        let axles: [(u8, u8, u8); 5] = [(0, 0, 2), (0, 1, 4), (0, 2, 4), (1, 0, 4), (1, 1, 4)];
+        let mut tires = Vec::new();
+        for (unit, axle, count) in axles {
+            for position in 0..count {
+                tires.push(TireSensor {
+                    unit,
+                    axle,
+                    position,
+                    pressure_psi: 105.0 + self.rng.gen_range(-4.0..4.0),
+                    temperature_c: 25.0 + self.rng.gen_range(-5.0..15.0),
+                    battery_percent: 90 + self.rng.gen_range(-10..10) as u8,
+                    alert: false,
+                });
+            }
+        }
+        let tpms = TpmsData { tires };
 
         SensorData {
             gps,
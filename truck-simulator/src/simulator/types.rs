@@ -56,14 +56,14 @@ pub struct ImuData {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TpmsData {
-    pub front_left: TireSensor,
-    pub front_right: TireSensor,
-    pub rear_left: TireSensor,
-    pub rear_right: TireSensor,
+    pub tires: Vec<TireSensor>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TireSensor {
+    pub unit: u8, // 0 = tractor, 1 = trailer
+    pub axle: u8,
+    pub position: u8, // from the left
     pub pressure_psi: f32,
     pub temperature_c: f32,
     pub battery_percent: u8,
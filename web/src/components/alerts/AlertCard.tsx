@@ -52,6 +52,7 @@ const AlertCard: React.FC<AlertCardProps> = ({ alert }) => {
       case 'LaneDeparture':
       case 'HarshBraking':
       case 'RapidAcceleration':
+      case 'HarshCornering':
       case 'OverSpeeding':
         return <SpeedIcon />;
       case 'CargoTamper':
@@ -86,6 +87,7 @@ const AlertCard: React.FC<AlertCardProps> = ({ alert }) => {
       CargoTamper: 'Cargo Tamper',
       HarshBraking: 'Harsh Braking',
       RapidAcceleration: 'Rapid Acceleration',
+      HarshCornering: 'Harsh Cornering',
       OverSpeeding: 'Over Speeding',
       HighTemperature: 'High Temperature',
       LowDiskSpace: 'Low Disk Space',
@@ -105,6 +105,8 @@ const AlertDetail = () => {
         return <SpeedIcon />;
       case 'RapidAcceleration':
         return <SpeedIcon />;
+      case 'HarshCornering':
+        return <SpeedIcon />;
       case 'OverSpeeding':
         return <SpeedIcon />;
       case 'HighTemperature':
@@ -142,6 +144,8 @@ const AlertDetail = () => {
         return 'Harsh Braking';
       case 'RapidAcceleration':
         return 'Rapid Acceleration';
+      case 'HarshCornering':
+        return 'Harsh Cornering';
       case 'OverSpeeding':
         return 'Over Speeding';
       case 'HighTemperature':
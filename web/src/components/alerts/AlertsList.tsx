@@ -104,6 +104,7 @@ const AlertsList: React.FC = () => {
         { value: 'CargoTamper', label: 'Cargo Tamper' },
         { value: 'HarshBraking', label: 'Harsh Braking' },
         { value: 'RapidAcceleration', label: 'Rapid Acceleration' },
+        { value: 'HarshCornering', label: 'Harsh Cornering' },
         { value: 'OverSpeeding', label: 'Over Speeding' },
         { value: 'HighTemperature', label: 'High Temperature' },
         { value: 'LowDiskSpace', label: 'Low Disk Space' },
@@ -38,7 +38,9 @@ type CommandType =
   | 'UpdateConfig'
   | 'RunHealthCheck'
   | 'CaptureSnapshot'
-  | 'FlushWAL';
+  | 'FlushWAL'
+  | 'RotateCertificate'
+  | 'ClearDtcs';
 
 interface RemoteCommand {
   id: string;
@@ -114,6 +116,8 @@ const RemoteCommandsList: React.FC = () => {
         'RunHealthCheck',
         'CaptureSnapshot',
         'FlushWAL',
+        'RotateCertificate',
+        'ClearDtcs',
       ].map(v => ({ value: v, label: v })),
     },
     {
@@ -148,8 +148,11 @@ const TruckDetail = () => {
                 <Box sx={{ display: 'flex', alignItems: 'center', gap: 2 }}>
                   <Typography variant="subtitle1">Tire Pressure:</Typography>
                   <Typography variant="body2">
-                    FL: {telemetry[0].sensors.tpms.front_left.pressure_psi.toFixed(1)} psi, 
-                    FR: {telemetry[0].sensors.tpms.front_right.pressure_psi.toFixed(1)} psi
+                    {telemetry[0].sensors.tpms.tires.length > 0
+                      ? `${telemetry[0].sensors.tpms.tires.length} tires, lowest ${Math.min(
+                          ...telemetry[0].sensors.tpms.tires.map(tire => tire.pressure_psi)
+                        ).toFixed(1)} psi, ${telemetry[0].sensors.tpms.tires.filter(tire => tire.alert).length} alerting`
+                      : 'No TPMS data'}
                   </Typography>
                 </Box>
               </>
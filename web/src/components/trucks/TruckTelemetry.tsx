@@ -23,10 +23,14 @@ import {
 } from '../charts';
 import { Search as SearchIcon } from '@mui/icons-material';
 import { useTelemetry } from '../../hooks/useTelemetry';
+import { formatTirePosition } from '../../utils/format';
 import { DateTimePicker } from '@mui/x-date-pickers';
 import { AdapterDateFns } from '@mui/x-date-pickers/AdapterDateFns';
 import { LocalizationProvider } from '@mui/x-date-pickers/LocalizationProvider';
 
+const averageTirePressure = (tires) =>
+  tires.length > 0 ? tires.reduce((sum, tire) => sum + tire.pressure_psi, 0) / tires.length : 0;
+
 const TruckTelemetry = ({ truckId }) => {
   const { telemetry, loading, error, fetchTelemetry } = useTelemetry();
   const [activeTab, setActiveTab] = useState(0);
@@ -69,12 +73,7 @@ const TruckTelemetry = ({ truckId }) => {
       accel_x: t.sensors.imu.accel_x,
       accel_y: t.sensors.imu.accel_y,
       accel_z: t.sensors.imu.accel_z,
-      tire_pressure: (
-        t.sensors.tpms.front_left.pressure_psi +
-        t.sensors.tpms.front_right.pressure_psi +
-        t.sensors.tpms.rear_left.pressure_psi +
-        t.sensors.tpms.rear_right.pressure_psi
-      ) / 4,
+      tire_pressure: averageTirePressure(t.sensors.tpms.tires),
     }));
   };
 
@@ -261,12 +260,10 @@ const TruckTelemetry = ({ truckId }) => {
               </Typography>
               {filteredTelemetry.length > 0 ? (
                 <PieChart
-                  data={[
-                    { name: 'Front Left', value: filteredTelemetry[0].sensors.tpms.front_left.pressure_psi },
-                    { name: 'Front Right', value: filteredTelemetry[0].sensors.tpms.front_right.pressure_psi },
-                    { name: 'Rear Left', value: filteredTelemetry[0].sensors.tpms.rear_left.pressure_psi },
-                    { name: 'Rear Right', value: filteredTelemetry[0].sensors.tpms.rear_right.pressure_psi },
-                  ]}
+                  data={filteredTelemetry[0].sensors.tpms.tires.map(tire => ({
+                    name: formatTirePosition(tire.position),
+                    value: tire.pressure_psi,
+                  }))}
                   dataKey="value"
                   nameKey="name"
                   height={300}
@@ -317,12 +314,7 @@ const TruckTelemetry = ({ truckId }) => {
                       {t.sensors.obd.fuel_level}
                     </td>
                     <td style={{ border: '1px solid #ddd', padding: '8px' }}>
-                      {(
-                        t.sensors.tpms.front_left.pressure_psi +
-                        t.sensors.tpms.front_right.pressure_psi +
-                        t.sensors.tpms.rear_left.pressure_psi +
-                        t.sensors.tpms.rear_right.pressure_psi
-                      ) / 4}
+                      {averageTirePressure(t.sensors.tpms.tires).toFixed(1)}
                     </td>
                   </tr>
                 ))}
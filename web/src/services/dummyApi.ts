@@ -969,6 +969,7 @@ class DummyApiService {
         issued_at: issuedAt,
         deadline: Math.random() > 0.5 ? new Date(now + 24 * 3600000).toISOString() : null,
         requires_ack: Math.random() > 0.5,
+        issued_by: 'ops@fleet.example',
         status,
         result: status === 'Success' ? this.generateCommandResult(commandType) : null,
         error: status === 'Failed' ? 'Command execution failed: timeout' : null,
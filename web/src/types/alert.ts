@@ -13,6 +13,7 @@ export enum AlertType {
   SensorFailure = 'SensorFailure',
   HarshBraking = 'HarshBraking',
   RapidAcceleration = 'RapidAcceleration',
+  HarshCornering = 'HarshCornering',
   SeatbeltNotFastened = 'SeatbeltNotFastened',
   DoorOpenWhileMoving = 'DoorOpenWhileMoving',
   OverSpeeding = 'OverSpeeding',
@@ -33,6 +33,8 @@ export enum CommandType {
   RunHealthCheck = 'RunHealthCheck',
   CaptureSnapshot = 'CaptureSnapshot',
   FlushWAL = 'FlushWAL',
+  RotateCertificate = 'RotateCertificate',
+  ClearDtcs = 'ClearDtcs',
 }
 
 export enum CommandStatus {
@@ -86,6 +88,7 @@ export interface RemoteCommand {
   issued_at: string;
   deadline: string | null;
   requires_ack: boolean;
+  issued_by: string | null;
   status: CommandStatus;
   result: any | null; // JSON object with command result
   error: string | null;
@@ -18,6 +18,9 @@ export interface SensorData {
   obd: ObdData;
   imu: ImuData;
   tpms: TpmsData;
+  j1939?: J1939Data | null;
+  dtc?: DtcData | null;
+  position?: PositionData | null;
 }
 
 export interface GpsData {
@@ -28,6 +31,38 @@ export interface GpsData {
   heading: number;
   satellites: number;
   fix_quality: number;
+  fix_type?: GnssFixType | null;
+  hdop?: number | null;
+  vdop?: number | null;
+  pdop?: number | null;
+  horizontal_accuracy_m?: number | null;
+  vertical_accuracy_m?: number | null;
+  satellites_in_view?: SatelliteInView[];
+}
+
+/** Output of the agent's GNSS/IMU/wheel-speed filter. */
+export interface PositionData {
+  latitude: number;
+  longitude: number;
+  speed_kmh: number;
+  heading: number;
+  /** East variance, north variance and their covariance, m². */
+  covariance_m2: [number, number, number];
+  speed_std_kmh: number;
+  heading_std_deg: number;
+  source: 'Gnss' | 'Fused' | 'DeadReckoned';
+  gnss_age_ms: number;
+}
+
+export type GnssFixType = 'NoFix' | 'DeadReckoning' | 'Fix2d' | 'Fix3d';
+
+export interface SatelliteInView {
+  system: 'Gps' | 'Sbas' | 'Galileo' | 'Beidou' | 'Qzss' | 'Glonass' | 'Navic';
+  prn: number;
+  elevation_deg?: number | null;
+  azimuth_deg?: number | null;
+  snr_dbhz?: number | null;
+  used: boolean;
 }
 
 export interface ObdData {
@@ -37,6 +72,8 @@ export interface ObdData {
   fuel_level: number;
   engine_load: number;
   throttle_pos: number;
+  // Extra PIDs from the agent's catalogue, by name
+  extra?: Record<string, number>;
 }
 
 export interface ImuData {
@@ -46,22 +83,72 @@ export interface ImuData {
   gyro_x: number;
   gyro_y: number;
   gyro_z: number;
+  // Gravity removed, truck axes; absent until the agent has calibrated the mounting
+  longitudinal_g?: number | null;
+  lateral_g?: number | null;
+  vertical_g?: number | null;
 }
 
 export interface TpmsData {
-  front_left: TireSensor;
-  front_right: TireSensor;
-  rear_left: TireSensor;
-  rear_right: TireSensor;
+  tires: TireSensor[];
+}
+
+// unit 0 is the tractor, 1 the first trailer; axles count from the front,
+// positions from the left-most tire
+export interface TirePosition {
+  unit: number;
+  axle: number;
+  position: number;
 }
 
 export interface TireSensor {
+  position: TirePosition;
   pressure_psi: number;
   temperature_c: number;
-  battery_percent: number;
+  battery_percent?: number;
   alert: boolean;
 }
 
+// Fields an ECU has not reported are null
+export interface J1939Data {
+  engine_speed_rpm: number | null;
+  wheel_speed_kmh: number | null;
+  fuel_rate_lph: number | null;
+  total_fuel_used_l: number | null;
+  odometer_km: number | null;
+  engine_hours: number | null;
+  coolant_temp_c: number | null;
+  oil_temp_c: number | null;
+  amber_warning_lamp: boolean;
+  red_stop_lamp: boolean;
+  active_faults: J1939Fault[];
+}
+
+export interface J1939Fault {
+  source_address: number;
+  spn: number;
+  fmi: number;
+  occurrence_count: number;
+}
+
+export interface DtcData {
+  mil_on: boolean;
+  stored: DiagnosticCode[];
+  pending: DiagnosticCode[];
+  permanent: DiagnosticCode[];
+  freeze_frame?: FreezeFrame;
+}
+
+export interface DiagnosticCode {
+  code: string;
+  description?: string;
+}
+
+export interface FreezeFrame {
+  code: string;
+  values: ObdData;
+}
+
 export interface CameraData {
   front_camera: CameraFrameRef | null;
   driver_camera: CameraFrameRef | null;
@@ -102,8 +189,7 @@ export interface TelemetrySummary {
   last_accel_x: number;
   last_accel_y: number;
   last_accel_z: number;
-  tire_pressures: [number, number, number, number];
-  tire_temperatures: [number, number, number, number];
+  tires: TireSensor[];
 }
 
 export interface TelemetryStatistics {
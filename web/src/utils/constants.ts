@@ -17,6 +17,7 @@ export const ALERT_TYPES = [
   { value: 'CargoTamper', label: 'Cargo Tamper' },
   { value: 'HarshBraking', label: 'Harsh Braking' },
   { value: 'RapidAcceleration', label: 'Rapid Acceleration' },
+  { value: 'HarshCornering', label: 'Harsh Cornering' },
   { value: 'OverSpeeding', label: 'Over Speeding' },
   { value: 'HighTemperature', label: 'High Temperature' },
   { value: 'LowDiskSpace', label: 'Low Disk Space' },
@@ -78,6 +79,8 @@ export const COMMAND_TYPES = [
   { value: 'RunHealthCheck', label: 'Run Health Check' },
   { value: 'CaptureSnapshot', label: 'Capture Snapshot' },
   { value: 'FlushWAL', label: 'Flush WAL' },
+  { value: 'RotateCertificate', label: 'Rotate Certificate' },
+  { value: 'ClearDtcs', label: 'Clear Trouble Codes' },
 ];
 
 export const COMMAND_STATUSES = [
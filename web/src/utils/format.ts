@@ -68,6 +68,8 @@ export const formatAlertType = (type:string) => {
             return 'Harsh Braking';
         case 'RapidAcceleration':
             return 'Rapid Acceleration';
+        case 'HarshCornering':
+            return 'Harsh Cornering';
         case 'OverSpeeding':
             return 'Over Speeding';
         case 'HighTemperature':
@@ -178,6 +180,10 @@ export const formatCommandType = (type:string) => {
             return 'Capture Snapshot';
         case 'FlushWAL':
             return 'Flush WAL';
+        case 'RotateCertificate':
+            return 'Rotate Certificate';
+        case 'ClearDtcs':
+            return 'Clear Trouble Codes';
         default:
             return type;
     }
@@ -200,4 +206,9 @@ export const formatCommandStatus = (status:string) => {
         default:
             return status;
     }
-};
\ No newline at end of file
+};
+
+export const formatTirePosition = (position: { unit: number; axle: number; position: number }) => {
+    const unit = position.unit === 0 ? 'Tractor' : `Trailer ${position.unit}`;
+    return `${unit} axle ${position.axle + 1} #${position.position + 1}`;
+};